@@ -4,14 +4,23 @@ use bon::Builder;
 use serde::Deserialize;
 use serde::Serialize;
 
+mod adaptive;
+mod capacity;
+mod chaos;
 mod defaults;
 pub mod docker;
 pub mod generic;
 mod kind;
+mod queue;
 pub mod tes;
 
+pub use adaptive::Adaptive;
+pub use capacity::Capacity;
+pub use capacity::Oversized;
+pub use chaos::Chaos;
 pub use defaults::Defaults;
 pub use kind::Kind;
+pub use queue::Queue;
 
 /// A configuration object for an execution backend.
 #[derive(Builder, Deserialize, Serialize, Debug, Clone)]
@@ -33,6 +42,32 @@ pub struct Config {
     /// The execution defaults.
     #[builder(into)]
     defaults: Option<Defaults>,
+
+    /// Load-aware adaptation of the number of concurrent tasks.
+    #[builder(into)]
+    adaptive: Option<Adaptive>,
+
+    /// Fault injection for testing the robustness of pipelines.
+    #[builder(into)]
+    chaos: Option<Chaos>,
+
+    /// Limits on the number of tasks waiting to run.
+    #[builder(into)]
+    queue: Option<Queue>,
+
+    /// The resources available to the tasks running on the backend.
+    #[builder(into)]
+    capacity: Option<Capacity>,
+
+    /// The backend to use instead if the backend cannot be initialized.
+    ///
+    /// This allows a configuration written for a cluster to be used on a
+    /// development host that lacks the cluster's runtime (e.g., by falling
+    /// back from a generic backend that submits to a scheduler to a local
+    /// Docker backend, or from a local Docker backend to a remote TES
+    /// backend). The fallback shares the backend's other settings.
+    #[builder(into)]
+    fallback: Option<Kind>,
 }
 
 impl Config {
@@ -56,13 +91,79 @@ impl Config {
         self.defaults.as_ref()
     }
 
+    /// Gets the load-aware adaptation settings of the backend.
+    pub fn adaptive(&self) -> Option<&Adaptive> {
+        self.adaptive.as_ref()
+    }
+
+    /// Gets the fault injection settings of the backend.
+    pub fn chaos(&self) -> Option<&Chaos> {
+        self.chaos.as_ref()
+    }
+
+    /// Gets the queue limits of the backend.
+    pub fn queue(&self) -> Option<&Queue> {
+        self.queue.as_ref()
+    }
+
+    /// Gets the resource capacity of the backend.
+    pub fn capacity(&self) -> Option<&Capacity> {
+        self.capacity.as_ref()
+    }
+
+    /// Gets the backend to use instead if the backend cannot be initialized.
+    pub fn fallback(&self) -> Option<&Kind> {
+        self.fallback.as_ref()
+    }
+
     /// Consumes `self` returns the constituent, owned parts of the
     /// configuration.
-    pub fn into_parts(self) -> (String, Kind, usize, Option<Defaults>) {
-        (self.name, self.kind, self.max_tasks, self.defaults)
+    pub fn into_parts(self) -> Parts {
+        Parts {
+            name: self.name,
+            kind: self.kind,
+            max_tasks: self.max_tasks,
+            defaults: self.defaults,
+            adaptive: self.adaptive,
+            chaos: self.chaos,
+            queue: self.queue,
+            capacity: self.capacity,
+            fallback: self.fallback,
+        }
     }
 }
 
+/// The constituent, owned parts of a backend [`Config`].
+#[derive(Debug, Clone)]
+pub struct Parts {
+    /// The name.
+    pub name: String,
+
+    /// The type.
+    pub kind: Kind,
+
+    /// The maximum number of concurrent tasks that can run.
+    pub max_tasks: usize,
+
+    /// The execution defaults.
+    pub defaults: Option<Defaults>,
+
+    /// Load-aware adaptation of the number of concurrent tasks.
+    pub adaptive: Option<Adaptive>,
+
+    /// Fault injection for testing the robustness of pipelines.
+    pub chaos: Option<Chaos>,
+
+    /// Limits on the number of tasks waiting to run.
+    pub queue: Option<Queue>,
+
+    /// The resources available to the tasks running on the backend.
+    pub capacity: Option<Capacity>,
+
+    /// The backend to use instead if the backend cannot be initialized.
+    pub fallback: Option<Kind>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
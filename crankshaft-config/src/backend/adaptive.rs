@@ -0,0 +1,73 @@
+//! Configuration options related to adapting the number of concurrent tasks
+//! to the load of the host.
+
+use bon::Builder;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// The default minimum number of concurrent tasks when adapting to host load.
+pub const DEFAULT_MIN_TASKS: usize = 1;
+
+/// The default number of seconds between samples of the host's load.
+pub const DEFAULT_INTERVAL: u64 = 5;
+
+/// A utility function used to set the default value for `min_tasks` via serde.
+fn default_min_tasks() -> usize {
+    DEFAULT_MIN_TASKS
+}
+
+/// Load-aware adaptation of the number of concurrent tasks.
+///
+/// When enabled, the number of tasks allowed to run concurrently is reduced
+/// (one task at a time) while the host is considered to be under pressure and
+/// ramped back up (one task at a time) once the pressure subsides.
+///
+/// This is only meaningful for backends that execute tasks on the same host as
+/// the engine (e.g., a local Docker daemon or a generic backend with a local
+/// locale). Load information is read from `/proc`, so adaptation has no effect
+/// on hosts where that information is unavailable.
+#[derive(Builder, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[builder(builder_type = Builder)]
+pub struct Adaptive {
+    /// The one minute load average, divided by the number of CPUs on the
+    /// host, above which the host is considered under pressure.
+    max_load_per_cpu: Option<f64>,
+
+    /// The percentage of time over the last ten seconds that some tasks were
+    /// stalled on CPU (as reported by pressure stall information) above which
+    /// the host is considered under pressure.
+    max_cpu_pressure: Option<f64>,
+
+    /// The minimum number of concurrent tasks, regardless of host load.
+    #[serde(default = "default_min_tasks")]
+    #[builder(default = DEFAULT_MIN_TASKS)]
+    min_tasks: usize,
+
+    /// The number of seconds between samples of the host's load.
+    ///
+    /// An interval of zero is treated as one second.
+    interval: Option<u64>,
+}
+
+impl Adaptive {
+    /// Gets the maximum load average per CPU.
+    pub fn max_load_per_cpu(&self) -> Option<f64> {
+        self.max_load_per_cpu
+    }
+
+    /// Gets the maximum CPU pressure percentage.
+    pub fn max_cpu_pressure(&self) -> Option<f64> {
+        self.max_cpu_pressure
+    }
+
+    /// Gets the minimum number of concurrent tasks.
+    pub fn min_tasks(&self) -> usize {
+        self.min_tasks
+    }
+
+    /// Gets the number of seconds between samples of the host's load.
+    pub fn interval(&self) -> u64 {
+        self.interval.unwrap_or(DEFAULT_INTERVAL).max(1)
+    }
+}
@@ -0,0 +1,74 @@
+//! Configuration options related to the resource capacity of a backend.
+
+use bon::Builder;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// What to do with tasks that request more resources than the capacity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Oversized {
+    /// The task waits until no other tasks are running and then runs alone,
+    /// oversubscribing the capacity.
+    #[default]
+    RunAlone,
+
+    /// The task is rejected when it is submitted.
+    Reject,
+}
+
+/// The resources available to the tasks running on a backend.
+///
+/// When set, tasks are only started once the CPUs and memory they request
+/// fit within the capacity not already requested by running tasks (in
+/// addition to the limit on the number of concurrent tasks).
+///
+/// If GPU devices are listed, tasks requesting GPUs are assigned devices that
+/// are not in use by other running tasks.
+#[derive(Builder, Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[builder(builder_type = Builder)]
+pub struct Capacity {
+    /// The number of CPUs available to tasks.
+    cpu: Option<f64>,
+
+    /// The amount of RAM (in GiB) available to tasks.
+    ///
+    /// May also be written with a unit (see [`crate::size`]).
+    #[serde(default, with = "crate::size::gib")]
+    ram: Option<f64>,
+
+    /// The IDs of the GPU devices available to tasks (e.g., `["0", "1"]`).
+    #[serde(default)]
+    #[builder(into, default)]
+    gpus: Vec<String>,
+
+    /// What to do with tasks that request more CPUs, memory, or GPUs than the
+    /// capacity.
+    #[serde(default)]
+    #[builder(default)]
+    oversized: Oversized,
+}
+
+impl Capacity {
+    /// Gets the number of CPUs available to tasks.
+    pub fn cpu(&self) -> Option<f64> {
+        self.cpu
+    }
+
+    /// Gets the amount of RAM (in GiB) available to tasks.
+    pub fn ram(&self) -> Option<f64> {
+        self.ram
+    }
+
+    /// Gets the IDs of the GPU devices available to tasks.
+    pub fn gpus(&self) -> &[String] {
+        &self.gpus
+    }
+
+    /// Gets what to do with tasks that request more resources than the
+    /// capacity.
+    pub fn oversized(&self) -> Oversized {
+        self.oversized
+    }
+}
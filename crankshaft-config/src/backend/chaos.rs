@@ -0,0 +1,55 @@
+//! Configuration options related to injecting faults into a backend.
+
+use bon::Builder;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Fault injection for testing the robustness of pipelines.
+///
+/// When enabled, tasks spawned on the backend are subjected to artificial
+/// latency, injected failures, and simulated out-of-memory kills at the
+/// configured rates. This is intended to allow pipeline authors to exercise
+/// their retry and error-handling logic; it should never be enabled in
+/// production.
+#[derive(Builder, Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[builder(builder_type = Builder)]
+pub struct Chaos {
+    /// The probability (between `0.0` and `1.0`) that a task fails with an
+    /// injected error before it runs.
+    #[serde(default)]
+    #[builder(default)]
+    failure_rate: f64,
+
+    /// The probability (between `0.0` and `1.0`) that a task is reported as
+    /// killed due to running out of memory instead of running.
+    #[serde(default)]
+    #[builder(default)]
+    oom_rate: f64,
+
+    /// The maximum number of milliseconds of latency to add before a task
+    /// runs.
+    ///
+    /// The latency for each task is chosen uniformly at random up to this
+    /// maximum.
+    max_latency: Option<u64>,
+}
+
+impl Chaos {
+    /// Gets the probability that a task fails with an injected error.
+    pub fn failure_rate(&self) -> f64 {
+        self.failure_rate
+    }
+
+    /// Gets the probability that a task is reported as killed due to running
+    /// out of memory.
+    pub fn oom_rate(&self) -> f64 {
+        self.oom_rate
+    }
+
+    /// Gets the maximum number of milliseconds of latency to add before a task
+    /// runs.
+    pub fn max_latency(&self) -> Option<u64> {
+        self.max_latency
+    }
+}
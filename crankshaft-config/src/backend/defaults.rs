@@ -26,12 +26,16 @@ pub struct Defaults {
     /// This is a float because RAM can be allocated more granularly than in
     /// gibibytes. These may be rounded to any level of precision that is
     /// required for a particular environment.
+    ///
+    /// May also be written with a unit (see [`crate::size`]).
+    #[serde(default, with = "crate::size::gib")]
     ram: Option<f64>,
 
     /// The default limit of random access memory that a container can use (in
     /// GiB).
     ///
     /// Not all backends support limits on memory usage.
+    #[serde(default, with = "crate::size::gib")]
     ram_limit: Option<f64>,
 
     /// The amount of disk (in GiB) to use during execution.
@@ -39,6 +43,9 @@ pub struct Defaults {
     /// This is a float because disks can be allocated more granularly than in
     /// gibibytes. These may be rounded to any level of precision that is
     /// required for a particular environment.
+    ///
+    /// May also be written with a unit (see [`crate::size`]).
+    #[serde(default, with = "crate::size::gib")]
     disk: Option<f64>,
 }
 
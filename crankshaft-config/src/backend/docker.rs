@@ -1,18 +1,118 @@
 //! Configuration related to the _Docker_ execution backend.
 
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
 use bon::Builder;
-use bon::builder;
 use serde::Deserialize;
 use serde::Serialize;
 
 /// The default value for cleaning up Docker containers.
 pub const DEFAULT_CLEANUP: bool = true;
 
+/// The default value for using image labels as resource defaults.
+pub const DEFAULT_IMAGE_DEFAULTS: bool = false;
+
+/// The default value for binding the host's CA bundle into containers.
+pub const DEFAULT_CA_BUNDLE: bool = false;
+
+/// The default maximum number of attempts to pull an image.
+pub const DEFAULT_MAX_PULL_ATTEMPTS: u32 = 3;
+
+/// The default size (in KiB) of the end of each output stream kept for
+/// failure reports.
+pub const DEFAULT_OUTPUT_TAIL: u64 = 4;
+
 /// A utility function used to set the default value for `cleanup` via serde.
 fn default_cleanup() -> bool {
     DEFAULT_CLEANUP
 }
 
+/// A utility function used to set the default value for `image_defaults` via
+/// serde.
+fn default_image_defaults() -> bool {
+    DEFAULT_IMAGE_DEFAULTS
+}
+
+/// A utility function used to set the default value for `ca_bundle` via
+/// serde.
+fn default_ca_bundle() -> bool {
+    DEFAULT_CA_BUNDLE
+}
+
+/// A utility function used to set the default value for `max_pull_attempts`
+/// via serde.
+fn default_max_pull_attempts() -> u32 {
+    DEFAULT_MAX_PULL_ATTEMPTS
+}
+
+/// A utility function used to set the default value for `output_tail` via
+/// serde.
+fn default_output_tail() -> u64 {
+    DEFAULT_OUTPUT_TAIL
+}
+
+/// The deprecation of an image.
+#[derive(Builder, Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[builder(builder_type = DeprecationBuilder)]
+pub struct Deprecation {
+    /// The image that should be used instead.
+    #[builder(into)]
+    replacement: Option<String>,
+
+    /// A message explaining the deprecation.
+    #[builder(into)]
+    message: Option<String>,
+}
+
+impl Deprecation {
+    /// Gets the image that should be used instead.
+    pub fn replacement(&self) -> Option<&str> {
+        self.replacement.as_deref()
+    }
+
+    /// Gets the message explaining the deprecation.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+/// When to preserve the working directories of tasks once they complete.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PreserveWorkDirs {
+    /// Working directories are always removed.
+    Never,
+
+    /// Working directories are preserved for tasks that fail (to aid in
+    /// debugging them) and removed otherwise.
+    #[default]
+    OnFailure,
+
+    /// Working directories are always preserved.
+    Always,
+}
+
+/// How the output of containers is decoded into text (e.g., for the lines of
+/// output reported while tasks run and the output included in errors).
+///
+/// The files that output is written to always contain the raw bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputEncoding {
+    /// UTF-8, with invalid sequences replaced.
+    #[default]
+    Utf8,
+
+    /// ISO-8859-1 (Latin-1).
+    Latin1,
+
+    /// UTF-8, with the bytes of invalid sequences escaped as `\xNN`.
+    Escaped,
+}
+
 /// A configuration object for a Docker execution backend.
 #[derive(Builder, Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
@@ -23,6 +123,292 @@ pub struct Config {
     #[serde(default = "default_cleanup")]
     #[builder(default = DEFAULT_CLEANUP)]
     cleanup: bool,
+
+    /// Whether or not to read resource defaults from the labels of an image.
+    ///
+    /// When enabled, resources recommended by the image's labels are applied
+    /// before the resources requested by the task itself (so requests made by
+    /// the task always take precedence).
+    #[serde(default = "default_image_defaults")]
+    #[builder(default = DEFAULT_IMAGE_DEFAULTS)]
+    image_defaults: bool,
+
+    /// Whether or not to bind the host's CA bundle into containers.
+    ///
+    /// When enabled, `SSL_CERT_FILE` (and similar variables) are set within
+    /// the container to point to the bound bundle. This allows HTTPS requests
+    /// made within a container to succeed behind TLS-intercepting proxies.
+    #[serde(default = "default_ca_bundle")]
+    #[builder(default = DEFAULT_CA_BUNDLE)]
+    ca_bundle: bool,
+
+    /// The maximum number of attempts to pull an image.
+    ///
+    /// Pulls that fail for transient reasons (e.g., timeouts or rate limiting
+    /// by the registry) are retried with exponential backoff.
+    #[serde(default = "default_max_pull_attempts")]
+    #[builder(default = DEFAULT_MAX_PULL_ATTEMPTS)]
+    max_pull_attempts: u32,
+
+    /// The sources to pull images from before their own registries keyed by
+    /// image reference.
+    ///
+    /// Sources are tried in order and are either references to the image in
+    /// another registry (e.g., `mirror.internal/library/ubuntu:22.04`) or
+    /// archives (e.g., `docker-archive:/images/ubuntu.tar`). An image's own
+    /// registry is tried last.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[builder(default)]
+    image_sources: HashMap<String, Vec<String>>,
+
+    /// The architecture of the images to pull (e.g., `amd64` or `arm64`).
+    ///
+    /// Common alternative names for architectures (e.g., `x86_64` or
+    /// `aarch64`) are accepted. The architecture must match that of the local
+    /// Docker daemon (the architectures of swarm nodes are not checked). When
+    /// not set, images are pulled for the Docker daemon's architecture.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    arch: Option<String>,
+
+    /// The maximum number of images pulled at once across every registry.
+    ///
+    /// When not set, the number of concurrent pulls is not limited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_concurrent_pulls: Option<usize>,
+
+    /// The maximum number of images pulled at once from each registry.
+    ///
+    /// Limiting this avoids being rate limited by registries (e.g., Docker
+    /// Hub) when many tasks need images at once. When not set, the number of
+    /// concurrent pulls from a registry is not limited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_concurrent_pulls_per_registry: Option<usize>,
+
+    /// The directory to cache inputs downloaded from remote URLs in.
+    ///
+    /// Inputs downloaded from the same URL are reused across tasks. When not
+    /// set, inputs are downloaded into each task's temporary directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    input_cache: Option<PathBuf>,
+
+    /// The maximum total size (in GiB) of the input cache.
+    ///
+    /// When exceeded, the least recently used inputs that are not in use by a
+    /// running task are evicted. May also be written with a unit (see
+    /// [`crate::size`]).
+    #[serde(
+        default,
+        with = "crate::size::gib",
+        skip_serializing_if = "Option::is_none"
+    )]
+    input_cache_max_size: Option<f64>,
+
+    /// The maximum number of seconds an input may go unused before it is
+    /// evicted from the input cache.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    input_cache_max_age: Option<u64>,
+
+    /// The directory to create the temporary directories of tasks in.
+    ///
+    /// Each task's temporary directory holds its literal inputs (and its
+    /// remote inputs when no input cache is configured). The directory is
+    /// created if it does not exist. When not set, the system's temporary
+    /// directory is used, which may be too small on some compute nodes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    temp_dir_root: Option<PathBuf>,
+
+    /// The minimum free disk space (in GiB) required in each directory used
+    /// by a task before it runs.
+    ///
+    /// This applies to the temporary directory root, the input cache, the
+    /// working directory root, and the Docker daemon's root directory (if it
+    /// is on the local host), in addition to any disk space requested by the
+    /// task. Tasks are failed before they run if a directory does not have
+    /// enough free space.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_free_disk: Option<f64>,
+
+    /// The directory to create the working directories of tasks in.
+    ///
+    /// When set, a unique working directory is created for each task and
+    /// bound into its containers at `/mnt/task`, which is also used as the
+    /// working directory of executions that do not specify one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    work_dir_root: Option<PathBuf>,
+
+    /// When to preserve the working directories of tasks once they complete.
+    #[serde(default)]
+    #[builder(default)]
+    preserve_work_dirs: PreserveWorkDirs,
+
+    /// Whether to run the commands of executions through a generated Bash
+    /// script.
+    ///
+    /// The script is written to the task's working directory (so this
+    /// requires `work-dir-root`) with `set -euo pipefail`, exports of the
+    /// execution's environment, and the program followed by its quoted
+    /// arguments. The program is written verbatim, so it may be a command
+    /// with pipes or several statements. The exit code of the command is
+    /// recorded in the working directory next to the script.
+    #[serde(default)]
+    #[builder(default)]
+    script_wrapper: bool,
+
+    /// The directory to write the structured logs of task attempts to.
+    ///
+    /// When set, each attempt of a task is given a directory at
+    /// `<logs-root>/<task>/attempt-<n>` with a subdirectory for each
+    /// execution containing `cmd.json`, `stdout`, `stderr`, `exit_code`,
+    /// `timing.json`, and `resources.json`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    logs_root: Option<PathBuf>,
+
+    /// Whether to remove the containers and working directories left behind
+    /// by interrupted tasks (e.g., after a crash) when the backend is
+    /// initialized.
+    ///
+    /// This must not be enabled if other processes run tasks against the
+    /// same Docker daemon, as their containers would be removed.
+    #[serde(default)]
+    #[builder(default)]
+    reconcile: bool,
+
+    /// Whether the still-running containers of interrupted tasks (and their
+    /// working directories) are kept when reconciling so that the runner can
+    /// reattach to them and recover their results.
+    #[serde(default)]
+    #[builder(default)]
+    reattach: bool,
+
+    /// The size (in KiB) of the end of each execution's stdout and stderr to
+    /// keep for reporting failures.
+    ///
+    /// The end of the output is kept even when it is not otherwise captured
+    /// and is included in the error of an execution that exits with a non-zero
+    /// status. A size of `0` disables this.
+    #[serde(default = "default_output_tail")]
+    #[builder(default = DEFAULT_OUTPUT_TAIL)]
+    output_tail: u64,
+
+    /// How the output of containers is decoded into text.
+    #[serde(default)]
+    #[builder(default)]
+    output_encoding: OutputEncoding,
+
+    /// The number of seconds a canceled task's container is given to exit
+    /// after being sent `SIGTERM` before it is killed.
+    ///
+    /// When not set, the containers of canceled tasks are killed immediately.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stop_grace_period: Option<u64>,
+
+    /// Whether containers share the host's PID namespace.
+    ///
+    /// By default, each container has its own PID namespace.
+    #[serde(default)]
+    #[builder(default)]
+    host_pid: bool,
+
+    /// Whether containers share the host's IPC namespace.
+    ///
+    /// By default, each container has its own IPC namespace.
+    #[serde(default)]
+    #[builder(default)]
+    host_ipc: bool,
+
+    /// Whether containers share the host's UTS namespace (i.e., its hostname).
+    ///
+    /// By default, each container has its own UTS namespace. This cannot be
+    /// combined with tasks that set a hostname.
+    #[serde(default)]
+    #[builder(default)]
+    host_uts: bool,
+
+    /// Whether to run an init process as PID 1 within containers.
+    ///
+    /// When not set, the Docker daemon's configuration applies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    init: Option<bool>,
+
+    /// The niceness of containers (from `-20`, the highest priority, to `19`,
+    /// the lowest).
+    ///
+    /// This is applied as the relative CPU shares of containers, so it only
+    /// has an effect while the host's CPUs are contended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nice: Option<i32>,
+
+    /// The IO priority level of containers (from `0`, the highest priority, to
+    /// `7`, the lowest).
+    ///
+    /// This is applied as the relative block IO weight of containers, so it
+    /// only has an effect while the host's disks are contended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    io_priority: Option<u8>,
+
+    /// The CPUs containers may run on (e.g., `0-3,8`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    cpuset: Option<String>,
+
+    /// The host environment variables to pass through to containers.
+    ///
+    /// Each entry is either the exact name of a variable or a pattern where
+    /// `*` matches any sequence of characters (e.g., `SLURM_*`). The
+    /// environment of an execution takes precedence over passed through
+    /// variables.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[builder(default)]
+    pass_env: Vec<String>,
+
+    /// The version requirement of the Docker daemon (e.g., `>=24.0`).
+    ///
+    /// When set, the backend fails to initialize if the daemon's version does
+    /// not satisfy the requirement.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    min_version: Option<String>,
+
+    /// Whether to refuse to run images that are not pinned to a digest (e.g.,
+    /// `ubuntu@sha256:...`).
+    ///
+    /// Pinned images are verified to match their digest once pulled, so this
+    /// ensures that tasks only run the exact images they reference.
+    #[serde(default)]
+    #[builder(default)]
+    require_digests: bool,
+
+    /// The digests of the images that are trusted to run (e.g.,
+    /// `sha256:...`).
+    ///
+    /// When not empty, tasks are refused if the digest of their image is not
+    /// in this list.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[builder(default)]
+    trusted_digests: Vec<String>,
+
+    /// The images that are deprecated keyed by image reference.
+    ///
+    /// Tasks using a deprecated image still run, but a warning is emitted
+    /// (including any replacement) to aid in migrating off the image.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[builder(default)]
+    deprecated_images: HashMap<String, Deprecation>,
+
+    /// The labels images are required to have to be run.
+    ///
+    /// Each label is either a key (e.g., `org.opencontainers.image.source`),
+    /// which images must have with any value, or a key and value (e.g.,
+    /// `com.example.approved=true`), which images must have with exactly that
+    /// value. Tasks using an image missing any of the labels are refused.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[builder(default)]
+    required_labels: Vec<String>,
 }
 
 impl Config {
@@ -32,6 +418,200 @@ impl Config {
     pub fn cleanup(&self) -> bool {
         self.cleanup
     }
+
+    /// Gets whether the backend is configured to read resource defaults from
+    /// the labels of an image.
+    pub fn image_defaults(&self) -> bool {
+        self.image_defaults
+    }
+
+    /// Gets whether the backend is configured to bind the host's CA bundle
+    /// into containers.
+    pub fn ca_bundle(&self) -> bool {
+        self.ca_bundle
+    }
+
+    /// Gets the maximum number of attempts to pull an image.
+    pub fn max_pull_attempts(&self) -> u32 {
+        self.max_pull_attempts
+    }
+
+    /// Gets the sources to pull images from before their own registries keyed
+    /// by image reference.
+    pub fn image_sources(&self) -> &HashMap<String, Vec<String>> {
+        &self.image_sources
+    }
+
+    /// Gets the architecture of the images to pull (if one is configured).
+    pub fn arch(&self) -> Option<&str> {
+        self.arch.as_deref()
+    }
+
+    /// Gets the maximum number of images pulled at once across every registry
+    /// (if one is configured).
+    pub fn max_concurrent_pulls(&self) -> Option<usize> {
+        self.max_concurrent_pulls
+    }
+
+    /// Gets the maximum number of images pulled at once from each registry
+    /// (if one is configured).
+    pub fn max_concurrent_pulls_per_registry(&self) -> Option<usize> {
+        self.max_concurrent_pulls_per_registry
+    }
+
+    /// Gets the directory to cache inputs downloaded from remote URLs in (if
+    /// one is configured).
+    pub fn input_cache(&self) -> Option<&Path> {
+        self.input_cache.as_deref()
+    }
+
+    /// Gets the maximum total size (in GiB) of the input cache (if one is
+    /// configured).
+    pub fn input_cache_max_size(&self) -> Option<f64> {
+        self.input_cache_max_size
+    }
+
+    /// Gets the maximum number of seconds an input may go unused before it is
+    /// evicted from the input cache (if one is configured).
+    pub fn input_cache_max_age(&self) -> Option<u64> {
+        self.input_cache_max_age
+    }
+
+    /// Gets the directory to create the temporary directories of tasks in (if
+    /// one is configured).
+    pub fn temp_dir_root(&self) -> Option<&Path> {
+        self.temp_dir_root.as_deref()
+    }
+
+    /// Gets the directory to create the working directories of tasks in (if
+    /// one is configured).
+    pub fn work_dir_root(&self) -> Option<&Path> {
+        self.work_dir_root.as_deref()
+    }
+
+    /// Gets the minimum free disk space (in GiB) required in each directory
+    /// used by a task (if one is configured).
+    pub fn min_free_disk(&self) -> Option<f64> {
+        self.min_free_disk
+    }
+
+    /// Gets when to preserve the working directories of tasks once they
+    /// complete.
+    pub fn preserve_work_dirs(&self) -> PreserveWorkDirs {
+        self.preserve_work_dirs
+    }
+
+    /// Gets whether to run the commands of executions through a generated
+    /// Bash script.
+    pub fn script_wrapper(&self) -> bool {
+        self.script_wrapper
+    }
+
+    /// Gets the directory to write the structured logs of task attempts to
+    /// (if one is configured).
+    pub fn logs_root(&self) -> Option<&Path> {
+        self.logs_root.as_deref()
+    }
+
+    /// Gets whether to remove the containers and working directories left
+    /// behind by interrupted tasks when the backend is initialized.
+    pub fn reconcile(&self) -> bool {
+        self.reconcile
+    }
+
+    /// Gets whether the still-running containers of interrupted tasks are kept
+    /// when reconciling so that they can be reattached to.
+    pub fn reattach(&self) -> bool {
+        self.reattach
+    }
+
+    /// Gets the size (in KiB) of the end of each execution's stdout and stderr
+    /// to keep for reporting failures.
+    pub fn output_tail(&self) -> u64 {
+        self.output_tail
+    }
+
+    /// Gets how the output of containers is decoded into text.
+    pub fn output_encoding(&self) -> OutputEncoding {
+        self.output_encoding
+    }
+
+    /// Gets the number of seconds a canceled task's container is given to exit
+    /// before it is killed (if one is configured).
+    pub fn stop_grace_period(&self) -> Option<u64> {
+        self.stop_grace_period
+    }
+
+    /// Gets whether containers share the host's PID namespace.
+    pub fn host_pid(&self) -> bool {
+        self.host_pid
+    }
+
+    /// Gets whether containers share the host's IPC namespace.
+    pub fn host_ipc(&self) -> bool {
+        self.host_ipc
+    }
+
+    /// Gets whether containers share the host's UTS namespace.
+    pub fn host_uts(&self) -> bool {
+        self.host_uts
+    }
+
+    /// Gets whether to run an init process within containers (if
+    /// configured).
+    pub fn init(&self) -> Option<bool> {
+        self.init
+    }
+
+    /// Gets the niceness of containers (if configured).
+    pub fn nice(&self) -> Option<i32> {
+        self.nice
+    }
+
+    /// Gets the IO priority level of containers (if configured).
+    pub fn io_priority(&self) -> Option<u8> {
+        self.io_priority
+    }
+
+    /// Gets the CPUs containers may run on (if configured).
+    pub fn cpuset(&self) -> Option<&str> {
+        self.cpuset.as_deref()
+    }
+
+    /// Gets the patterns of the host environment variables passed through to
+    /// containers.
+    pub fn pass_env(&self) -> &[String] {
+        &self.pass_env
+    }
+
+    /// Gets the version requirement of the Docker daemon (if one is
+    /// configured).
+    pub fn min_version(&self) -> Option<&str> {
+        self.min_version.as_deref()
+    }
+
+    /// Gets whether the backend refuses to run images that are not pinned to a
+    /// digest.
+    pub fn require_digests(&self) -> bool {
+        self.require_digests
+    }
+
+    /// Gets the digests of the images that are trusted to run.
+    ///
+    /// An empty list means that images with any digest may run.
+    pub fn trusted_digests(&self) -> &[String] {
+        &self.trusted_digests
+    }
+
+    /// Gets the images that are deprecated keyed by image reference.
+    pub fn deprecated_images(&self) -> &HashMap<String, Deprecation> {
+        &self.deprecated_images
+    }
+
+    /// Gets the labels images are required to have to be run.
+    pub fn required_labels(&self) -> &[String] {
+        &self.required_labels
+    }
 }
 
 impl Default for Config {
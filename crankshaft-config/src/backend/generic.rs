@@ -5,7 +5,6 @@ use std::collections::HashMap;
 use std::sync::LazyLock;
 
 use bon::Builder;
-use bon::builder;
 use regex::Captures;
 use regex::Regex;
 use serde::Deserialize;
@@ -66,6 +65,15 @@ pub struct Config {
     driver: driver::Config,
 
     /// The script used for job submission.
+    ///
+    /// The script may reference the image of an execution as `~{image}` to
+    /// run the command within a container on the compute node (e.g.,
+    /// `singularity exec docker://~{image} ~{command}`).
+    ///
+    /// Alternatively, the script may reference `~{docker_run}`, a complete
+    /// `docker run` command for the execution rendered by the engine with the
+    /// same image, mounts, environment, working directory, and resource
+    /// limits as the Docker backend would use (e.g., `srun ~{docker_run}`).
     #[builder(into)]
     submit: String,
 
@@ -139,6 +147,18 @@ impl Config {
         &self.submit
     }
 
+    /// Gets whether the submit command references the image of an execution
+    /// (`~{image}` or `~{docker_run}`).
+    pub fn uses_image(&self) -> bool {
+        self.submit.contains("~{image}") || self.uses_docker_run()
+    }
+
+    /// Gets whether the submit command references the rendered `docker run`
+    /// command of an execution (`~{docker_run}`).
+    pub fn uses_docker_run(&self) -> bool {
+        self.submit.contains("~{docker_run}")
+    }
+
     /// Gets the job id regex.
     pub fn job_id_regex(&self) -> Option<&str> {
         self.job_id_regex.as_deref()
@@ -231,5 +251,6 @@ mod tests {
         assert!(demo.monitor_frequency().is_none());
         assert_eq!(demo.kill(), "echo 'killing'");
         assert!(demo.attributes().is_empty());
+        assert!(!demo.uses_image());
     }
 }
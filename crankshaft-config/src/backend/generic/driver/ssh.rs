@@ -1,5 +1,8 @@
 //! Configuration related to an SSH-based command drivers.
 
+use std::path::Path;
+use std::path::PathBuf;
+
 use bon::Builder;
 use serde::Deserialize;
 use serde::Serialize;
@@ -20,6 +23,22 @@ pub struct Config {
     /// The SSH username.
     #[builder(into)]
     username: Option<String>,
+
+    /// The path to the private key to authenticate with.
+    ///
+    /// If this is not set, the identity within the SSH agent is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    key: Option<PathBuf>,
+
+    /// A directory on the remote host for files that are reused between
+    /// tasks (e.g., images).
+    ///
+    /// The directory is made available to commands as the `~{cache_dir}`
+    /// substitution.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    cache_dir: Option<String>,
 }
 
 impl Config {
@@ -38,6 +57,16 @@ impl Config {
         self.username.as_deref()
     }
 
+    /// Gets the path to the private key (if available).
+    pub fn key(&self) -> Option<&Path> {
+        self.key.as_deref()
+    }
+
+    /// Gets the directory on the remote host for cached files (if available).
+    pub fn cache_dir(&self) -> Option<&str> {
+        self.cache_dir.as_deref()
+    }
+
     /// Converts the configuration into its parts.
     pub fn into_parts(self) -> (String, u16, Option<String>) {
         (self.host, self.port, self.username)
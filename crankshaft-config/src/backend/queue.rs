@@ -0,0 +1,39 @@
+//! Configuration options related to limiting the number of queued tasks.
+
+use bon::Builder;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Limits on the number of tasks waiting to run on a backend.
+///
+/// Tasks that have been submitted but cannot yet run (because the backend is
+/// already running its maximum number of tasks) are queued. Without limits,
+/// the queue grows without bound; with them, submissions are rejected once
+/// the queue is full so that callers can apply backpressure upstream.
+#[derive(Builder, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[builder(builder_type = Builder)]
+pub struct Queue {
+    /// The maximum number of tasks that may be queued.
+    max_depth: usize,
+
+    /// The maximum number of milliseconds an asynchronous submission waits
+    /// for space in the queue before being rejected.
+    ///
+    /// If not set, submissions are rejected immediately when the queue is
+    /// full.
+    timeout: Option<u64>,
+}
+
+impl Queue {
+    /// Gets the maximum number of tasks that may be queued.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Gets the maximum number of milliseconds a submission waits for space in
+    /// the queue.
+    pub fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+}
@@ -19,6 +19,8 @@ use serde::Deserialize;
 use serde::Serialize;
 
 pub mod backend;
+pub mod size;
+pub mod telemetry;
 
 /// The prefix for any environment variables that influence the configuration of
 /// Crankshaft.
@@ -48,6 +50,10 @@ pub struct Config {
     /// All registered backends.
     #[builder(into)]
     backends: Vec<backend::Config>,
+
+    /// Anonymous usage statistics (opt-in).
+    #[builder(into)]
+    telemetry: Option<telemetry::Telemetry>,
 }
 
 impl Config {
@@ -56,6 +62,11 @@ impl Config {
         self.backends.as_slice()
     }
 
+    /// Gets the anonymous usage statistics settings (if enabled).
+    pub fn telemetry(&self) -> Option<&telemetry::Telemetry> {
+        self.telemetry.as_ref()
+    }
+
     /// Consumes `self` and returns the backends.
     pub fn into_backends(self) -> impl Iterator<Item = backend::Config> {
         self.backends.into_iter()
@@ -144,4 +155,23 @@ mod tests {
         assert_eq!(backend.defaults().unwrap().cpu(), Some(1.0));
         assert_eq!(backend.defaults().unwrap().ram(), Some(1.0));
     }
+
+    #[test]
+    fn loading_config_holds_adaptive_fields() {
+        let config = Config::fixture("example.toml").unwrap();
+        let adaptive = config.backends[2].adaptive().unwrap();
+
+        assert_eq!(adaptive.max_load_per_cpu(), Some(1.5));
+        assert_eq!(adaptive.max_cpu_pressure(), None);
+        assert_eq!(adaptive.min_tasks(), 2);
+    }
+
+    #[test]
+    fn loading_config_holds_fallback() {
+        let config = Config::fixture("example.toml").unwrap();
+        let fallback = config.backends[0].fallback().unwrap();
+
+        assert!(fallback.as_docker().unwrap().cleanup());
+        assert!(config.backends[1].fallback().is_none());
+    }
 }
@@ -0,0 +1,125 @@
+//! Human-readable sizes of memory and disk.
+//!
+//! Sizes are represented throughout Crankshaft as a (possibly fractional)
+//! number of gibibytes (GiB). When written by hand, a size may be either a
+//! bare number of gibibytes (e.g., `4` or `0.5`) or a number followed by a
+//! unit (e.g., `"4GiB"`, `"512M"`, or `"1.5 TB"`).
+//!
+//! Units with an `i` (`Ki`, `Mi`, `Gi`, `Ti`) are binary (powers of 1024)
+//! while units without one (`K`, `M`, `G`, `T`) are decimal (powers of 1000).
+//! A trailing `B` is optional and units are case-insensitive.
+
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serializer;
+use thiserror::Error;
+
+/// The number of bytes in a gibibyte.
+const BYTES_PER_GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// An error parsing a size.
+#[derive(Debug, Error)]
+#[error(
+    "invalid size `{0}`: expected a non-negative number of GiB or a number with a unit (e.g., \
+     `4GiB` or `512M`)"
+)]
+pub struct InvalidSize(String);
+
+/// Parses a human-readable size into a number of gibibytes.
+pub fn parse(value: &str) -> Result<f64, InvalidSize> {
+    let invalid = || InvalidSize(value.to_string());
+
+    let trimmed = value.trim();
+    let split = trimmed
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    let number = number.trim().parse::<f64>().map_err(|_| invalid())?;
+    if !number.is_finite() || number < 0.0 {
+        return Err(invalid());
+    }
+
+    let unit = unit.to_ascii_lowercase();
+    let unit = unit.strip_suffix('b').unwrap_or(&unit);
+    let bytes: f64 = match unit {
+        // A bare number is already in gibibytes
+        "" if split == trimmed.len() => return Ok(number),
+        "" => 1.0,
+        "k" => 1e3,
+        "m" => 1e6,
+        "g" => 1e9,
+        "t" => 1e12,
+        "ki" => 1024.0,
+        "mi" => 1024.0 * 1024.0,
+        "gi" => BYTES_PER_GIB,
+        "ti" => 1024.0 * BYTES_PER_GIB,
+        _ => return Err(invalid()),
+    };
+
+    Ok(number * bytes / BYTES_PER_GIB)
+}
+
+/// Serialization of an optional size in gibibytes.
+///
+/// Sizes are serialized as a number of gibibytes and deserialized from either
+/// a number of gibibytes or a human-readable string (see [`parse()`]).
+pub mod gib {
+    use super::*;
+
+    /// A size as written in a configuration file.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        /// A number of gibibytes.
+        Number(f64),
+
+        /// A human-readable size.
+        Text(String),
+    }
+
+    /// Serializes the size as a number of gibibytes.
+    pub fn serialize<S: Serializer>(size: &Option<f64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match size {
+            Some(size) => serializer.serialize_some(size),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes the size from a number of gibibytes or a human-readable
+    /// string.
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<f64>, D::Error> {
+        match Option::<Raw>::deserialize(deserializer)? {
+            Some(Raw::Number(size)) if size.is_finite() && size >= 0.0 => Ok(Some(size)),
+            Some(Raw::Number(size)) => Err(serde::de::Error::custom(InvalidSize(size.to_string()))),
+            Some(Raw::Text(size)) => parse(&size).map(Some).map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse("4").unwrap(), 4.0);
+        assert_eq!(parse("0.5").unwrap(), 0.5);
+        assert_eq!(parse("4GiB").unwrap(), 4.0);
+        assert_eq!(parse("4 Gi").unwrap(), 4.0);
+        assert_eq!(parse("512MiB").unwrap(), 0.5);
+        assert_eq!(parse("512M").unwrap(), 512e6 / BYTES_PER_GIB);
+        assert_eq!(parse("2tb").unwrap(), 2e12 / BYTES_PER_GIB);
+        assert_eq!(parse("1073741824B").unwrap(), 1.0);
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        for value in ["", "GiB", "-1GiB", "4 parsecs", "NaN", "inf"] {
+            assert!(parse(value).is_err(), "`{value}` should be invalid");
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Configuration related to anonymous usage statistics.
+
+use bon::Builder;
+use serde::Deserialize;
+use serde::Serialize;
+use url::Url;
+
+/// The default number of seconds between reports of usage statistics.
+pub const DEFAULT_INTERVAL: u64 = 3600;
+
+/// Anonymous usage statistics.
+///
+/// Telemetry is strictly opt-in: nothing is reported unless this section is
+/// present in the configuration. When enabled, only aggregate counters are
+/// reported (the number of tasks run per backend type and the number of
+/// failures per category); no task names, images, commands, or host
+/// information are ever included.
+#[derive(Builder, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[builder(builder_type = Builder)]
+pub struct Telemetry {
+    /// The URL that reports are posted to.
+    endpoint: Url,
+
+    /// The number of seconds between reports.
+    interval: Option<u64>,
+}
+
+impl Telemetry {
+    /// Gets the URL that reports are posted to.
+    pub fn endpoint(&self) -> &Url {
+        &self.endpoint
+    }
+
+    /// Gets the number of seconds between reports.
+    pub fn interval(&self) -> u64 {
+        self.interval.unwrap_or(DEFAULT_INTERVAL)
+    }
+}
@@ -4,25 +4,437 @@
 #![allow(missing_docs)]
 #![allow(clippy::missing_docs_in_private_items)]
 
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
 use anyhow::Result;
 use anyhow::anyhow;
+use bollard::container::LogOutput;
+use bollard::models::ContainerSummary;
+use bollard::models::ContainerSummaryStateEnum;
+use bollard::models::HostConfig;
+use bollard::query_parameters::InspectContainerOptions;
+use bollard::query_parameters::LogsOptions;
 use clap::Parser;
 use clap::Subcommand;
 use clap_verbosity_flag::Verbosity;
+use crankshaft_docker::Bind;
+use crankshaft_docker::BuildOptions;
 use crankshaft_docker::Container;
 use crankshaft_docker::Docker;
+use crankshaft_docker::EvictionPolicy;
+use crankshaft_docker::ImageCache;
+use crankshaft_docker::ImageMetadata;
+use crankshaft_docker::ImageSource;
+use crankshaft_docker::ImageUsage;
+use crankshaft_docker::Locale;
+use crankshaft_docker::NamespaceOptions;
+use crankshaft_docker::PullOptions;
+use crankshaft_docker::PullSource;
+use crankshaft_docker::Requirement;
+use crankshaft_docker::SchedulingOptions;
+use crankshaft_docker::SecurityOptions;
+use crankshaft_docker::Tmpfs;
+use crankshaft_docker::Version;
+use crankshaft_docker::container;
+use crankshaft_docker::container::Entrypoint;
+use futures::StreamExt as _;
+use indexmap::IndexMap;
+use indicatif::ProgressBar;
+use indicatif::ProgressStyle;
+use tokio::io::AsyncWriteExt as _;
 use tracing_log::AsTrace;
 use tracing_subscriber::EnvFilter;
 
+/// Parses a build argument of the form `NAME=value`.
+fn parse_build_arg(arg: &str) -> Result<(String, String)> {
+    arg.split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| anyhow!("build argument `{arg}` must be of the form `NAME=value`"))
+}
+
 #[derive(clap::Parser)]
 struct Args {
     #[command(subcommand)]
     command: Command,
 
+    /// The format of the result printed by a command.
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
     #[command(flatten)]
     verbose: Verbosity,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// The files to write a container's output streams to.
+#[derive(clap::Args)]
+struct Logs {
+    /// The file to write the container's stdout stream to.
+    #[arg(long)]
+    stdout: Option<PathBuf>,
+
+    /// The file to write the container's stderr stream to.
+    #[arg(long)]
+    stderr: Option<PathBuf>,
+}
+
+/// A specification of a task to run in a container.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct TaskSpec {
+    /// The name of the container (defaults to the name of the spec file).
+    name: Option<String>,
+
+    /// The image (e.g., `ubuntu:latest`).
+    image: String,
+
+    /// The program to run (if not set, the image's entrypoint is honored).
+    program: Option<String>,
+
+    #[serde(default)]
+    args: Vec<String>,
+
+    #[serde(default)]
+    env: IndexMap<String, String>,
+
+    #[serde(default)]
+    env_files: Vec<PathBuf>,
+
+    /// Bind mounts in the `SOURCE:TARGET[:ro|rw]` or
+    /// `type=bind,source=SOURCE,target=TARGET[,readonly]` syntax.
+    #[serde(default)]
+    binds: Vec<String>,
+
+    /// Whether to create the missing sources of bind mounts as directories.
+    #[serde(default)]
+    create_bind_sources: bool,
+
+    #[serde(default)]
+    tmpfs: Vec<Tmpfs>,
+
+    work_dir: Option<String>,
+
+    /// A directory on the host to bind as the working directory (created if
+    /// it does not exist).
+    host_work_dir: Option<PathBuf>,
+
+    /// The permissions of the host working directory if it is created (e.g.,
+    /// `0o750`).
+    work_dir_mode: Option<u32>,
+
+    /// A directory on the host to bind the container's temporary directories
+    /// within.
+    scratch_dir: Option<PathBuf>,
+
+    user: Option<String>,
+
+    network: Option<String>,
+
+    /// The CPU limit (in cores).
+    cpus: Option<f64>,
+
+    /// The memory limit (in bytes).
+    memory: Option<i64>,
+
+    #[serde(default)]
+    security: SecurityOptions,
+
+    #[serde(default)]
+    namespaces: NamespaceOptions,
+
+    #[serde(default)]
+    scheduling: SchedulingOptions,
+
+    locale: Option<Locale>,
+}
+
+impl TaskSpec {
+    /// Reads a task specification from a YAML (or JSON) file.
+    fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read task spec `{}`", path.display()))?;
+        serde_yaml::from_str(&contents)
+            .with_context(|| format!("invalid task spec `{}`", path.display()))
+    }
+
+    /// Reads one or more task specifications from a YAML (or JSON) file.
+    ///
+    /// The file may contain either a single specification or a list of them.
+    /// Each specification is paired with the name of its container.
+    fn read_all(path: &Path) -> Result<Vec<(String, Self)>> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read task spec `{}`", path.display()))?;
+        let value: serde_yaml::Value = serde_yaml::from_str(&contents)
+            .with_context(|| format!("invalid task spec `{}`", path.display()))?;
+
+        if !value.is_sequence() {
+            let spec: Self = serde_yaml::from_value(value)
+                .with_context(|| format!("invalid task spec `{}`", path.display()))?;
+            return Ok(vec![(spec.name(path), spec)]);
+        }
+
+        let specs: Vec<Self> = serde_yaml::from_value(value)
+            .with_context(|| format!("invalid task spec `{}`", path.display()))?;
+        Ok(specs
+            .into_iter()
+            .enumerate()
+            .map(|(i, spec)| {
+                let name = match &spec.name {
+                    Some(name) => name.clone(),
+                    None => format!("{name}-{i}", name = spec.name(path)),
+                };
+                (name, spec)
+            })
+            .collect())
+    }
+
+    /// Gets the name of the container for the task.
+    fn name(&self, path: &Path) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("task"))
+        })
+    }
+
+    /// Converts the specification into a container builder.
+    fn into_builder(self, docker: &Docker) -> Result<container::Builder> {
+        let mut builder = docker
+            .container_builder()
+            .image(self.image)
+            .args(self.args)
+            .envs(self.env)
+            .security(self.security)
+            .namespaces(self.namespaces)
+            .scheduling(self.scheduling)
+            .create_bind_sources(self.create_bind_sources)
+            .host_config(HostConfig {
+                nano_cpus: self.cpus.map(|cpus| (cpus * 1_000_000_000.0) as i64),
+                memory: self.memory,
+                ..Default::default()
+            });
+
+        builder = match self.program {
+            Some(program) => builder.program(program),
+            None => builder.entrypoint(Entrypoint::Honor),
+        };
+
+        for path in self.env_files {
+            builder = builder.env_file(path);
+        }
+
+        for bind in self.binds {
+            builder = builder.mount(bind.parse::<Bind>()?);
+        }
+
+        for tmpfs in self.tmpfs {
+            builder = builder.mount(tmpfs);
+        }
+
+        if let Some(work_dir) = self.work_dir {
+            builder = builder.work_dir(work_dir);
+        }
+
+        if let Some(host_work_dir) = self.host_work_dir {
+            builder = builder.host_work_dir(host_work_dir);
+        }
+
+        if let Some(mode) = self.work_dir_mode {
+            builder = builder.work_dir_mode(mode);
+        }
+
+        if let Some(scratch_dir) = self.scratch_dir {
+            builder = builder.scratch_dir(scratch_dir);
+        }
+
+        if let Some(user) = self.user {
+            builder = builder.user(user);
+        }
+
+        if let Some(network) = self.network {
+            builder = builder.network(network);
+        }
+
+        if let Some(locale) = self.locale {
+            builder = builder.locale(locale);
+        }
+
+        Ok(builder)
+    }
+}
+
+/// The result of running a container.
+#[derive(serde::Serialize)]
+struct RunResult {
+    name: String,
+    exit_code: Option<i32>,
+    success: bool,
+    duration_ms: u128,
+    stdout: Option<PathBuf>,
+    stderr: Option<PathBuf>,
+}
+
+/// The result of running a task as part of a batch.
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+enum BatchTaskResult {
+    /// The task ran to completion.
+    Completed(RunResult),
+
+    /// The task could not be run.
+    Errored { name: String, error: String },
+}
+
+impl BatchTaskResult {
+    /// Determines if the task succeeded.
+    fn success(&self) -> bool {
+        matches!(self, Self::Completed(result) if result.success)
+    }
+}
+
+/// The results of running a batch of tasks.
+#[derive(serde::Serialize)]
+struct BatchResult {
+    tasks: Vec<BatchTaskResult>,
+    succeeded: usize,
+    failed: usize,
+    duration_ms: u128,
+}
+
+/// The features of the Docker daemon reported by the `check` subcommand.
+#[derive(serde::Serialize)]
+struct CheckResult {
+    version: Option<String>,
+    os: Option<String>,
+    architecture: Option<String>,
+    cpus: Option<i64>,
+    memory: Option<i64>,
+    cgroup_version: Option<String>,
+    cgroup_driver: Option<String>,
+    rootless: bool,
+    user_namespaces: bool,
+    seccomp: bool,
+    selinux: bool,
+    runtimes: Vec<String>,
+    default_runtime: Option<String>,
+    swarm: Option<String>,
+}
+
+/// Checks the Docker daemon and prints the features it supports.
+async fn check(docker: Docker, min_version: Option<String>, output: OutputFormat) -> Result<()> {
+    let info = docker
+        .info()
+        .await
+        .context("failed to connect to the Docker daemon")?;
+
+    let security = info.security_options.unwrap_or_default();
+    let has_option = |name: &str| {
+        security
+            .iter()
+            .any(|option| option.split(',').any(|kv| kv == format!("name={name}")))
+    };
+
+    let mut runtimes = info
+        .runtimes
+        .map(|r| r.into_keys().collect::<Vec<_>>())
+        .unwrap_or_default();
+    runtimes.sort();
+
+    let result = CheckResult {
+        version: info.server_version,
+        os: info.operating_system,
+        architecture: info.architecture,
+        cpus: info.ncpu,
+        memory: info.mem_total,
+        cgroup_version: info.cgroup_version.map(|v| v.to_string()),
+        cgroup_driver: info.cgroup_driver.map(|d| d.to_string()),
+        rootless: has_option("rootless"),
+        user_namespaces: has_option("userns"),
+        seccomp: has_option("seccomp"),
+        selinux: has_option("selinux"),
+        runtimes,
+        default_runtime: info.default_runtime,
+        swarm: info
+            .swarm
+            .and_then(|s| s.local_node_state)
+            .map(|s| s.to_string()),
+    };
+
+    match output {
+        OutputFormat::Text => {
+            /// Formats an optional value, showing `<unknown>` if it is missing.
+            fn show<T: std::fmt::Display>(value: Option<T>) -> String {
+                value
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|| String::from("<unknown>"))
+            }
+
+            println!("version: {}", show(result.version.as_ref()));
+            println!("os: {}", show(result.os.as_ref()));
+            println!("architecture: {}", show(result.architecture.as_ref()));
+            println!("cpus: {}", show(result.cpus));
+            println!("memory: {}", show(result.memory));
+            println!(
+                "cgroups: {} ({})",
+                show(result.cgroup_version.as_ref()),
+                show(result.cgroup_driver.as_ref())
+            );
+            println!("rootless: {}", result.rootless);
+            println!("user namespaces: {}", result.user_namespaces);
+            println!("seccomp: {}", result.seccomp);
+            println!("selinux: {}", result.selinux);
+            println!("runtimes: {}", result.runtimes.join(", "));
+            println!("default runtime: {}", show(result.default_runtime.as_ref()));
+            println!("swarm: {}", show(result.swarm.as_ref()));
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&result)?),
+    }
+
+    if let Some(min_version) = min_version {
+        let requirement: Requirement = min_version.parse()?;
+        let version: Version = result.version.as_deref().unwrap_or_default().parse()?;
+        if !requirement.matches(&version) {
+            return Err(anyhow!(
+                "Docker daemon version `{version}` does not satisfy the requirement \
+                 `{requirement}`"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The result of ensuring an image exists.
+#[derive(serde::Serialize)]
+struct EnsureImageResult {
+    image: String,
+    source: Option<String>,
+    id: Option<String>,
+    digests: Vec<String>,
+    duration_ms: u128,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum CatalogFormat {
+    Json,
+    Csv,
+}
+
+#[derive(serde::Serialize)]
+struct CatalogEntry {
+    image: String,
+    #[serde(flatten)]
+    metadata: Option<ImageMetadata>,
+    error: Option<String>,
+}
+
 #[derive(Subcommand)]
 enum Command {
     /// Creates a container.
@@ -51,6 +463,62 @@ enum Command {
         #[arg(short, long, default_value = "latest")]
         /// The tag for the image.
         tag: String,
+
+        /// Prints the request that would create the container (as JSON)
+        /// instead of running it.
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        logs: Logs,
+    },
+    /// Runs an image's entrypoint (or default command) with the provided
+    /// arguments and prints the result.
+    RunImage {
+        /// The name of the image.
+        image: String,
+
+        /// The name of the container.
+        name: String,
+
+        /// The arguments to pass to the image's entrypoint.
+        args: Vec<String>,
+
+        #[arg(short, long, default_value = "latest")]
+        /// The tag for the image.
+        tag: String,
+
+        /// Prints the request that would create the container (as JSON)
+        /// instead of running it.
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        logs: Logs,
+    },
+    /// Starts an interactive shell within an image.
+    ///
+    /// The shell is connected to the terminal through a pseudo-terminal. If a
+    /// task specification is given, the shell runs with the task's mounts,
+    /// environment, and resources. The container is removed when the shell
+    /// exits.
+    Shell {
+        /// The name of the image.
+        #[arg(required_unless_present = "file")]
+        image: Option<String>,
+
+        /// The path to a task specification to take the image and container
+        /// options from.
+        #[arg(short, long, conflicts_with = "image")]
+        file: Option<PathBuf>,
+
+        #[arg(short, long, default_value = "latest")]
+        /// The tag for the image.
+        tag: String,
+
+        /// The shell to run.
+        #[arg(short, long, default_value = "/bin/sh")]
+        shell: String,
     },
     /// Removes a container.
     RemoveContainer {
@@ -68,6 +536,83 @@ enum Command {
         ///
         /// If a tag is not specified, a default tag of `latest` is used.
         image: String,
+
+        /// The architecture of the image to pull (e.g., `amd64` or `arm64`).
+        ///
+        /// Defaults to the architecture of the Docker daemon.
+        #[arg(long)]
+        arch: Option<String>,
+
+        /// A source to pull the image from before its own registry (e.g.,
+        /// `mirror.internal/library/ubuntu:22.04` or
+        /// `docker-archive:ubuntu.tar`).
+        ///
+        /// Sources are tried in the order they are given.
+        #[arg(long = "source")]
+        sources: Vec<PullSource>,
+    },
+
+    /// Builds an image from a build context directory.
+    BuildImage {
+        /// The path to the build context directory.
+        context: PathBuf,
+
+        /// The tag for the built image.
+        tag: String,
+
+        /// The path to the Dockerfile, relative to the build context.
+        #[arg(short, long)]
+        file: Option<String>,
+
+        /// A build argument (e.g., `NAME=value`).
+        #[arg(long = "build-arg", value_parser = parse_build_arg)]
+        args: Vec<(String, String)>,
+
+        /// Whether to build without using the build cache.
+        #[arg(long, default_value_t = false)]
+        no_cache: bool,
+    },
+
+    /// Loads the images within an archive or OCI image layout without
+    /// contacting a registry and prints their references.
+    LoadImage {
+        /// The source of the images (`docker-archive:<path>`,
+        /// `oci-archive:<path>`, or `oci:<path>`).
+        source: ImageSource,
+    },
+
+    /// Unpacks the filesystem of an image into a directory for inspection.
+    ///
+    /// The image is pulled if it does not exist. A directory can be built back
+    /// into an image with `build-image`.
+    UnpackImage {
+        /// The name of the image.
+        image: String,
+
+        /// The directory to unpack the image into.
+        destination: PathBuf,
+    },
+
+    /// Inspects images and prints a catalog of their metadata.
+    Catalog {
+        /// The images to include in the catalog.
+        images: Vec<String>,
+
+        /// A file listing additional images to include (one per line).
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+
+        /// Whether to include every image stored in the Docker daemon.
+        #[arg(short, long, default_value_t = false)]
+        all: bool,
+
+        /// Whether to pull images that are not stored in the Docker daemon.
+        #[arg(short, long, default_value_t = false)]
+        pull: bool,
+
+        /// The format of the catalog.
+        #[arg(long, value_enum, default_value_t = CatalogFormat::Json)]
+        format: CatalogFormat,
     },
 
     /// Lists all images.
@@ -85,17 +630,780 @@ enum Command {
 
     /// Removes all images.
     RemoveAllImages,
+
+    /// Runs a task from a specification file.
+    ///
+    /// The specification is written in YAML (or JSON) and describes the
+    /// image, program, arguments, environment, mounts, and resources of the
+    /// task. The container is removed once the task completes.
+    RunTask {
+        /// The path to the task specification.
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Prints the request that would create the container (as JSON)
+        /// instead of running it.
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        logs: Logs,
+    },
+
+    /// Explains how each setting of a task's container is derived.
+    ///
+    /// Each argument, environment variable, mount, and limit of the request
+    /// that would create the container is attributed to the option, host
+    /// configuration, or environment file of the task specification that
+    /// produced it. The task is not run.
+    Explain {
+        /// The path to the task specification.
+        #[arg(short, long)]
+        file: PathBuf,
+    },
+
+    /// Compares two task specifications or result files and prints their
+    /// differences.
+    ///
+    /// Each file is read as YAML (or JSON), so task specifications, the
+    /// requests printed by `--dry-run`, and the results printed with `--output
+    /// json` (e.g., image digests, commands, environments, mounts, and
+    /// resources) can be compared to answer what changed between a run that
+    /// worked and a run that failed.
+    Diff {
+        /// The path to the first file.
+        left: PathBuf,
+
+        /// The path to the second file.
+        right: PathBuf,
+    },
+
+    /// Manages long-running containers that commands are executed within.
+    Instance {
+        #[command(subcommand)]
+        command: InstanceCommand,
+    },
+
+    /// Checks the Docker daemon and reports the features it supports.
+    ///
+    /// Fails if the daemon cannot be reached or does not satisfy the version
+    /// requirement.
+    Check {
+        /// The version requirement of the Docker daemon (e.g., `24.0` or
+        /// `>=24.0`).
+        #[arg(long)]
+        min_version: Option<String>,
+    },
+
+    /// Manages the images stored in the Docker daemon.
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+
+    /// Lists the running containers of tasks launched by Crankshaft.
+    ///
+    /// Tasks are found by the labels the engine's Docker backend sets on their
+    /// containers, so tasks survive across restarts of the process that
+    /// launched them. The CPU usage and resident memory of each task are
+    /// sampled from `/proc` and are only reported if the Docker daemon runs
+    /// on this host.
+    Ps {
+        /// The number of milliseconds to sample CPU usage over.
+        #[arg(long, default_value_t = 500)]
+        interval_ms: u64,
+    },
+
+    /// Runs a batch of tasks from specification files concurrently.
+    ///
+    /// Each file may contain a single task specification or a list of them
+    /// (see `run-task`). The result of each task is reported along with a
+    /// summary; the command fails if any task fails.
+    Batch {
+        /// The paths to the task specifications.
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// The maximum number of tasks to run at once.
+        #[arg(short = 'j', long, default_value_t = 4)]
+        max_parallel: usize,
+
+        /// A directory to write the stdout and stderr streams of each task to.
+        #[arg(long)]
+        logs_dir: Option<PathBuf>,
+    },
+
+    /// Prints the output of a task launched by Crankshaft.
+    ///
+    /// The output is read from the task's running container (found by the
+    /// labels the engine's Docker backend sets on it) or, if the logs root
+    /// the backend writes structured logs to is given, from the log directory
+    /// of the task's latest attempt.
+    Logs {
+        /// The name of the task.
+        task: String,
+
+        /// Whether to keep printing output as the task writes it until the
+        /// task completes.
+        #[arg(short, long, default_value_t = false)]
+        follow: bool,
+
+        /// The logs root of the engine's Docker backend.
+        #[arg(long)]
+        logs_root: Option<PathBuf>,
+    },
 }
 
-async fn create_container(
+#[derive(Subcommand)]
+enum InstanceCommand {
+    /// Starts an instance from an image's entrypoint (or default command).
+    ///
+    /// The instance keeps running until it is stopped or its entrypoint
+    /// exits.
+    Start {
+        /// The name of the image.
+        image: String,
+
+        /// The name of the instance.
+        name: String,
+
+        /// The arguments to pass to the image's entrypoint.
+        args: Vec<String>,
+
+        #[arg(short, long, default_value = "latest")]
+        /// The tag for the image.
+        tag: String,
+    },
+
+    /// Stops an instance.
+    Stop {
+        /// The name of the instance.
+        name: String,
+
+        /// The number of seconds to wait for the instance to exit before
+        /// killing it.
+        #[arg(short, long)]
+        grace: Option<u64>,
+
+        /// Whether to remove the instance once it has stopped.
+        #[arg(long, default_value_t = false)]
+        remove: bool,
+    },
+
+    /// Lists instances.
+    List {
+        /// Whether to include instances that are not running.
+        #[arg(short, long, default_value_t = false)]
+        all: bool,
+    },
+
+    /// Executes a command within a running instance.
+    ///
+    /// The command's output is printed and the subcommand fails if the
+    /// command exits with a non-zero exit code.
+    Exec {
+        /// The name of the instance.
+        name: String,
+
+        /// The command to execute.
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Lists images with their sizes, when they were last used, and the
+    /// total size of the cache.
+    Ls,
+
+    /// Removes every image that no container was created from.
+    Clean {
+        /// Prints the images that would be removed instead of removing them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Removes images that no container was created from, least recently
+    /// used first, until the cache is within its limits.
+    #[command(group = clap::ArgGroup::new("limits").required(true).multiple(true))]
+    Prune {
+        /// The number of days after which an unused image is removed.
+        #[arg(long, group = "limits")]
+        max_age_days: Option<u64>,
+
+        /// The maximum total size of the cache in GiB.
+        #[arg(long, group = "limits")]
+        max_size_gib: Option<f64>,
+
+        /// Prints the images that would be removed instead of removing them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Formats a number of bytes for display.
+fn format_bytes(bytes: u64) -> String {
+    format!("{:.1}MiB", bytes as f64 / 1048576.0)
+}
+
+/// Formats a time (in seconds since the Unix epoch) for display.
+fn format_time(secs: i64) -> String {
+    chrono::DateTime::from_timestamp(secs, 0)
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default()
+}
+
+/// Prints images of the cache and their total size.
+fn print_images(images: &[&ImageUsage], output: OutputFormat) -> Result<()> {
+    match output {
+        OutputFormat::Text => {
+            println!("ID\tTAGS\tSIZE\tLAST USED\tCONTAINERS");
+            for image in images {
+                println!(
+                    "{id}\t{tags}\t{size}\t{last_used}\t{containers}",
+                    id = &image.id[..image.id.len().min(19)],
+                    tags = image.tags.join(","),
+                    size = format_bytes(image.size),
+                    last_used = format_time(image.last_used),
+                    containers = image.containers,
+                );
+            }
+
+            println!(
+                "total: {size} in {count} image(s)",
+                size = format_bytes(images.iter().map(|i| i.size).sum()),
+                count = images.len()
+            );
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(images)?),
+    }
+
+    Ok(())
+}
+
+/// Runs a `cache` subcommand.
+async fn cache(docker: Docker, command: CacheCommand, output: OutputFormat) -> Result<()> {
+    let (policy, dry_run) = match command {
+        CacheCommand::Ls => {
+            let mut images = docker.image_usage().await?;
+            images.sort_by_key(|i| std::cmp::Reverse(i.last_used));
+            return print_images(&images.iter().collect::<Vec<_>>(), output);
+        }
+        CacheCommand::Clean { dry_run } => (EvictionPolicy::default(), dry_run),
+        CacheCommand::Prune {
+            max_age_days,
+            max_size_gib,
+            dry_run,
+        } => {
+            let mut policy = EvictionPolicy::default();
+            if let Some(days) = max_age_days {
+                policy = policy.max_age(std::time::Duration::from_secs(days * 24 * 60 * 60));
+            }
+
+            if let Some(gib) = max_size_gib {
+                policy = policy.max_size((gib * 1073741824.0) as u64);
+            }
+
+            (policy, dry_run)
+        }
+    };
+
+    let evicted = if dry_run {
+        let images = docker.image_usage().await?;
+        policy
+            .select(&images, chrono::Utc::now().timestamp())
+            .into_iter()
+            .cloned()
+            .collect()
+    } else {
+        docker.evict_images(&policy).await?
+    };
+
+    print_images(&evicted.iter().collect::<Vec<_>>(), output)
+}
+
+/// An instance as reported by the `instance list` subcommand.
+#[derive(serde::Serialize)]
+struct InstanceSummary {
+    id: String,
+    names: Vec<String>,
+    image: Option<String>,
+    state: Option<String>,
+    status: Option<String>,
+}
+
+/// The result of executing a command within an instance.
+#[derive(serde::Serialize)]
+struct ExecResult {
+    name: String,
+    exit_code: Option<i32>,
+    success: bool,
+    stdout: String,
+    stderr: String,
+}
+
+/// Runs an `instance` subcommand.
+async fn instance(docker: Docker, command: InstanceCommand, output: OutputFormat) -> Result<()> {
+    match command {
+        InstanceCommand::Start {
+            image,
+            name,
+            args,
+            tag,
+        } => {
+            let image = format!("{image}:{tag}");
+            pull_image(&docker, &image).await?;
+
+            let container = docker
+                .container_builder()
+                .image(&image)
+                .entrypoint(Entrypoint::Honor)
+                .args(args)
+                .try_build(&name)
+                .await?;
+            container.start().await?;
+            println!("{id}", id = container.id());
+        }
+        InstanceCommand::Stop {
+            name,
+            grace,
+            remove,
+        } => {
+            let container = docker.container_from_name(name, None, None);
+            container
+                .stop(grace.map(std::time::Duration::from_secs))
+                .await?;
+
+            if remove {
+                container.remove().await?;
+            }
+        }
+        InstanceCommand::List { all } => {
+            let instances = docker
+                .list_containers(all)
+                .await?
+                .into_iter()
+                .map(|summary| InstanceSummary {
+                    id: summary.id.unwrap_or_default(),
+                    // Docker prefixes the names of containers with a `/`
+                    names: summary
+                        .names
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|name| name.trim_start_matches('/').to_string())
+                        .collect(),
+                    image: summary.image,
+                    state: summary.state.map(|s| s.to_string()),
+                    status: summary.status,
+                })
+                .collect::<Vec<_>>();
+
+            match output {
+                OutputFormat::Text => {
+                    for instance in instances {
+                        println!(
+                            "{id}\t{names}\t{image}\t{status}",
+                            id = &instance.id[..instance.id.len().min(12)],
+                            names = instance.names.join(","),
+                            image = instance.image.unwrap_or_default(),
+                            status = instance.status.unwrap_or_default(),
+                        );
+                    }
+                }
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&instances)?),
+            }
+        }
+        InstanceCommand::Exec { name, command } => {
+            let container = docker.container_from_name(&name, None, None);
+            let result = container.exec(command).await?;
+            let exit_code = result.status.code();
+
+            match output {
+                OutputFormat::Text => {
+                    let mut stdout = tokio::io::stdout();
+                    stdout.write_all(&result.stdout).await?;
+                    stdout.flush().await?;
+
+                    let mut stderr = tokio::io::stderr();
+                    stderr.write_all(&result.stderr).await?;
+                    stderr.flush().await?;
+                }
+                OutputFormat::Json => {
+                    let result = ExecResult {
+                        name,
+                        exit_code,
+                        success: result.status.success(),
+                        stdout: String::from_utf8_lossy(&result.stdout).into_owned(),
+                        stderr: String::from_utf8_lossy(&result.stderr).into_owned(),
+                    };
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                }
+            }
+
+            if !result.status.success() {
+                return Err(match exit_code {
+                    Some(code) => anyhow!("command exited with exit code {code}"),
+                    None => anyhow!("command was terminated by a signal"),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The label the engine's Docker backend sets on the containers it creates.
+const INSTANCE_LABEL: &str = "crankshaft.instance";
+
+/// The label the engine's Docker backend sets to the name of a container's
+/// task.
+const TASK_LABEL: &str = "crankshaft.task";
+
+/// A running task as reported by the `ps` subcommand.
+#[derive(serde::Serialize)]
+struct TaskProcess {
+    task: String,
+    container: String,
+    pid: Option<i64>,
+    elapsed_secs: Option<i64>,
+    cpu_percent: Option<f64>,
+    rss_bytes: Option<u64>,
+}
+
+/// Gets the number of clock ticks per second that CPU times in `/proc` are
+/// reported in.
+fn clock_ticks() -> f64 {
+    #[cfg(unix)]
+    {
+        // SAFETY: `sysconf` has no preconditions
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks > 0 {
+            return ticks as f64;
+        }
+    }
+
+    100.0
+}
+
+/// Reads the parent and CPU time (in clock ticks) of a process from
+/// `/proc/<pid>/stat`.
+fn process_stat(pid: i64) -> Option<(i64, u64)> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command of the process is parenthesized and may contain spaces
+    let fields = stat[stat.rfind(')')? + 1..]
+        .split_whitespace()
+        .collect::<Vec<_>>();
+    let ppid = fields.get(1)?.parse().ok()?;
+    let utime = fields.get(11)?.parse::<u64>().ok()?;
+    let stime = fields.get(12)?.parse::<u64>().ok()?;
+    Some((ppid, utime + stime))
+}
+
+/// Reads the resident memory (in bytes) of a process from
+/// `/proc/<pid>/status`.
+fn process_rss(pid: i64) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+/// Samples the CPU time (in clock ticks) and resident memory of the
+/// processes descended from each of the given processes (inclusive).
+fn sample_processes(roots: &[i64]) -> IndexMap<i64, (u64, u64)> {
+    let mut parents = IndexMap::new();
+    if let Ok(entries) = std::fs::read_dir("/proc") {
+        for entry in entries.flatten() {
+            let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse().ok()) else {
+                continue;
+            };
+
+            if let Some((ppid, _)) = process_stat(pid) {
+                parents.insert(pid, ppid);
+            }
+        }
+    }
+
+    let mut samples = IndexMap::new();
+    for &root in roots {
+        if !parents.contains_key(&root) {
+            continue;
+        }
+
+        let (mut ticks, mut rss) = (0, 0);
+        for &pid in parents.keys() {
+            // Walk up from the process to see if it descends from the root
+            let mut current = pid;
+            while current != root && current > 1 {
+                current = parents.get(&current).copied().unwrap_or(0);
+            }
+
+            if current == root {
+                ticks += process_stat(pid).map(|(_, t)| t).unwrap_or(0);
+                rss += process_rss(pid).unwrap_or(0);
+            }
+        }
+
+        samples.insert(root, (ticks, rss));
+    }
+
+    samples
+}
+
+/// Lists the running containers of tasks launched by Crankshaft.
+async fn ps(docker: Docker, interval_ms: u64, output: OutputFormat) -> Result<()> {
+    let mut tasks = Vec::new();
+    for summary in docker.list_labeled_containers(INSTANCE_LABEL).await? {
+        let id = summary.id.unwrap_or_default();
+        let details = docker
+            .inner()
+            .inspect_container(&id, None::<InspectContainerOptions>)
+            .await?;
+        let state = details.state.unwrap_or_default();
+        if state.running != Some(true) {
+            continue;
+        }
+
+        let container = details
+            .name
+            .map(|name| name.trim_start_matches('/').to_string())
+            .unwrap_or_else(|| id.clone());
+        let started = state
+            .started_at
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok());
+        tasks.push(TaskProcess {
+            task: summary
+                .labels
+                .and_then(|mut labels| labels.remove(TASK_LABEL))
+                .unwrap_or_else(|| container.clone()),
+            container,
+            // The daemon reports a PID of zero if the process is unknown
+            pid: state.pid.filter(|pid| *pid > 0),
+            elapsed_secs: started.map(|s| (chrono::Utc::now() - s.to_utc()).num_seconds()),
+            cpu_percent: None,
+            rss_bytes: None,
+        });
+    }
+
+    // Sample twice to compute the CPU usage over the interval
+    let pids = tasks.iter().filter_map(|t| t.pid).collect::<Vec<_>>();
+    let before = sample_processes(&pids);
+    let interval = std::time::Duration::from_millis(interval_ms.max(1));
+    tokio::time::sleep(interval).await;
+    let after = sample_processes(&pids);
+
+    for task in &mut tasks {
+        let Some(pid) = task.pid else { continue };
+        if let (Some((start, _)), Some((end, rss))) = (before.get(&pid), after.get(&pid)) {
+            let secs = end.saturating_sub(*start) as f64 / clock_ticks();
+            task.cpu_percent = Some(secs / interval.as_secs_f64() * 100.0);
+            task.rss_bytes = Some(*rss);
+        }
+    }
+
+    match output {
+        OutputFormat::Text => {
+            println!("TASK\tCONTAINER\tPID\tELAPSED\tCPU%\tRSS");
+            for task in tasks {
+                let or_dash = |value: Option<String>| value.unwrap_or_else(|| String::from("-"));
+                println!(
+                    "{task}\t{container}\t{pid}\t{elapsed}\t{cpu}\t{rss}",
+                    task = task.task,
+                    container = task.container,
+                    pid = or_dash(task.pid.map(|p| p.to_string())),
+                    elapsed = or_dash(task.elapsed_secs.map(|s| format!(
+                        "{h:02}:{m:02}:{s:02}",
+                        h = s / 3600,
+                        m = s / 60 % 60,
+                        s = s % 60
+                    ))),
+                    cpu = or_dash(task.cpu_percent.map(|c| format!("{c:.1}"))),
+                    rss = or_dash(
+                        task.rss_bytes
+                            .map(|b| format!("{:.1}MiB", b as f64 / 1048576.0))
+                    ),
+                );
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&tasks)?),
+    }
+
+    Ok(())
+}
+
+/// How often the `logs` subcommand checks a log directory for new output.
+const LOGS_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Gets the containers of a task, most recently created first.
+async fn task_containers(docker: &Docker, task: &str) -> Result<Vec<ContainerSummary>> {
+    let mut containers = docker
+        .list_labeled_containers(format!("{TASK_LABEL}={task}"))
+        .await?;
+    containers.sort_by_key(|c| std::cmp::Reverse(c.created));
+    Ok(containers)
+}
+
+/// Determines if a container is running.
+fn is_running(container: &ContainerSummary) -> bool {
+    container.state == Some(ContainerSummaryStateEnum::RUNNING)
+}
+
+/// Prints the output of a task's containers.
+///
+/// When following, the output of each container the task runs is printed
+/// until no container of the task is running.
+async fn follow_containers(docker: &Docker, task: &str, follow: bool) -> Result<()> {
+    let mut seen = std::collections::HashSet::new();
+    loop {
+        let containers = task_containers(docker, task).await?;
+        let container = if follow {
+            containers
+                .into_iter()
+                .find(|c| is_running(c) && !seen.contains(&c.id))
+        } else {
+            // Prefer a running container over one that has exited
+            let running = containers.iter().position(is_running);
+            containers.into_iter().nth(running.unwrap_or_default())
+        };
+
+        let Some(id) = container.and_then(|c| c.id) else {
+            if seen.is_empty() {
+                return Err(match follow {
+                    true => anyhow!("task `{task}` has no running containers"),
+                    false => anyhow!("task `{task}` has no containers"),
+                });
+            }
+
+            return Ok(());
+        };
+
+        seen.insert(Some(id.clone()));
+        let mut logs = docker.inner().logs(
+            &id,
+            Some(LogsOptions {
+                follow,
+                stdout: true,
+                stderr: true,
+                ..Default::default()
+            }),
+        );
+
+        let mut stdout = tokio::io::stdout();
+        let mut stderr = tokio::io::stderr();
+        while let Some(output) = logs.next().await {
+            match output? {
+                LogOutput::StdErr { message } => {
+                    stderr.write_all(&message).await?;
+                    stderr.flush().await?;
+                }
+                output => {
+                    stdout.write_all(&output.into_bytes()).await?;
+                    stdout.flush().await?;
+                }
+            }
+        }
+
+        if !follow {
+            return Ok(());
+        }
+    }
+}
+
+/// Gets the log directory of the latest attempt of a task.
+fn latest_attempt(root: &Path, task: &str) -> Result<PathBuf> {
+    let dir = root.join(task);
+    let entries = std::fs::read_dir(&dir)
+        .with_context(|| format!("failed to read log directory `{dir}`", dir = dir.display()))?;
+
+    let mut latest = None;
+    for entry in entries {
+        let name = entry?.file_name();
+        let attempt = name
+            .to_str()
+            .and_then(|n| n.strip_prefix("attempt-"))
+            .and_then(|n| n.parse::<u32>().ok());
+        if let Some(attempt) = attempt {
+            latest = latest.max(Some(attempt));
+        }
+    }
+
+    let attempt = latest
+        .ok_or_else(|| anyhow!("log directory `{dir}` has no attempts", dir = dir.display()))?;
+    Ok(dir.join(format!("attempt-{attempt}")))
+}
+
+/// Writes the contents of a file from an offset, advancing the offset past
+/// what was written.
+///
+/// A file that does not exist (yet) has no contents.
+fn copy_from(path: &Path, offset: &mut u64, out: &mut impl std::io::Write) -> Result<()> {
+    use std::io::Read as _;
+    use std::io::Seek as _;
+
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("failed to open `{path}`", path = path.display()));
+        }
+    };
+
+    file.seek(std::io::SeekFrom::Start(*offset))?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    out.write_all(&contents)?;
+    out.flush()?;
+    *offset += contents.len() as u64;
+    Ok(())
+}
+
+/// Prints the output of each execution within the log directory of a task's
+/// latest attempt.
+///
+/// When following, the output of the running execution is printed as it is
+/// written until the last execution has exited and no container of the task
+/// is running.
+async fn follow_log_dir(docker: &Docker, root: &Path, task: &str, follow: bool) -> Result<()> {
+    let attempt = latest_attempt(root, task)?;
+    let mut index = 0;
+    let mut offsets = [0, 0];
+    loop {
+        let dir = attempt.join(index.to_string());
+        copy_from(&dir.join("stdout"), &mut offsets[0], &mut std::io::stdout())?;
+        copy_from(&dir.join("stderr"), &mut offsets[1], &mut std::io::stderr())?;
+
+        // The exit code is written once an execution has exited
+        let exited = dir.join("exit_code").exists();
+        if exited && attempt.join((index + 1).to_string()).exists() {
+            index += 1;
+            offsets = [0, 0];
+            continue;
+        }
+
+        if !follow || (exited && !task_containers(docker, task).await?.iter().any(is_running)) {
+            return Ok(());
+        }
+
+        tokio::time::sleep(LOGS_POLL_INTERVAL).await;
+    }
+}
+
+fn container_builder(
     docker: Docker,
     image: impl AsRef<str>,
     tag: impl AsRef<str>,
-    name: impl Into<String>,
     program: impl Into<String>,
     args: impl IntoIterator<Item = impl Into<String>>,
-) -> Result<Container> {
-    Ok(docker
+) -> container::Builder {
+    docker
         .container_builder()
         .image(format!(
             "{image}:{tag}",
@@ -104,12 +1412,448 @@ async fn create_container(
         ))
         .program(program)
         .args(args)
+}
+
+async fn create_container(
+    docker: Docker,
+    image: impl AsRef<str>,
+    tag: impl AsRef<str>,
+    name: impl Into<String>,
+    program: impl Into<String>,
+    args: impl IntoIterator<Item = impl Into<String>>,
+) -> Result<Container> {
+    Ok(container_builder(docker, image, tag, program, args)
         .try_build(name)
         .await?)
 }
 
+/// Creates and runs a container, optionally removing it once it completes.
+async fn execute(
+    mut builder: container::Builder,
+    name: String,
+    logs: Logs,
+    remove: bool,
+) -> Result<RunResult> {
+    if let Some(path) = &logs.stdout {
+        builder = builder.stdout(path);
+    }
+
+    if let Some(path) = &logs.stderr {
+        builder = builder.stderr(path);
+    }
+
+    let start = std::time::Instant::now();
+    let container = builder.try_build(&name).await?;
+    let status = container.run(&name, || {}).await;
+
+    if remove {
+        container.remove().await?;
+    }
+
+    let status = status?;
+    Ok(RunResult {
+        name,
+        exit_code: status.code(),
+        success: status.success(),
+        duration_ms: start.elapsed().as_millis(),
+        stdout: logs.stdout,
+        stderr: logs.stderr,
+    })
+}
+
+/// Runs a batch of tasks concurrently, printing the result of each task and
+/// a summary.
+async fn batch(
+    docker: Docker,
+    files: Vec<PathBuf>,
+    max_parallel: usize,
+    logs_dir: Option<PathBuf>,
+    output: OutputFormat,
+) -> Result<()> {
+    let mut specs = Vec::new();
+    for file in &files {
+        specs.extend(TaskSpec::read_all(file)?);
+    }
+
+    if let Some(dir) = &logs_dir {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create logs directory `{}`", dir.display()))?;
+    }
+
+    let images = ImageCache::new(docker.clone());
+    let start = std::time::Instant::now();
+    let run = |name: String, spec: TaskSpec| {
+        let docker = docker.clone();
+        let images = images.clone();
+        let logs = Logs {
+            stdout: logs_dir.as_ref().map(|d| d.join(format!("{name}.stdout"))),
+            stderr: logs_dir.as_ref().map(|d| d.join(format!("{name}.stderr"))),
+        };
+
+        async move {
+            images.ensure(&spec.image).await?;
+            let builder = spec.into_builder(&docker)?;
+            execute(builder, name, logs, true).await
+        }
+    };
+
+    let tasks = futures::stream::iter(specs)
+        .map(|(name, spec)| {
+            let run = run(name.clone(), spec);
+            async move {
+                let task = match run.await {
+                    Ok(result) => BatchTaskResult::Completed(result),
+                    Err(e) => BatchTaskResult::Errored {
+                        name,
+                        error: format!("{e:#}"),
+                    },
+                };
+
+                if output == OutputFormat::Text {
+                    match &task {
+                        BatchTaskResult::Completed(result) => println!(
+                            "{name}: {outcome} (exit code {code}) in {ms}ms",
+                            name = result.name,
+                            outcome = if result.success {
+                                "succeeded"
+                            } else {
+                                "failed"
+                            },
+                            code = result
+                                .exit_code
+                                .map(|c| c.to_string())
+                                .unwrap_or_else(|| String::from("<signal>")),
+                            ms = result.duration_ms
+                        ),
+                        BatchTaskResult::Errored { name, error } => {
+                            println!("{name}: error: {error}")
+                        }
+                    }
+                }
+
+                task
+            }
+        })
+        .buffer_unordered(max_parallel.max(1))
+        .collect::<Vec<_>>()
+        .await;
+
+    let succeeded = tasks.iter().filter(|t| t.success()).count();
+    let result = BatchResult {
+        failed: tasks.len() - succeeded,
+        succeeded,
+        tasks,
+        duration_ms: start.elapsed().as_millis(),
+    };
+
+    match output {
+        OutputFormat::Text => println!(
+            "{succeeded} succeeded, {failed} failed in {ms}ms",
+            succeeded = result.succeeded,
+            failed = result.failed,
+            ms = result.duration_ms
+        ),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&result)?),
+    }
+
+    if result.failed > 0 {
+        return Err(anyhow!("{} task(s) failed", result.failed));
+    }
+
+    Ok(())
+}
+
+/// A difference between two files compared by the `diff` command.
+#[derive(serde::Serialize)]
+struct Difference {
+    /// The path to the differing value (e.g., `binds[0]`).
+    path: String,
+
+    /// The value within the left file (if present).
+    left: Option<String>,
+
+    /// The value within the right file (if present).
+    right: Option<String>,
+}
+
+/// Collects the differences between two values at and below `path`.
+///
+/// Mappings are compared key-wise and sequences element-wise; any other values
+/// are compared as a whole.
+fn diff_values(
+    path: &str,
+    left: Option<&serde_yaml::Value>,
+    right: Option<&serde_yaml::Value>,
+    differences: &mut Vec<Difference>,
+) {
+    use serde_yaml::Value;
+
+    match (left, right) {
+        (Some(Value::Mapping(l)), Some(Value::Mapping(r))) => {
+            for key in l.keys().chain(r.keys().filter(|k| !l.contains_key(*k))) {
+                let key_path = match key {
+                    Value::String(key) => key.clone(),
+                    key => serde_json::to_string(key).unwrap_or_default(),
+                };
+                let key_path = if path.is_empty() {
+                    key_path
+                } else {
+                    format!("{path}.{key_path}")
+                };
+
+                diff_values(&key_path, l.get(key), r.get(key), differences);
+            }
+        }
+        (Some(Value::Sequence(l)), Some(Value::Sequence(r))) => {
+            for i in 0..l.len().max(r.len()) {
+                diff_values(&format!("{path}[{i}]"), l.get(i), r.get(i), differences);
+            }
+        }
+        (l, r) if l != r => differences.push(Difference {
+            path: if path.is_empty() { "." } else { path }.to_string(),
+            left: l.map(|v| serde_json::to_string(v).unwrap_or_default()),
+            right: r.map(|v| serde_json::to_string(v).unwrap_or_default()),
+        }),
+        _ => {}
+    }
+}
+
+/// Compares two YAML (or JSON) files and prints their differences.
+fn diff(left: &Path, right: &Path, output: OutputFormat) -> Result<()> {
+    let read = |path: &Path| -> Result<serde_yaml::Value> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+        serde_yaml::from_str(&contents)
+            .with_context(|| format!("invalid file `{}`", path.display()))
+    };
+
+    let mut differences = Vec::new();
+    diff_values(
+        "",
+        Some(&read(left)?),
+        Some(&read(right)?),
+        &mut differences,
+    );
+    match output {
+        OutputFormat::Text if differences.is_empty() => println!("no differences"),
+        OutputFormat::Text => {
+            for difference in differences {
+                println!(
+                    "{path}: {left} -> {right}",
+                    path = difference.path,
+                    left = difference.left.as_deref().unwrap_or("<none>"),
+                    right = difference.right.as_deref().unwrap_or("<none>")
+                );
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&differences)?),
+    }
+
+    Ok(())
+}
+
+/// Prints the result of running a container.
+fn print_result(result: &RunResult, output: OutputFormat) -> Result<()> {
+    match output {
+        OutputFormat::Text => match result.exit_code {
+            Some(code) => println!("exit code: {code}"),
+            None => println!("exit code: <terminated by signal>"),
+        },
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(result)?),
+    }
+
+    Ok(())
+}
+
+/// Ensures that an image exists, rendering a progress bar while it is pulled.
+async fn pull_image(docker: &Docker, image: &str) -> Result<()> {
+    pull_image_with(docker, image, &PullOptions::default()).await?;
+    Ok(())
+}
+
+/// Ensures that an image exists with the given pull options, rendering a
+/// progress bar while it is pulled.
+///
+/// Returns the source the image was pulled from (if it was pulled).
+async fn pull_image_with(
+    docker: &Docker,
+    image: &str,
+    options: &PullOptions,
+) -> Result<Option<PullSource>> {
+    let bar = ProgressBar::new(0);
+    bar.set_style(
+        ProgressStyle::with_template(
+            "{spinner:.cyan/blue} [{elapsed_precise}] [{wide_bar:.cyan/blue}] \
+             {bytes}/{total_bytes} ({eta}) {msg}",
+        )
+        .unwrap()
+        .progress_chars("#>-"),
+    );
+    bar.set_message(format!("pulling `{image}`"));
+
+    let result = docker
+        .ensure_image_with(image, options, |progress| {
+            bar.set_length(progress.total);
+            bar.set_position(progress.current);
+            if let Some(status) = &progress.status {
+                bar.set_message(format!("pulling `{image}`: {status}"));
+            }
+        })
+        .await;
+
+    bar.finish_and_clear();
+    Ok(result?)
+}
+
+/// Prints the request that a container builder would make as JSON.
+fn print_dry_run(builder: &container::Builder) -> Result<()> {
+    let body = builder.dry_run()?;
+    println!("{}", serde_json::to_string_pretty(&body)?);
+    Ok(())
+}
+
+async fn shell(
+    docker: Docker,
+    image: Option<String>,
+    tag: String,
+    file: Option<PathBuf>,
+    shell: String,
+) -> Result<()> {
+    // Use the mounts, environment, and resources of the task (if given)
+    let (builder, image) = match file {
+        Some(file) => {
+            let spec = TaskSpec::read(&file)?;
+            let image = spec.image.clone();
+            (spec.into_builder(&docker)?, image)
+        }
+        None => {
+            let image = format!(
+                "{image}:{tag}",
+                image = image.context("an image or a task specification is required")?
+            );
+            (docker.container_builder().image(&image), image)
+        }
+    };
+
+    pull_image(&docker, &image).await?;
+    let status = builder.shell_interactive(shell).await?;
+    // Exit with the shell's status so that scripts can tell if it failed
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+
+    Ok(())
+}
+
+/// Quotes a field of a CSV record.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+async fn catalog(
+    docker: Docker,
+    mut images: Vec<String>,
+    file: Option<PathBuf>,
+    all: bool,
+    pull: bool,
+    format: CatalogFormat,
+) -> Result<()> {
+    if let Some(file) = file {
+        let contents = std::fs::read_to_string(&file)
+            .with_context(|| format!("failed to read `{file}`", file = file.display()))?;
+        images.extend(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from),
+        );
+    }
+
+    if all {
+        for image in docker.list_images().await? {
+            if image.repo_tags.is_empty() {
+                images.push(image.id);
+            } else {
+                images.extend(image.repo_tags);
+            }
+        }
+    }
+
+    let mut entries = Vec::with_capacity(images.len());
+    for image in images {
+        let result = async {
+            if pull {
+                docker.ensure_image(&image).await?;
+            }
+
+            docker.image_metadata(&image).await
+        }
+        .await;
+
+        entries.push(match result {
+            Ok(metadata) => CatalogEntry {
+                image,
+                metadata: Some(metadata),
+                error: None,
+            },
+            Err(e) => CatalogEntry {
+                image,
+                metadata: None,
+                error: Some(e.to_string()),
+            },
+        });
+    }
+
+    match format {
+        CatalogFormat::Json => println!("{}", serde_json::to_string_pretty(&entries)?),
+        CatalogFormat::Csv => {
+            println!("image,id,size,architecture,os,digests,labels,error");
+            for entry in entries {
+                let metadata = entry.metadata.unwrap_or_default();
+                let record = [
+                    entry.image,
+                    metadata.id.unwrap_or_default(),
+                    metadata.size.map(|s| s.to_string()).unwrap_or_default(),
+                    metadata.architecture.unwrap_or_default(),
+                    metadata.os.unwrap_or_default(),
+                    metadata.digests.join(" "),
+                    metadata
+                        .labels
+                        .iter()
+                        .map(|(k, v)| format!("{k}={v}"))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    entry.error.unwrap_or_default(),
+                ];
+
+                println!(
+                    "{}",
+                    record
+                        .iter()
+                        .map(|field| csv_field(field))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
 async fn run(args: Args) -> Result<()> {
+    // Comparing files does not require the Docker daemon
+    if let Command::Diff { left, right } = &args.command {
+        return diff(left, right, args.output);
+    }
+
     let docker = Docker::with_defaults().unwrap();
+    let output = args.output;
 
     match args.command {
         Command::CreateContainer { image, name, tag } => {
@@ -128,16 +1872,50 @@ async fn run(args: Args) -> Result<()> {
             name,
             command,
             tag,
+            dry_run,
+            logs,
         } => {
             let mut command =
                 shlex::split(&command).ok_or_else(|| anyhow!("invalid command `{command}`"))?;
             let args = command.split_off(1);
 
-            let container =
-                create_container(docker, image, tag, &name, command.remove(0), args).await?;
-            let status = container.run(&name, || {}).await?;
+            let builder = container_builder(docker, image, tag, command.remove(0), args);
+            if dry_run {
+                return print_dry_run(&builder);
+            }
+
+            let result = execute(builder, name, logs, false).await?;
+            print_result(&result, output)?;
+        }
+        Command::RunImage {
+            image,
+            name,
+            args,
+            tag,
+            dry_run,
+            logs,
+        } => {
+            let image = format!("{image}:{tag}");
+            let builder = docker
+                .container_builder()
+                .image(&image)
+                .entrypoint(Entrypoint::Honor)
+                .args(args);
+            if dry_run {
+                return print_dry_run(&builder);
+            }
 
-            println!("exit code: {}", status);
+            pull_image(&docker, &image).await?;
+            let result = execute(builder, name, logs, false).await?;
+            print_result(&result, output)?;
+        }
+        Command::Shell {
+            image,
+            file,
+            tag,
+            shell,
+        } => {
+            self::shell(docker, image, tag, file, shell).await?;
         }
         Command::RemoveContainer { name, force } => {
             let container = docker.container_from_name(name, None, None);
@@ -148,8 +1926,132 @@ async fn run(args: Args) -> Result<()> {
                 container.remove().await?;
             }
         }
-        Command::EnsureImage { image } => {
-            docker.ensure_image(image).await?;
+        Command::RunTask {
+            file,
+            dry_run,
+            logs,
+        } => {
+            let spec = TaskSpec::read(&file)?;
+            let name = spec.name(&file);
+            let image = spec.image.clone();
+            let builder = spec.into_builder(&docker)?;
+            if dry_run {
+                return print_dry_run(&builder);
+            }
+
+            pull_image(&docker, &image).await?;
+            let result = execute(builder, name, logs, true).await?;
+            print_result(&result, output)?;
+        }
+        Command::Diff { .. } => unreachable!("files are compared before connecting to Docker"),
+        Command::Explain { file } => {
+            let builder = TaskSpec::read(&file)?.into_builder(&docker)?;
+            let settings = builder.explain()?;
+            match output {
+                OutputFormat::Text => {
+                    for setting in settings {
+                        println!("{setting}");
+                    }
+                }
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&settings)?),
+            }
+        }
+        Command::Instance { command } => {
+            instance(docker, command, output).await?;
+        }
+        Command::Check { min_version } => {
+            check(docker, min_version, output).await?;
+        }
+        Command::Cache { command } => {
+            cache(docker, command, output).await?;
+        }
+        Command::Ps { interval_ms } => {
+            ps(docker, interval_ms, output).await?;
+        }
+        Command::Logs {
+            task,
+            follow,
+            logs_root,
+        } => match logs_root {
+            Some(root) => follow_log_dir(&docker, &root, &task, follow).await?,
+            None => follow_containers(&docker, &task, follow).await?,
+        },
+        Command::Batch {
+            files,
+            max_parallel,
+            logs_dir,
+        } => {
+            batch(docker, files, max_parallel, logs_dir, output).await?;
+        }
+        Command::EnsureImage {
+            image,
+            arch,
+            sources,
+        } => {
+            let start = std::time::Instant::now();
+            let mut options = PullOptions::default();
+            if let Some(arch) = arch {
+                options = options.arch(arch);
+            }
+
+            if !sources.is_empty() {
+                options = options.sources(&image, sources);
+            }
+
+            let source = pull_image_with(&docker, &image, &options).await?;
+
+            if output == OutputFormat::Json {
+                let duration_ms = start.elapsed().as_millis();
+                let metadata = docker.image_metadata(&image).await?;
+                let result = EnsureImageResult {
+                    image,
+                    source: source.map(|s| s.to_string()),
+                    id: metadata.id,
+                    digests: metadata.digests,
+                    duration_ms,
+                };
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            } else if let Some(source) = source {
+                println!("pulled `{image}` from `{source}`");
+            }
+        }
+        Command::BuildImage {
+            context,
+            tag,
+            file,
+            args,
+            no_cache,
+        } => {
+            let mut options = BuildOptions::default().no_cache(no_cache);
+
+            if let Some(file) = file {
+                options = options.dockerfile(file);
+            }
+
+            for (name, value) in args {
+                options = options.arg(name, value);
+            }
+
+            let id = docker.build_image(context, tag, &options).await?;
+            println!("{id}");
+        }
+        Command::LoadImage { source } => {
+            for image in docker.load_image(&source).await? {
+                println!("{image}");
+            }
+        }
+        Command::UnpackImage { image, destination } => {
+            pull_image(&docker, &image).await?;
+            docker.unpack_image(&image, &destination).await?;
+        }
+        Command::Catalog {
+            images,
+            file,
+            all,
+            pull,
+            format,
+        } => {
+            catalog(docker, images, file, all, pull, format).await?;
         }
         Command::ListImages => {
             docker.list_images().await?;
@@ -168,12 +2070,16 @@ async fn run(args: Args) -> Result<()> {
 pub fn main() -> Result<()> {
     let args = Args::parse();
 
+    // Logs are written to stderr so as to not interfere with the results
+    // printed to stdout
     match std::env::var("RUST_LOG") {
         Ok(_) => tracing_subscriber::fmt()
             .with_env_filter(EnvFilter::from_default_env())
+            .with_writer(std::io::stderr)
             .init(),
         Err(_) => tracing_subscriber::fmt()
             .with_max_level(args.verbose.log_level_filter().as_trace())
+            .with_writer(std::io::stderr)
             .init(),
     };
 
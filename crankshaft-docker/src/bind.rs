@@ -0,0 +1,237 @@
+//! Bind mounts.
+
+use std::fmt;
+use std::str::FromStr;
+
+use bollard::secret::Mount;
+use bollard::secret::MountTypeEnum;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Error;
+
+/// A bind mount of a host path into a container.
+///
+/// A bind can be parsed from the familiar `SOURCE:TARGET[:ro|rw]` syntax or
+/// from the `--mount` syntax (e.g.,
+/// `type=bind,source=/data,target=/mnt,readonly`) and is converted into a
+/// [`Mount`] of type `bind` when provided to a container or service builder.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Bind {
+    /// The path on the host.
+    source: String,
+
+    /// The path within the container.
+    target: String,
+
+    /// Whether or not the bind is read-only.
+    #[serde(default)]
+    read_only: bool,
+}
+
+impl Bind {
+    /// Creates a new, writable [`Bind`].
+    pub fn new(source: impl Into<String>, target: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            target: target.into(),
+            read_only: false,
+        }
+    }
+
+    /// Creates a new, read-only [`Bind`].
+    pub fn read_only(source: impl Into<String>, target: impl Into<String>) -> Self {
+        Self {
+            read_only: true,
+            ..Self::new(source, target)
+        }
+    }
+
+    /// Gets the path on the host.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Gets the path within the container.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Gets whether or not the bind is read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+}
+
+impl fmt::Display for Bind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{source}:{target}:{mode}",
+            source = self.source,
+            target = self.target,
+            mode = if self.read_only { "ro" } else { "rw" }
+        )
+    }
+}
+
+/// The keys of the `--mount` syntax.
+const MOUNT_KEYS: &[&str] = &[
+    "type",
+    "source",
+    "src",
+    "target",
+    "destination",
+    "dst",
+    "readonly",
+    "ro",
+];
+
+impl Bind {
+    /// Parses a bind from the `--mount` syntax.
+    ///
+    /// Only mounts of type `bind` are supported.
+    fn from_mount(s: &str) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::Message(format!("invalid bind `{s}`: {reason}"));
+
+        let mut source = None;
+        let mut target = None;
+        let mut read_only = false;
+        for field in s.split(',') {
+            let (key, value) = match field.split_once('=') {
+                Some((key, value)) => (key, Some(value)),
+                None => (field, None),
+            };
+
+            match (key, value) {
+                ("type", Some("bind")) => {}
+                ("type", Some(ty)) => {
+                    return Err(invalid(&format!("mounts of type `{ty}` are not supported")));
+                }
+                ("source" | "src", Some(value)) if !value.is_empty() => source = Some(value),
+                ("target" | "destination" | "dst", Some(value)) if !value.is_empty() => {
+                    target = Some(value)
+                }
+                ("readonly" | "ro", None | Some("true" | "1")) => read_only = true,
+                ("readonly" | "ro", Some("false" | "0")) => read_only = false,
+                _ => return Err(invalid(&format!("unsupported option `{field}`"))),
+            }
+        }
+
+        Ok(Self {
+            source: source.ok_or_else(|| invalid("missing source"))?.to_string(),
+            target: target.ok_or_else(|| invalid("missing target"))?.to_string(),
+            read_only,
+        })
+    }
+}
+
+impl FromStr for Bind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // The `--mount` syntax starts with one of its keys
+        let first = s.split(',').next().unwrap_or_default();
+        let key = first.split_once('=').map_or(first, |(key, _)| key);
+        if MOUNT_KEYS.contains(&key) {
+            return Self::from_mount(s);
+        }
+
+        let invalid = || Error::Message(format!("invalid bind `{s}`"));
+
+        let mut parts = s.split(':');
+        let source = parts.next().filter(|p| !p.is_empty()).ok_or_else(invalid)?;
+        let target = parts.next().filter(|p| !p.is_empty()).ok_or_else(invalid)?;
+
+        let read_only = match parts.next() {
+            None | Some("rw") => false,
+            Some("ro") => true,
+            Some(_) => return Err(invalid()),
+        };
+
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            target: target.to_string(),
+            read_only,
+        })
+    }
+}
+
+impl From<Bind> for Mount {
+    fn from(bind: Bind) -> Self {
+        Mount {
+            target: Some(bind.target),
+            source: Some(bind.source),
+            typ: Some(MountTypeEnum::BIND),
+            read_only: Some(bind.read_only),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_binds() {
+        let bind = "/data:/mnt/data".parse::<Bind>().unwrap();
+        assert_eq!(bind, Bind::new("/data", "/mnt/data"));
+
+        let bind = "/data:/mnt/data:ro".parse::<Bind>().unwrap();
+        assert_eq!(bind, Bind::read_only("/data", "/mnt/data"));
+        assert_eq!(bind.to_string(), "/data:/mnt/data:ro");
+
+        assert!("/data".parse::<Bind>().is_err());
+        assert!("/data:/mnt/data:z".parse::<Bind>().is_err());
+        assert!(":/mnt/data".parse::<Bind>().is_err());
+    }
+
+    #[test]
+    fn parses_mounts() {
+        let bind = "type=bind,source=/data,target=/mnt/data"
+            .parse::<Bind>()
+            .unwrap();
+        assert_eq!(bind, Bind::new("/data", "/mnt/data"));
+
+        let bind = "src=/data,dst=/mnt/data,readonly".parse::<Bind>().unwrap();
+        assert_eq!(bind, Bind::read_only("/data", "/mnt/data"));
+
+        let bind = "type=bind,source=/data,destination=/mnt/data,ro=false"
+            .parse::<Bind>()
+            .unwrap();
+        assert_eq!(bind, Bind::new("/data", "/mnt/data"));
+
+        assert!(
+            "type=volume,source=data,target=/mnt/data"
+                .parse::<Bind>()
+                .is_err()
+        );
+        assert!("type=bind,source=/data".parse::<Bind>().is_err());
+        assert!("type=bind,target=/mnt/data".parse::<Bind>().is_err());
+        assert!(
+            "type=bind,source=/data,target=/mnt/data,z"
+                .parse::<Bind>()
+                .is_err()
+        );
+        assert!(
+            "type=bind,source=,target=/mnt/data"
+                .parse::<Bind>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn converts_to_mount() {
+        let mount = Mount::from(Bind::read_only("/data", "/mnt/data"));
+        assert_eq!(mount.source.as_deref(), Some("/data"));
+        assert_eq!(mount.target.as_deref(), Some("/mnt/data"));
+        assert_eq!(mount.typ, Some(MountTypeEnum::BIND));
+        assert_eq!(mount.read_only, Some(true));
+    }
+}
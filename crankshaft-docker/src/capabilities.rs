@@ -0,0 +1,178 @@
+//! The resource limits supported by a Docker daemon.
+//!
+//! Not every Docker daemon can enforce every resource limit (e.g., rootless
+//! daemons on hosts with cgroups v1 cannot limit memory). Rather than having
+//! the daemon silently ignore or reject such limits, they can be adjusted up
+//! front with [`Capabilities::apply()`].
+
+use bollard::secret::HostConfig;
+use bollard::secret::SystemInfo;
+use serde::Serialize;
+
+/// The number of CPU shares equivalent to a single CPU.
+const SHARES_PER_CPU: f64 = 1024.0;
+
+/// The resource limits supported by a Docker daemon.
+///
+/// Limits that a daemon does not report on are assumed to be supported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// Whether memory limits are supported.
+    pub memory_limit: bool,
+
+    /// Whether swap limits are supported.
+    pub swap_limit: bool,
+
+    /// Whether CPU quotas (and therefore `nano_cpus`) are supported.
+    pub cpu_quota: bool,
+
+    /// Whether relative CPU shares are supported.
+    pub cpu_shares: bool,
+
+    /// Whether process count limits are supported.
+    pub pids_limit: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            memory_limit: true,
+            swap_limit: true,
+            cpu_quota: true,
+            cpu_shares: true,
+            pids_limit: true,
+        }
+    }
+}
+
+impl From<&SystemInfo> for Capabilities {
+    fn from(info: &SystemInfo) -> Self {
+        Self {
+            memory_limit: info.memory_limit.unwrap_or(true),
+            swap_limit: info.swap_limit.unwrap_or(true),
+            cpu_quota: info.cpu_cfs_quota.unwrap_or(true),
+            cpu_shares: info.cpu_shares.unwrap_or(true),
+            pids_limit: info.pids_limit.unwrap_or(true),
+        }
+    }
+}
+
+impl Capabilities {
+    /// Gets the names of the limits that are not supported.
+    pub fn unsupported(&self) -> Vec<&'static str> {
+        [
+            ("memory limits", self.memory_limit),
+            ("swap limits", self.swap_limit),
+            ("CPU quotas", self.cpu_quota),
+            ("CPU shares", self.cpu_shares),
+            ("process limits", self.pids_limit),
+        ]
+        .into_iter()
+        .filter_map(|(name, supported)| (!supported).then_some(name))
+        .collect()
+    }
+
+    /// Adjusts a host configuration to only use the supported limits.
+    ///
+    /// Unsupported limits are removed, except that a CPU limit is converted
+    /// into relative CPU shares when quotas are unsupported but shares are.
+    ///
+    /// Returns a description of each adjustment that was made.
+    pub fn apply(&self, host_config: &mut HostConfig) -> Vec<String> {
+        let mut adjustments = Vec::new();
+
+        if !self.cpu_quota {
+            if let Some(nano_cpus) = host_config.nano_cpus.take() {
+                let cpus = nano_cpus as f64 / 1_000_000_000.0;
+                if self.cpu_shares && host_config.cpu_shares.is_none() {
+                    let shares = (cpus * SHARES_PER_CPU).round() as i64;
+                    host_config.cpu_shares = Some(shares);
+                    adjustments.push(format!(
+                        "CPU limit of {cpus} was converted to {shares} CPU shares"
+                    ));
+                } else {
+                    adjustments.push(format!("CPU limit of {cpus} was removed"));
+                }
+            }
+
+            for (name, value) in [
+                ("CPU period", &mut host_config.cpu_period),
+                ("CPU quota", &mut host_config.cpu_quota),
+            ] {
+                if value.take().is_some() {
+                    adjustments.push(format!("{name} was removed"));
+                }
+            }
+        }
+
+        if !self.cpu_shares && host_config.cpu_shares.take().is_some() {
+            adjustments.push(String::from("CPU shares were removed"));
+        }
+
+        if !self.memory_limit {
+            for (name, value) in [
+                ("memory limit", &mut host_config.memory),
+                ("memory reservation", &mut host_config.memory_reservation),
+            ] {
+                if value.take().is_some() {
+                    adjustments.push(format!("{name} was removed"));
+                }
+            }
+        }
+
+        if (!self.memory_limit || !self.swap_limit) && host_config.memory_swap.take().is_some() {
+            adjustments.push(String::from("swap limit was removed"));
+        }
+
+        if !self.pids_limit && host_config.pids_limit.take().is_some() {
+            adjustments.push(String::from("process limit was removed"));
+        }
+
+        adjustments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_capabilities() {
+        let host_config = HostConfig {
+            nano_cpus: Some(2_000_000_000),
+            memory: Some(1024),
+            memory_swap: Some(2048),
+            ..Default::default()
+        };
+
+        let mut unchanged = host_config.clone();
+        assert!(Capabilities::default().apply(&mut unchanged).is_empty());
+        assert_eq!(unchanged, host_config);
+
+        let capabilities = Capabilities {
+            memory_limit: false,
+            cpu_quota: false,
+            ..Default::default()
+        };
+        assert_eq!(capabilities.unsupported(), ["memory limits", "CPU quotas"]);
+
+        let mut adjusted = host_config.clone();
+        assert_eq!(capabilities.apply(&mut adjusted).len(), 3);
+        assert_eq!(adjusted.nano_cpus, None);
+        assert_eq!(adjusted.cpu_shares, Some(2048));
+        assert_eq!(adjusted.memory, None);
+        assert_eq!(adjusted.memory_swap, None);
+
+        let capabilities = Capabilities {
+            cpu_quota: false,
+            cpu_shares: false,
+            ..Default::default()
+        };
+        let mut adjusted = host_config.clone();
+        capabilities.apply(&mut adjusted);
+        assert_eq!(adjusted.nano_cpus, None);
+        assert_eq!(adjusted.cpu_shares, None);
+        assert_eq!(adjusted.memory, Some(1024));
+    }
+}
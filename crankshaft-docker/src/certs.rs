@@ -0,0 +1,41 @@
+//! Host certificate authority (CA) bundles.
+
+use std::path::PathBuf;
+
+/// The path within a container where the host's CA bundle is bound.
+pub const CA_BUNDLE_TARGET: &str = "/etc/crankshaft/ca-certificates.crt";
+
+/// The environment variables set to [`CA_BUNDLE_TARGET`] within a container
+/// when the host's CA bundle is bound.
+///
+/// `SSL_CERT_FILE` is respected by OpenSSL, while the others are respected by
+/// `curl` and Python's `requests` respectively.
+pub const CA_BUNDLE_ENV: &[&str] = &["SSL_CERT_FILE", "CURL_CA_BUNDLE", "REQUESTS_CA_BUNDLE"];
+
+/// The locations of the CA bundle across common Linux distributions.
+///
+/// The locations are checked in order.
+pub const CA_BUNDLE_CANDIDATES: &[&str] = &[
+    // Debian, Ubuntu, Gentoo, and Arch
+    "/etc/ssl/certs/ca-certificates.crt",
+    // Fedora, RHEL, and CentOS
+    "/etc/pki/ca-trust/extracted/pem/tls-ca-bundle.pem",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    // openSUSE
+    "/etc/ssl/ca-bundle.pem",
+    // Alpine and macOS
+    "/etc/ssl/cert.pem",
+];
+
+/// Locates the host's CA bundle.
+///
+/// If `SSL_CERT_FILE` is set on the host and refers to an existing file, it is
+/// used. Otherwise, the first of [`CA_BUNDLE_CANDIDATES`] that exists is
+/// returned.
+pub fn host_ca_bundle() -> Option<PathBuf> {
+    std::env::var_os("SSL_CERT_FILE")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(CA_BUNDLE_CANDIDATES.iter().map(PathBuf::from))
+        .find(|path| path.is_file())
+}
@@ -1,35 +1,69 @@
 //! Containers.
 
+use std::fmt;
 use std::io::Cursor;
 #[cfg(unix)]
 use std::os::unix::process::ExitStatusExt as _;
 #[cfg(windows)]
 use std::os::windows::process::ExitStatusExt as _;
+use std::path::Component;
+use std::path::Path;
 use std::path::PathBuf;
 use std::process::ExitStatus;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
 
 use bollard::Docker;
 use bollard::body_full;
 use bollard::container::LogOutput;
+use bollard::exec::StartExecResults;
+use bollard::models::ExecConfig;
 use bollard::query_parameters::AttachContainerOptions;
+use bollard::query_parameters::DownloadFromContainerOptions;
 use bollard::query_parameters::InspectContainerOptions;
+use bollard::query_parameters::KillContainerOptions;
+use bollard::query_parameters::LogsOptions;
 use bollard::query_parameters::RemoveContainerOptions;
+use bollard::query_parameters::ResizeContainerTTYOptions;
 use bollard::query_parameters::StartContainerOptions;
+use bollard::query_parameters::StatsOptions;
+use bollard::query_parameters::StopContainerOptions;
 use bollard::query_parameters::UploadToContainerOptions;
 use bollard::query_parameters::WaitContainerOptions;
 use bollard::secret::ContainerWaitResponse;
-use tokio::fs::File;
+use bytes::Bytes;
+use tokio::io::AsyncRead;
 use tokio::io::AsyncWriteExt;
+use tokio::task::JoinHandle;
 use tokio_stream::StreamExt as _;
 use tracing::debug;
 use tracing::info;
+use tracing::instrument;
+use tracing::warn;
 
+use crate::Encoding;
 use crate::Error;
 use crate::Result;
+use crate::container::output::Combiner;
+use crate::container::output::Lines;
+use crate::container::output::Outputs;
+use crate::container::output::Sink;
+use crate::container::output::TailBuffer;
 
 mod builder;
+mod output;
+mod terminal;
 
 pub use builder::Builder;
+pub use builder::Entrypoint;
+pub use builder::Invocation;
+pub use builder::Setting;
+pub use builder::SettingSource;
+pub use builder::Template;
+pub use output::Stream as OutputStream;
 
 /// The default capacity of bytes for a TAR being built.
 ///
@@ -38,6 +72,504 @@ pub use builder::Builder;
 /// allocations.
 const DEFAULT_TAR_CAPACITY: usize = 0xFFFF;
 
+/// A source for a container's stdin stream.
+type Stdin = Box<dyn AsyncRead + Send + Unpin>;
+
+/// A callback that receives the heartbeats of a running container.
+type HeartbeatCallback = dyn Fn(&Heartbeat) + Send + Sync;
+
+/// A callback that receives the lines of output of a running container.
+type LineCallback = dyn Fn(OutputStream, &str) + Send + Sync;
+
+/// Converts a container exit code into an [`ExitStatus`].
+fn exit_status(code: i64) -> ExitStatus {
+    // See WEXITSTATUS from wait(2) to explain the shift
+    #[cfg(unix)]
+    let status = ExitStatus::from_raw((code as i32) << 8);
+
+    #[cfg(windows)]
+    let status = ExitStatus::from_raw(code as u32);
+
+    status
+}
+
+/// Infers the signal that terminated a container from its exit code.
+fn signal(code: i64) -> Option<i32> {
+    // Only the standard signals (1 to 31) are considered
+    (129..=159).contains(&code).then(|| (code - 128) as i32)
+}
+
+/// The output of a command executed within a running container.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ExecOutput {
+    /// The exit status of the command.
+    pub status: ExitStatus,
+
+    /// The bytes written by the command to stdout.
+    ///
+    /// This is empty if stdout was written to a file (see
+    /// [`ExecOptions::stdout()`]).
+    pub stdout: Vec<u8>,
+
+    /// The bytes written by the command to stderr.
+    ///
+    /// This is empty if stderr was written to a file (see
+    /// [`ExecOptions::stderr()`]).
+    pub stderr: Vec<u8>,
+
+    /// Whether the captured stdout was truncated (see
+    /// [`ExecOptions::max_captured_bytes()`]).
+    pub stdout_truncated: bool,
+
+    /// Whether the captured stderr was truncated (see
+    /// [`ExecOptions::max_captured_bytes()`]).
+    pub stderr_truncated: bool,
+
+    /// The combined and timestamped stdout and stderr of the command.
+    ///
+    /// This is only captured if the streams were combined (see
+    /// [`ExecOptions::combine()`]), in which case [`Self::stdout`] and
+    /// [`Self::stderr`] are empty.
+    pub combined: Vec<u8>,
+
+    /// Whether the captured combined output was truncated (see
+    /// [`ExecOptions::max_captured_bytes()`]).
+    pub combined_truncated: bool,
+}
+
+impl ExecOutput {
+    /// Gets the captured stdout as [`Bytes`].
+    pub fn stdout_bytes(&self) -> Bytes {
+        Bytes::copy_from_slice(&self.stdout)
+    }
+
+    /// Gets the captured stderr as [`Bytes`].
+    pub fn stderr_bytes(&self) -> Bytes {
+        Bytes::copy_from_slice(&self.stderr)
+    }
+
+    /// Gets the captured combined output as [`Bytes`].
+    pub fn combined_bytes(&self) -> Bytes {
+        Bytes::copy_from_slice(&self.combined)
+    }
+
+    /// Decodes the captured stdout into text with the given encoding.
+    pub fn stdout_text(&self, encoding: Encoding) -> String {
+        encoding.decode(&self.stdout)
+    }
+
+    /// Decodes the captured stderr into text with the given encoding.
+    pub fn stderr_text(&self, encoding: Encoding) -> String {
+        encoding.decode(&self.stderr)
+    }
+}
+
+/// Options for executing a command within a running container.
+#[derive(Clone, Debug, Default)]
+pub struct ExecOptions {
+    /// The maximum number of bytes to capture from each output stream.
+    max_captured_bytes: Option<usize>,
+
+    /// The path to the file to write the command's stdout stream to.
+    stdout: Option<PathBuf>,
+
+    /// The path to the file to write the command's stderr stream to.
+    stderr: Option<PathBuf>,
+
+    /// Whether to combine the command's stdout and stderr streams.
+    combine: bool,
+
+    /// The path to the file to write the command's combined output to.
+    combined: Option<PathBuf>,
+
+    /// The environment variables to set for the command.
+    env: Vec<(String, String)>,
+
+    /// The working directory of the command.
+    work_dir: Option<String>,
+}
+
+impl ExecOptions {
+    /// Sets the maximum number of bytes to capture from each of the command's
+    /// output streams.
+    ///
+    /// Output beyond the limit is discarded and a marker noting the number of
+    /// bytes omitted is appended to the captured output. Defaults to no
+    /// limit.
+    pub fn max_captured_bytes(mut self, max: usize) -> Self {
+        self.max_captured_bytes = Some(max);
+        self
+    }
+
+    /// Sets the path to a file to stream the command's stdout to instead of
+    /// capturing it.
+    ///
+    /// The file is created (or truncated) when the command is executed.
+    pub fn stdout(mut self, path: impl Into<PathBuf>) -> Self {
+        self.stdout = Some(path.into());
+        self
+    }
+
+    /// Sets the path to a file to stream the command's stderr to instead of
+    /// capturing it.
+    ///
+    /// The file is created (or truncated) when the command is executed.
+    pub fn stderr(mut self, path: impl Into<PathBuf>) -> Self {
+        self.stderr = Some(path.into());
+        self
+    }
+
+    /// Sets whether to combine the command's stdout and stderr streams into a
+    /// single stream captured in [`ExecOutput::combined`].
+    ///
+    /// Lines from the two streams are interleaved in the order they are
+    /// received, each prefixed with an RFC 3339 timestamp and the name of the
+    /// stream it was written to. When set, the [`Self::stdout()`] and
+    /// [`Self::stderr()`] files are not used. Defaults to `false`.
+    pub fn combine(mut self, combine: bool) -> Self {
+        self.combine = combine;
+        self
+    }
+
+    /// Sets the path to a file to stream the command's combined output to
+    /// instead of capturing it.
+    ///
+    /// This implies [`Self::combine()`]. The file is created (or truncated)
+    /// when the command is executed.
+    pub fn combined_output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.combine = true;
+        self.combined = Some(path.into());
+        self
+    }
+
+    /// Sets an environment variable for the command (in addition to the
+    /// container's environment).
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the working directory of the command.
+    ///
+    /// Defaults to the working directory of the container.
+    pub fn work_dir(mut self, work_dir: impl Into<String>) -> Self {
+        self.work_dir = Some(work_dir.into());
+        self
+    }
+
+    /// Creates the destinations of the command's output streams.
+    async fn outputs(&self) -> Result<Outputs> {
+        if self.combine {
+            return Ok(Outputs::Combined {
+                combiner: Combiner::default(),
+                sink: Sink::new(
+                    "combined output",
+                    self.combined.as_ref(),
+                    self.max_captured_bytes,
+                )
+                .await?,
+            });
+        }
+
+        Ok(Outputs::Separate {
+            stdout: Some(Sink::new("stdout", self.stdout.as_ref(), self.max_captured_bytes).await?),
+            stderr: Some(Sink::new("stderr", self.stderr.as_ref(), self.max_captured_bytes).await?),
+        })
+    }
+}
+
+/// The most recent output of a container's stdout and stderr streams.
+///
+/// See [`Builder::tail()`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Tail {
+    /// The end of the stdout stream.
+    pub stdout: String,
+
+    /// The end of the stderr stream.
+    pub stderr: String,
+}
+
+impl Tail {
+    /// Determines if neither stream had any output.
+    pub fn is_empty(&self) -> bool {
+        self.stdout.is_empty() && self.stderr.is_empty()
+    }
+}
+
+impl fmt::Display for Tail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, output) in [("stdout", &self.stdout), ("stderr", &self.stderr)] {
+            if !output.is_empty() {
+                writeln!(f, "--- end of {name} ---")?;
+                write!(f, "{output}")?;
+                if !output.ends_with('\n') {
+                    writeln!(f)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The resources consumed by a container while it ran.
+///
+/// The CPU time and memory usage are sampled from the Docker daemon's stats
+/// (typically once per second); as such, they may be absent for containers
+/// that run very briefly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Usage {
+    /// The wall-clock time the container ran for.
+    pub wall: Duration,
+
+    /// The total CPU time consumed by the container.
+    pub cpu: Option<Duration>,
+
+    /// The peak memory usage of the container, in bytes.
+    pub max_memory: Option<u64>,
+}
+
+/// A record of a container's run for provenance.
+///
+/// See [`Container::report()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Report {
+    /// The image reference the container was created from.
+    pub image: String,
+
+    /// The id (i.e., the content digest) of the image that ran.
+    pub image_id: Option<String>,
+
+    /// The resolved program and arguments that ran (including any
+    /// entrypoint).
+    pub command: Vec<String>,
+
+    /// When the container was started.
+    pub started: SystemTime,
+
+    /// When the container exited.
+    pub ended: SystemTime,
+
+    /// The exit status of the container.
+    pub status: ExitStatus,
+
+    /// The signal that likely terminated the container (if any).
+    ///
+    /// Docker reports the exit code of a container terminated by a signal as
+    /// `128` plus the signal's number, so this is inferred from the exit code
+    /// (a program may also exit with such a code itself).
+    pub signal: Option<i32>,
+
+    /// Whether the container was killed for exceeding its memory limit.
+    pub oom_killed: bool,
+
+    /// The resources consumed by the container.
+    pub usage: Usage,
+
+    /// The file the container's stdout stream was written to (if any).
+    pub stdout: Option<PathBuf>,
+
+    /// The file the container's stderr stream was written to (if any).
+    pub stderr: Option<PathBuf>,
+
+    /// The file the container's combined stdout and stderr streams were
+    /// written to (if any).
+    pub combined: Option<PathBuf>,
+}
+
+/// A periodic report of the resources consumed by a running container.
+///
+/// See [`Builder::heartbeat()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Heartbeat {
+    /// The wall-clock time the container has been running for.
+    pub elapsed: Duration,
+
+    /// The total CPU time consumed by the container so far.
+    pub cpu: Option<Duration>,
+
+    /// The peak memory usage of the container so far, in bytes.
+    pub max_memory: Option<u64>,
+}
+
+/// Reports heartbeats of a running container at an interval until aborted.
+async fn send_heartbeats(
+    interval: Duration,
+    callback: Arc<HeartbeatCallback>,
+    begin: Instant,
+    usage: Arc<Mutex<Usage>>,
+) {
+    let mut interval = tokio::time::interval_at((begin + interval).into(), interval);
+    loop {
+        interval.tick().await;
+
+        let usage = *usage.lock().unwrap();
+        callback(&Heartbeat {
+            elapsed: begin.elapsed(),
+            cpu: usage.cpu,
+            max_memory: usage.max_memory,
+        });
+    }
+}
+
+/// Samples the resources consumed by a container until its stats stream ends.
+///
+/// The samples are accumulated into the provided usage.
+async fn sample_usage(client: Docker, id: String, usage: Arc<Mutex<Usage>>) {
+    let mut stream = client.stats(
+        &id,
+        Some(StatsOptions {
+            stream: true,
+            one_shot: false,
+        }),
+    );
+
+    while let Some(Ok(stats)) = stream.next().await {
+        let cpu = stats
+            .cpu_stats
+            .and_then(|s| s.cpu_usage)
+            .and_then(|u| u.total_usage)
+            .filter(|n| *n > 0);
+        let memory = stats
+            .memory_stats
+            .and_then(|s| s.max_usage.max(s.usage))
+            .filter(|n| *n > 0);
+
+        let mut usage = usage.lock().unwrap();
+        if let Some(cpu) = cpu {
+            usage.cpu = Some(Duration::from_nanos(cpu));
+        }
+
+        if let Some(memory) = memory {
+            usage.max_memory = Some(usage.max_memory.unwrap_or_default().max(memory));
+        }
+    }
+}
+
+/// A guard for a container started by [`Container::run()`].
+///
+/// Dropping the guard aborts the tasks observing the container and, unless
+/// the container has exited, stops it. This occurs when the future running the
+/// container is dropped before the container exits (e.g., when it loses a
+/// `select!` or a timeout elapses), so that the container does not keep running
+/// unobserved.
+struct Running {
+    /// The client of the Docker daemon.
+    client: Docker,
+
+    /// The id of the container.
+    id: String,
+
+    /// The tasks observing the container (e.g., sampling its usage).
+    tasks: Vec<JoinHandle<()>>,
+
+    /// Whether the container has exited.
+    exited: bool,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+
+        if self.exited {
+            return;
+        }
+
+        // Stopping the container requires a runtime, which is not available if
+        // the guard is dropped while the runtime shuts down
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!(
+                "container `{id}` was abandoned while running and could not be stopped",
+                id = self.id
+            );
+            return;
+        };
+
+        let client = self.client.clone();
+        let id = std::mem::take(&mut self.id);
+        debug!("stopping abandoned container `{id}`");
+        runtime.spawn(async move {
+            if let Err(e) = client
+                .stop_container(&id, None::<StopContainerOptions>)
+                .await
+            {
+                warn!("failed to stop abandoned container `{id}`: {e}");
+            }
+        });
+    }
+}
+
+/// Unpacks an archive of a path copied from a container to a destination.
+///
+/// The archive's top-level entry (the copied path itself) is unpacked to the
+/// destination and any entries beneath it are unpacked relative to the
+/// destination.
+///
+/// Archives containing symbolic or hard links are rejected, as a link created
+/// by one entry could otherwise direct a later entry outside of the
+/// destination.
+///
+/// Returns the total size (in bytes) of the files unpacked.
+fn unpack(archive: &std::fs::File, destination: &Path) -> std::io::Result<u64> {
+    let mut archive = tar::Archive::new(archive);
+    let mut size = 0;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+
+        let mut components = path.components();
+        components.next();
+        let relative = components.as_path();
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "archive entry `{path}` is not a normal path",
+                    path = path.display()
+                ),
+            ));
+        }
+
+        let kind = entry.header().entry_type();
+        if kind.is_symlink() || kind.is_hard_link() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("archive entry `{path}` is a link", path = path.display()),
+            ));
+        }
+
+        let target = if relative.as_os_str().is_empty() {
+            destination.to_path_buf()
+        } else {
+            destination.join(relative)
+        };
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        if entry.header().entry_type().is_file() {
+            size += entry.size();
+        }
+
+        entry.unpack(&target)?;
+    }
+
+    Ok(size)
+}
+
 /// A container.
 pub struct Container {
     /// A reference to the [`Docker`] client that will be used to create this
@@ -47,11 +579,46 @@ pub struct Container {
     /// The id of the created container.
     id: String,
 
+    /// The source of the container's stdin stream.
+    ///
+    /// This is taken when the container is run.
+    stdin: Mutex<Option<Stdin>>,
+
+    /// The resources consumed by the container during its last run.
+    usage: Mutex<Option<Usage>>,
+
+    /// The record of the container's last run.
+    report: Mutex<Option<Report>>,
+
     /// The path to the file to write the container's stdout stream to.
     stdout: Option<PathBuf>,
 
     /// The path to the file to write the container's stderr stream to.
     stderr: Option<PathBuf>,
+
+    /// The path to the file to write the container's combined stdout and
+    /// stderr streams to.
+    combined: Option<PathBuf>,
+
+    /// Whether to append to the stdout and stderr files rather than truncate
+    /// them.
+    append_output: bool,
+
+    /// The interval and callback for reporting heartbeats while the container
+    /// runs.
+    heartbeat: Option<(Duration, Arc<HeartbeatCallback>)>,
+
+    /// The number of bytes of the end of each output stream to keep.
+    tail: Option<usize>,
+
+    /// The callback for the lines of output of the container while it runs.
+    on_line: Option<Arc<LineCallback>>,
+
+    /// How the output of the container is decoded into text.
+    output_encoding: Encoding,
+
+    /// The end of the output streams of the container's last run.
+    tails: Mutex<Option<Tail>>,
 }
 
 impl Container {
@@ -68,11 +635,49 @@ impl Container {
         Self {
             client,
             id,
+            stdin: Default::default(),
+            usage: Default::default(),
+            report: Default::default(),
             stdout,
             stderr,
+            combined: None,
+            append_output: false,
+            heartbeat: None,
+            tail: None,
+            on_line: None,
+            output_encoding: Default::default(),
+            tails: Default::default(),
         }
     }
 
+    /// Gets the id of the container.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Gets the resources consumed by the container during its last run.
+    ///
+    /// Returns `None` if the container has not been run (see [`Self::run()`]).
+    pub fn usage(&self) -> Option<Usage> {
+        *self.usage.lock().unwrap()
+    }
+
+    /// Gets the record of the container's last run, including what ran, when,
+    /// and where its output was written.
+    ///
+    /// Returns `None` if the container has not been run (see [`Self::run()`]).
+    pub fn report(&self) -> Option<Report> {
+        self.report.lock().unwrap().clone()
+    }
+
+    /// Gets the end of the output streams of the container's last run.
+    ///
+    /// Returns `None` if the container has not been run or it was not built to
+    /// keep the end of its output (see [`Builder::tail()`]).
+    pub fn tail(&self) -> Option<Tail> {
+        self.tails.lock().unwrap().clone()
+    }
+
     /// Uploads an input file to the container.
     pub async fn upload_file(&self, path: &str, contents: &[u8]) -> Result<()> {
         let mut tar = tar::Builder::new(Vec::with_capacity(DEFAULT_TAR_CAPACITY));
@@ -101,31 +706,124 @@ impl Container {
             .map_err(Error::Docker)
     }
 
+    /// Copies a file or directory out of the container to a destination on
+    /// the host.
+    ///
+    /// If `path` is a directory, its contents are copied into `destination`
+    /// (which is created if it does not exist). The container does not need
+    /// to be running. Paths that are (or contain) symbolic or hard links are
+    /// refused so that the container cannot write outside of `destination`.
+    ///
+    /// Returns the total size (in bytes) of the files copied or `None` if
+    /// `path` does not exist within the container.
+    pub async fn copy_from(&self, path: &str, destination: &Path) -> Result<Option<u64>> {
+        debug!(
+            "copying `{path}` from container `{id}` to `{destination}`",
+            id = self.id,
+            destination = destination.display()
+        );
+
+        let io_error = |e: std::io::Error| {
+            Error::Message(format!(
+                "failed to copy `{path}` from container `{id}` to `{destination}`: {e}",
+                id = self.id,
+                destination = destination.display()
+            ))
+        };
+
+        // Stream the archive of the path to a temporary file next to the
+        // destination rather than buffering it in memory
+        let parent = destination
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+        let archive = tempfile::NamedTempFile::new_in(parent).map_err(io_error)?;
+        let mut file = tokio::fs::File::from_std(archive.reopen().map_err(io_error)?);
+
+        let mut stream = self.client.download_from_container(
+            &self.id,
+            Some(DownloadFromContainerOptions {
+                path: path.to_string(),
+            }),
+        );
+
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(bytes) => file.write_all(&bytes).await.map_err(io_error)?,
+                Err(bollard::errors::Error::DockerResponseServerError {
+                    status_code: 404, ..
+                }) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        file.flush().await.map_err(io_error)?;
+        drop(file);
+
+        let target = destination.to_path_buf();
+        tokio::task::spawn_blocking(move || unpack(archive.as_file(), &target))
+            .await
+            .map_err(|e| Error::Message(format!("failed to unpack `{path}`: {e}")))?
+            .map(Some)
+            .map_err(io_error)
+    }
+
     /// Runs a container and waits for the execution to end.
+    ///
+    /// If the returned future is dropped after the container has started but
+    /// before it exits, the container is stopped (with its stop timeout) in
+    /// the background.
+    ///
+    /// If the container was killed for exceeding its memory limit,
+    /// [`Error::OutOfMemory`] is returned; the container's report is still
+    /// recorded (see [`Self::report()`]).
+    #[instrument(skip_all, fields(container = %self.id, task = name))]
     pub async fn run(&self, name: &str, started: impl FnOnce()) -> Result<ExitStatus> {
+        let stdin = self.stdin.lock().unwrap().take();
+
         // Attach to the container before we start it
-        let stream = if self.stdout.is_some() || self.stderr.is_some() {
+        let stream = if stdin.is_some() || self.has_outputs() || self.observes_output() {
             debug!(
                 "attaching to container `{id}` (task `{name}`)",
                 id = self.id
             );
 
             // Attach to the logs stream.
-            Some(
-                self.client
-                    .attach_container(
-                        &self.id,
-                        Some(AttachContainerOptions {
-                            stdout: self.stdout.is_some(),
-                            stderr: self.stderr.is_some(),
-                            stream: true,
-                            ..Default::default()
-                        }),
-                    )
-                    .await
-                    .map_err(Error::Docker)?
-                    .output,
-            )
+            let results = self
+                .client
+                .attach_container(
+                    &self.id,
+                    Some(AttachContainerOptions {
+                        stdin: stdin.is_some(),
+                        stdout: self.stdout.is_some()
+                            || self.combined.is_some()
+                            || self.observes_output(),
+                        stderr: self.stderr.is_some()
+                            || self.combined.is_some()
+                            || self.observes_output(),
+                        stream: true,
+                        ..Default::default()
+                    }),
+                )
+                .await
+                .map_err(Error::Docker)?;
+
+            // Pipe the stdin stream in the background, closing it once the
+            // source is exhausted
+            if let Some(mut stdin) = stdin {
+                let mut input = results.input;
+                let id = self.id.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = tokio::io::copy(&mut stdin, &mut input).await {
+                        warn!("failed to write to stdin of container `{id}`: {e}");
+                    }
+
+                    let _ = input.shutdown().await;
+                });
+            }
+
+            Some(results.output)
         } else {
             None
         };
@@ -133,71 +831,93 @@ impl Container {
         info!("starting container `{id}` (task `{name}`)", id = self.id);
 
         // Start the container.
+        let started_at = SystemTime::now();
         self.client
             .start_container(&self.id, None::<StartContainerOptions>)
             .await
             .map_err(Error::Docker)?;
 
+        // Sample the resources consumed by the container while it runs
+        let begin = Instant::now();
+        let usage = Arc::new(Mutex::new(Usage::default()));
+        let mut running = Running {
+            client: self.client.clone(),
+            id: self.id.clone(),
+            tasks: vec![tokio::spawn(sample_usage(
+                self.client.clone(),
+                self.id.clone(),
+                usage.clone(),
+            ))],
+            exited: false,
+        };
+        if let Some((interval, callback)) = &self.heartbeat {
+            running.tasks.push(tokio::spawn(send_heartbeats(
+                *interval,
+                callback.clone(),
+                begin,
+                usage.clone(),
+            )));
+        }
+
         // Notify that the container has started
         started();
 
         info!("container `{id}` (task `{name}`) has started", id = self.id);
 
-        // Write the log streams
-        if self.stdout.is_some() || self.stderr.is_some() {
-            let mut stdout = match &self.stdout {
-                Some(path) => Some(File::create(path).await.map_err(|e| {
-                    Error::Message(format!(
-                        "failed to create stdout file `{path}`: {e}",
-                        path = path.display()
-                    ))
-                })?),
-                None => None,
-            };
-
-            let mut stderr = match &self.stderr {
-                Some(path) => Some(File::create(path).await.map_err(|e| {
-                    Error::Message(format!(
-                        "failed to create stderr file `{path}`: {e}",
-                        path = path.display()
-                    ))
-                })?),
-                None => None,
+        // Write the log streams, keeping the end of each and reporting their
+        // lines
+        if self.has_outputs() || self.observes_output() {
+            let mut outputs = match self.has_outputs() {
+                true => Some(self.outputs().await?),
+                false => None,
             };
+            let mut tails = self
+                .tail
+                .map(|limit| (TailBuffer::new(limit), TailBuffer::new(limit)));
+            let mut lines = Lines::new(self.output_encoding);
 
             let mut stream = stream.expect("should have attached to the container");
             while let Some(result) = stream.next().await {
                 let output = result.map_err(Error::Docker)?;
-                match output {
-                    LogOutput::StdOut { message } => {
-                        stdout
-                            .as_mut()
-                            .unwrap()
-                            .write(&message)
-                            .await
-                            .map_err(|e| {
-                                Error::Message(format!(
-                                    "failed to write to stdout file `{path}`: {e}",
-                                    path = self.stdout.as_ref().unwrap().display()
-                                ))
-                            })?;
+                if let Some((stdout, stderr)) = &mut tails {
+                    match &output {
+                        LogOutput::StdOut { message } => stdout.push(message),
+                        LogOutput::StdErr { message } => stderr.push(message),
+                        _ => {}
                     }
-                    LogOutput::StdErr { message } => {
-                        stderr
-                            .as_mut()
-                            .unwrap()
-                            .write(&message)
-                            .await
-                            .map_err(|e| {
-                                Error::Message(format!(
-                                    "failed to write to stderr file `{path}`: {e}",
-                                    path = self.stderr.as_ref().unwrap().display()
-                                ))
-                            })?;
+                }
+
+                if let Some(on_line) = &self.on_line {
+                    let (stream, message) = match &output {
+                        LogOutput::StdOut { message } => (OutputStream::Stdout, message),
+                        LogOutput::StdErr { message } => (OutputStream::Stderr, message),
+                        _ => continue,
+                    };
+
+                    for line in lines.push(stream, message) {
+                        on_line(stream, &line);
                     }
-                    _ => {}
+                }
+
+                if let Some(outputs) = &mut outputs {
+                    outputs.write(output).await?;
                 }
             }
+
+            if let Some(outputs) = outputs {
+                outputs.finish().await?;
+            }
+
+            if let Some(on_line) = &self.on_line {
+                for (stream, line) in lines.finish() {
+                    on_line(stream, &line);
+                }
+            }
+
+            *self.tails.lock().unwrap() = tails.map(|(stdout, stderr)| Tail {
+                stdout: stdout.contents(self.output_encoding),
+                stderr: stderr.contents(self.output_encoding),
+            });
         }
 
         // Wait for the container to be completed.
@@ -219,40 +939,407 @@ impl Container {
                 | Err(bollard::errors::Error::DockerContainerWaitError { code, .. }) => {
                     exit_code = Some(code);
                 }
+                Err(bollard::errors::Error::DockerResponseServerError {
+                    status_code: 404, ..
+                }) => {
+                    running.exited = true;
+                    return Err(Error::Vanished(self.id.clone()));
+                }
                 Err(e) => return Err(e.into()),
             }
         }
 
-        if exit_code.is_none() {
-            // Get the exit code if the wait was immediate
-            let container = self
+        running.exited = true;
+        drop(running);
+
+        let ended = SystemTime::now();
+
+        // Inspect the container to get the exit code (if the wait was
+        // immediate), determine if it ran out of memory, and record what ran
+        let inspect = self
+            .client
+            .inspect_container(&self.id, None::<InspectContainerOptions>)
+            .await
+            .map_err(Error::Docker)?;
+        let state = inspect
+            .state
+            .expect("Docker reported a container without a state");
+
+        let code = exit_code
+            .or(state.exit_code)
+            .expect("Docker reported a finished contained without an exit code");
+        let status = exit_status(code);
+        let oom_killed = code != 0 && state.oom_killed == Some(true);
+
+        let usage = Usage {
+            wall: begin.elapsed(),
+            ..*usage.lock().unwrap()
+        };
+        *self.usage.lock().unwrap() = Some(usage);
+        *self.report.lock().unwrap() = Some(Report {
+            image: inspect.config.and_then(|c| c.image).unwrap_or_default(),
+            image_id: inspect.image,
+            command: inspect
+                .path
+                .into_iter()
+                .chain(inspect.args.unwrap_or_default())
+                .collect(),
+            started: started_at,
+            ended,
+            status,
+            signal: signal(code),
+            oom_killed,
+            usage,
+            stdout: self.stdout.clone(),
+            stderr: self.stderr.clone(),
+            combined: self.combined.clone(),
+        });
+
+        // The report is kept so that the exit status of a container that ran
+        // out of memory can still be determined
+        if oom_killed {
+            return Err(Error::OutOfMemory(self.id.clone()));
+        }
+
+        info!(
+            "container `{id}` (task `{name}`) has exited with {status}",
+            id = self.id
+        );
+
+        Ok(status)
+    }
+
+    /// Runs a container created with a pseudo-terminal, connecting it to the
+    /// host's terminal until the container exits.
+    ///
+    /// The host's terminal is put into raw mode while the container runs and
+    /// the container's pseudo-terminal is resized along with it (on Unix).
+    pub(crate) async fn run_interactive(&self) -> Result<ExitStatus> {
+        let results = self
+            .client
+            .attach_container(
+                &self.id,
+                Some(AttachContainerOptions {
+                    stdin: true,
+                    stdout: true,
+                    stderr: true,
+                    stream: true,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .map_err(Error::Docker)?;
+
+        self.client
+            .start_container(&self.id, None::<StartContainerOptions>)
+            .await
+            .map_err(Error::Docker)?;
+
+        let raw = terminal::RawMode::enable();
+
+        // Resize the container's pseudo-terminal whenever the host's terminal
+        // is resized
+        let resize = |client: Docker, id: String| async move {
+            if let Some((rows, columns)) = terminal::size() {
+                let options = ResizeContainerTTYOptions {
+                    h: rows.into(),
+                    w: columns.into(),
+                };
+
+                if let Err(e) = client.resize_container_tty(&id, options).await {
+                    debug!("failed to resize the terminal of container `{id}`: {e}");
+                }
+            }
+        };
+
+        resize(self.client.clone(), self.id.clone()).await;
+        #[cfg(unix)]
+        let resizer = {
+            let client = self.client.clone();
+            let id = self.id.clone();
+            let mut signals =
+                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::window_change())
+                    .map_err(|e| Error::Message(format!("failed to watch the terminal: {e}")))?;
+            tokio::spawn(async move {
+                while signals.recv().await.is_some() {
+                    resize(client.clone(), id.clone()).await;
+                }
+            })
+        };
+
+        // Forward the terminal's input until the container exits
+        let mut input = results.input;
+        let forwarder = tokio::spawn(async move {
+            let _ = tokio::io::copy(&mut tokio::io::stdin(), &mut input).await;
+        });
+
+        let mut output = results.output;
+        let mut stdout = tokio::io::stdout();
+        let result: Result<()> = async {
+            while let Some(chunk) = output.next().await {
+                stdout
+                    .write_all(&chunk.map_err(Error::Docker)?.into_bytes())
+                    .await
+                    .map_err(|e| Error::Message(format!("failed to write to the terminal: {e}")))?;
+                stdout.flush().await.ok();
+            }
+
+            Ok(())
+        }
+        .await;
+
+        forwarder.abort();
+        #[cfg(unix)]
+        resizer.abort();
+        drop(raw);
+        result?;
+
+        self.exit_status().await
+    }
+
+    /// Waits for a container that was already started (e.g., by a process
+    /// that has since exited) to exit.
+    ///
+    /// Any files the container's output streams are written to are rewritten
+    /// from the container's logs, starting from when the container started.
+    pub async fn wait(&self) -> Result<ExitStatus> {
+        if self.has_outputs() {
+            let mut outputs = self.outputs().await?;
+            let mut logs = self.client.logs(
+                &self.id,
+                Some(LogsOptions {
+                    follow: true,
+                    stdout: self.stdout.is_some() || self.combined.is_some(),
+                    stderr: self.stderr.is_some() || self.combined.is_some(),
+                    ..Default::default()
+                }),
+            );
+
+            while let Some(result) = logs.next().await {
+                outputs.write(result.map_err(Error::Docker)?).await?;
+            }
+
+            outputs.finish().await?;
+        }
+
+        self.exit_status().await
+    }
+
+    /// Waits for the container to exit and gets its exit status.
+    async fn exit_status(&self) -> Result<ExitStatus> {
+        let mut wait = self
+            .client
+            .wait_container(&self.id, None::<WaitContainerOptions>);
+        let code = match wait.next().await {
+            // Bollard turns non-zero exit codes into wait errors, so check for both
+            Some(Ok(ContainerWaitResponse { status_code, .. })) => status_code,
+            Some(Err(bollard::errors::Error::DockerContainerWaitError { code, .. })) => code,
+            Some(Err(bollard::errors::Error::DockerResponseServerError {
+                status_code: 404,
+                ..
+            })) => return Err(Error::Vanished(self.id.clone())),
+            Some(Err(e)) => return Err(e.into()),
+            None => self
                 .client
                 .inspect_container(&self.id, None::<InspectContainerOptions>)
                 .await
-                .map_err(Error::Docker)?;
+                .map_err(Error::Docker)?
+                .state
+                .and_then(|state| state.exit_code)
+                .ok_or_else(|| Error::Vanished(self.id.clone()))?,
+        };
 
-            exit_code = Some(
-                container
-                    .state
-                    .expect("Docker reported a container without a state")
-                    .exit_code
-                    .expect("Docker reported a finished contained without an exit code"),
-            );
+        Ok(exit_status(code))
+    }
+
+    /// Determines if any of the container's output streams are written to
+    /// files.
+    fn has_outputs(&self) -> bool {
+        self.stdout.is_some() || self.stderr.is_some() || self.combined.is_some()
+    }
+
+    /// Determines if the container's output streams are observed other than
+    /// by writing them to files.
+    fn observes_output(&self) -> bool {
+        self.tail.is_some() || self.on_line.is_some()
+    }
+
+    /// Creates the destinations of the container's output streams.
+    async fn outputs(&self) -> Result<Outputs> {
+        if let Some(path) = &self.combined {
+            return Ok(Outputs::Combined {
+                combiner: Combiner::default(),
+                sink: Sink::file("combined output", path, self.append_output).await?,
+            });
         }
 
-        // See WEXITSTATUS from wait(2) to explain the shift
-        #[cfg(unix)]
-        let status = ExitStatus::from_raw((exit_code.unwrap() as i32) << 8);
+        Ok(Outputs::Separate {
+            stdout: match &self.stdout {
+                Some(path) => Some(Sink::file("stdout", path, self.append_output).await?),
+                None => None,
+            },
+            stderr: match &self.stderr {
+                Some(path) => Some(Sink::file("stderr", path, self.append_output).await?),
+                None => None,
+            },
+        })
+    }
 
-        #[cfg(windows)]
-        let status = ExitStatus::from_raw(exit_code.unwrap() as u32);
+    /// Starts the container without attaching to or waiting on it.
+    ///
+    /// This is useful for long-running containers that commands are later
+    /// executed within (see [`Self::exec()`]). To run a container to
+    /// completion, see [`Self::run()`].
+    pub async fn start(&self) -> Result<()> {
+        debug!("starting container `{id}`", id = self.id);
+        self.client
+            .start_container(&self.id, None::<StartContainerOptions>)
+            .await
+            .map_err(Error::Docker)
+    }
 
-        info!(
-            "container `{id}` (task `{name}`) has exited with {status}",
+    /// Stops a running container.
+    ///
+    /// The container is sent `SIGTERM` and, if it has not exited by the time
+    /// the grace period elapses, `SIGKILL`. If no grace period is specified,
+    /// the daemon's default is used.
+    pub async fn stop(&self, grace: Option<Duration>) -> Result<()> {
+        debug!("stopping container `{id}`", id = self.id);
+        self.client
+            .stop_container(
+                &self.id,
+                Some(StopContainerOptions {
+                    t: grace.map(|d| d.as_secs().try_into().unwrap_or(i32::MAX)),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .map_err(Error::Docker)
+    }
+
+    /// Sends a signal to the running container.
+    ///
+    /// If no signal is specified, `SIGKILL` is sent. To give the container a
+    /// chance to exit gracefully, see [`Self::stop()`].
+    pub async fn kill(&self, signal: Option<&str>) -> Result<()> {
+        debug!(
+            "sending signal `{signal}` to container `{id}`",
+            signal = signal.unwrap_or("SIGKILL"),
             id = self.id
         );
 
-        Ok(status)
+        self.client
+            .kill_container(
+                &self.id,
+                Some(KillContainerOptions {
+                    signal: signal.unwrap_or("SIGKILL").to_string(),
+                }),
+            )
+            .await
+            .map_err(Error::Docker)
+    }
+
+    /// Executes a command within the running container and waits for it to
+    /// complete.
+    ///
+    /// The container must have been started (see [`Self::start()`]). The
+    /// command's stdout and stderr are collected and returned along with its
+    /// exit status.
+    ///
+    /// The output is captured in memory without limit; to limit it or to
+    /// write it to files instead, see [`Self::exec_with()`].
+    pub async fn exec(
+        &self,
+        command: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<ExecOutput> {
+        self.exec_with(command, &ExecOptions::default()).await
+    }
+
+    /// Executes a command within the running container with the given
+    /// options and waits for it to complete.
+    ///
+    /// Docker cannot stop an individual execution, so if the returned future
+    /// is dropped, the command keeps running until it exits or the container
+    /// is stopped.
+    ///
+    /// See [`Self::exec()`].
+    #[instrument(skip_all, fields(container = %self.id))]
+    pub async fn exec_with(
+        &self,
+        command: impl IntoIterator<Item = impl Into<String>>,
+        options: &ExecOptions,
+    ) -> Result<ExecOutput> {
+        let cmd = command.into_iter().map(Into::into).collect::<Vec<_>>();
+        if cmd.is_empty() {
+            return Err(Error::Message(String::from(
+                "cannot execute an empty command",
+            )));
+        }
+
+        debug!(
+            "executing `{cmd}` in container `{id}`",
+            cmd = cmd.join(" "),
+            id = self.id
+        );
+
+        let exec = self
+            .client
+            .create_exec(
+                &self.id,
+                ExecConfig {
+                    attach_stdout: Some(true),
+                    attach_stderr: Some(true),
+                    cmd: Some(cmd),
+                    env: (!options.env.is_empty()).then(|| {
+                        options
+                            .env
+                            .iter()
+                            .map(|(name, value)| format!("{name}={value}"))
+                            .collect()
+                    }),
+                    working_dir: options.work_dir.clone(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(Error::Docker)?;
+
+        let mut outputs = options.outputs().await?;
+        if let StartExecResults::Attached { mut output, .. } = self
+            .client
+            .start_exec(&exec.id, None)
+            .await
+            .map_err(Error::Docker)?
+        {
+            while let Some(result) = output.next().await {
+                outputs.write(result.map_err(Error::Docker)?).await?;
+            }
+        }
+
+        let captured = outputs.finish().await?;
+
+        let inspect = self
+            .client
+            .inspect_exec(&exec.id)
+            .await
+            .map_err(Error::Docker)?;
+
+        let code = inspect.exit_code.ok_or_else(|| {
+            Error::Message(format!(
+                "Docker did not report an exit code for exec `{id}`",
+                id = exec.id
+            ))
+        })?;
+
+        Ok(ExecOutput {
+            status: exit_status(code),
+            stdout: captured.stdout.0,
+            stderr: captured.stderr.0,
+            stdout_truncated: captured.stdout.1,
+            stderr_truncated: captured.stderr.1,
+            combined: captured.combined.0,
+            combined_truncated: captured.combined.1,
+        })
     }
 
     /// Removes a container with the level of force specified.
@@ -293,3 +1380,77 @@ impl Container {
         self.remove_inner(true).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Seek as _;
+
+    use super::*;
+
+    #[test]
+    fn infers_signals() {
+        assert_eq!(signal(0), None);
+        assert_eq!(signal(1), None);
+        assert_eq!(signal(128), None);
+        assert_eq!(signal(137), Some(9));
+        assert_eq!(signal(143), Some(15));
+        assert_eq!(signal(255), None);
+    }
+
+    #[test]
+    fn unpacks_archives() {
+        let mut builder = tar::Builder::new(tempfile::tempfile().unwrap());
+        for (path, contents) in [("out/a.txt", "hello"), ("out/sub/b.txt", "world!")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+
+        let mut archive = builder.into_inner().unwrap();
+        archive.rewind().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("collected");
+        assert_eq!(unpack(&archive, &destination).unwrap(), 11);
+        assert_eq!(
+            std::fs::read_to_string(destination.join("a.txt")).unwrap(),
+            "hello"
+        );
+        assert_eq!(
+            std::fs::read_to_string(destination.join("sub/b.txt")).unwrap(),
+            "world!"
+        );
+    }
+
+    #[test]
+    fn rejects_links() {
+        let outside = tempfile::tempdir().unwrap();
+
+        let mut builder = tar::Builder::new(tempfile::tempfile().unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        builder
+            .append_link(&mut header, "out/escape", outside.path())
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "out/escape/file.txt", "owned".as_bytes())
+            .unwrap();
+
+        let mut archive = builder.into_inner().unwrap();
+        archive.rewind().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let e = unpack(&archive, &dir.path().join("collected")).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+        assert!(!outside.path().join("file.txt").exists());
+    }
+}
@@ -1,18 +1,142 @@
 //! Builders for containers.
 
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::Path;
 use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
 
 use bollard::Docker;
 use bollard::models::ContainerCreateBody;
 use bollard::query_parameters::CreateContainerOptions;
 use bollard::secret::HostConfig;
+use bollard::secret::Mount;
+use bollard::secret::MountTypeEnum;
 use indexmap::IndexMap;
+use tokio::io::AsyncRead;
 use tracing::info;
 use tracing::warn;
 
+use crate::Bind;
 use crate::Container;
+use crate::Encoding;
 use crate::Error;
+use crate::Heartbeat;
+use crate::Locale;
+use crate::NamespaceOptions;
 use crate::Result;
+use crate::SchedulingOptions;
+use crate::SecurityOptions;
+use crate::Tmpfs;
+use crate::Volume;
+use crate::certs::CA_BUNDLE_ENV;
+use crate::certs::CA_BUNDLE_TARGET;
+use crate::container::HeartbeatCallback;
+use crate::container::LineCallback;
+use crate::container::OutputStream;
+use crate::container::Stdin;
+use crate::validate;
+use crate::validate::ValidationError;
+
+mod cli;
+mod explain;
+mod template;
+
+pub use explain::Setting;
+pub use explain::SettingSource;
+pub use template::Invocation;
+pub use template::Template;
+
+/// The policy for handling an image's entrypoint.
+///
+/// This mirrors the semantics of `docker run`: an image's entrypoint is
+/// either replaced by the provided program, prefixed to the provided program
+/// and arguments, or run as-is with only the provided arguments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Entrypoint {
+    /// The image's entrypoint is ignored and the provided program is run
+    /// with its arguments.
+    ///
+    /// A program must be provided.
+    #[default]
+    Ignore,
+
+    /// The image's entrypoint is prefixed to the provided program and its
+    /// arguments.
+    ///
+    /// A program must be provided.
+    Prefix,
+
+    /// The image's entrypoint is run with only the provided arguments.
+    ///
+    /// A program must not be provided. If no arguments are provided, the
+    /// image's default command is used.
+    Honor,
+}
+
+/// Appends values to an optional list within the host configuration.
+///
+/// The list is left untouched if there are no values to append.
+fn append<T>(list: &mut Option<Vec<T>>, values: Vec<T>) {
+    if !values.is_empty() {
+        list.get_or_insert_default().extend(values);
+    }
+}
+
+/// The subdirectories of a scratch directory and the paths they are bound to
+/// within the container.
+const SCRATCH_DIRS: [(&str, &str); 2] = [("tmp", "/tmp"), ("var_tmp", "/var/tmp")];
+
+/// Creates a directory on the host (and any missing parents) if it does not
+/// exist, optionally with the given permissions.
+async fn create_dir(name: &str, path: &Path, mode: Option<u32>) -> Result<()> {
+    let mut builder = tokio::fs::DirBuilder::new();
+    builder.recursive(true);
+
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        builder.mode(mode);
+    }
+
+    #[cfg(not(unix))]
+    let _ = mode;
+
+    builder.create(path).await.map_err(|e| {
+        Error::Message(format!(
+            "failed to create {name} `{path}`: {e}",
+            path = path.display()
+        ))
+    })
+}
+
+/// A customization of the request to create a container.
+type Customization = dyn Fn(&mut ContainerCreateBody) + Send + Sync;
+
+/// Parses the output of `env` into a map of variable names to values.
+///
+/// Lines without a `=` (e.g., continuations of values containing newlines) are
+/// appended to the value of the preceding variable.
+fn parse_env(output: &str) -> IndexMap<String, String> {
+    let mut env = IndexMap::<String, String>::new();
+    for line in output.lines() {
+        match line.split_once('=') {
+            Some((name, value)) if !name.is_empty() => {
+                env.insert(name.to_string(), value.to_string());
+            }
+            _ => {
+                if let Some((_, value)) = env.last_mut() {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+            }
+        }
+    }
+
+    env
+}
 
 /// A builder for a [`Container`].
 pub struct Builder {
@@ -29,20 +153,123 @@ pub struct Builder {
     /// The arguments to the command.
     args: Vec<String>,
 
+    /// The policy for handling the image's entrypoint.
+    entrypoint: Entrypoint,
+
+    /// The source of the container's stdin stream.
+    stdin: Option<Stdin>,
+
     /// The file path to write the container's stdout stream to.
     stdout: Option<PathBuf>,
 
     /// The file path to write the container's stderr stream to.
     stderr: Option<PathBuf>,
 
+    /// The file path to write the container's combined stdout and stderr
+    /// streams to.
+    combined: Option<PathBuf>,
+
+    /// Whether to append to the stdout and stderr files rather than truncate
+    /// them.
+    append_output: bool,
+
+    /// The number of bytes of the end of each output stream to keep.
+    tail: Option<usize>,
+
+    /// The callback for the lines of output of the container while it runs.
+    on_line: Option<Arc<LineCallback>>,
+
+    /// How the output of the container is decoded into text.
+    output_encoding: Encoding,
+
     /// Environment variables.
     env: IndexMap<String, String>,
 
+    /// The environment files to read variables from.
+    env_files: Vec<PathBuf>,
+
+    /// Environment variables whose values are sensitive.
+    secret_env: IndexMap<String, String>,
+
+    /// The locale and timezone.
+    locale: Locale,
+
     /// The working directory.
     work_dir: Option<String>,
 
+    /// The directory on the host to bind as the working directory.
+    host_work_dir: Option<PathBuf>,
+
+    /// The permissions of the host working directory if it is created.
+    work_dir_mode: Option<u32>,
+
+    /// The directory on the host to bind as the container's temporary
+    /// directories.
+    scratch_dir: Option<PathBuf>,
+
+    /// The user (and optionally group) to run as.
+    user: Option<String>,
+
+    /// The supplementary groups for the user.
+    groups: Vec<String>,
+
+    /// Whether to prevent processes from gaining additional privileges.
+    no_new_privileges: bool,
+
+    /// The security options.
+    security: SecurityOptions,
+
+    /// The user namespace mode.
+    userns_mode: Option<String>,
+
+    /// The namespace isolation options.
+    namespaces: NamespaceOptions,
+
+    /// The scheduling priority options.
+    scheduling: SchedulingOptions,
+
     /// Host configuration.
     host_config: Option<HostConfig>,
+
+    /// The mounts for the container.
+    ///
+    /// These are appended to any mounts within the host configuration.
+    mounts: Vec<Mount>,
+
+    /// Whether to create the missing sources of bind mounts as directories.
+    create_bind_sources: bool,
+
+    /// The extra `/etc/hosts` entries as pairs of hostname and IP address.
+    extra_hosts: Vec<(String, String)>,
+
+    /// The DNS servers.
+    dns: Vec<String>,
+
+    /// The DNS search domains.
+    dns_search: Vec<String>,
+
+    /// The DNS resolver options.
+    dns_options: Vec<String>,
+
+    /// The network to connect to.
+    network: Option<String>,
+
+    /// The hostname.
+    hostname: Option<String>,
+
+    /// The time given to the container to exit when stopped before it is
+    /// killed.
+    stop_timeout: Option<Duration>,
+
+    /// The labels of the container.
+    labels: HashMap<String, String>,
+
+    /// The interval and callback for reporting heartbeats while the container
+    /// runs.
+    heartbeat: Option<(Duration, Arc<HeartbeatCallback>)>,
+
+    /// The customizations to apply to the request to create the container.
+    customizations: Vec<Arc<Customization>>,
 }
 
 impl Builder {
@@ -53,11 +280,43 @@ impl Builder {
             image: Default::default(),
             program: Default::default(),
             args: Default::default(),
+            entrypoint: Default::default(),
+            stdin: None,
             stdout: None,
             stderr: None,
+            combined: None,
+            append_output: false,
+            tail: None,
+            on_line: None,
+            output_encoding: Default::default(),
             env: Default::default(),
+            env_files: Default::default(),
+            secret_env: Default::default(),
+            locale: Default::default(),
             work_dir: Default::default(),
+            host_work_dir: Default::default(),
+            work_dir_mode: Default::default(),
+            scratch_dir: Default::default(),
+            user: Default::default(),
+            groups: Default::default(),
+            no_new_privileges: false,
+            security: Default::default(),
+            userns_mode: Default::default(),
+            namespaces: Default::default(),
+            scheduling: Default::default(),
             host_config: Default::default(),
+            mounts: Default::default(),
+            create_bind_sources: false,
+            extra_hosts: Default::default(),
+            dns: Default::default(),
+            dns_search: Default::default(),
+            dns_options: Default::default(),
+            network: Default::default(),
+            hostname: Default::default(),
+            stop_timeout: None,
+            labels: Default::default(),
+            heartbeat: None,
+            customizations: Default::default(),
         }
     }
 
@@ -85,6 +344,30 @@ impl Builder {
         self
     }
 
+    /// Sets the policy for handling the image's entrypoint.
+    ///
+    /// Defaults to [`Entrypoint::Ignore`].
+    pub fn entrypoint(mut self, entrypoint: Entrypoint) -> Self {
+        self.entrypoint = entrypoint;
+        self
+    }
+
+    /// Sets the reader to pipe to the container's stdin stream.
+    ///
+    /// The container's stdin stream is closed once the reader is exhausted.
+    pub fn stdin(mut self, reader: impl AsyncRead + Send + Unpin + 'static) -> Self {
+        self.stdin = Some(Box::new(reader));
+        self
+    }
+
+    /// Sets the bytes to pipe to the container's stdin stream.
+    ///
+    /// The container's stdin stream is closed once all of the bytes are
+    /// written.
+    pub fn stdin_bytes(self, bytes: impl Into<Vec<u8>>) -> Self {
+        self.stdin(Cursor::new(bytes.into()))
+    }
+
     /// Sets the file to write the container's stdout stream to.
     pub fn stdout(mut self, path: impl Into<PathBuf>) -> Self {
         self.stdout = Some(path.into());
@@ -97,6 +380,78 @@ impl Builder {
         self
     }
 
+    /// Sets the file to write the container's stdout and stderr streams to as
+    /// a single, combined stream.
+    ///
+    /// Lines from the two streams are interleaved in the order they are
+    /// received, each prefixed with an RFC 3339 timestamp and the name of the
+    /// stream it was written to. This cannot be set with [`Self::stdout()`] or
+    /// [`Self::stderr()`].
+    pub fn combined_output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.combined = Some(path.into());
+        self
+    }
+
+    /// Sets whether to append to the stdout and stderr files rather than
+    /// truncate them when the container is run.
+    ///
+    /// The files are created if they do not exist. Defaults to `false`.
+    pub fn append_output(mut self, append: bool) -> Self {
+        self.append_output = append;
+        self
+    }
+
+    /// Keeps the last `bytes` of the container's stdout and stderr streams
+    /// when it is run (see [`Container::tail()`]).
+    ///
+    /// The end of the output is kept whether or not the streams are written
+    /// to files so that the final messages of a failing program are always
+    /// available (e.g., for error reports).
+    pub fn tail(mut self, bytes: usize) -> Self {
+        self.tail = Some(bytes);
+        self
+    }
+
+    /// Sets a callback to receive each line of the container's stdout and
+    /// stderr streams as it is written while the container runs.
+    ///
+    /// Lines are reported without their line endings; a final partial line is
+    /// reported once the container exits. This is independent of whether the
+    /// streams are written to files.
+    pub fn on_line(
+        mut self,
+        callback: impl Fn(OutputStream, &str) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_line = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sets how the output of the container is decoded into the text of the
+    /// lines reported to [`Self::on_line()`] and the end of the output kept
+    /// with [`Self::tail()`].
+    ///
+    /// This does not affect the files the output is written to, which always
+    /// contain the raw bytes. Defaults to [`Encoding::Utf8`].
+    pub fn output_encoding(mut self, encoding: Encoding) -> Self {
+        self.output_encoding = encoding;
+        self
+    }
+
+    /// Sets a callback to receive a [`Heartbeat`] at the given interval while
+    /// the container runs.
+    ///
+    /// Heartbeats report the time the container has been running along with
+    /// the resources it has consumed so far, allowing long-running containers
+    /// to be monitored.
+    pub fn heartbeat(
+        mut self,
+        interval: Duration,
+        callback: impl Fn(&Heartbeat) + Send + Sync + 'static,
+    ) -> Self {
+        self.heartbeat = Some((interval, Arc::new(callback)));
+        self
+    }
+
     /// Sets an environment variable.
     pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
         self.env.insert(name.into(), value.into());
@@ -113,32 +468,694 @@ impl Builder {
         self
     }
 
-    /// Sets the working directory.
+    /// Sets a label on the container.
+    ///
+    /// Labels can be used to find the container later (see
+    /// [`Docker::list_labeled_containers()`](crate::Docker::list_labeled_containers)).
+    pub fn label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets environment variables from an environment file on the host.
+    ///
+    /// The file is read when the builder is built (see [`crate::env::parse()`]
+    /// for its format). Environment variables set with [`Self::env()`] take
+    /// precedence over those read from environment files.
+    pub fn env_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.env_files.push(path.into());
+        self
+    }
+
+    /// Sets an environment variable whose value is sensitive.
+    ///
+    /// The value is never logged. Secret environment variables take
+    /// precedence over all other environment variables.
+    pub fn secret_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.secret_env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets the locale and timezone.
+    ///
+    /// Environment variables set with [`Self::env()`] take precedence over
+    /// those derived from the locale.
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Sets the working directory (i.e., the directory the program starts in
+    /// within the container).
+    ///
+    /// To change where the container's temporary files are written, see
+    /// [`Self::scratch_dir()`].
     pub fn work_dir(mut self, work_dir: impl Into<String>) -> Self {
         self.work_dir = Some(work_dir.into());
         self
     }
 
+    /// Binds a directory on the host as the working directory.
+    ///
+    /// The directory is bound to the working directory set with
+    /// [`Self::work_dir()`] or, if none is set, to the same path within the
+    /// container. If the directory does not exist, it (and any missing
+    /// parents) is created when the container is built rather than the
+    /// container failing to start.
+    pub fn host_work_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.host_work_dir = Some(path.into());
+        self
+    }
+
+    /// Sets the permissions (e.g., `0o750`) of the directories created for
+    /// the host working directory (see [`Self::host_work_dir()`]).
+    ///
+    /// The permissions are subject to the process's umask and are only
+    /// applied on Unix. Existing directories are left unchanged.
+    pub fn work_dir_mode(mut self, mode: u32) -> Self {
+        self.work_dir_mode = Some(mode);
+        self
+    }
+
+    /// Binds subdirectories of a directory on the host as the container's
+    /// `/tmp` and `/var/tmp` directories.
+    ///
+    /// This keeps large temporary files out of the container's writable layer
+    /// (e.g., by placing them on a scratch filesystem). The `tmp` and
+    /// `var_tmp` subdirectories are created when the container is built if
+    /// they do not exist. Unlike [`Self::work_dir()`], this does not change
+    /// the directory the program starts in.
+    pub fn scratch_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.scratch_dir = Some(path.into());
+        self
+    }
+
+    /// Gets the binds of the scratch directory (if one is set).
+    fn scratch_binds(&self) -> Vec<Bind> {
+        self.scratch_dir
+            .iter()
+            .flat_map(|dir| {
+                SCRATCH_DIRS
+                    .iter()
+                    .map(|(name, target)| Bind::new(dir.join(name).display().to_string(), *target))
+            })
+            .collect()
+    }
+
+    /// Gets the working directory within the container.
+    fn guest_work_dir(&self) -> Option<String> {
+        self.work_dir.clone().or_else(|| {
+            self.host_work_dir
+                .as_ref()
+                .map(|path| path.display().to_string())
+        })
+    }
+
+    /// Sets the user (and optionally group) to run as.
+    ///
+    /// This may be a name or id, optionally followed by a group name or id
+    /// (e.g., `nobody`, `1000`, or `1000:1000`). If not set, the user
+    /// specified by the image is used.
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Runs as the root user (and group), regardless of the user specified by
+    /// the image.
+    ///
+    /// This is needed for tasks that must appear to be root within the
+    /// container (e.g., to install packages).
+    pub fn root(self) -> Self {
+        self.user("0:0")
+    }
+
+    /// Adds a supplementary group (by name or id) for the user to be a member
+    /// of.
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.groups.push(group.into());
+        self
+    }
+
+    /// Sets whether to prevent processes from gaining additional privileges
+    /// (e.g., through setuid binaries).
+    pub fn no_new_privileges(mut self, no_new_privileges: bool) -> Self {
+        self.no_new_privileges = no_new_privileges;
+        self
+    }
+
+    /// Sets the security options (e.g., the seccomp profile, capabilities,
+    /// and SELinux label).
+    pub fn security(mut self, security: SecurityOptions) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// Sets the user namespace mode.
+    ///
+    /// When the Docker daemon remaps users into a user namespace, setting this
+    /// to `host` runs the container in the host's user namespace instead (so
+    /// that user and group ids are not remapped).
+    pub fn userns_mode(mut self, mode: impl Into<String>) -> Self {
+        self.userns_mode = Some(mode.into());
+        self
+    }
+
+    /// Sets the namespace isolation options (i.e., which of the PID, IPC,
+    /// and UTS namespaces are shared with the host and whether to run an init
+    /// process).
+    pub fn namespaces(mut self, namespaces: NamespaceOptions) -> Self {
+        self.namespaces = namespaces;
+        self
+    }
+
+    /// Sets the scheduling priority options (i.e., the niceness and IO
+    /// priority of the container and the CPUs it may run on).
+    pub fn scheduling(mut self, scheduling: SchedulingOptions) -> Self {
+        self.scheduling = scheduling;
+        self
+    }
+
     /// Sets the host configuration.
     pub fn host_config(mut self, host_config: HostConfig) -> Self {
         self.host_config = Some(host_config);
         self
     }
 
-    /// Consumes `self` and attempts to create a Docker container.
+    /// Sets a mount for the container.
+    pub fn mount(mut self, mount: impl Into<Mount>) -> Self {
+        self.mounts.push(mount.into());
+        self
+    }
+
+    /// Sets multiple mounts for the container.
+    pub fn mounts(mut self, mounts: impl IntoIterator<Item = impl Into<Mount>>) -> Self {
+        self.mounts.extend(mounts.into_iter().map(Into::into));
+        self
+    }
+
+    /// Binds a host path into the container.
+    pub fn bind(self, source: impl Into<String>, target: impl Into<String>) -> Self {
+        self.mount(Bind::new(source, target))
+    }
+
+    /// Binds a host path into the container as read-only.
+    pub fn bind_ro(self, source: impl Into<String>, target: impl Into<String>) -> Self {
+        self.mount(Bind::read_only(source, target))
+    }
+
+    /// Sets whether to create the missing sources of bind mounts (as
+    /// directories) before the container is created.
     ///
-    /// Note that the creation of a container does not start the container.
-    pub async fn try_build(self, name: impl Into<String>) -> Result<Container> {
+    /// By default, a bind mount whose source does not exist fails validation
+    /// (see [`Self::validate()`]).
+    pub fn create_bind_sources(mut self, create: bool) -> Self {
+        self.create_bind_sources = create;
+        self
+    }
+
+    /// Mounts a named volume into the container.
+    ///
+    /// The volume is created if it does not exist and its contents persist
+    /// after the container is removed.
+    pub fn volume(self, name: impl Into<String>, target: impl Into<String>) -> Self {
+        self.mount(Volume::new(name, target))
+    }
+
+    /// Mounts a writable, in-memory filesystem of an optional maximum size (in
+    /// bytes) for scratch space.
+    pub fn tmpfs(self, target: impl Into<String>, size: Option<u64>) -> Self {
+        let tmpfs = Tmpfs::new(target);
+        self.mount(match size {
+            Some(size) => tmpfs.size(size),
+            None => tmpfs,
+        })
+    }
+
+    /// Adds an entry to the container's `/etc/hosts` file.
+    pub fn extra_host(mut self, hostname: impl Into<String>, ip: impl Into<String>) -> Self {
+        self.extra_hosts.push((hostname.into(), ip.into()));
+        self
+    }
+
+    /// Adds a DNS server for the container to use.
+    ///
+    /// Setting a DNS server replaces the nameservers of the container's
+    /// `/etc/resolv.conf` file.
+    pub fn dns(mut self, server: impl Into<String>) -> Self {
+        self.dns.push(server.into());
+        self
+    }
+
+    /// Adds a DNS search domain for the container to use.
+    pub fn dns_search(mut self, domain: impl Into<String>) -> Self {
+        self.dns_search.push(domain.into());
+        self
+    }
+
+    /// Adds a DNS resolver option (e.g., `ndots:2`) for the container to use.
+    pub fn dns_option(mut self, option: impl Into<String>) -> Self {
+        self.dns_options.push(option.into());
+        self
+    }
+
+    /// Sets the network to connect to.
+    ///
+    /// This may be the name or id of a user-defined network or one of the
+    /// networks built into Docker (`bridge`, `host`, or `none`).
+    pub fn network(mut self, network: impl Into<String>) -> Self {
+        self.network = Some(network.into());
+        self
+    }
+
+    /// Isolates the container from the network.
+    ///
+    /// This is equivalent to connecting to the `none` network.
+    pub fn disable_network(self) -> Self {
+        self.network("none")
+    }
+
+    /// Sets the hostname of the container.
+    pub fn hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    /// Sets the time given to the container to exit after being sent
+    /// `SIGTERM` when stopped (e.g., with [`Container::stop()`] without a
+    /// grace period) before it is killed.
+    ///
+    /// Defaults to the Docker daemon's default (usually 10 seconds).
+    pub fn stop_timeout(mut self, timeout: Duration) -> Self {
+        self.stop_timeout = Some(timeout);
+        self
+    }
+
+    /// Binds a CA bundle from the host into the container as read-only.
+    ///
+    /// The bundle is bound to [`CA_BUNDLE_TARGET`] and each of the variables in
+    /// [`CA_BUNDLE_ENV`] is set to point to it. The host's CA bundle can be
+    /// located with [`host_ca_bundle()`](crate::certs::host_ca_bundle).
+    pub fn ca_bundle(self, path: impl Into<String>) -> Self {
+        self.bind_ro(path, CA_BUNDLE_TARGET)
+            .envs(CA_BUNDLE_ENV.iter().map(|name| (*name, CA_BUNDLE_TARGET)))
+    }
+
+    /// Customizes the request to create the container with options that the
+    /// builder does not otherwise support (e.g., site-specific labels or
+    /// runtimes).
+    ///
+    /// Customizations are applied in the order they were added after every
+    /// other option, so they may override the builder's own options. They are
+    /// not checked by [`Self::validate()`].
+    pub fn customize(
+        mut self,
+        customize: impl Fn(&mut ContainerCreateBody) + Send + Sync + 'static,
+    ) -> Self {
+        self.customizations.push(Arc::new(customize));
+        self
+    }
+
+    /// Validates the builder without creating a container.
+    ///
+    /// This checks that the image and program are not empty, that the sources
+    /// of bind mounts exist and are accessible, that paths within the container
+    /// are absolute and normalized, and that no conflicting options are
+    /// set. Validation is performed automatically by [`Self::try_build()`].
+    pub fn validate(&self) -> Result<()> {
+        self.validate_on(true)
+    }
+
+    /// Validates the builder, checking that the sources of bind mounts exist
+    /// only if the container is created on this host (`local`).
+    fn validate_on(&self, local: bool) -> Result<()> {
         let image = self
             .image
+            .as_deref()
+            .ok_or(Error::MissingBuilderField("image"))?;
+        validate::non_empty("image", image)?;
+
+        match (&self.program, self.entrypoint) {
+            (Some(_), Entrypoint::Honor) => {
+                return Err(ValidationError::Conflict("program", "Entrypoint::Honor").into());
+            }
+            (Some(program), _) => validate::non_empty("program", program)?,
+            (None, Entrypoint::Honor) => {}
+            (None, _) => return Err(Error::MissingBuilderField("program")),
+        }
+
+        if self.combined.is_some() {
+            if self.stdout.is_some() {
+                return Err(ValidationError::Conflict("combined_output", "stdout").into());
+            }
+
+            if self.stderr.is_some() {
+                return Err(ValidationError::Conflict("combined_output", "stderr").into());
+            }
+        }
+
+        if let Some(work_dir) = self.guest_work_dir() {
+            validate::guest_path("working directory", &work_dir)?;
+        }
+
+        if let Some(path) = &self.host_work_dir {
+            validate::host_path("host working directory", path)?;
+        }
+
+        if let Some(path) = &self.scratch_dir {
+            validate::host_path("scratch directory", path)?;
+
+            let targets = SCRATCH_DIRS.map(|(_, target)| target);
+            let mounts = self
+                .host_config
+                .as_ref()
+                .and_then(|c| c.mounts.as_deref())
+                .unwrap_or_default();
+            if mounts
+                .iter()
+                .chain(&self.mounts)
+                .any(|m| m.target.as_deref().is_some_and(|t| targets.contains(&t)))
+            {
+                return Err(ValidationError::Conflict(
+                    "scratch_dir",
+                    "a mount at `/tmp` or `/var/tmp`",
+                )
+                .into());
+            }
+        }
+
+        let host_mounts = self
+            .host_config
+            .as_ref()
+            .and_then(|c| c.mounts.as_deref())
+            .unwrap_or_default();
+        validate::mounts(
+            host_mounts.iter().chain(&self.mounts),
+            local,
+            self.create_bind_sources,
+        )?;
+
+        let network = self.network.as_deref().unwrap_or_default();
+        if network.starts_with("container:") {
+            if self.hostname.is_some() {
+                return Err(ValidationError::Conflict("hostname", "a `container:` network").into());
+            }
+
+            if !self.dns.is_empty() || !self.dns_search.is_empty() || !self.dns_options.is_empty() {
+                return Err(ValidationError::Conflict("dns", "a `container:` network").into());
+            }
+
+            if !self.extra_hosts.is_empty() {
+                return Err(
+                    ValidationError::Conflict("extra_host", "a `container:` network").into(),
+                );
+            }
+        }
+
+        if network == "host" && self.hostname.is_some() {
+            return Err(ValidationError::Conflict("hostname", "the `host` network").into());
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the request that would be sent to the Docker daemon to create
+    /// the container without creating it.
+    ///
+    /// This is useful for debugging how the builder's options are translated
+    /// and for embedding the request elsewhere (e.g., in job scripts). The
+    /// builder is validated (see [`Self::validate()`]) and any environment
+    /// files are read, but the values of secret environment variables (see
+    /// [`Self::secret_env()`]) are redacted.
+    pub fn dry_run(&self) -> Result<ContainerCreateBody> {
+        self.validate()?;
+        self.body(true)
+    }
+
+    /// Runs an interactive shell within a container created with the same
+    /// options, connecting it to the host's terminal through a
+    /// pseudo-terminal.
+    ///
+    /// This allows debugging a task within the same mounts, environment, and
+    /// resources that it runs with. The program, arguments, and output
+    /// options of the builder are replaced by the shell. The container is
+    /// removed once the shell exits.
+    ///
+    /// The image must already exist within the Docker daemon.
+    pub async fn shell_interactive(mut self, shell: impl Into<String>) -> Result<ExitStatus> {
+        self.program = Some(shell.into());
+        self.args = Vec::new();
+        self.entrypoint = Entrypoint::Ignore;
+        self.stdin = None;
+        self.stdout = None;
+        self.stderr = None;
+        self.combined = None;
+        self.append_output = false;
+        self.heartbeat = None;
+        self.tail = None;
+        self.on_line = None;
+        self.customizations
+            .push(Arc::new(|body: &mut ContainerCreateBody| {
+                body.tty = Some(true);
+                body.open_stdin = Some(true);
+                body.stdin_once = Some(true);
+                body.attach_stdin = Some(true);
+                body.attach_stdout = Some(true);
+                body.attach_stderr = Some(true);
+            }));
+
+        let name = format!("crankshaft-shell-{id:08x}", id = rand::random::<u32>());
+        let container = self.try_build(&name).await?;
+        let result = container.run_interactive().await;
+        if let Err(e) = container.force_remove().await {
+            warn!("failed to remove container `{name}` used for an interactive shell: {e}");
+        }
+
+        result
+    }
+
+    /// Resolves the environment the container's program would see by running
+    /// `env` within a container created with the same options.
+    ///
+    /// Unlike [`Self::dry_run()`], this includes the variables set by the
+    /// image itself. The values of secret environment variables (see
+    /// [`Self::secret_env()`]) are redacted. The probe container is removed
+    /// once it exits.
+    ///
+    /// The image must already exist within the Docker daemon and must contain
+    /// an `env` program.
+    pub async fn probe_env(mut self) -> Result<IndexMap<String, String>> {
+        let io_error =
+            |e: std::io::Error| Error::Message(format!("failed to probe environment: {e}"));
+
+        let dir = tempfile::tempdir().map_err(io_error)?;
+        let path = dir.path().join("env");
+        let secrets = self.secret_env.keys().cloned().collect::<Vec<_>>();
+
+        self.program = Some(String::from("env"));
+        self.args = Vec::new();
+        self.entrypoint = Entrypoint::Ignore;
+        self.stdin = None;
+        self.stdout = Some(path.clone());
+        self.stderr = None;
+        self.combined = None;
+        self.append_output = false;
+        self.heartbeat = None;
+        self.tail = None;
+        self.on_line = None;
+
+        let name = format!("crankshaft-env-{id:08x}", id = rand::random::<u32>());
+        let container = self.try_build(&name).await?;
+        let result = container.run(&name, || {}).await;
+        if let Err(e) = container.force_remove().await {
+            warn!("failed to remove container `{name}` used to probe the environment: {e}");
+        }
+
+        let status = result?;
+        if !status.success() {
+            return Err(Error::Message(format!(
+                "failed to probe environment: `env` exited with {status}"
+            )));
+        }
+
+        let mut env = parse_env(&std::fs::read_to_string(&path).map_err(io_error)?);
+        for name in secrets {
+            if let Some(value) = env.get_mut(&name) {
+                *value = String::from("<redacted>");
+            }
+        }
+
+        Ok(env)
+    }
+
+    /// Resolves the body of the request to create the container, optionally
+    /// redacting the values of secret environment variables.
+    fn body(&self, redact: bool) -> Result<ContainerCreateBody> {
+        let mut body = self.uncustomized_body(redact)?;
+        for customize in &self.customizations {
+            customize(&mut body);
+        }
+
+        Ok(body)
+    }
+
+    /// Resolves the body of the request to create the container before any
+    /// customizations are applied.
+    fn uncustomized_body(&self, redact: bool) -> Result<ContainerCreateBody> {
+        let image = self
+            .image
+            .clone()
             .ok_or_else(|| Error::MissingBuilderField("image"))?;
-        let program = self
-            .program
-            .ok_or_else(|| Error::MissingBuilderField("program"))?;
 
-        let mut cmd = Vec::with_capacity(1 + self.args.len());
-        cmd.push(program);
-        cmd.extend(self.args);
+        let (entrypoint, cmd) = match self.entrypoint {
+            Entrypoint::Ignore | Entrypoint::Prefix => {
+                let program = self
+                    .program
+                    .clone()
+                    .ok_or_else(|| Error::MissingBuilderField("program"))?;
+
+                let mut cmd = Vec::with_capacity(1 + self.args.len());
+                cmd.push(program);
+                cmd.extend(self.args.iter().cloned());
+
+                // Overriding the entrypoint with an empty string resets it to
+                // the default Docker entrypoint as we're providing the full
+                // command
+                let entrypoint = match self.entrypoint {
+                    Entrypoint::Ignore => Some(vec![String::new()]),
+                    _ => None,
+                };
+
+                (entrypoint, Some(cmd))
+            }
+            // NOTE: validation ensures a program is not specified when
+            // honoring the image's entrypoint
+            Entrypoint::Honor => (None, (!self.args.is_empty()).then(|| self.args.clone())),
+        };
+
+        let mut host_config = self.host_config.clone().unwrap_or_default();
+        append(&mut host_config.mounts, self.mounts.clone());
+        if let (Some(source), Some(target)) = (&self.host_work_dir, self.guest_work_dir()) {
+            append(
+                &mut host_config.mounts,
+                vec![Bind::new(source.display().to_string(), target).into()],
+            );
+        }
+        append(
+            &mut host_config.mounts,
+            self.scratch_binds().into_iter().map(Into::into).collect(),
+        );
+        append(
+            &mut host_config.extra_hosts,
+            self.extra_hosts
+                .iter()
+                .map(|(hostname, ip)| format!("{hostname}:{ip}"))
+                .collect(),
+        );
+        append(&mut host_config.dns, self.dns.clone());
+        append(&mut host_config.dns_search, self.dns_search.clone());
+        append(&mut host_config.dns_options, self.dns_options.clone());
+        append(&mut host_config.group_add, self.groups.clone());
+
+        append(&mut host_config.security_opt, self.security.security_opt()?);
+        append(&mut host_config.cap_add, self.security.add_caps().to_vec());
+        append(
+            &mut host_config.cap_drop,
+            self.security.drop_caps().to_vec(),
+        );
+
+        if self.no_new_privileges {
+            append(
+                &mut host_config.security_opt,
+                vec![String::from("no-new-privileges:true")],
+            );
+        }
+
+        if let Some(network) = &self.network {
+            host_config.network_mode = Some(network.clone());
+        }
+
+        if let Some(mode) = &self.userns_mode {
+            host_config.userns_mode = Some(mode.clone());
+        }
+
+        self.namespaces.apply(&mut host_config);
+        self.scheduling.apply(&mut host_config);
+
+        let mut env = self.locale.env();
+        for path in &self.env_files {
+            env.extend(crate::env::read(path)?);
+        }
+        env.extend(self.env.clone());
+        env.extend(self.secret_env.iter().map(|(k, v)| {
+            let v = if redact { "<redacted>" } else { v.as_str() };
+            (k.clone(), v.to_string())
+        }));
+
+        let observed = self.tail.is_some() || self.on_line.is_some();
+        Ok(ContainerCreateBody {
+            // NOTE: even though the following fields are optional, I
+            // want _this_ struct to require the explicit designation
+            // one way or the other and not rely on the default.
+            cmd,
+            image: Some(image),
+            entrypoint,
+            attach_stdin: Some(self.stdin.is_some()),
+            open_stdin: Some(self.stdin.is_some()),
+            stdin_once: Some(self.stdin.is_some()),
+            attach_stdout: Some(self.stdout.is_some() || self.combined.is_some() || observed),
+            attach_stderr: Some(self.stderr.is_some() || self.combined.is_some() || observed),
+            // END NOTE
+            working_dir: self.guest_work_dir(),
+            user: self.user.clone(),
+            hostname: self.hostname.clone(),
+            stop_timeout: self
+                .stop_timeout
+                .map(|t| t.as_secs().try_into().unwrap_or(i64::MAX)),
+            host_config: Some(host_config),
+            env: Some(env.iter().map(|(k, v)| format!("{k}={v}")).collect()),
+            labels: (!self.labels.is_empty()).then(|| self.labels.clone()),
+            ..Default::default()
+        })
+    }
+
+    /// Consumes `self` and attempts to create a Docker container.
+    ///
+    /// Note that the creation of a container does not start the container.
+    ///
+    /// The builder is validated (see [`Self::validate()`]) before the container
+    /// is created.
+    pub async fn try_build(self, name: impl Into<String>) -> Result<Container> {
+        self.validate()?;
+        self.create(name).await
+    }
+
+    /// Consumes `self` and creates a Docker container without validating the
+    /// builder.
+    async fn create(self, name: impl Into<String>) -> Result<Container> {
+        let body = self.body(false)?;
+
+        if let Some(path) = &self.host_work_dir {
+            create_dir("working directory", path, self.work_dir_mode).await?;
+        }
+
+        for bind in self.scratch_binds() {
+            create_dir("scratch directory", Path::new(bind.source()), None).await?;
+        }
+
+        if self.create_bind_sources {
+            for source in body
+                .host_config
+                .iter()
+                .flat_map(|c| c.mounts.iter().flatten())
+                .filter(|m| m.typ == Some(MountTypeEnum::BIND))
+                .filter_map(|m| m.source.as_deref())
+                .map(Path::new)
+                .filter(|p| !p.exists())
+            {
+                create_dir("bind mount source", source, None).await?;
+            }
+        }
 
         let name = name.into();
         let response = self
@@ -148,23 +1165,7 @@ impl Builder {
                     name: Some(name.clone()),
                     ..Default::default()
                 }),
-                ContainerCreateBody {
-                    // NOTE: even though the following fields are optional, I
-                    // want _this_ struct to require the explicit designation
-                    // one way or the other and not rely on the default.
-                    cmd: Some(cmd),
-                    image: Some(image),
-                    // Override the entrypoint to the default Docker entrypoint as we're providing
-                    // the full command
-                    entrypoint: Some(vec![String::new()]),
-                    attach_stdout: Some(self.stdout.is_some()),
-                    attach_stderr: Some(self.stderr.is_some()),
-                    // END NOTE
-                    working_dir: self.work_dir,
-                    host_config: self.host_config,
-                    env: Some(self.env.iter().map(|(k, v)| format!("{k}={v}")).collect()),
-                    ..Default::default()
-                },
+                body,
             )
             .await
             .map_err(Error::Docker)?;
@@ -178,8 +1179,102 @@ impl Builder {
         Ok(Container {
             client: self.client,
             id: response.id,
+            stdin: Mutex::new(self.stdin),
+            usage: Default::default(),
+            report: Default::default(),
             stdout: self.stdout,
             stderr: self.stderr,
+            combined: self.combined,
+            append_output: self.append_output,
+            heartbeat: self.heartbeat,
+            tail: self.tail,
+            on_line: self.on_line,
+            output_encoding: self.output_encoding,
+            tails: Default::default(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_env() {
+        let env = parse_env("PATH=/usr/bin:/bin\nEMPTY=\nMULTI=first\nsecond\nEQ=a=b\n");
+        assert_eq!(env["PATH"], "/usr/bin:/bin");
+        assert_eq!(env["EMPTY"], "");
+        assert_eq!(env["MULTI"], "first\nsecond");
+        assert_eq!(env["EQ"], "a=b");
+        assert_eq!(env.len(), 4);
+    }
+
+    #[test]
+    fn customizations() {
+        let client = Docker::connect_with_http_defaults().unwrap();
+        let body = Builder::new(client)
+            .image("ubuntu:latest")
+            .program("true")
+            .user("nobody")
+            .customize(|body| {
+                body.labels = Some([(String::from("site"), String::from("hpc"))].into());
+            })
+            .customize(|body| body.user = Some(String::from("root")))
+            .dry_run()
+            .unwrap();
+
+        assert_eq!(body.labels.unwrap()["site"], "hpc");
+        assert_eq!(body.user.as_deref(), Some("root"));
+    }
+
+    #[test]
+    fn host_work_dir() {
+        let client = Docker::connect_with_http_defaults().unwrap();
+        let builder = Builder::new(client)
+            .image("ubuntu:latest")
+            .program("true")
+            .host_work_dir("/does/not/exist");
+
+        // The directory is only created when the container is built
+        let body = builder.dry_run().unwrap();
+        assert_eq!(body.working_dir.as_deref(), Some("/does/not/exist"));
+        let mount = &body.host_config.unwrap().mounts.unwrap()[0];
+        assert_eq!(mount.source.as_deref(), Some("/does/not/exist"));
+        assert_eq!(mount.target.as_deref(), Some("/does/not/exist"));
+
+        let body = builder.work_dir("/work").dry_run().unwrap();
+        assert_eq!(body.working_dir.as_deref(), Some("/work"));
+
+        let client = Docker::connect_with_http_defaults().unwrap();
+        assert!(
+            Builder::new(client)
+                .image("ubuntu:latest")
+                .program("true")
+                .host_work_dir("relative")
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn scratch_dir() {
+        let client = Docker::connect_with_http_defaults().unwrap();
+        let builder = Builder::new(client)
+            .image("ubuntu:latest")
+            .program("true")
+            .scratch_dir("/scratch");
+
+        let body = builder.dry_run().unwrap();
+        assert_eq!(body.working_dir, None);
+        let mounts = body.host_config.unwrap().mounts.unwrap();
+        assert_eq!(
+            mounts
+                .iter()
+                .map(|m| (m.source.as_deref().unwrap(), m.target.as_deref().unwrap()))
+                .collect::<Vec<_>>(),
+            [("/scratch/tmp", "/tmp"), ("/scratch/var_tmp", "/var/tmp")]
+        );
+
+        assert!(builder.tmpfs("/tmp", None).validate().is_err());
+    }
+}
@@ -0,0 +1,315 @@
+//! Rendering builders as the arguments of `docker run`.
+
+use bollard::secret::DeviceRequest;
+use bollard::secret::Mount;
+use bollard::secret::MountTypeEnum;
+
+use super::Builder;
+use crate::Error;
+use crate::Result;
+
+/// The fields of the host configuration that can be rendered as flags.
+const SUPPORTED: &[&str] = &[
+    "Mounts",
+    "Memory",
+    "MemoryReservation",
+    "MemorySwap",
+    "NanoCpus",
+    "CpuShares",
+    "BlkioWeight",
+    "CpusetCpus",
+    "PidsLimit",
+    "ShmSize",
+    "NetworkMode",
+    "UsernsMode",
+    "PidMode",
+    "IpcMode",
+    "UTSMode",
+    "Dns",
+    "DnsSearch",
+    "DnsOptions",
+    "ExtraHosts",
+    "GroupAdd",
+    "CapAdd",
+    "CapDrop",
+    "SecurityOpt",
+    "Privileged",
+    "ReadonlyRootfs",
+    "Init",
+    "Runtime",
+    "StorageOpt",
+    "DeviceRequests",
+];
+
+/// Quotes a field of a comma-separated flag value (e.g., of `--mount`) if it
+/// contains a comma or a quote.
+fn field(name: &str, value: &str) -> String {
+    let field = format!("{name}={value}");
+    if field.contains([',', '"']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+/// Renders a mount as the value of a `--mount` flag.
+fn mount(mount: &Mount) -> String {
+    let mut fields = Vec::new();
+    let typ = mount.typ.unwrap_or(MountTypeEnum::BIND);
+    fields.push(field("type", typ.as_ref()));
+
+    if typ != MountTypeEnum::TMPFS {
+        if let Some(source) = &mount.source {
+            fields.push(field("source", source));
+        }
+    }
+
+    fields.push(field("target", mount.target.as_deref().unwrap_or_default()));
+
+    if mount.read_only == Some(true) {
+        fields.push(String::from("readonly"));
+    }
+
+    if let Some(size) = mount.tmpfs_options.as_ref().and_then(|o| o.size_bytes) {
+        fields.push(field("tmpfs-size", &size.to_string()));
+    }
+
+    fields.join(",")
+}
+
+/// Renders a request for GPUs as the value of a `--gpus` flag.
+fn gpus(request: &DeviceRequest) -> Result<String> {
+    let gpu = request
+        .capabilities
+        .iter()
+        .flatten()
+        .any(|c| c.iter().any(|c| c == "gpu"));
+    if !gpu {
+        return Err(Error::Message(String::from(
+            "device requests other than for GPUs cannot be rendered as `docker run` flags",
+        )));
+    }
+
+    Ok(match (&request.device_ids, request.count) {
+        (Some(ids), _) => format!("\"device={ids}\"", ids = ids.join(",")),
+        (None, Some(-1) | None) => String::from("all"),
+        (None, Some(count)) => count.to_string(),
+    })
+}
+
+impl Builder {
+    /// Renders the builder as the arguments of `docker run` (i.e., the
+    /// arguments following `run`).
+    ///
+    /// This allows containers to be run by launchers that wrap the Docker
+    /// CLI (e.g., site-specific job scripts) with the same flags, mounts, and
+    /// environment as containers created by the builder. The builder is
+    /// validated (see [`Self::validate()`]) and environment files are read,
+    /// but the sources of bind mounts are not checked, as the container may be
+    /// run on another host.
+    ///
+    /// Unlike [`Self::dry_run()`], the values of secret environment variables
+    /// are included, so the arguments should be treated as secret if there
+    /// are any. Files that output is written to (e.g., [`Self::stdout()`]) are
+    /// not rendered, and customizations that set fields without an equivalent
+    /// flag are an error.
+    pub fn run_args(&self) -> Result<Vec<String>> {
+        self.validate_on(false)?;
+        let body = self.body(false)?;
+        let mut args = Vec::new();
+        let mut flag = |name: &str, value: &str| {
+            args.push(format!("--{name}={value}"));
+        };
+
+        if body.open_stdin == Some(true) {
+            flag("interactive", "true");
+        }
+
+        if body.tty == Some(true) {
+            flag("tty", "true");
+        }
+
+        if let Some(entrypoint) = &body.entrypoint {
+            match entrypoint.as_slice() {
+                [entrypoint] => flag("entrypoint", entrypoint),
+                _ => {
+                    return Err(Error::Message(String::from(
+                        "an entrypoint with arguments cannot be rendered as a `docker run` flag",
+                    )));
+                }
+            }
+        }
+
+        if let Some(timeout) = body.stop_timeout {
+            flag("stop-timeout", &timeout.to_string());
+        }
+
+        for (name, value) in [
+            ("workdir", &body.working_dir),
+            ("user", &body.user),
+            ("hostname", &body.hostname),
+        ] {
+            if let Some(value) = value {
+                flag(name, value);
+            }
+        }
+
+        for var in body.env.iter().flatten() {
+            flag("env", var);
+        }
+
+        let mut labels = body.labels.iter().flatten().collect::<Vec<_>>();
+        labels.sort();
+        for (key, value) in labels {
+            flag("label", &format!("{key}={value}"));
+        }
+
+        let host_config = body.host_config.unwrap_or_default();
+        let unsupported = match serde_json::to_value(&host_config) {
+            Ok(serde_json::Value::Object(map)) => map
+                .into_iter()
+                .filter(|(k, v)| !v.is_null() && !SUPPORTED.contains(&k.as_str()))
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>(),
+            _ => Vec::new(),
+        };
+        if let Some(field) = unsupported.first() {
+            return Err(Error::Message(format!(
+                "`HostConfig.{field}` cannot be rendered as a `docker run` flag"
+            )));
+        }
+
+        for m in host_config.mounts.iter().flatten() {
+            flag("mount", &mount(m));
+        }
+
+        for (name, value) in [
+            ("memory", host_config.memory),
+            ("memory-reservation", host_config.memory_reservation),
+            ("memory-swap", host_config.memory_swap),
+            ("cpu-shares", host_config.cpu_shares),
+            ("blkio-weight", host_config.blkio_weight.map(i64::from)),
+            ("pids-limit", host_config.pids_limit),
+            ("shm-size", host_config.shm_size),
+        ] {
+            if let Some(value) = value {
+                flag(name, &value.to_string());
+            }
+        }
+
+        if let Some(nano_cpus) = host_config.nano_cpus {
+            flag("cpus", &(nano_cpus as f64 / 1e9).to_string());
+        }
+
+        for (name, value) in [
+            ("cpuset-cpus", &host_config.cpuset_cpus),
+            ("network", &host_config.network_mode),
+            ("userns", &host_config.userns_mode),
+            ("pid", &host_config.pid_mode),
+            ("ipc", &host_config.ipc_mode),
+            ("uts", &host_config.uts_mode),
+            ("runtime", &host_config.runtime),
+        ] {
+            if let Some(value) = value {
+                flag(name, value);
+            }
+        }
+
+        for (name, values) in [
+            ("dns", &host_config.dns),
+            ("dns-search", &host_config.dns_search),
+            ("dns-option", &host_config.dns_options),
+            ("add-host", &host_config.extra_hosts),
+            ("group-add", &host_config.group_add),
+            ("cap-add", &host_config.cap_add),
+            ("cap-drop", &host_config.cap_drop),
+            ("security-opt", &host_config.security_opt),
+        ] {
+            for value in values.iter().flatten() {
+                flag(name, value);
+            }
+        }
+
+        for (name, value) in [
+            ("privileged", host_config.privileged),
+            ("read-only", host_config.readonly_rootfs),
+            ("init", host_config.init),
+        ] {
+            if let Some(value) = value {
+                flag(name, &value.to_string());
+            }
+        }
+
+        let mut storage_opt = host_config.storage_opt.iter().flatten().collect::<Vec<_>>();
+        storage_opt.sort();
+        for (key, value) in storage_opt {
+            flag("storage-opt", &format!("{key}={value}"));
+        }
+
+        for request in host_config.device_requests.iter().flatten() {
+            flag("gpus", &gpus(request)?);
+        }
+
+        args.push(body.image.unwrap_or_default());
+        args.extend(body.cmd.into_iter().flatten());
+        Ok(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bollard::Docker;
+    use bollard::secret::HostConfig;
+
+    use super::*;
+
+    #[test]
+    fn renders_run_args() {
+        let client = Docker::connect_with_http_defaults().unwrap();
+        let builder = Builder::new(client)
+            .image("ubuntu:latest")
+            .program("echo")
+            .args(["hello", "world"])
+            .env("GREETING", "hello")
+            .work_dir("/work")
+            .volume("cache", "/cache")
+            .tmpfs("/scratch", Some(1024))
+            .host_config(HostConfig {
+                memory: Some(1024),
+                nano_cpus: Some(1_500_000_000),
+                ..Default::default()
+            });
+
+        let args = builder.run_args().unwrap();
+        for expected in [
+            "--entrypoint=",
+            "--workdir=/work",
+            "--env=GREETING=hello",
+            "--mount=type=volume,source=cache,target=/cache",
+            "--mount=type=tmpfs,target=/scratch,tmpfs-size=1024",
+            "--memory=1024",
+            "--cpus=1.5",
+        ] {
+            assert!(args.iter().any(|a| a == expected), "{expected}: {args:?}");
+        }
+        assert!(args.ends_with(&["ubuntu:latest", "echo", "hello", "world"].map(String::from)));
+
+        let client = Docker::connect_with_http_defaults().unwrap();
+        assert!(
+            Builder::new(client)
+                .image("ubuntu:latest")
+                .program("true")
+                .host_config(HostConfig {
+                    oom_score_adj: Some(100),
+                    ..Default::default()
+                })
+                .run_args()
+                .is_err()
+        );
+
+        assert_eq!(
+            field("source", "/data/a,b"),
+            "\"source=/data/a,b\"".to_string()
+        );
+    }
+}
@@ -0,0 +1,424 @@
+//! Explaining how the options of a builder resolve into a container.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use bollard::models::ContainerCreateBody;
+use bollard::secret::Mount;
+use bollard::secret::MountTypeEnum;
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use super::Builder;
+use super::Entrypoint;
+use crate::Bind;
+use crate::Result;
+use crate::certs::CA_BUNDLE_ENV;
+use crate::certs::CA_BUNDLE_TARGET;
+
+/// Where a resolved setting of a container came from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "kind", content = "name")]
+pub enum SettingSource {
+    /// The builder option with the given name (e.g., `mount` or
+    /// `scratch_dir`).
+    Option(&'static str),
+
+    /// The host configuration given to the builder (see
+    /// [`Builder::host_config()`]).
+    HostConfig,
+
+    /// The given environment file (see [`Builder::env_file()`]).
+    EnvFile(PathBuf),
+
+    /// A customization (see [`Builder::customize()`]).
+    Customization,
+}
+
+impl fmt::Display for SettingSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Option(name) => write!(f, "option `{name}`"),
+            Self::HostConfig => write!(f, "host config"),
+            Self::EnvFile(path) => write!(f, "env file `{path}`", path = path.display()),
+            Self::Customization => write!(f, "customization"),
+        }
+    }
+}
+
+/// A resolved setting of a container and where it came from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Setting {
+    /// The field of the request to create the container that the setting is
+    /// within (e.g., `Env` or `HostConfig.Mounts`).
+    pub field: String,
+
+    /// The value of the setting.
+    ///
+    /// Each element of a list (e.g., each environment variable or mount) is a
+    /// separate setting.
+    pub value: String,
+
+    /// Where the setting came from.
+    pub source: SettingSource,
+}
+
+impl Setting {
+    /// Creates a new setting.
+    fn new(field: impl Into<String>, value: impl Into<String>, source: SettingSource) -> Self {
+        Self {
+            field: field.into(),
+            value: value.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {} (from {})", self.field, self.value, self.source)
+    }
+}
+
+/// Describes a mount.
+fn describe(mount: &Mount) -> String {
+    let target = mount.target.as_deref().unwrap_or_default();
+    let ro = if mount.read_only == Some(true) {
+        " (read-only)"
+    } else {
+        ""
+    };
+
+    match (mount.typ, mount.source.as_deref()) {
+        (Some(MountTypeEnum::TMPFS), _) => format!("tmpfs at `{target}`"),
+        (Some(MountTypeEnum::VOLUME), Some(source)) => {
+            format!("volume `{source}` at `{target}`{ro}")
+        }
+        (_, Some(source)) => format!("`{source}` at `{target}`{ro}"),
+        (_, None) => format!("`{target}`{ro}"),
+    }
+}
+
+/// Gets the non-null fields of the request to create a container (including
+/// the fields of its host configuration) as JSON, keyed by field name.
+fn fields(body: &ContainerCreateBody) -> IndexMap<String, serde_json::Value> {
+    fn object(value: impl Serialize, prefix: &str) -> Vec<(String, serde_json::Value)> {
+        match serde_json::to_value(value).unwrap_or_default() {
+            serde_json::Value::Object(map) => map
+                .into_iter()
+                .filter(|(k, v)| !v.is_null() && k != "HostConfig")
+                .map(|(k, v)| (format!("{prefix}{k}"), v))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    let mut fields = object(body, "");
+    fields.extend(object(&body.host_config, "HostConfig."));
+    fields.into_iter().collect()
+}
+
+/// Splits a JSON value into the values of settings.
+fn values(value: serde_json::Value) -> Vec<String> {
+    match value {
+        serde_json::Value::Array(items) => items.into_iter().flat_map(values).collect(),
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .map(|(k, v)| format!("{k}={v}", v = values(v).join(",")))
+            .collect(),
+        serde_json::Value::String(s) => vec![s],
+        value => vec![value.to_string()],
+    }
+}
+
+impl Builder {
+    /// Explains how the request to create the container was resolved from the
+    /// builder's options.
+    ///
+    /// Each resolved setting (e.g., the image, each argument, environment
+    /// variable, and mount, and each resource limit) is attributed to the
+    /// builder option, host configuration, environment file, or customization
+    /// that produced it. Environment variables set by more than one source are
+    /// attributed to the source that took precedence.
+    ///
+    /// As with [`Self::dry_run()`], the builder is validated and the values of
+    /// secret environment variables are redacted.
+    pub fn explain(&self) -> Result<Vec<Setting>> {
+        self.validate()?;
+        let uncustomized = self.uncustomized_body(true)?;
+        let body = self.body(true)?;
+
+        let mut settings = Vec::new();
+        let mut push = |field: &str, value: &str, source| {
+            settings.push(Setting::new(field, value, source));
+        };
+
+        if let Some(image) = &self.image {
+            push("Image", image, SettingSource::Option("image"));
+        }
+
+        if self.entrypoint == Entrypoint::Ignore {
+            push("Entrypoint", "\"\"", SettingSource::Option("entrypoint"));
+        }
+
+        if let Some(program) = &self.program {
+            push("Cmd", program, SettingSource::Option("program"));
+        }
+
+        for arg in &self.args {
+            push("Cmd", arg, SettingSource::Option("args"));
+        }
+
+        if let Some(work_dir) = &uncustomized.working_dir {
+            let option = if self.work_dir.is_some() {
+                "work_dir"
+            } else {
+                "host_work_dir"
+            };
+            push("WorkingDir", work_dir, SettingSource::Option(option));
+        }
+
+        if let Some(user) = &self.user {
+            push("User", user, SettingSource::Option("user"));
+        }
+
+        if let Some(hostname) = &self.hostname {
+            push("Hostname", hostname, SettingSource::Option("hostname"));
+        }
+
+        // Each variable is attributed to the last source that set it, which
+        // mirrors the order in which the sources are layered
+        let mut env = IndexMap::new();
+        for name in self.locale.env().into_keys() {
+            env.insert(name, SettingSource::Option("locale"));
+        }
+        for path in &self.env_files {
+            for name in crate::env::read(path)?.into_keys() {
+                env.insert(name, SettingSource::EnvFile(path.clone()));
+            }
+        }
+        for (name, value) in &self.env {
+            let option = if CA_BUNDLE_ENV.contains(&name.as_str()) && value == CA_BUNDLE_TARGET {
+                "ca_bundle"
+            } else {
+                "env"
+            };
+            env.insert(name.clone(), SettingSource::Option(option));
+        }
+        for name in self.secret_env.keys() {
+            env.insert(name.clone(), SettingSource::Option("secret_env"));
+        }
+        for var in uncustomized.env.iter().flatten() {
+            let name = var.split_once('=').map(|(name, _)| name).unwrap_or(var);
+            if let Some(source) = env.get(name) {
+                push("Env", var, source.clone());
+            }
+        }
+
+        let mut labels = self.labels.iter().collect::<Vec<_>>();
+        labels.sort();
+        for (key, value) in labels {
+            push(
+                "Labels",
+                &format!("{key}={value}"),
+                SettingSource::Option("label"),
+            );
+        }
+
+        let host_config = self.host_config.clone().unwrap_or_default();
+        let work_dir = self
+            .host_work_dir
+            .as_ref()
+            .zip(self.guest_work_dir())
+            .map(|(source, target)| Mount::from(Bind::new(source.display().to_string(), target)));
+        let scratch = self
+            .scratch_binds()
+            .into_iter()
+            .map(Mount::from)
+            .collect::<Vec<_>>();
+        let mounts = uncustomized
+            .host_config
+            .as_ref()
+            .and_then(|c| c.mounts.as_ref());
+        for mount in mounts.into_iter().flatten() {
+            let source = if host_config.mounts.iter().flatten().any(|m| m == mount) {
+                SettingSource::HostConfig
+            } else if work_dir.as_ref() == Some(mount) {
+                SettingSource::Option("host_work_dir")
+            } else if scratch.contains(mount) {
+                SettingSource::Option("scratch_dir")
+            } else if mount.target.as_deref() == Some(CA_BUNDLE_TARGET) {
+                SettingSource::Option("ca_bundle")
+            } else {
+                SettingSource::Option("mount")
+            };
+            push("HostConfig.Mounts", &describe(mount), source);
+        }
+
+        // The builder's own options are appended to any set within the host
+        // configuration, so the latter are explained first
+        let mut host_config = fields(&ContainerCreateBody {
+            host_config: Some(host_config),
+            ..Default::default()
+        });
+        host_config.shift_remove("HostConfig.Mounts");
+        for (field, value) in host_config {
+            for value in values(value) {
+                push(&field, &value, SettingSource::HostConfig);
+            }
+        }
+
+        let lists: [(&str, &[String], &str); 4] = [
+            ("HostConfig.Dns", &self.dns, "dns"),
+            ("HostConfig.DnsSearch", &self.dns_search, "dns_search"),
+            ("HostConfig.DnsOptions", &self.dns_options, "dns_option"),
+            ("HostConfig.GroupAdd", &self.groups, "group"),
+        ];
+        for (field, values, option) in lists {
+            for value in values {
+                push(field, value, SettingSource::Option(option));
+            }
+        }
+
+        for (hostname, ip) in &self.extra_hosts {
+            push(
+                "HostConfig.ExtraHosts",
+                &format!("{hostname}:{ip}"),
+                SettingSource::Option("extra_host"),
+            );
+        }
+
+        for opt in self.security.security_opt()? {
+            push(
+                "HostConfig.SecurityOpt",
+                &opt,
+                SettingSource::Option("security"),
+            );
+        }
+        for cap in self.security.add_caps() {
+            push("HostConfig.CapAdd", cap, SettingSource::Option("security"));
+        }
+        for cap in self.security.drop_caps() {
+            push("HostConfig.CapDrop", cap, SettingSource::Option("security"));
+        }
+        if self.no_new_privileges {
+            push(
+                "HostConfig.SecurityOpt",
+                "no-new-privileges:true",
+                SettingSource::Option("no_new_privileges"),
+            );
+        }
+
+        if let Some(network) = &self.network {
+            push(
+                "HostConfig.NetworkMode",
+                network,
+                SettingSource::Option("network"),
+            );
+        }
+
+        if let Some(mode) = &self.userns_mode {
+            push(
+                "HostConfig.UsernsMode",
+                mode,
+                SettingSource::Option("userns_mode"),
+            );
+        }
+
+        // Customizations may change any field, so every field that differs
+        // from the uncustomized request is attributed to them in its entirety
+        let before = fields(&uncustomized);
+        let after = fields(&body);
+        let customized = before
+            .keys()
+            .chain(after.keys())
+            .filter(|field| before.get(*field) != after.get(*field))
+            .cloned()
+            .collect::<Vec<_>>();
+        settings.retain(|s| !customized.contains(&s.field));
+        for field in customized {
+            if field == "HostConfig.Mounts" {
+                let mounts = body.host_config.as_ref().and_then(|c| c.mounts.as_ref());
+                for mount in mounts.into_iter().flatten() {
+                    settings.push(Setting::new(
+                        &field,
+                        describe(mount),
+                        SettingSource::Customization,
+                    ));
+                }
+                continue;
+            }
+
+            for value in after.get(&field).cloned().map(values).into_iter().flatten() {
+                settings.push(Setting::new(&field, value, SettingSource::Customization));
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bollard::Docker;
+    use bollard::secret::HostConfig;
+
+    use super::*;
+
+    #[test]
+    fn explains_settings() {
+        let client = Docker::connect_with_http_defaults().unwrap();
+        let settings = Builder::new(client)
+            .image("ubuntu:latest")
+            .program("echo")
+            .arg("hello")
+            .env("GREETING", "hello")
+            .secret_env("TOKEN", "hunter2")
+            .host_work_dir("/does/not/exist")
+            .volume("cache", "/cache")
+            .host_config(HostConfig {
+                memory: Some(1024),
+                ..Default::default()
+            })
+            .customize(|body| body.user = Some(String::from("root")))
+            .explain()
+            .unwrap();
+
+        let find = |field: &str, value: &str| {
+            settings
+                .iter()
+                .find(|s| s.field == field && s.value == value)
+                .map(|s| s.source.clone())
+        };
+
+        assert_eq!(
+            find("Image", "ubuntu:latest"),
+            Some(SettingSource::Option("image"))
+        );
+        assert_eq!(find("Cmd", "hello"), Some(SettingSource::Option("args")));
+        assert_eq!(
+            find("Env", "GREETING=hello"),
+            Some(SettingSource::Option("env"))
+        );
+        assert_eq!(
+            find("Env", "TOKEN=<redacted>"),
+            Some(SettingSource::Option("secret_env"))
+        );
+        assert_eq!(
+            find(
+                "HostConfig.Mounts",
+                "`/does/not/exist` at `/does/not/exist`"
+            ),
+            Some(SettingSource::Option("host_work_dir"))
+        );
+        assert_eq!(
+            find("HostConfig.Mounts", "volume `cache` at `/cache`"),
+            Some(SettingSource::Option("mount"))
+        );
+        assert_eq!(
+            find("HostConfig.Memory", "1024"),
+            Some(SettingSource::HostConfig)
+        );
+        assert_eq!(find("User", "root"), Some(SettingSource::Customization));
+    }
+}
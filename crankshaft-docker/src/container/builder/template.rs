@@ -0,0 +1,277 @@
+//! Templates of containers that are created many times with small changes.
+
+use std::path::PathBuf;
+
+use bollard::models::ContainerCreateBody;
+
+use super::Builder;
+use crate::Container;
+use crate::Result;
+use crate::validate;
+use crate::validate::ValidationError;
+
+/// A validated builder that many near-identical containers are created from.
+///
+/// Running thousands of containers that differ only by their arguments,
+/// environment, or working directory (e.g., the shards of a scatter) with a
+/// fresh [`Builder`] each time repeats the same work for every container:
+/// reading environment files and checking that the source of every bind mount
+/// exists. A template does that work once (see [`Builder::template()`]) and
+/// each [`Invocation`] only checks what it overrides.
+///
+/// The image is not pulled by the template; it must exist within the Docker
+/// daemon before containers are created from it.
+pub struct Template(Builder);
+
+impl Builder {
+    /// Validates the builder (see [`Self::validate()`]) and converts it into a
+    /// template that containers can be created from with per-invocation
+    /// overrides.
+    ///
+    /// Environment files are read once, when the template is created, rather
+    /// than for each container. A template cannot have stdin (see
+    /// [`Self::stdin()`]), as stdin cannot be shared between containers.
+    pub fn template(mut self) -> Result<Template> {
+        self.validate()?;
+
+        if self.stdin.is_some() {
+            return Err(ValidationError::Conflict("template", "stdin").into());
+        }
+
+        let mut env = indexmap::IndexMap::new();
+        for path in self.env_files.drain(..) {
+            env.extend(crate::env::read(&path)?);
+        }
+        env.extend(std::mem::take(&mut self.env));
+        self.env = env;
+
+        Ok(Template(self))
+    }
+}
+
+impl Template {
+    /// Starts an invocation of the template.
+    ///
+    /// The invocation creates a container with the template's options and any
+    /// overrides set on the invocation.
+    pub fn invocation(&self) -> Invocation {
+        let builder = &self.0;
+        Invocation {
+            builder: Builder {
+                client: builder.client.clone(),
+                image: builder.image.clone(),
+                program: builder.program.clone(),
+                args: builder.args.clone(),
+                entrypoint: builder.entrypoint,
+                stdin: None,
+                stdout: builder.stdout.clone(),
+                stderr: builder.stderr.clone(),
+                combined: builder.combined.clone(),
+                append_output: builder.append_output,
+                tail: builder.tail,
+                on_line: builder.on_line.clone(),
+                output_encoding: builder.output_encoding,
+                env: builder.env.clone(),
+                env_files: Vec::new(),
+                secret_env: builder.secret_env.clone(),
+                locale: builder.locale.clone(),
+                work_dir: builder.work_dir.clone(),
+                host_work_dir: builder.host_work_dir.clone(),
+                work_dir_mode: builder.work_dir_mode,
+                scratch_dir: builder.scratch_dir.clone(),
+                user: builder.user.clone(),
+                groups: builder.groups.clone(),
+                no_new_privileges: builder.no_new_privileges,
+                security: builder.security.clone(),
+                userns_mode: builder.userns_mode.clone(),
+                namespaces: builder.namespaces.clone(),
+                scheduling: builder.scheduling.clone(),
+                host_config: builder.host_config.clone(),
+                mounts: builder.mounts.clone(),
+                create_bind_sources: builder.create_bind_sources,
+                extra_hosts: builder.extra_hosts.clone(),
+                dns: builder.dns.clone(),
+                dns_search: builder.dns_search.clone(),
+                dns_options: builder.dns_options.clone(),
+                network: builder.network.clone(),
+                hostname: builder.hostname.clone(),
+                stop_timeout: builder.stop_timeout,
+                labels: builder.labels.clone(),
+                heartbeat: builder.heartbeat.clone(),
+                customizations: builder.customizations.clone(),
+            },
+            work_dir: false,
+            outputs: false,
+        }
+    }
+}
+
+/// An invocation of a [`Template`] that overrides some of its options.
+///
+/// Only the overridden options are checked when the container is created.
+pub struct Invocation {
+    /// The builder of the container.
+    builder: Builder,
+
+    /// Whether the working directory was overridden.
+    work_dir: bool,
+
+    /// Whether the files that output is written to were overridden.
+    outputs: bool,
+}
+
+impl Invocation {
+    /// Adds an argument after the template's arguments.
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.builder.args.push(arg.into());
+        self
+    }
+
+    /// Adds multiple arguments after the template's arguments.
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.builder.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets an environment variable, overriding any set by the template.
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.builder.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets multiple environment variables, overriding any set by the
+    /// template.
+    pub fn envs(
+        mut self,
+        variables: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
+    ) -> Self {
+        self.builder
+            .env
+            .extend(variables.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Sets a label on the container.
+    pub fn label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.builder.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Overrides the working directory within the container (see
+    /// [`Builder::work_dir()`]).
+    pub fn work_dir(mut self, work_dir: impl Into<String>) -> Self {
+        self.builder.work_dir = Some(work_dir.into());
+        self.work_dir = true;
+        self
+    }
+
+    /// Overrides the working directory on the host (see
+    /// [`Builder::host_work_dir()`]).
+    pub fn host_work_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.builder.host_work_dir = Some(path.into());
+        self.work_dir = true;
+        self
+    }
+
+    /// Overrides the file the container's stdout is written to.
+    pub fn stdout(mut self, path: impl Into<PathBuf>) -> Self {
+        self.builder.stdout = Some(path.into());
+        self.outputs = true;
+        self
+    }
+
+    /// Overrides the file the container's stderr is written to.
+    pub fn stderr(mut self, path: impl Into<PathBuf>) -> Self {
+        self.builder.stderr = Some(path.into());
+        self.outputs = true;
+        self
+    }
+
+    /// Checks the options overridden by the invocation.
+    fn validate(&self) -> Result<()> {
+        let builder = &self.builder;
+        if self.outputs && builder.combined.is_some() {
+            let option = if builder.stdout.is_some() {
+                "stdout"
+            } else {
+                "stderr"
+            };
+            return Err(ValidationError::Conflict("combined_output", option).into());
+        }
+
+        if self.work_dir {
+            if let Some(work_dir) = builder.guest_work_dir() {
+                validate::guest_path("working directory", &work_dir)?;
+            }
+
+            if let Some(path) = &builder.host_work_dir {
+                validate::host_path("host working directory", path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the request that would be sent to the Docker daemon to create
+    /// the container without creating it (see [`Builder::dry_run()`]).
+    pub fn dry_run(&self) -> Result<ContainerCreateBody> {
+        self.validate()?;
+        self.builder.body(true)
+    }
+
+    /// Consumes the invocation and attempts to create a Docker container.
+    ///
+    /// Note that the creation of a container does not start the container.
+    pub async fn try_build(self, name: impl Into<String>) -> Result<Container> {
+        self.validate()?;
+        self.builder.create(name).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bollard::Docker;
+
+    use super::*;
+
+    #[test]
+    fn invocations() {
+        let dir = tempfile::tempdir().unwrap();
+        let env_file = dir.path().join("env");
+        std::fs::write(&env_file, "SHARED=file\nOVERRIDDEN=file\n").unwrap();
+
+        let client = Docker::connect_with_http_defaults().unwrap();
+        let template = Builder::new(client)
+            .image("ubuntu:latest")
+            .program("echo")
+            .arg("shard")
+            .env_file(&env_file)
+            .env("OVERRIDDEN", "template")
+            .template()
+            .unwrap();
+
+        // The environment file is only read when the template is created
+        std::fs::remove_file(&env_file).unwrap();
+
+        let body = template
+            .invocation()
+            .arg("1")
+            .env("SHARD", "1")
+            .work_dir("/work/1")
+            .dry_run()
+            .unwrap();
+        assert_eq!(body.cmd.unwrap(), ["echo", "shard", "1"]);
+        assert_eq!(body.working_dir.as_deref(), Some("/work/1"));
+        assert!(
+            body.env
+                .unwrap()
+                .ends_with(&["SHARED=file", "OVERRIDDEN=template", "SHARD=1"].map(String::from))
+        );
+
+        let body = template.invocation().arg("2").dry_run().unwrap();
+        assert_eq!(body.cmd.unwrap(), ["echo", "shard", "2"]);
+        assert_eq!(body.working_dir, None);
+
+        assert!(template.invocation().work_dir("work").dry_run().is_err());
+    }
+}
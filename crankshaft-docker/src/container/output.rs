@@ -0,0 +1,522 @@
+//! Destinations for the output streams of containers and executed commands.
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::path::PathBuf;
+
+use bollard::container::LogOutput;
+use chrono::SecondsFormat;
+use chrono::Utc;
+use tokio::fs::File;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt as _;
+
+use crate::Encoding;
+use crate::Error;
+use crate::Result;
+
+/// An output stream of a container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stream {
+    /// The stdout stream.
+    Stdout,
+
+    /// The stderr stream.
+    Stderr,
+}
+
+impl Stream {
+    /// Gets the name of the stream.
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+}
+
+/// Combines a container's stdout and stderr streams into a single stream.
+///
+/// Lines are emitted in the order they are completed, each prefixed with the
+/// time it was received (in RFC 3339 format) and the stream it was written to
+/// (e.g., `2025-01-01T00:00:00.000Z stderr error: ...`). Partial lines are
+/// buffered until they are completed so that the lines of the two streams are
+/// never spliced together.
+#[derive(Debug, Default)]
+pub(crate) struct Combiner {
+    /// The partial line most recently written to stdout.
+    stdout: Vec<u8>,
+
+    /// The partial line most recently written to stderr.
+    stderr: Vec<u8>,
+}
+
+impl Combiner {
+    /// Pushes a chunk of output from the given stream, returning the combined
+    /// output of any lines it completes.
+    pub(crate) fn push(&mut self, stream: Stream, chunk: &[u8]) -> Vec<u8> {
+        self.push_at(stream, chunk, &timestamp())
+    }
+
+    /// Finishes combining the streams, returning the combined output of any
+    /// remaining partial lines.
+    pub(crate) fn finish(&mut self) -> Vec<u8> {
+        self.finish_at(&timestamp())
+    }
+
+    /// Pushes a chunk of output received at the given time.
+    fn push_at(&mut self, stream: Stream, chunk: &[u8], timestamp: &str) -> Vec<u8> {
+        let partial = match stream {
+            Stream::Stdout => &mut self.stdout,
+            Stream::Stderr => &mut self.stderr,
+        };
+
+        partial.extend_from_slice(chunk);
+
+        let mut output = Vec::new();
+        while let Some(pos) = partial.iter().position(|b| *b == b'\n') {
+            let line = partial.drain(..=pos).collect::<Vec<_>>();
+            write_line(&mut output, timestamp, stream, &line);
+        }
+
+        output
+    }
+
+    /// Finishes combining the streams at the given time.
+    fn finish_at(&mut self, timestamp: &str) -> Vec<u8> {
+        let mut output = Vec::new();
+        for stream in [Stream::Stdout, Stream::Stderr] {
+            let partial = match stream {
+                Stream::Stdout => std::mem::take(&mut self.stdout),
+                Stream::Stderr => std::mem::take(&mut self.stderr),
+            };
+
+            if !partial.is_empty() {
+                write_line(&mut output, timestamp, stream, &partial);
+                output.push(b'\n');
+            }
+        }
+
+        output
+    }
+}
+
+/// Gets the current time as an RFC 3339 timestamp.
+fn timestamp() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Writes a line prefixed with its timestamp and stream.
+fn write_line(output: &mut Vec<u8>, timestamp: &str, stream: Stream, line: &[u8]) {
+    output.extend_from_slice(timestamp.as_bytes());
+    output.push(b' ');
+    output.extend_from_slice(stream.name().as_bytes());
+    output.push(b' ');
+    output.extend_from_slice(line);
+}
+
+/// Opens a file to write an output stream to.
+///
+/// The file is created if it does not exist; otherwise, it is either appended
+/// to or truncated.
+async fn open(path: &Path, append: bool, name: &str) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .await
+        .map_err(|e| {
+            Error::Message(format!(
+                "failed to open {name} file `{path}`: {e}",
+                path = path.display()
+            ))
+        })
+}
+
+/// Where the output written to a sink goes.
+enum Destination {
+    /// The output is captured in memory, up to an optional limit.
+    Capture {
+        /// The bytes captured so far.
+        bytes: Vec<u8>,
+
+        /// The maximum number of bytes to capture.
+        limit: Option<usize>,
+
+        /// The number of bytes discarded once the limit was reached.
+        omitted: u64,
+    },
+
+    /// The output is written to a file.
+    File {
+        /// The path to the file.
+        path: PathBuf,
+
+        /// The open file.
+        file: File,
+    },
+}
+
+/// A destination for an output stream.
+pub(crate) struct Sink {
+    /// The name of the output stream (e.g., `stdout`).
+    name: &'static str,
+
+    /// Where the output goes.
+    destination: Destination,
+}
+
+impl Sink {
+    /// Creates a sink that captures output in memory up to the given limit.
+    pub(crate) fn capture(name: &'static str, limit: Option<usize>) -> Self {
+        Self {
+            name,
+            destination: Destination::Capture {
+                bytes: Vec::new(),
+                limit,
+                omitted: 0,
+            },
+        }
+    }
+
+    /// Creates a sink that writes output to a file.
+    pub(crate) async fn file(name: &'static str, path: &Path, append: bool) -> Result<Self> {
+        Ok(Self {
+            name,
+            destination: Destination::File {
+                path: path.to_path_buf(),
+                file: open(path, append, name).await?,
+            },
+        })
+    }
+
+    /// Creates a sink that writes to the given file or, if there is none,
+    /// captures output up to the given limit.
+    pub(crate) async fn new(
+        name: &'static str,
+        path: Option<&PathBuf>,
+        limit: Option<usize>,
+    ) -> Result<Self> {
+        match path {
+            Some(path) => Self::file(name, path, false).await,
+            None => Ok(Self::capture(name, limit)),
+        }
+    }
+
+    /// Writes a chunk of output to the sink.
+    pub(crate) async fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        match &mut self.destination {
+            Destination::Capture {
+                bytes,
+                limit,
+                omitted,
+            } => {
+                let remaining = limit.map_or(chunk.len(), |limit| {
+                    limit.saturating_sub(bytes.len()).min(chunk.len())
+                });
+                bytes.extend_from_slice(&chunk[..remaining]);
+                *omitted += (chunk.len() - remaining) as u64;
+                Ok(())
+            }
+            Destination::File { path, file } => file.write_all(chunk).await.map_err(|e| {
+                Error::Message(format!(
+                    "failed to write to {name} file `{path}`: {e}",
+                    name = self.name,
+                    path = path.display()
+                ))
+            }),
+        }
+    }
+
+    /// Finishes writing to the sink, returning the captured output and
+    /// whether it was truncated.
+    pub(crate) async fn finish(self) -> Result<(Vec<u8>, bool)> {
+        match self.destination {
+            Destination::Capture {
+                mut bytes, omitted, ..
+            } => {
+                if omitted > 0 {
+                    bytes.extend_from_slice(
+                        format!(
+                            "\n[{omitted} bytes of {name} truncated]\n",
+                            name = self.name
+                        )
+                        .as_bytes(),
+                    );
+                }
+
+                Ok((bytes, omitted > 0))
+            }
+            Destination::File { path, mut file } => {
+                file.flush().await.map_err(|e| {
+                    Error::Message(format!(
+                        "failed to write to {name} file `{path}`: {e}",
+                        name = self.name,
+                        path = path.display()
+                    ))
+                })?;
+
+                Ok((Vec::new(), false))
+            }
+        }
+    }
+}
+
+/// The destinations of the stdout and stderr streams of a container or
+/// executed command.
+pub(crate) enum Outputs {
+    /// The streams are written to separate sinks.
+    ///
+    /// A stream without a sink is discarded.
+    Separate {
+        /// The sink for the stdout stream.
+        stdout: Option<Sink>,
+
+        /// The sink for the stderr stream.
+        stderr: Option<Sink>,
+    },
+
+    /// The streams are combined into a single sink (see [`Combiner`]).
+    Combined {
+        /// The combiner of the streams.
+        combiner: Combiner,
+
+        /// The sink for the combined stream.
+        sink: Sink,
+    },
+}
+
+impl Outputs {
+    /// Writes an output message to its destination.
+    pub(crate) async fn write(&mut self, output: LogOutput) -> Result<()> {
+        let (stream, message) = match output {
+            LogOutput::StdOut { message } => (Stream::Stdout, message),
+            LogOutput::StdErr { message } => (Stream::Stderr, message),
+            _ => return Ok(()),
+        };
+
+        match self {
+            Self::Separate { stdout, stderr } => {
+                let sink = match stream {
+                    Stream::Stdout => stdout,
+                    Stream::Stderr => stderr,
+                };
+
+                match sink {
+                    Some(sink) => sink.write(&message).await,
+                    None => Ok(()),
+                }
+            }
+            Self::Combined { combiner, sink } => sink.write(&combiner.push(stream, &message)).await,
+        }
+    }
+
+    /// Finishes writing the output streams, returning the captured output of
+    /// each.
+    pub(crate) async fn finish(self) -> Result<Captured> {
+        let mut captured = Captured::default();
+        match self {
+            Self::Separate { stdout, stderr } => {
+                if let Some(stdout) = stdout {
+                    captured.stdout = stdout.finish().await?;
+                }
+
+                if let Some(stderr) = stderr {
+                    captured.stderr = stderr.finish().await?;
+                }
+            }
+            Self::Combined {
+                mut combiner,
+                mut sink,
+            } => {
+                sink.write(&combiner.finish()).await?;
+                captured.combined = sink.finish().await?;
+            }
+        }
+
+        Ok(captured)
+    }
+}
+
+/// The output captured from a container or executed command.
+///
+/// Each stream is paired with whether its captured output was truncated.
+#[derive(Debug, Default)]
+pub(crate) struct Captured {
+    /// The captured stdout stream.
+    pub stdout: (Vec<u8>, bool),
+
+    /// The captured stderr stream.
+    pub stderr: (Vec<u8>, bool),
+
+    /// The captured combined stream.
+    pub combined: (Vec<u8>, bool),
+}
+
+/// Splits a container's stdout and stderr streams into lines.
+///
+/// Partial lines are buffered until they are completed; line endings are not
+/// included in the lines.
+#[derive(Debug, Default)]
+pub(crate) struct Lines {
+    /// The partial line most recently written to stdout.
+    stdout: Vec<u8>,
+
+    /// The partial line most recently written to stderr.
+    stderr: Vec<u8>,
+
+    /// How lines are decoded into text.
+    encoding: Encoding,
+}
+
+impl Lines {
+    /// Creates a splitter that decodes lines with the given encoding.
+    pub(crate) fn new(encoding: Encoding) -> Self {
+        Self {
+            encoding,
+            ..Default::default()
+        }
+    }
+
+    /// Pushes a chunk of output from the given stream, returning any lines it
+    /// completes.
+    pub(crate) fn push(&mut self, stream: Stream, chunk: &[u8]) -> Vec<String> {
+        let partial = match stream {
+            Stream::Stdout => &mut self.stdout,
+            Stream::Stderr => &mut self.stderr,
+        };
+
+        partial.extend_from_slice(chunk);
+
+        let mut lines = Vec::new();
+        while let Some(pos) = partial.iter().position(|b| *b == b'\n') {
+            let line = partial.drain(..=pos).collect::<Vec<_>>();
+            lines.push(to_line(&line, self.encoding));
+        }
+
+        lines
+    }
+
+    /// Finishes splitting the streams, returning any remaining partial lines.
+    pub(crate) fn finish(&mut self) -> Vec<(Stream, String)> {
+        [Stream::Stdout, Stream::Stderr]
+            .into_iter()
+            .filter_map(|stream| {
+                let partial = match stream {
+                    Stream::Stdout => std::mem::take(&mut self.stdout),
+                    Stream::Stderr => std::mem::take(&mut self.stderr),
+                };
+
+                (!partial.is_empty()).then(|| (stream, to_line(&partial, self.encoding)))
+            })
+            .collect()
+    }
+}
+
+/// Converts the bytes of a line into a string without its line ending.
+fn to_line(line: &[u8], encoding: Encoding) -> String {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    encoding.decode(line)
+}
+
+/// A bounded buffer of the most recent output of a stream.
+#[derive(Debug)]
+pub(crate) struct TailBuffer {
+    /// The most recent bytes of output.
+    bytes: VecDeque<u8>,
+
+    /// The maximum number of bytes to keep.
+    limit: usize,
+}
+
+impl TailBuffer {
+    /// Creates a buffer that keeps up to `limit` bytes of output.
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            bytes: VecDeque::with_capacity(limit),
+            limit,
+        }
+    }
+
+    /// Pushes a chunk of output, discarding the oldest output beyond the
+    /// limit.
+    pub(crate) fn push(&mut self, chunk: &[u8]) {
+        let chunk = &chunk[chunk.len().saturating_sub(self.limit)..];
+        let overflow = (self.bytes.len() + chunk.len()).saturating_sub(self.limit);
+        self.bytes.drain(..overflow);
+        self.bytes.extend(chunk);
+    }
+
+    /// Gets the buffered output as a string decoded with the given encoding.
+    ///
+    /// With UTF-8, invalid sequences (e.g., a character split at the start of
+    /// the buffer) are replaced.
+    pub(crate) fn contents(&self, encoding: Encoding) -> String {
+        let (front, back) = self.bytes.as_slices();
+        encoding.decode(&[front, back].concat())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_lines() {
+        let mut lines = Lines::default();
+        assert!(lines.push(Stream::Stdout, b"hel").is_empty());
+        assert_eq!(lines.push(Stream::Stderr, b"oops\r\n"), ["oops"]);
+        assert_eq!(
+            lines.push(Stream::Stdout, b"lo\nworld\n\npartial"),
+            ["hello", "world", ""]
+        );
+        assert_eq!(lines.finish(), [(Stream::Stdout, String::from("partial"))]);
+        assert!(lines.finish().is_empty());
+    }
+
+    #[test]
+    fn keeps_tail() {
+        let mut tail = TailBuffer::new(8);
+        tail.push(b"hello ");
+        assert_eq!(tail.contents(Encoding::Utf8), "hello ");
+        tail.push(b"world");
+        assert_eq!(tail.contents(Encoding::Utf8), "lo world");
+        tail.push(b"0123456789");
+        assert_eq!(tail.contents(Encoding::Utf8), "23456789");
+    }
+
+    #[test]
+    fn interleaves_lines() {
+        let mut combiner = Combiner::default();
+
+        assert!(combiner.push_at(Stream::Stdout, b"hel", "t0").is_empty());
+        assert_eq!(
+            combiner.push_at(Stream::Stderr, b"oops\nfail", "t1"),
+            b"t1 stderr oops\n"
+        );
+        assert_eq!(
+            combiner.push_at(Stream::Stdout, b"lo\nworld\n", "t2"),
+            b"t2 stdout hello\nt2 stdout world\n"
+        );
+        assert_eq!(combiner.finish_at("t3"), b"t3 stderr fail\n");
+        assert!(combiner.finish_at("t4").is_empty());
+    }
+
+    #[tokio::test]
+    async fn capture_limit() {
+        let mut sink = Sink::capture("stdout", Some(4));
+        sink.write(b"abc").await.unwrap();
+        sink.write(b"defgh").await.unwrap();
+
+        let (bytes, truncated) = sink.finish().await.unwrap();
+        assert!(truncated);
+        assert_eq!(bytes, b"abcd\n[4 bytes of stdout truncated]\n");
+
+        let mut sink = Sink::capture("stdout", None);
+        sink.write(b"abc").await.unwrap();
+        assert_eq!(sink.finish().await.unwrap(), (b"abc".to_vec(), false));
+    }
+}
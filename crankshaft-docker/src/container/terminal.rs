@@ -0,0 +1,89 @@
+//! Control of the host's terminal for interactive containers.
+//!
+//! The terminal is only controlled on Unix; elsewhere, input is passed through
+//! as the host's terminal delivers it and the container's pseudo-terminal
+//! keeps its default size.
+
+#[cfg(unix)]
+use std::mem::MaybeUninit;
+
+/// A guard that puts the host's terminal into raw mode, restoring its previous
+/// mode when dropped.
+///
+/// In raw mode, input is passed to the container's pseudo-terminal as it is
+/// typed (including control characters such as `Ctrl-C`) rather than being
+/// interpreted by the host's terminal.
+pub(crate) struct RawMode(#[cfg(unix)] libc::termios);
+
+impl RawMode {
+    /// Puts the terminal attached to stdin into raw mode.
+    ///
+    /// Returns `None` if stdin is not a terminal.
+    #[cfg(unix)]
+    pub(crate) fn enable() -> Option<Self> {
+        let mut original = MaybeUninit::<libc::termios>::uninit();
+
+        // SAFETY: `tcgetattr` initializes the given `termios` when it succeeds,
+        // and the raw mode settings are derived from that initialized value.
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) != 1
+                || libc::tcgetattr(libc::STDIN_FILENO, original.as_mut_ptr()) != 0
+            {
+                return None;
+            }
+
+            let original = original.assume_init();
+            let mut raw = original;
+            libc::cfmakeraw(&mut raw);
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return None;
+            }
+
+            Some(Self(original))
+        }
+    }
+
+    /// Puts the terminal attached to stdin into raw mode.
+    ///
+    /// Raw mode is not supported on this platform, so this returns `None`.
+    #[cfg(not(unix))]
+    pub(crate) fn enable() -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: the settings being restored were read from the terminal.
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0);
+        }
+    }
+}
+
+/// Gets the size of the terminal attached to stdout as rows and columns.
+///
+/// Returns `None` if stdout is not a terminal.
+#[cfg(unix)]
+pub(crate) fn size() -> Option<(u16, u16)> {
+    let mut size = MaybeUninit::<libc::winsize>::uninit();
+
+    // SAFETY: `TIOCGWINSZ` initializes the given `winsize` when it succeeds.
+    unsafe {
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, size.as_mut_ptr()) != 0 {
+            return None;
+        }
+
+        let size = size.assume_init();
+        (size.ws_row > 0 && size.ws_col > 0).then_some((size.ws_row, size.ws_col))
+    }
+}
+
+/// Gets the size of the terminal attached to stdout as rows and columns.
+///
+/// The size is not available on this platform, so this returns `None`.
+#[cfg(not(unix))]
+pub(crate) fn size() -> Option<(u16, u16)> {
+    None
+}
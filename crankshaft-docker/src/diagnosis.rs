@@ -0,0 +1,289 @@
+//! Diagnosis of common failures reported by the Docker daemon.
+//!
+//! The Docker daemon reports failures as free-form messages (often wrapped in
+//! several layers of context from the container runtime). A [`Diagnosis`]
+//! classifies the most common of them and pairs each with a hint for fixing
+//! it, so that callers can report something more actionable than the raw
+//! message.
+
+use std::fmt;
+
+use crate::Error;
+
+/// A diagnosed cause of a failure reported by the Docker daemon.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Diagnosis {
+    /// The image does not exist in its registry (or the registry denied
+    /// access to it).
+    ImageNotFound,
+
+    /// The registry requires authentication that was not provided or was
+    /// rejected.
+    Unauthorized,
+
+    /// The image does not have a variant for the requested platform.
+    PlatformMismatch,
+
+    /// The source of a bind mount does not exist on the Docker host.
+    BindSourceMissing {
+        /// The path of the source (if it could be determined).
+        path: Option<String>,
+    },
+
+    /// The program to run does not exist within the image.
+    ExecutableNotFound {
+        /// The name of the program (if it could be determined).
+        program: Option<String>,
+    },
+
+    /// The program to run is built for another architecture.
+    ExecFormat,
+
+    /// Permission was denied (e.g., to the Docker socket or to execute the
+    /// program).
+    PermissionDenied,
+
+    /// The Docker daemon could not be reached.
+    DaemonUnavailable,
+
+    /// The Docker host ran out of disk space.
+    NoSpace,
+}
+
+/// Gets the text following a marker within a message, up to the end of the
+/// path or name it introduces.
+fn following<'a>(message: &'a str, marker: &str) -> Option<&'a str> {
+    let start = message.find(marker)? + marker.len();
+    let rest = message[start..].trim_start_matches([' ', '"', '\\']);
+    let end = rest
+        .find(|c: char| c == '"' || c == '\\' || c == ':' || c.is_whitespace())
+        .unwrap_or(rest.len());
+    Some(&rest[..end]).filter(|s| !s.is_empty())
+}
+
+impl Diagnosis {
+    /// Diagnoses a failure from the message reported by the Docker daemon.
+    ///
+    /// Returns `None` if the message is not a recognized failure.
+    pub fn from_message(message: &str) -> Option<Self> {
+        let lower = message.to_lowercase();
+        let contains = |needles: &[&str]| needles.iter().any(|n| lower.contains(n));
+
+        // The more specific failures are checked first, as the messages of
+        // runtime failures often also contain more general phrases
+        if contains(&[
+            "bind source path does not exist",
+            "bind mount source path does not exist",
+        ]) {
+            return Some(Self::BindSourceMissing {
+                path: following(message, "does not exist:").map(str::to_string),
+            });
+        }
+
+        if contains(&[
+            "executable file not found",
+            "no such file or directory: unknown",
+        ]) {
+            return Some(Self::ExecutableNotFound {
+                program: following(message, "exec:").map(str::to_string),
+            });
+        }
+
+        if contains(&["exec format error"]) {
+            return Some(Self::ExecFormat);
+        }
+
+        if contains(&[
+            "no matching manifest for",
+            "does not match the specified platform",
+        ]) {
+            return Some(Self::PlatformMismatch);
+        }
+
+        if contains(&["no space left on device"]) {
+            return Some(Self::NoSpace);
+        }
+
+        if contains(&[
+            "manifest unknown",
+            "pull access denied",
+            "repository does not exist",
+            "no such image",
+        ]) {
+            return Some(Self::ImageNotFound);
+        }
+
+        if contains(&["unauthorized", "authentication required"]) {
+            return Some(Self::Unauthorized);
+        }
+
+        if contains(&[
+            "cannot connect to the docker daemon",
+            "is the docker daemon running",
+        ]) {
+            return Some(Self::DaemonUnavailable);
+        }
+
+        if contains(&["permission denied"]) {
+            return Some(Self::PermissionDenied);
+        }
+
+        None
+    }
+
+    /// Diagnoses a failure from an error.
+    ///
+    /// Returns `None` if the error is not a recognized failure.
+    pub fn from_error(error: &Error) -> Option<Self> {
+        use bollard::errors::Error as Bollard;
+
+        match error {
+            Error::Docker(Bollard::DockerResponseServerError { message, .. }) => {
+                Self::from_message(message)
+            }
+            Error::Docker(Bollard::DockerStreamError { error }) => Self::from_message(error),
+            Error::Docker(Bollard::IOError { err })
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused
+                ) =>
+            {
+                Some(Self::DaemonUnavailable)
+            }
+            Error::Docker(Bollard::IOError { err })
+                if err.kind() == std::io::ErrorKind::PermissionDenied =>
+            {
+                Some(Self::PermissionDenied)
+            }
+            Error::Docker(e) => Self::from_message(&e.to_string()),
+            Error::Message(message) => Self::from_message(message),
+            _ => None,
+        }
+    }
+
+    /// Gets a hint for fixing the failure.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::ImageNotFound => {
+                "check the spelling of the image's name and tag, and that you have access to its \
+                 registry"
+            }
+            Self::Unauthorized => {
+                "log in to the image's registry (e.g., with `docker login`) or provide credentials \
+                 for it"
+            }
+            Self::PlatformMismatch => {
+                "use an image built for the Docker host's platform or request a platform the image \
+                 supports"
+            }
+            Self::BindSourceMissing { .. } => {
+                "bind sources must exist on the Docker host (which may not be the local host) \
+                 before the container is created"
+            }
+            Self::ExecutableNotFound { .. } => {
+                "check that the program is installed within the image and is on its `PATH`, or use \
+                 its absolute path"
+            }
+            Self::ExecFormat => {
+                "the program was built for another architecture than the Docker host; use an image \
+                 for the host's architecture"
+            }
+            Self::PermissionDenied => {
+                "check that the user can access the Docker socket (e.g., is in the `docker` group) \
+                 and that the program is executable"
+            }
+            Self::DaemonUnavailable => {
+                "check that the Docker daemon is running and that `DOCKER_HOST` (if set) points to \
+                 it"
+            }
+            Self::NoSpace => {
+                "free disk space on the Docker host (e.g., with `docker system prune`)"
+            }
+        }
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ImageNotFound => write!(f, "the image was not found")?,
+            Self::Unauthorized => write!(f, "the registry requires authentication")?,
+            Self::PlatformMismatch => write!(f, "the image does not support the platform")?,
+            Self::BindSourceMissing { path: Some(path) } => {
+                write!(f, "bind source `{path}` does not exist")?
+            }
+            Self::BindSourceMissing { path: None } => write!(f, "a bind source does not exist")?,
+            Self::ExecutableNotFound {
+                program: Some(program),
+            } => write!(f, "program `{program}` was not found in the image")?,
+            Self::ExecutableNotFound { program: None } => {
+                write!(f, "the program was not found in the image")?
+            }
+            Self::ExecFormat => write!(f, "the program cannot be executed on the host")?,
+            Self::PermissionDenied => write!(f, "permission was denied")?,
+            Self::DaemonUnavailable => write!(f, "the Docker daemon is unavailable")?,
+            Self::NoSpace => write!(f, "the Docker host is out of disk space")?,
+        }
+
+        write!(f, " (hint: {hint})", hint = self.hint())
+    }
+}
+
+impl Error {
+    /// Diagnoses the cause of the error (see [`Diagnosis::from_error()`]).
+    pub fn diagnosis(&self) -> Option<Diagnosis> {
+        Diagnosis::from_error(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnoses_messages() {
+        assert_eq!(
+            Diagnosis::from_message(
+                "invalid mount config for type \"bind\": bind source path does not exist: \
+                 /data/missing"
+            ),
+            Some(Diagnosis::BindSourceMissing {
+                path: Some(String::from("/data/missing"))
+            })
+        );
+        assert_eq!(
+            Diagnosis::from_message(
+                "failed to create task for container: failed to create shim task: OCI runtime \
+                 create failed: runc create failed: unable to start container process: exec: \
+                 \"samtools\": executable file not found in $PATH: unknown"
+            ),
+            Some(Diagnosis::ExecutableNotFound {
+                program: Some(String::from("samtools"))
+            })
+        );
+        assert_eq!(
+            Diagnosis::from_message(
+                "manifest for ubuntu:nope not found: manifest unknown: manifest unknown"
+            ),
+            Some(Diagnosis::ImageNotFound)
+        );
+        assert_eq!(
+            Diagnosis::from_message(
+                "no matching manifest for linux/arm64/v8 in the manifest list entries"
+            ),
+            Some(Diagnosis::PlatformMismatch)
+        );
+        assert_eq!(
+            Diagnosis::from_message("exec /usr/bin/tool: exec format error"),
+            Some(Diagnosis::ExecFormat)
+        );
+        assert_eq!(Diagnosis::from_message("something else went wrong"), None);
+
+        assert!(
+            Diagnosis::ImageNotFound
+                .to_string()
+                .starts_with("the image was not found (hint: ")
+        );
+    }
+}
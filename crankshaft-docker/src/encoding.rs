@@ -0,0 +1,63 @@
+//! Decoding of the output of containers.
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How the bytes written to a container's output streams are decoded into
+/// text (e.g., for the lines reported to [`Builder::on_line()`] or the end of
+/// the output kept with [`Builder::tail()`]).
+///
+/// [`Builder::on_line()`]: crate::container::Builder::on_line
+/// [`Builder::tail()`]: crate::container::Builder::tail
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Encoding {
+    /// UTF-8, with invalid sequences replaced by `U+FFFD`.
+    #[default]
+    Utf8,
+
+    /// ISO-8859-1 (Latin-1), where each byte is the character with the same
+    /// code point.
+    Latin1,
+
+    /// UTF-8, with the bytes of invalid sequences escaped as `\xNN` so that
+    /// no data is lost.
+    Escaped,
+}
+
+impl Encoding {
+    /// Decodes bytes into text.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            Self::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Self::Latin1 => bytes.iter().map(|b| char::from(*b)).collect(),
+            Self::Escaped => {
+                let mut text = String::with_capacity(bytes.len());
+                for chunk in bytes.utf8_chunks() {
+                    text.push_str(chunk.valid());
+                    for byte in chunk.invalid() {
+                        text.push_str(&format!("\\x{byte:02X}"));
+                    }
+                }
+
+                text
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes() {
+        let bytes = b"caf\xe9 \xe2\x9c\x93";
+        assert_eq!(Encoding::Utf8.decode(bytes), "caf\u{FFFD} \u{2713}");
+        assert_eq!(
+            Encoding::Latin1.decode(bytes),
+            "caf\u{e9} \u{e2}\u{9c}\u{93}"
+        );
+        assert_eq!(Encoding::Escaped.decode(bytes), "caf\\xE9 \u{2713}");
+    }
+}
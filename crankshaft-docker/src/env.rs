@@ -0,0 +1,93 @@
+//! Environment files.
+
+use std::path::Path;
+
+use indexmap::IndexMap;
+
+use crate::Error;
+use crate::Result;
+
+/// Parses the contents of an environment file.
+///
+/// The format matches that of Docker's `--env-file` option:
+///
+/// * Each line is of the form `NAME=VALUE`; the value is taken verbatim (quotes
+///   are not removed).
+/// * A line with only `NAME` takes the value of the variable from the host's
+///   environment; it is skipped if the host does not define it.
+/// * Blank lines and lines starting with `#` are ignored.
+pub fn parse(contents: &str) -> Result<IndexMap<String, String>> {
+    let mut variables = IndexMap::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (name, value) = match line.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (line.trim_end(), None),
+        };
+
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(Error::Message(format!(
+                "invalid environment variable name `{name}` on line {line}",
+                line = index + 1
+            )));
+        }
+
+        if let Some(value) = value.or_else(|| std::env::var(name).ok()) {
+            variables.insert(name.to_string(), value);
+        }
+    }
+
+    Ok(variables)
+}
+
+/// Reads and parses an environment file.
+///
+/// See [`parse()`] for the format of the file.
+pub fn read(path: impl AsRef<Path>) -> Result<IndexMap<String, String>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        Error::Message(format!(
+            "failed to read environment file `{path}`: {e}",
+            path = path.display()
+        ))
+    })?;
+
+    parse(&contents).map_err(|e| {
+        Error::Message(format!(
+            "invalid environment file `{path}`: {e}",
+            path = path.display()
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses() {
+        let variables = parse(
+            "# a comment\n\nFOO=bar\nQUOTED=\"a \
+             b\"\nEMPTY=\nEQUALS=a=b\nCRANKSHAFT_UNDEFINED_VAR\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            variables.into_iter().collect::<Vec<_>>(),
+            [
+                (String::from("FOO"), String::from("bar")),
+                (String::from("QUOTED"), String::from("\"a b\"")),
+                (String::from("EMPTY"), String::new()),
+                (String::from("EQUALS"), String::from("a=b")),
+            ]
+        );
+
+        assert!(parse("=value").is_err());
+        assert!(parse("BAD NAME=value").is_err());
+    }
+}
@@ -2,22 +2,69 @@
 
 use std::collections::HashMap;
 
+use bollard::auth::DockerCredentials;
 use bollard::query_parameters::CreateImageOptions;
 use bollard::query_parameters::ListImagesOptions;
 use bollard::query_parameters::RemoveImageOptions;
 use bollard::secret::ImageDeleteResponseItem;
+use bollard::secret::ImageInspect;
 use bollard::secret::ImageSummary;
 use futures::stream::FuturesUnordered;
 use tokio_stream::StreamExt as _;
 use tracing::Level;
+use tracing::Span;
 use tracing::debug;
 use tracing::enabled;
+use tracing::field;
+use tracing::instrument;
 use tracing::trace;
+use tracing::warn;
 
 use crate::Docker;
 use crate::Error;
 use crate::Result;
 
+mod auth;
+mod build;
+mod cache;
+mod extract;
+mod limits;
+mod load;
+mod metadata;
+mod platform;
+mod policy;
+mod retry;
+mod sources;
+mod usage;
+
+pub use auth::DEFAULT_REGISTRY;
+pub use auth::PASSWORD_ENV;
+pub use auth::RegistryAuth;
+pub use auth::TOKEN_ENV;
+pub use auth::USERNAME_ENV;
+pub use auth::registry;
+pub use build::BuildOptions;
+pub use build::DEFAULT_DOCKERFILE;
+pub(crate) use build::build_image;
+pub use cache::DEFAULT_MAX_CONCURRENT_PULLS;
+pub use cache::ImageCache;
+pub(crate) use extract::read_image_file;
+pub(crate) use extract::unpack_image;
+pub use limits::PullLimits;
+pub use load::ImageSource;
+pub use load::InvalidImageSource;
+pub(crate) use load::load_image;
+pub use metadata::ImageMetadata;
+pub use platform::normalize_arch;
+pub(crate) use platform::requested_arch;
+pub use policy::ImagePolicy;
+pub use retry::RetryPolicy;
+pub use sources::PullSource;
+pub use usage::EvictionPolicy;
+pub use usage::ImageUsage;
+pub(crate) use usage::evict_images;
+pub(crate) use usage::image_usage;
+
 /// Gets all of the images stored in the Docker daemon.
 pub(crate) async fn list_images(docker: &Docker) -> Result<Vec<ImageSummary>> {
     debug!("listing images");
@@ -55,16 +102,284 @@ pub(crate) async fn list_images(docker: &Docker) -> Result<Vec<ImageSummary>> {
     Ok(images)
 }
 
+/// The progress of an image pull.
+///
+/// The byte counts are aggregated across all of the layers of the image that
+/// have reported progress so far.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct PullProgress {
+    /// The layer that the most recent update refers to (if any).
+    pub layer: Option<String>,
+
+    /// The status message of the most recent update (if any).
+    pub status: Option<String>,
+
+    /// The number of bytes transferred so far.
+    pub current: u64,
+
+    /// The total number of bytes to transfer (as known so far).
+    pub total: u64,
+}
+
+/// Options for pulling images.
+#[derive(Clone, Debug)]
+pub struct PullOptions {
+    /// The policy for retrying failed pulls.
+    retry: RetryPolicy,
+
+    /// The credentials for authenticating with the image's registry.
+    auth: Option<RegistryAuth>,
+
+    /// Whether to read credentials from the Docker configuration file when
+    /// none are explicitly provided.
+    docker_config: bool,
+
+    /// The limits on the number of images pulled at once.
+    limits: PullLimits,
+
+    /// The architecture of the images to pull (if not the daemon's).
+    arch: Option<String>,
+
+    /// The sources that images are pulled from before their own registries
+    /// keyed by image reference.
+    sources: HashMap<String, Vec<PullSource>>,
+}
+
+impl Default for PullOptions {
+    fn default() -> Self {
+        Self {
+            retry: Default::default(),
+            auth: None,
+            docker_config: true,
+            limits: Default::default(),
+            arch: None,
+            sources: Default::default(),
+        }
+    }
+}
+
+impl PullOptions {
+    /// Sets the policy for retrying failed pulls.
+    ///
+    /// Defaults to [`RetryPolicy::default()`].
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Sets the credentials for authenticating with the image's registry.
+    pub fn auth(mut self, auth: RegistryAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Sets whether to read credentials from the Docker configuration file
+    /// when none are explicitly provided (see
+    /// [`RegistryAuth::from_docker_config()`]).
+    ///
+    /// Defaults to `true`.
+    pub fn docker_config(mut self, docker_config: bool) -> Self {
+        self.docker_config = docker_config;
+        self
+    }
+
+    /// Sets the limits on the number of images pulled at once.
+    ///
+    /// Defaults to no limits.
+    pub fn limits(mut self, limits: PullLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets the architecture of the images to pull (e.g., `amd64` or `arm64`).
+    ///
+    /// The variant of each image for the architecture is pulled, and images
+    /// are verified to be for the architecture once pulled. Images that
+    /// already exist in the Docker daemon for a different architecture are
+    /// pulled again. Common alternative names for architectures (e.g.,
+    /// `x86_64` or `aarch64`) are accepted.
+    ///
+    /// Defaults to the architecture of the Docker daemon.
+    pub fn arch(mut self, arch: impl Into<String>) -> Self {
+        self.arch = Some(arch.into());
+        self
+    }
+
+    /// Sets the sources to pull an image from, in order, before pulling it from
+    /// its own registry.
+    ///
+    /// This allows images to be pulled from internal mirrors or loaded from
+    /// archives on networks with unreliable access to upstream registries.
+    /// Each source is tried in turn (a registry source with the retry
+    /// policy) until one provides the image, which is then tagged with the
+    /// image's reference; the image's own registry is tried last. Sources are
+    /// not used for images pinned to a digest, as a pinned reference can only
+    /// be satisfied by the registry it names.
+    pub fn sources(
+        mut self,
+        image: impl Into<String>,
+        sources: impl IntoIterator<Item = PullSource>,
+    ) -> Self {
+        self.sources
+            .insert(image.into(), sources.into_iter().collect());
+        self
+    }
+
+    /// Resolves the credentials to use to pull the given image (if any).
+    fn credentials(&self, image: &str) -> Result<Option<DockerCredentials>> {
+        let registry = registry(image);
+
+        let auth = match &self.auth {
+            Some(auth) => Some(auth.clone()),
+            None if self.docker_config => RegistryAuth::from_docker_config(registry)?,
+            None => None,
+        };
+
+        Ok(auth.map(|auth| auth.credentials(registry)))
+    }
+}
+
+/// Gets the digest an image reference is pinned to (if any).
+///
+/// For example, the digest of `ubuntu@sha256:abc...` is `sha256:abc...`.
+pub fn digest(image: &str) -> Option<&str> {
+    image.split_once('@').map(|(_, digest)| digest)
+}
+
+/// Pins an image reference to a digest, replacing any tag or digest it
+/// already has.
+///
+/// For example, pinning `ubuntu:22.04` to `sha256:abc...` gives
+/// `ubuntu@sha256:abc...`.
+pub fn pin(image: &str, digest: &str) -> String {
+    let name = image.split_once('@').map_or(image, |(name, _)| name);
+
+    // A `:` after the last `/` separates the tag rather than a registry's port
+    let name = match name.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => repository,
+        _ => name,
+    };
+
+    format!("{name}@{digest}")
+}
+
+/// Validates the format of a digest (e.g., `sha256:<64 hex characters>`).
+fn validate_digest(digest: &str) -> Result<()> {
+    let is_hex = |hex: &str| hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    let valid = match digest.split_once(':') {
+        Some(("sha256", hex)) => hex.len() == 64 && is_hex(hex),
+        Some(("sha512", hex)) => hex.len() == 128 && is_hex(hex),
+        _ => false,
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Message(format!("invalid image digest `{digest}`")))
+    }
+}
+
+/// Verifies that an image pinned to a digest in the Docker daemon has the
+/// requested content.
+///
+/// Images that are not pinned to a digest are not verified.
+async fn verify_digest(docker: &Docker, image: &str) -> Result<()> {
+    let Some(digest) = digest(image) else {
+        return Ok(());
+    };
+
+    let inspect = inspect_image(docker, image).await?;
+    let verified = inspect
+        .repo_digests
+        .iter()
+        .flatten()
+        .any(|d| self::digest(d) == Some(digest));
+
+    if !verified {
+        return Err(Error::Message(format!(
+            "image `{image}` does not match the requested digest `{digest}`"
+        )));
+    }
+
+    debug!("verified image `{image}` matches digest `{digest}`");
+    Ok(())
+}
+
+/// Resolves an image reference to a reference pinned to the digest the image
+/// currently has in its registry.
+///
+/// References that are already pinned to a digest are returned unchanged. The
+/// registry is queried rather than the Docker daemon, so the image does not
+/// need to have been pulled.
+pub(crate) async fn resolve_image(
+    docker: &Docker,
+    image: &str,
+    options: &PullOptions,
+) -> Result<String> {
+    if digest(image).is_some() {
+        return Ok(image.to_string());
+    }
+
+    let inspect = docker
+        .inner()
+        .inspect_registry_image(image, options.credentials(image)?)
+        .await
+        .map_err(Error::Docker)?;
+    let digest = inspect.descriptor.digest.ok_or_else(|| {
+        Error::Message(format!(
+            "the registry did not report a digest for image `{image}`"
+        ))
+    })?;
+    validate_digest(&digest)?;
+
+    let pinned = pin(image, &digest);
+    debug!("resolved image `{image}` to `{pinned}`");
+    Ok(pinned)
+}
+
 /// Ensures that an image exists in the Docker daemon.
 ///
 /// If the image does not specify a tag, a default tag of `latest` will be used.
 ///
+/// If the image is pinned to a digest (e.g., `ubuntu@sha256:...`), the digest
+/// is validated before pulling and the image in the Docker daemon is verified
+/// to match it.
+///
 /// It does this by:
 ///
 /// * Confirming that the image already exists there, or
 /// * Pulling the image from the remote repository.
+///
+/// Pulls use the default [`PullOptions`].
 pub(crate) async fn ensure_image(docker: &Docker, image: impl Into<String>) -> Result<()> {
+    ensure_image_with(docker, image, &PullOptions::default(), |_| {}).await?;
+    Ok(())
+}
+
+/// Ensures that an image exists in the Docker daemon, pulling with the
+/// provided options and reporting the progress of any pull to the provided
+/// callback.
+///
+/// Returns the source that the image was pulled from, or `None` if it already
+/// existed.
+///
+/// See [`ensure_image()`] for more details.
+#[instrument(skip_all, fields(image = field::Empty))]
+pub(crate) async fn ensure_image_with(
+    docker: &Docker,
+    image: impl Into<String>,
+    options: &PullOptions,
+    mut progress: impl FnMut(&PullProgress),
+) -> Result<Option<PullSource>> {
     let image = image.into();
+    Span::current().record("image", image.as_str());
+
+    if let Some(digest) = digest(&image) {
+        validate_digest(digest)?;
+    }
+
+    let arch = options.arch.as_deref().map(requested_arch).transpose()?;
 
     debug!("ensuring image `{image}` exists locally");
 
@@ -89,10 +404,120 @@ pub(crate) async fn ensure_image(docker: &Docker, image: impl Into<String>) -> R
             );
         }
 
-        return Ok(());
+        match arch {
+            Some(arch) if platform::image_arch(docker, &image).await?.as_deref() != Some(arch) => {
+                debug!("image `{image}` exists locally for another architecture than `{arch}`");
+            }
+            _ => return verify_digest(docker, &image).await.map(|_| None),
+        }
     }
 
     debug!("image `{image}` does not exist locally; attempting to pull from remote");
+
+    // Try the image's alternative sources in order before its own registry
+    let mut failures = Vec::new();
+    let alternatives = match digest(&image) {
+        Some(_) => None,
+        None => options.sources.get(&image),
+    };
+    for source in alternatives.into_iter().flatten() {
+        let result = match source {
+            PullSource::Archive(archive) => sources::load_as(docker, archive, &image).await,
+            PullSource::Registry(reference) => {
+                match pull_with_retries(docker, reference, arch, options, &mut progress).await {
+                    Ok(()) => sources::tag_image(docker, reference, &image).await,
+                    Err(e) => Err(e),
+                }
+            }
+        };
+
+        let result = match (result, arch) {
+            (Ok(()), Some(arch)) => platform::verify_arch(docker, &image, arch).await,
+            (result, _) => result,
+        };
+
+        match result {
+            Ok(()) => {
+                debug!("pulled image `{image}` from source `{source}`");
+                return Ok(Some(source.clone()));
+            }
+            Err(e) => {
+                warn!("failed to pull image `{image}` from source `{source}`: {e}");
+                failures.push(format!("`{source}`: {e}"));
+            }
+        }
+    }
+
+    match pull_with_retries(docker, &image, arch, options, &mut progress).await {
+        Ok(()) => {
+            if let Some(arch) = arch {
+                platform::verify_arch(docker, &image, arch).await?;
+            }
+
+            verify_digest(docker, &image).await?;
+            Ok(Some(PullSource::Registry(image)))
+        }
+        Err(e) if failures.is_empty() => Err(e),
+        Err(e) => Err(Error::Message(format!(
+            "failed to pull image `{image}` from any of its sources: {failures}; `{image}`: {e}",
+            failures = failures.join("; ")
+        ))),
+    }
+}
+
+/// Pulls an image from its registry, retrying failed pulls with the provided
+/// options' retry policy.
+async fn pull_with_retries(
+    docker: &Docker,
+    image: &str,
+    arch: Option<&str>,
+    options: &PullOptions,
+    progress: &mut impl FnMut(&PullProgress),
+) -> Result<()> {
+    let policy = &options.retry;
+    let credentials = options.credentials(image)?;
+
+    // The permits are held across retries so that a registry that is rate
+    // limiting pulls isn't sent more of them in the meantime
+    let _permits = options.limits.acquire(registry(image)).await;
+
+    let mut attempt = 1;
+    loop {
+        match pull_image(docker, image, arch, credentials.clone(), progress).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < policy.attempts() && RetryPolicy::is_retryable(&e) => {
+                let delay = policy.backoff(attempt);
+                warn!(
+                    "failed to pull image `{image}` (attempt {attempt} of {attempts}): {e}; \
+                     retrying in {delay:?}",
+                    attempts = policy.attempts()
+                );
+
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) if RetryPolicy::is_rate_limited(&e) => {
+                return Err(Error::Message(format!(
+                    "the registry `{registry}` rate limited pulling image `{image}` (consider \
+                     lowering the limits on concurrent pulls or authenticating with the \
+                     registry): {e}",
+                    registry = registry(image)
+                )));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Pulls an image from the remote repository, reporting the progress of the
+/// pull to the provided callback.
+async fn pull_image(
+    docker: &Docker,
+    image: &str,
+    arch: Option<&str>,
+    credentials: Option<DockerCredentials>,
+    progress: &mut impl FnMut(&PullProgress),
+) -> Result<()> {
     let mut stream = docker.inner().create_image(
         Some(CreateImageOptions {
             tag: Some(if image.contains(':') {
@@ -100,13 +525,17 @@ pub(crate) async fn ensure_image(docker: &Docker, image: impl Into<String>) -> R
             } else {
                 String::from("latest")
             }),
-            from_image: Some(image),
+            from_image: Some(image.to_string()),
+            platform: arch.map(|arch| format!("linux/{arch}")).unwrap_or_default(),
             ..Default::default()
         }),
         None,
-        None,
+        credentials,
     );
 
+    // The (current, total) bytes for each layer keyed by layer id
+    let mut layers: HashMap<String, (u64, u64)> = HashMap::new();
+
     while let Some(result) = stream.next().await {
         let update = result.map_err(Error::Docker)?;
 
@@ -114,13 +543,17 @@ pub(crate) async fn ensure_image(docker: &Docker, image: impl Into<String>) -> R
             trace!(
                 "pull update: {}",
                 [
-                    update.id.map(|id| format!("id: {id}")),
-                    update.error.map(|err| format!("error: {err}")),
-                    update.status.map(|status| format!("status: {status}")),
-                    update.progress.map(|progress| format!(
+                    update.id.as_ref().map(|id| format!("id: {id}")),
+                    update.error.as_ref().map(|err| format!("error: {err}")),
+                    update
+                        .status
+                        .as_ref()
+                        .map(|status| format!("status: {status}")),
+                    update.progress.as_ref().map(|progress| format!(
                         "progress: {progress}{}",
                         update
                             .progress_detail
+                            .as_ref()
                             .map(|detailed| format!(
                                 " ({}/{})",
                                 detailed
@@ -141,11 +574,45 @@ pub(crate) async fn ensure_image(docker: &Docker, image: impl Into<String>) -> R
                 .join("; ")
             )
         }
+
+        if let (Some(id), Some(detail)) = (&update.id, &update.progress_detail) {
+            let entry = layers.entry(id.clone()).or_default();
+
+            if let Some(current) = detail.current {
+                entry.0 = current.try_into().unwrap_or_default();
+            }
+
+            if let Some(total) = detail.total {
+                entry.1 = total.try_into().unwrap_or_default();
+            }
+        }
+
+        let (current, total) = layers
+            .values()
+            .fold((0, 0), |(current, total), (c, t)| (current + c, total + t));
+
+        progress(&PullProgress {
+            layer: update.id,
+            status: update.status,
+            current,
+            total,
+        });
     }
 
     Ok(())
 }
 
+/// Inspects an image within the Docker daemon.
+pub(crate) async fn inspect_image(docker: &Docker, image: &str) -> Result<ImageInspect> {
+    debug!("inspecting image `{image}`");
+
+    docker
+        .inner()
+        .inspect_image(image)
+        .await
+        .map_err(Error::Docker)
+}
+
 /// Removes an image from the Docker daemon.
 pub(crate) async fn remove_image<T: AsRef<str>, U: AsRef<str>>(
     docker: &Docker,
@@ -200,3 +667,41 @@ pub(crate) async fn remove_all_images(docker: &Docker) -> Result<Vec<ImageDelete
 
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digests() {
+        let sha = "sha256:".to_string() + &"a".repeat(64);
+
+        assert_eq!(digest("ubuntu:22.04"), None);
+        assert_eq!(digest(&format!("ubuntu@{sha}")), Some(sha.as_str()));
+        assert_eq!(
+            digest(&format!("ghcr.io/org/image:1.0@{sha}")),
+            Some(sha.as_str())
+        );
+
+        assert!(validate_digest(&sha).is_ok());
+        assert!(validate_digest(&("sha512:".to_string() + &"0".repeat(128))).is_ok());
+        assert!(validate_digest("sha256:abc").is_err());
+        assert!(validate_digest(&("sha256:".to_string() + &"A".repeat(64))).is_err());
+        assert!(validate_digest(&("md5:".to_string() + &"a".repeat(32))).is_err());
+        assert!(validate_digest("latest").is_err());
+    }
+
+    #[test]
+    fn pins_references() {
+        assert_eq!(pin("ubuntu", "sha256:abc"), "ubuntu@sha256:abc");
+        assert_eq!(pin("ubuntu:22.04", "sha256:abc"), "ubuntu@sha256:abc");
+        assert_eq!(
+            pin("localhost:5000/tool", "sha256:abc"),
+            "localhost:5000/tool@sha256:abc"
+        );
+        assert_eq!(
+            pin("localhost:5000/tool:1.0@sha256:def", "sha256:abc"),
+            "localhost:5000/tool@sha256:abc"
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Authentication with private registries.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use bollard::auth::DockerCredentials;
+use serde::Deserialize;
+
+use crate::Error;
+use crate::Result;
+
+/// The environment variable for the username used to authenticate with a
+/// registry.
+pub const USERNAME_ENV: &str = "CRANKSHAFT_REGISTRY_USERNAME";
+
+/// The environment variable for the password used to authenticate with a
+/// registry.
+pub const PASSWORD_ENV: &str = "CRANKSHAFT_REGISTRY_PASSWORD";
+
+/// The environment variable for the identity token used to authenticate with
+/// a registry.
+pub const TOKEN_ENV: &str = "CRANKSHAFT_REGISTRY_TOKEN";
+
+/// The registry used for images that do not name a registry.
+pub const DEFAULT_REGISTRY: &str = "docker.io";
+
+/// The host names that refer to Docker Hub.
+const DOCKER_HUB: &[&str] = &["docker.io", "index.docker.io", "registry-1.docker.io"];
+
+/// Credentials for authenticating with a registry.
+#[derive(Clone, PartialEq, Eq)]
+pub enum RegistryAuth {
+    /// Authenticate with a username and password.
+    Basic {
+        /// The username.
+        username: String,
+        /// The password.
+        password: String,
+    },
+    /// Authenticate with an identity token.
+    Token {
+        /// The identity token.
+        token: String,
+    },
+}
+
+impl std::fmt::Debug for RegistryAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the secrets
+        match self {
+            Self::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            Self::Token { .. } => f.debug_struct("Token").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// An entry within the `auths` section of a Docker configuration file.
+#[derive(Deserialize)]
+struct DockerConfigAuth {
+    /// The base64-encoded `username:password`.
+    auth: Option<String>,
+    /// The identity token.
+    identitytoken: Option<String>,
+}
+
+/// A Docker configuration file (e.g., `~/.docker/config.json`).
+#[derive(Deserialize)]
+struct DockerConfig {
+    /// The credentials keyed by registry.
+    #[serde(default)]
+    auths: HashMap<String, DockerConfigAuth>,
+}
+
+impl RegistryAuth {
+    /// Reads credentials from the environment.
+    ///
+    /// If [`TOKEN_ENV`] is set, token authentication is used. Otherwise, if
+    /// both [`USERNAME_ENV`] and [`PASSWORD_ENV`] are set, basic
+    /// authentication is used.
+    pub fn from_env() -> Option<Self> {
+        if let Ok(token) = std::env::var(TOKEN_ENV) {
+            return Some(Self::Token { token });
+        }
+
+        match (std::env::var(USERNAME_ENV), std::env::var(PASSWORD_ENV)) {
+            (Ok(username), Ok(password)) => Some(Self::Basic { username, password }),
+            _ => None,
+        }
+    }
+
+    /// Reads the credentials for a registry from the Docker configuration
+    /// file.
+    ///
+    /// The file is located at `$DOCKER_CONFIG/config.json` or, if
+    /// `DOCKER_CONFIG` is not set, `~/.docker/config.json`. Credentials stored
+    /// by credential helpers (`credsStore` and `credHelpers`) are not
+    /// supported.
+    ///
+    /// Returns `Ok(None)` if the file does not exist or has no credentials for
+    /// the registry.
+    pub fn from_docker_config(registry: &str) -> Result<Option<Self>> {
+        let Some(path) = std::env::var_os("DOCKER_CONFIG")
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".docker")))
+            .map(|dir| dir.join("config.json"))
+        else {
+            return Ok(None);
+        };
+
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(Error::Message(format!(
+                    "failed to read Docker configuration file `{path}`: {e}",
+                    path = path.display()
+                )));
+            }
+        };
+
+        Self::from_docker_config_str(&contents, registry).map_err(|e| {
+            Error::Message(format!(
+                "invalid Docker configuration file `{path}`: {e}",
+                path = path.display()
+            ))
+        })
+    }
+
+    /// Reads the credentials for a registry from the contents of a Docker
+    /// configuration file.
+    fn from_docker_config_str(contents: &str, registry: &str) -> Result<Option<Self>> {
+        let config: DockerConfig =
+            serde_json::from_str(contents).map_err(|e| Error::Message(e.to_string()))?;
+
+        let registry = normalize(registry);
+        let Some(entry) = config
+            .auths
+            .iter()
+            .find_map(|(k, v)| (normalize(k) == registry).then_some(v))
+        else {
+            return Ok(None);
+        };
+
+        if let Some(token) = &entry.identitytoken {
+            return Ok(Some(Self::Token {
+                token: token.clone(),
+            }));
+        }
+
+        let Some(auth) = &entry.auth else {
+            return Ok(None);
+        };
+
+        let decoded = STANDARD
+            .decode(auth)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| Error::Message(format!("invalid credentials for `{registry}`")))?;
+
+        let (username, password) = decoded
+            .split_once(':')
+            .ok_or_else(|| Error::Message(format!("invalid credentials for `{registry}`")))?;
+
+        Ok(Some(Self::Basic {
+            username: username.to_string(),
+            password: password.to_string(),
+        }))
+    }
+
+    /// Converts the credentials into those sent to the Docker daemon for the
+    /// given registry.
+    pub(crate) fn credentials(&self, registry: &str) -> DockerCredentials {
+        let serveraddress = Some(registry.to_string());
+
+        match self {
+            Self::Basic { username, password } => DockerCredentials {
+                username: Some(username.clone()),
+                password: Some(password.clone()),
+                serveraddress,
+                ..Default::default()
+            },
+            Self::Token { token } => DockerCredentials {
+                identitytoken: Some(token.clone()),
+                serveraddress,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Gets the registry of an image reference.
+///
+/// Images that do not name a registry (e.g., `ubuntu:22.04`) are from
+/// [`DEFAULT_REGISTRY`].
+pub fn registry(image: &str) -> &str {
+    match image.split_once('/') {
+        Some((first, _)) if first.contains('.') || first.contains(':') || first == "localhost" => {
+            first
+        }
+        _ => DEFAULT_REGISTRY,
+    }
+}
+
+/// Normalizes a registry for comparison.
+///
+/// Any scheme and path are removed and the aliases of Docker Hub are treated
+/// as the same registry.
+fn normalize(registry: &str) -> &str {
+    let registry = registry
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let registry = registry.split('/').next().unwrap_or(registry);
+
+    if DOCKER_HUB.contains(&registry) {
+        DEFAULT_REGISTRY
+    } else {
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registries() {
+        assert_eq!(registry("ubuntu"), "docker.io");
+        assert_eq!(registry("library/ubuntu:22.04"), "docker.io");
+        assert_eq!(registry("ghcr.io/org/image:1.0"), "ghcr.io");
+        assert_eq!(registry("localhost:5000/image"), "localhost:5000");
+        assert_eq!(registry("localhost/image"), "localhost");
+    }
+
+    #[test]
+    fn docker_config() {
+        let contents = r#"{
+            "auths": {
+                "https://index.docker.io/v1/": { "auth": "dXNlcjpwYXNz" },
+                "ghcr.io": { "identitytoken": "secret" }
+            }
+        }"#;
+
+        assert_eq!(
+            RegistryAuth::from_docker_config_str(contents, "docker.io").unwrap(),
+            Some(RegistryAuth::Basic {
+                username: String::from("user"),
+                password: String::from("pass"),
+            })
+        );
+        assert_eq!(
+            RegistryAuth::from_docker_config_str(contents, "ghcr.io").unwrap(),
+            Some(RegistryAuth::Token {
+                token: String::from("secret"),
+            })
+        );
+        assert_eq!(
+            RegistryAuth::from_docker_config_str(contents, "quay.io").unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Building images.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use bollard::body_full;
+use bollard::query_parameters::BuildImageOptions;
+use tokio_stream::StreamExt as _;
+use tracing::debug;
+use tracing::trace;
+
+use crate::Docker;
+use crate::Error;
+use crate::Result;
+
+/// The default name of the Dockerfile within a build context.
+pub const DEFAULT_DOCKERFILE: &str = "Dockerfile";
+
+/// Options for building an image.
+#[derive(Clone, Debug, Default)]
+pub struct BuildOptions {
+    /// The path to the Dockerfile, relative to the build context.
+    dockerfile: Option<String>,
+
+    /// The build arguments.
+    args: HashMap<String, String>,
+
+    /// The stage to build in a multi-stage Dockerfile.
+    target: Option<String>,
+
+    /// Whether to build without using the build cache.
+    no_cache: bool,
+
+    /// Whether to always pull newer versions of the base images.
+    pull: bool,
+}
+
+impl BuildOptions {
+    /// Sets the path to the Dockerfile, relative to the build context.
+    ///
+    /// Defaults to [`DEFAULT_DOCKERFILE`].
+    pub fn dockerfile(mut self, dockerfile: impl Into<String>) -> Self {
+        self.dockerfile = Some(dockerfile.into());
+        self
+    }
+
+    /// Adds a build argument.
+    pub fn arg(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.args.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets the stage to build in a multi-stage Dockerfile.
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Sets whether to build without using the build cache.
+    pub fn no_cache(mut self, no_cache: bool) -> Self {
+        self.no_cache = no_cache;
+        self
+    }
+
+    /// Sets whether to always pull newer versions of the base images.
+    pub fn pull(mut self, pull: bool) -> Self {
+        self.pull = pull;
+        self
+    }
+}
+
+/// Builds an image from a build context directory and tags it.
+///
+/// Returns the id of the built image.
+pub(crate) async fn build_image(
+    docker: &Docker,
+    context: &Path,
+    tag: &str,
+    options: &BuildOptions,
+) -> Result<String> {
+    debug!(
+        "building image `{tag}` from context `{context}`",
+        context = context.display()
+    );
+
+    let archive = archive(context.to_path_buf()).await?;
+
+    let mut stream = docker.inner().build_image(
+        BuildImageOptions {
+            dockerfile: options
+                .dockerfile
+                .clone()
+                .unwrap_or_else(|| DEFAULT_DOCKERFILE.to_string()),
+            t: Some(tag.to_string()),
+            buildargs: (!options.args.is_empty()).then(|| options.args.clone()),
+            target: options.target.clone().unwrap_or_default(),
+            nocache: options.no_cache,
+            pull: options.pull.then(|| String::from("true")),
+            rm: true,
+            forcerm: true,
+            ..Default::default()
+        },
+        None,
+        Some(body_full(archive.into())),
+    );
+
+    while let Some(result) = stream.next().await {
+        let info = result.map_err(Error::Docker)?;
+
+        if let Some(error) = info.error_detail.and_then(|d| d.message).or(info.error) {
+            return Err(Error::Message(format!(
+                "failed to build image `{tag}`: {error}"
+            )));
+        }
+
+        if let Some(line) = info.stream.as_deref().map(str::trim_end) {
+            if !line.is_empty() {
+                trace!("build output: {line}");
+            }
+        }
+    }
+
+    let id = docker
+        .inner()
+        .inspect_image(tag)
+        .await
+        .map_err(Error::Docker)?
+        .id
+        .ok_or_else(|| Error::Message(format!("built image `{tag}` has no id")))?;
+
+    debug!("built image `{tag}` ({id})");
+    Ok(id)
+}
+
+/// Creates a tar archive of a build context directory.
+async fn archive(context: PathBuf) -> Result<Vec<u8>> {
+    tokio::task::spawn_blocking(move || {
+        let mut tar = tar::Builder::new(Vec::new());
+        tar.follow_symlinks(false);
+        tar.append_dir_all(".", &context)
+            .and_then(|_| tar.into_inner())
+            .map_err(|e| {
+                Error::Message(format!(
+                    "failed to archive build context `{context}`: {e}",
+                    context = context.display()
+                ))
+            })
+    })
+    .await
+    .map_err(|e| Error::Message(format!("failed to archive build context: {e}")))?
+}
@@ -0,0 +1,325 @@
+//! An in-process cache of ensured images.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use futures::StreamExt as _;
+use futures::stream;
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+use crate::Docker;
+use crate::Error;
+use crate::Result;
+use crate::images::ImagePolicy;
+use crate::images::PullOptions;
+use crate::images::PullSource;
+
+/// The default maximum number of images to pull concurrently when warming the
+/// cache.
+pub const DEFAULT_MAX_CONCURRENT_PULLS: usize = 4;
+
+/// The cached contents of files read from images keyed by image reference and
+/// path.
+type FileContents = HashMap<(String, String), Arc<[u8]>>;
+
+/// An entry of the cache holding the source an image was pulled from (if it
+/// was pulled) or the message of the error that ensuring it failed with.
+type Entry = Arc<OnceCell<std::result::Result<Option<PullSource>, String>>>;
+
+/// A cache of images that are known to exist within the Docker daemon.
+///
+/// Many tasks commonly reference the same image. Rather than having each task
+/// independently ensure that the image exists (and potentially race to pull
+/// the same image), the cache ensures that only a single pull happens per
+/// image reference. Every other concurrent caller waits on the result of that
+/// pull.
+///
+/// If a pull fails, the failure is returned to the caller that attempted it
+/// and to every caller that was waiting on it. The failure is then evicted
+/// from the cache so that a subsequent call tries again.
+#[derive(Clone, Debug)]
+pub struct ImageCache {
+    /// The Docker client used to pull images.
+    docker: Docker,
+
+    /// The options for pulling images.
+    options: PullOptions,
+
+    /// The policy that images must satisfy.
+    policy: ImagePolicy,
+
+    /// The entries within the cache keyed by image reference.
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+
+    /// The contents of files read from images.
+    files: Arc<Mutex<FileContents>>,
+}
+
+impl ImageCache {
+    /// Creates a new, empty [`ImageCache`].
+    pub fn new(docker: Docker) -> Self {
+        Self {
+            docker,
+            options: Default::default(),
+            policy: Default::default(),
+            entries: Default::default(),
+            files: Default::default(),
+        }
+    }
+
+    /// Sets the options for pulling images.
+    ///
+    /// Pulls made by the cache are limited by the options' limits on
+    /// concurrent pulls (see [`PullOptions::limits()`]).
+    ///
+    /// Defaults to [`PullOptions::default()`].
+    pub fn with_pull_options(mut self, options: PullOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the policy that images must satisfy.
+    ///
+    /// Images are checked against the policy once they exist within the
+    /// Docker daemon; an image that violates the policy fails to be ensured
+    /// (and is checked again the next time it is ensured).
+    ///
+    /// Defaults to a policy that accepts every image.
+    pub fn with_policy(mut self, policy: ImagePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Ensures that an image exists in the Docker daemon.
+    ///
+    /// Only the first caller for a particular image reference will actually
+    /// ensure the image exists (see [`Docker::ensure_image()`]). Concurrent
+    /// callers for the same image wait for that first caller to complete.
+    ///
+    /// The image is then checked against the cache's policy (see
+    /// [`Self::with_policy()`]).
+    ///
+    /// If the first caller fails, the concurrent callers fail with its error;
+    /// the failure is not cached for later callers.
+    pub async fn ensure(&self, image: impl Into<String>) -> Result<()> {
+        let image = image.into();
+        self.ensure_with(&image, || async {
+            let source = self
+                .docker
+                .ensure_image_with(&image, &self.options, |_| {})
+                .await?;
+
+            if !self.policy.is_empty() {
+                let metadata = self.docker.image_metadata(&image).await?;
+                self.policy.check(&image, &metadata)?;
+            }
+
+            Ok(source)
+        })
+        .await
+    }
+
+    /// Initializes the entry of an image with `init` unless it has already
+    /// been initialized.
+    async fn ensure_with<F, Fut>(&self, image: &str, init: F) -> Result<()>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<PullSource>>>,
+    {
+        let entry = self.entry(image);
+
+        if let Some(Ok(_)) = entry.get() {
+            debug!("image `{image}` was found in the image cache");
+            return Ok(());
+        }
+
+        // The caller that ensures the image keeps its error; the callers
+        // waiting on it are given its message
+        let mut error = None;
+        let result = entry
+            .get_or_init(|| async {
+                init().await.map_err(|e| {
+                    let message = e.to_string();
+                    error = Some(e);
+                    message
+                })
+            })
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(message) => {
+                self.evict(image, &entry);
+                Err(error.unwrap_or_else(|| Error::Message(message.clone())))
+            }
+        }
+    }
+
+    /// Removes the entry of an image if it is the given entry.
+    ///
+    /// Callers already holding the entry still see its value.
+    fn evict(&self, image: &str, entry: &Entry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries
+            .get(image)
+            .is_some_and(|cached| Arc::ptr_eq(cached, entry))
+        {
+            entries.remove(image);
+        }
+    }
+
+    /// Resolves an image reference to a reference pinned to the digest the
+    /// image currently has in its registry using the cache's pull options.
+    ///
+    /// See [`Docker::resolve_image()`] for more details.
+    pub async fn resolve(&self, image: &str) -> Result<String> {
+        self.docker.resolve_image(image, &self.options).await
+    }
+
+    /// Ensures that a set of images exist in the Docker daemon, pulling up to
+    /// `max_concurrent` of them at a time.
+    ///
+    /// This allows every image a workflow uses to be staged before its first
+    /// task starts. Duplicate image references are only ensured once and
+    /// images that are already cached are not checked again.
+    ///
+    /// Returns the result of ensuring each distinct image in the order they
+    /// were first given. A failure to ensure one image does not prevent the
+    /// others from being ensured.
+    pub async fn warm(
+        &self,
+        images: impl IntoIterator<Item = impl Into<String>>,
+        max_concurrent: usize,
+    ) -> Vec<(String, Result<()>)> {
+        let mut seen = HashSet::new();
+        let images = images
+            .into_iter()
+            .map(Into::into)
+            .filter(|image: &String| seen.insert(image.clone()))
+            .collect::<Vec<_>>();
+
+        stream::iter(images)
+            .map(|image| async move {
+                let result = self.ensure(&image).await;
+                (image, result)
+            })
+            .buffered(max_concurrent.max(1))
+            .collect()
+            .await
+    }
+
+    /// Reads a file from an image without running a container.
+    ///
+    /// The image is ensured to exist first (see [`Self::ensure()`]). The
+    /// contents of the file are cached so that reading the same file from the
+    /// same image again does not contact the Docker daemon.
+    pub async fn read_file(&self, image: impl Into<String>, path: &str) -> Result<Arc<[u8]>> {
+        let image = image.into();
+        let key = (image, path.to_string());
+
+        if let Some(contents) = self.files.lock().unwrap().get(&key) {
+            debug!(
+                "file `{path}` of image `{image}` was found in the image cache",
+                image = key.0
+            );
+            return Ok(contents.clone());
+        }
+
+        self.ensure(&key.0).await?;
+        let contents: Arc<[u8]> = self.docker.read_image_file(&key.0, path).await?.into();
+        self.files.lock().unwrap().insert(key, contents.clone());
+        Ok(contents)
+    }
+
+    /// Returns whether or not an image has been ensured by the cache.
+    pub fn contains(&self, image: &str) -> bool {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(image)
+            .is_some_and(|entry| matches!(entry.get(), Some(Ok(_))))
+    }
+
+    /// Gets the source that an image ensured by the cache was pulled from.
+    ///
+    /// Returns `None` if the image has not been ensured or it already existed
+    /// within the Docker daemon.
+    pub fn source(&self, image: &str) -> Option<PullSource> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(image)
+            .and_then(|entry| entry.get()?.clone().ok().flatten())
+    }
+
+    /// Removes an image from the cache.
+    ///
+    /// This does not remove the image from the Docker daemon; it only causes
+    /// the next call to [`Self::ensure()`] for the image to check the daemon
+    /// again. Any files read from the image are also removed from the cache.
+    pub fn invalidate(&self, image: &str) {
+        self.entries.lock().unwrap().remove(image);
+        self.files
+            .lock()
+            .unwrap()
+            .retain(|(cached, _), _| cached != image);
+    }
+
+    /// Gets the entry for an image, inserting an uninitialized entry if one
+    /// does not yet exist.
+    fn entry(&self, image: &str) -> Entry {
+        self.entries
+            .lock()
+            .unwrap()
+            .entry(image.to_string())
+            .or_default()
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Ensures an image from `count` concurrent callers, counting the number
+    /// of times the image is pulled.
+    async fn ensure_concurrently(cache: &ImageCache, count: usize, fail: bool) -> usize {
+        let pulls = AtomicUsize::new(0);
+        let results = futures::future::join_all((0..count).map(|_| {
+            cache.ensure_with("ubuntu:22.04", || async {
+                pulls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                if fail {
+                    return Err(Error::Message(String::from("pull failed")));
+                }
+
+                Ok(None)
+            })
+        }))
+        .await;
+
+        assert!(results.iter().all(|result| result.is_ok() != fail));
+        pulls.into_inner()
+    }
+
+    #[tokio::test]
+    async fn concurrent_callers_share_a_pull() {
+        let cache = ImageCache::new(Docker::with_http_defaults().unwrap());
+
+        // Every caller waiting on a failed pull shares its failure
+        assert_eq!(ensure_concurrently(&cache, 4, true).await, 1);
+        assert!(!cache.contains("ubuntu:22.04"));
+
+        assert_eq!(ensure_concurrently(&cache, 4, false).await, 1);
+        assert!(cache.contains("ubuntu:22.04"));
+        assert_eq!(ensure_concurrently(&cache, 4, false).await, 0);
+    }
+}
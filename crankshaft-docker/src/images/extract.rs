@@ -0,0 +1,199 @@
+//! Extracting files from images.
+
+use std::io::Read as _;
+use std::path::Path;
+
+use bollard::models::ContainerCreateBody;
+use bollard::query_parameters::CreateContainerOptions;
+use bollard::query_parameters::DownloadFromContainerOptions;
+use bollard::query_parameters::RemoveContainerOptions;
+use tokio::io::AsyncWriteExt as _;
+use tokio_stream::StreamExt as _;
+use tracing::debug;
+use tracing::warn;
+
+use crate::Docker;
+use crate::Error;
+use crate::Result;
+
+/// Creates a container from an image (without starting it) so that its
+/// filesystem can be read, returning the container's id.
+async fn create(docker: &Docker, image: &str) -> Result<String> {
+    Ok(docker
+        .inner()
+        .create_container(
+            None::<CreateContainerOptions>,
+            ContainerCreateBody {
+                image: Some(image.to_string()),
+                // The container is never started, but a command is required to
+                // create containers from images that have none
+                cmd: Some(vec![String::from("/")]),
+                network_disabled: Some(true),
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(Error::Docker)?
+        .id)
+}
+
+/// Removes a container created by [`create()`].
+///
+/// Failures are logged rather than returned.
+async fn remove(docker: &Docker, id: &str, image: &str) {
+    if let Err(e) = docker
+        .inner()
+        .remove_container(
+            id,
+            Some(RemoveContainerOptions {
+                force: true,
+                ..Default::default()
+            }),
+        )
+        .await
+    {
+        warn!("failed to remove container `{id}` used to read from image `{image}`: {e}");
+    }
+}
+
+/// Reads a file from an image without running a container.
+///
+/// A container is created from the image (but never started) so that the
+/// file can be downloaded from its filesystem; the container is removed
+/// afterwards.
+///
+/// The image must already exist within the Docker daemon.
+pub(crate) async fn read_image_file(docker: &Docker, image: &str, path: &str) -> Result<Vec<u8>> {
+    debug!("reading file `{path}` from image `{image}`");
+
+    let id = create(docker, image).await?;
+
+    let mut archive = Vec::new();
+    let mut stream = docker.inner().download_from_container(
+        &id,
+        Some(DownloadFromContainerOptions {
+            path: path.to_string(),
+        }),
+    );
+
+    let result = async {
+        while let Some(bytes) = stream.next().await {
+            archive.extend_from_slice(&bytes.map_err(Error::Docker)?);
+        }
+
+        file_from_archive(&archive, path)
+    }
+    .await;
+
+    remove(docker, &id, image).await;
+    result
+}
+
+/// Unpacks the filesystem of an image into a directory on the host.
+///
+/// A container is created from the image (but never started) and its
+/// filesystem is exported to a temporary file before being unpacked; the
+/// container is removed afterwards. Special files (e.g., devices) are not
+/// unpacked.
+///
+/// The image must already exist within the Docker daemon.
+pub(crate) async fn unpack_image(docker: &Docker, image: &str, destination: &Path) -> Result<()> {
+    debug!(
+        "unpacking image `{image}` to `{destination}`",
+        destination = destination.display()
+    );
+
+    let error = |e: std::io::Error| {
+        Error::Message(format!(
+            "failed to unpack image `{image}` to `{destination}`: {e}",
+            destination = destination.display()
+        ))
+    };
+
+    tokio::fs::create_dir_all(destination)
+        .await
+        .map_err(error)?;
+    let archive = tempfile::NamedTempFile::new_in(destination).map_err(error)?;
+
+    let id = create(docker, image).await?;
+
+    let result = async {
+        let mut file = tokio::fs::File::from_std(archive.reopen().map_err(error)?);
+        let mut stream = docker.inner().export_container(&id);
+        while let Some(bytes) = stream.next().await {
+            file.write_all(&bytes.map_err(Error::Docker)?)
+                .await
+                .map_err(error)?;
+        }
+
+        file.flush().await.map_err(error)
+    }
+    .await;
+
+    remove(docker, &id, image).await;
+    result?;
+
+    let destination = destination.to_path_buf();
+    let unpacked = tokio::task::spawn_blocking(move || {
+        let mut archive = tar::Archive::new(archive.reopen()?);
+        archive.set_preserve_permissions(true);
+        archive.set_overwrite(true);
+        archive.unpack(&destination)
+    })
+    .await
+    .map_err(|e| Error::Message(format!("failed to unpack image `{image}`: {e}")))?;
+
+    unpacked.map_err(error)
+}
+
+/// Gets the contents of the file at the root of a tar archive.
+///
+/// The Docker daemon archives a single path with the path's file name at the
+/// root of the archive.
+fn file_from_archive(archive: &[u8], path: &str) -> Result<Vec<u8>> {
+    let error = |e: std::io::Error| Error::Message(format!("failed to read `{path}`: {e}"));
+
+    let mut archive = tar::Archive::new(archive);
+    let mut entry = archive
+        .entries()
+        .map_err(error)?
+        .next()
+        .ok_or_else(|| Error::Message(format!("path `{path}` was not found")))?
+        .map_err(error)?;
+
+    if !entry.header().entry_type().is_file() {
+        return Err(Error::Message(format!("path `{path}` is not a file")));
+    }
+
+    let mut contents = Vec::with_capacity(entry.size().try_into().unwrap_or_default());
+    entry.read_to_end(&mut contents).map_err(error)?;
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive(ty: tar::EntryType, contents: &[u8]) -> Vec<u8> {
+        let mut tar = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(ty);
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, "version.txt", contents)
+            .unwrap();
+        tar.into_inner().unwrap()
+    }
+
+    #[test]
+    fn files() {
+        let contents =
+            file_from_archive(&archive(tar::EntryType::Regular, b"1.0.0"), "/version.txt").unwrap();
+        assert_eq!(contents, b"1.0.0");
+
+        assert!(
+            file_from_archive(&archive(tar::EntryType::Directory, b""), "/version.txt").is_err()
+        );
+        assert!(file_from_archive(&[], "/version.txt").is_err());
+    }
+}
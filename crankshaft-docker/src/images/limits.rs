@@ -0,0 +1,111 @@
+//! Limiting the number of concurrent image pulls.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+
+/// Limits on the number of images pulled at once.
+///
+/// Registries commonly rate limit clients that pull many images at once
+/// (e.g., Docker Hub), so pulls can be limited both across every registry and
+/// within each registry. A pull waits until it is within both limits before it
+/// starts; images that already exist in the Docker daemon are never limited.
+///
+/// Clones of the limits share the same pulls, so every [`PullOptions`] and
+/// [`ImageCache`] that uses a clone is limited together.
+///
+/// [`PullOptions`]: crate::PullOptions
+/// [`ImageCache`]: crate::ImageCache
+#[derive(Clone, Debug, Default)]
+pub struct PullLimits {
+    /// The limit on pulls across every registry (if there is one).
+    global: Option<Arc<Semaphore>>,
+
+    /// The limit on pulls within each registry (if there is one).
+    per_registry: Option<usize>,
+
+    /// The limits of each registry pulled from so far.
+    registries: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+impl PullLimits {
+    /// Creates limits that do not limit pulls.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of images pulled at once across every registry.
+    ///
+    /// The maximum is treated as `1` if it is `0`.
+    pub fn max_concurrent(mut self, max: usize) -> Self {
+        self.global = Some(Arc::new(Semaphore::new(max.max(1))));
+        self
+    }
+
+    /// Sets the maximum number of images pulled at once from each registry.
+    ///
+    /// The maximum is treated as `1` if it is `0`.
+    pub fn max_concurrent_per_registry(mut self, max: usize) -> Self {
+        self.per_registry = Some(max.max(1));
+        self.registries.lock().unwrap().clear();
+        self
+    }
+
+    /// Waits until a pull from the given registry is within the limits.
+    ///
+    /// The pull is counted against the limits until the returned permits are
+    /// dropped.
+    pub(crate) async fn acquire(&self, registry: &str) -> Vec<OwnedSemaphorePermit> {
+        let registry = self.per_registry.map(|max| {
+            self.registries
+                .lock()
+                .unwrap()
+                .entry(registry.to_string())
+                .or_insert_with(|| Arc::new(Semaphore::new(max)))
+                .clone()
+        });
+
+        // The registry's limit is waited on first so that pulls waiting on a
+        // busy registry don't hold up pulls from other registries
+        let mut permits = Vec::with_capacity(2);
+        for semaphore in registry.into_iter().chain(self.global.clone()) {
+            // SAFETY: the semaphores are never closed.
+            permits.push(semaphore.acquire_owned().await.unwrap());
+        }
+
+        permits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn limits_pulls() {
+        let limits = PullLimits::new()
+            .max_concurrent(2)
+            .max_concurrent_per_registry(1);
+
+        let first = limits.acquire("docker.io").await;
+        assert_eq!(first.len(), 2);
+
+        // The registry is at its limit
+        let pending = limits.acquire("docker.io");
+        tokio::pin!(pending);
+        assert!(futures::poll!(&mut pending).is_pending());
+
+        // Other registries are within the limits until the global limit is hit
+        let second = limits.acquire("ghcr.io").await;
+        assert!(futures::poll!(Box::pin(limits.acquire("quay.io"))).is_pending());
+
+        drop(first);
+        drop(second);
+        assert_eq!(pending.await.len(), 2);
+
+        assert!(PullLimits::new().acquire("docker.io").await.is_empty());
+    }
+}
@@ -0,0 +1,214 @@
+//! Loading images from archives.
+
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bollard::body_full;
+use bollard::body_try_stream;
+use bollard::query_parameters::ImportImageOptions;
+use futures::stream;
+use thiserror::Error;
+use tokio::io::AsyncReadExt as _;
+use tokio_stream::StreamExt as _;
+use tracing::debug;
+use tracing::trace;
+
+use crate::Docker;
+
+/// The size of the chunks an archive is streamed to the Docker daemon in.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// An error for a malformed image source.
+#[derive(Debug, Error)]
+#[error(
+    "invalid image source `{0}`: expected `docker-archive:<path>`, `oci-archive:<path>`, or \
+     `oci:<path>`"
+)]
+pub struct InvalidImageSource(pub(crate) String);
+
+/// A source of an image that is loaded without contacting a registry.
+///
+/// Sources are written with the same transports as `skopeo` (e.g.,
+/// `docker-archive:ubuntu.tar`), which allows images exported from a registry
+/// elsewhere to be loaded on hosts without network access.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImageSource {
+    /// A tarball created with `docker save`.
+    DockerArchive(PathBuf),
+
+    /// A tarball of an OCI image layout.
+    OciArchive(PathBuf),
+
+    /// A directory containing an OCI image layout.
+    Oci(PathBuf),
+}
+
+impl ImageSource {
+    /// Gets the path of the source.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::DockerArchive(path) | Self::OciArchive(path) | Self::Oci(path) => path,
+        }
+    }
+}
+
+impl FromStr for ImageSource {
+    type Err = InvalidImageSource;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (transport, path) = s
+            .split_once(':')
+            .filter(|(_, path)| !path.is_empty())
+            .ok_or_else(|| InvalidImageSource(s.to_string()))?;
+
+        match transport {
+            "docker-archive" => Ok(Self::DockerArchive(path.into())),
+            "oci-archive" => Ok(Self::OciArchive(path.into())),
+            "oci" => Ok(Self::Oci(path.into())),
+            _ => Err(InvalidImageSource(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ImageSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let transport = match self {
+            Self::DockerArchive(_) => "docker-archive",
+            Self::OciArchive(_) => "oci-archive",
+            Self::Oci(_) => "oci",
+        };
+
+        write!(f, "{transport}:{path}", path = self.path().display())
+    }
+}
+
+/// Loads the images within a source into the Docker daemon.
+///
+/// Returns the references (or ids, for untagged images) of the loaded images.
+pub(crate) async fn load_image(
+    docker: &Docker,
+    source: &ImageSource,
+) -> crate::Result<Vec<String>> {
+    debug!("loading images from `{source}`");
+
+    let body = match source {
+        ImageSource::DockerArchive(path) | ImageSource::OciArchive(path) => {
+            let file = tokio::fs::File::open(path).await.map_err(|e| {
+                crate::Error::Message(format!(
+                    "failed to open image archive `{path}`: {e}",
+                    path = path.display()
+                ))
+            })?;
+
+            body_try_stream(stream::unfold(Some(file), |file| async move {
+                let mut file = file?;
+                let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+                match (&mut file)
+                    .take(CHUNK_SIZE as u64)
+                    .read_to_end(&mut chunk)
+                    .await
+                {
+                    Ok(0) => None,
+                    Ok(_) => Some((Ok(chunk.into()), Some(file))),
+                    Err(e) => Some((Err(e), None)),
+                }
+            }))
+        }
+        ImageSource::Oci(path) => body_full(archive(path.clone()).await?.into()),
+    };
+
+    let mut stream = docker.inner().import_image(
+        ImportImageOptions {
+            quiet: true,
+            ..Default::default()
+        },
+        body,
+        None,
+    );
+
+    let mut loaded = Vec::new();
+    while let Some(result) = stream.next().await {
+        let info = result.map_err(|e| {
+            crate::Error::Message(format!("failed to load images from `{source}`: {e}"))
+        })?;
+
+        for line in info.stream.iter().flat_map(|s| s.lines()) {
+            trace!("load output: {line}");
+            if let Some(image) = loaded_image(line) {
+                loaded.push(image.to_string());
+            }
+        }
+    }
+
+    debug!(
+        "loaded {count} image(s) from `{source}`",
+        count = loaded.len()
+    );
+    Ok(loaded)
+}
+
+/// Gets the image reported by a line of the output of loading images.
+fn loaded_image(line: &str) -> Option<&str> {
+    let line = line.trim();
+    line.strip_prefix("Loaded image: ")
+        .or_else(|| line.strip_prefix("Loaded image ID: "))
+        .map(str::trim)
+}
+
+/// Creates a tar archive of an OCI image layout directory.
+async fn archive(layout: PathBuf) -> crate::Result<Vec<u8>> {
+    tokio::task::spawn_blocking(move || {
+        let mut tar = tar::Builder::new(Vec::new());
+        tar.follow_symlinks(false);
+        tar.append_dir_all(".", &layout)
+            .and_then(|_| tar.into_inner())
+            .map_err(|e| {
+                crate::Error::Message(format!(
+                    "failed to archive OCI image layout `{layout}`: {e}",
+                    layout = layout.display()
+                ))
+            })
+    })
+    .await
+    .map_err(|e| crate::Error::Message(format!("failed to archive OCI image layout: {e}")))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sources() {
+        for source in [
+            "docker-archive:/tmp/ubuntu.tar",
+            "oci-archive:ubuntu.tar",
+            "oci:/images/ubuntu",
+        ] {
+            assert_eq!(source.parse::<ImageSource>().unwrap().to_string(), source);
+        }
+
+        assert_eq!(
+            "oci:layout".parse::<ImageSource>().unwrap(),
+            ImageSource::Oci(PathBuf::from("layout"))
+        );
+
+        for invalid in ["ubuntu.tar", "docker-archive:", "docker://ubuntu"] {
+            assert!(invalid.parse::<ImageSource>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn loaded_images() {
+        assert_eq!(
+            loaded_image("Loaded image: ubuntu:22.04\n"),
+            Some("ubuntu:22.04")
+        );
+        assert_eq!(
+            loaded_image("Loaded image ID: sha256:abc"),
+            Some("sha256:abc")
+        );
+        assert_eq!(loaded_image("Loading layer"), None);
+    }
+}
@@ -0,0 +1,127 @@
+//! Typed image metadata.
+
+use std::collections::BTreeMap;
+
+use bollard::secret::ImageInspect;
+use serde::Serialize;
+
+/// The metadata of an image.
+///
+/// This is a typed subset of the result of inspecting an image that is useful
+/// when deciding how (or whether) to run it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct ImageMetadata {
+    /// The content-addressable id of the image.
+    pub id: Option<String>,
+
+    /// The tags that reference the image.
+    pub tags: Vec<String>,
+
+    /// The digests of the manifests that reference the image.
+    pub digests: Vec<String>,
+
+    /// When the image was created.
+    pub created: Option<String>,
+
+    /// The total size of the image in bytes.
+    pub size: Option<u64>,
+
+    /// The CPU architecture the image runs on.
+    pub architecture: Option<String>,
+
+    /// The operating system the image runs on.
+    pub os: Option<String>,
+
+    /// The labels of the image.
+    pub labels: BTreeMap<String, String>,
+
+    /// The environment variables set by the image.
+    pub env: BTreeMap<String, String>,
+
+    /// The entrypoint of the image.
+    pub entrypoint: Vec<String>,
+
+    /// The default command of the image.
+    pub cmd: Vec<String>,
+
+    /// The working directory of the image.
+    pub work_dir: Option<String>,
+
+    /// The user the image runs as.
+    pub user: Option<String>,
+}
+
+impl ImageMetadata {
+    /// Gets the value of a label of the image.
+    pub fn label(&self, key: &str) -> Option<&str> {
+        self.labels.get(key).map(String::as_str)
+    }
+}
+
+impl From<ImageInspect> for ImageMetadata {
+    fn from(inspect: ImageInspect) -> Self {
+        let config = inspect.config.unwrap_or_default();
+
+        Self {
+            id: inspect.id,
+            tags: inspect.repo_tags.unwrap_or_default(),
+            digests: inspect.repo_digests.unwrap_or_default(),
+            created: inspect.created.map(|created| created.to_string()),
+            size: inspect.size.and_then(|size| size.try_into().ok()),
+            architecture: inspect.architecture,
+            os: inspect.os,
+            labels: config.labels.unwrap_or_default().into_iter().collect(),
+            env: config
+                .env
+                .unwrap_or_default()
+                .into_iter()
+                .map(|var| match var.split_once('=') {
+                    Some((name, value)) => (name.to_string(), value.to_string()),
+                    None => (var, String::new()),
+                })
+                .collect(),
+            entrypoint: config.entrypoint.unwrap_or_default(),
+            cmd: config.cmd.unwrap_or_default(),
+            work_dir: config.working_dir.filter(|dir| !dir.is_empty()),
+            user: config.user.filter(|user| !user.is_empty()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bollard::secret::ImageConfig;
+
+    use super::*;
+
+    #[test]
+    fn from_inspect() {
+        let metadata = ImageMetadata::from(ImageInspect {
+            id: Some(String::from("sha256:abc")),
+            repo_tags: Some(vec![String::from("ubuntu:22.04")]),
+            size: Some(1024),
+            config: Some(ImageConfig {
+                labels: Some([(String::from("gpu"), String::from("required"))].into()),
+                env: Some(vec![
+                    String::from("PATH=/usr/bin:/bin"),
+                    String::from("EMPTY"),
+                ]),
+                cmd: Some(vec![String::from("bash")]),
+                working_dir: Some(String::new()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        assert_eq!(metadata.id.as_deref(), Some("sha256:abc"));
+        assert_eq!(metadata.tags, ["ubuntu:22.04"]);
+        assert_eq!(metadata.size, Some(1024));
+        assert_eq!(metadata.label("gpu"), Some("required"));
+        assert_eq!(metadata.env["PATH"], "/usr/bin:/bin");
+        assert_eq!(metadata.env["EMPTY"], "");
+        assert_eq!(metadata.cmd, ["bash"]);
+        assert!(metadata.entrypoint.is_empty());
+        assert_eq!(metadata.work_dir, None);
+    }
+}
@@ -0,0 +1,90 @@
+//! Selecting the architecture of images.
+
+use crate::Docker;
+use crate::Error;
+use crate::Result;
+use crate::images::inspect_image;
+
+/// The names of architectures as used by Docker images, along with the other
+/// names they are commonly known by (e.g., as reported by `uname -m`).
+const ARCHITECTURES: &[(&str, &[&str])] = &[
+    ("amd64", &["x86_64", "x86-64"]),
+    ("arm64", &["aarch64", "arm64v8"]),
+    ("arm", &["armv7l", "armv7", "armhf"]),
+    ("386", &["i386", "i686", "x86"]),
+    ("ppc64le", &[]),
+    ("s390x", &[]),
+    ("riscv64", &[]),
+];
+
+/// Normalizes the name of an architecture to the name used by Docker images
+/// (e.g., `x86_64` is `amd64` and `aarch64` is `arm64`).
+///
+/// Returns `None` if the architecture is not recognized.
+pub fn normalize_arch(arch: &str) -> Option<&'static str> {
+    let arch = arch.trim().to_lowercase();
+    ARCHITECTURES
+        .iter()
+        .find(|(name, aliases)| *name == arch || aliases.contains(&arch.as_str()))
+        .map(|(name, _)| *name)
+}
+
+/// Normalizes the name of a requested architecture, failing if it is not
+/// recognized.
+pub(crate) fn requested_arch(arch: &str) -> Result<&'static str> {
+    normalize_arch(arch).ok_or_else(|| {
+        Error::Message(format!(
+            "unsupported architecture `{arch}`: expected one of {expected}",
+            expected = ARCHITECTURES
+                .iter()
+                .map(|(name, _)| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    })
+}
+
+/// Gets the normalized architecture of an image in the Docker daemon (if it
+/// reports one).
+pub(crate) async fn image_arch(docker: &Docker, image: &str) -> Result<Option<String>> {
+    let inspect = inspect_image(docker, image).await?;
+    Ok(inspect
+        .architecture
+        .map(|arch| normalize_arch(&arch).map(str::to_string).unwrap_or(arch)))
+}
+
+/// Verifies that an image in the Docker daemon is for the requested
+/// architecture.
+///
+/// Images that do not report an architecture are not verified.
+pub(crate) async fn verify_arch(docker: &Docker, image: &str, arch: &str) -> Result<()> {
+    match image_arch(docker, image).await? {
+        Some(actual) if actual != arch => Err(Error::Message(format!(
+            "image `{image}` is for architecture `{actual}`, but architecture `{arch}` was \
+             requested (the image may not have a variant for `{arch}`)"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_architectures() {
+        assert_eq!(normalize_arch("x86_64"), Some("amd64"));
+        assert_eq!(normalize_arch("AMD64"), Some("amd64"));
+        assert_eq!(normalize_arch("aarch64"), Some("arm64"));
+        assert_eq!(normalize_arch("ppc64le"), Some("ppc64le"));
+        assert_eq!(normalize_arch("sparc"), None);
+
+        assert!(requested_arch("arm64").is_ok());
+        assert!(
+            requested_arch("sparc")
+                .unwrap_err()
+                .to_string()
+                .contains("`amd64`")
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Policies that images must satisfy to be run.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::Error;
+use crate::Result;
+use crate::images::ImageMetadata;
+
+/// A custom rule of an image policy.
+///
+/// A rule returns the reason an image violates it (if it does).
+type Rule = dyn Fn(&str, &ImageMetadata) -> Option<String> + Send + Sync;
+
+/// A label an image is required to have.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RequiredLabel {
+    /// The key of the label.
+    key: String,
+
+    /// The value the label is required to have (if any).
+    value: Option<String>,
+}
+
+/// A policy that images must satisfy to be run, checked against the metadata
+/// of an image once it exists within the Docker daemon.
+///
+/// Institutions commonly require that images record where they came from
+/// (e.g., the `org.opencontainers.image.source` label) or that they carry a
+/// label applied by an approval process. A policy rejects images that are
+/// missing any of its required labels or that violate any of its custom rules.
+#[derive(Clone, Default)]
+pub struct ImagePolicy {
+    /// The labels images are required to have.
+    labels: Vec<RequiredLabel>,
+
+    /// The custom rules images must satisfy.
+    rules: Vec<Arc<Rule>>,
+}
+
+impl fmt::Debug for ImagePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImagePolicy")
+            .field("labels", &self.labels)
+            .field("rules", &self.rules.len())
+            .finish()
+    }
+}
+
+impl ImagePolicy {
+    /// Creates a policy that accepts every image.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires images to have a label.
+    ///
+    /// The label is either a key (e.g., `org.opencontainers.image.source`),
+    /// which the image must have with any value, or a key and value (e.g.,
+    /// `com.example.approved=true`), which the image must have with exactly
+    /// that value.
+    pub fn require_label(mut self, label: impl AsRef<str>) -> Self {
+        let label = label.as_ref();
+        self.labels.push(match label.split_once('=') {
+            Some((key, value)) => RequiredLabel {
+                key: key.to_string(),
+                value: Some(value.to_string()),
+            },
+            None => RequiredLabel {
+                key: label.to_string(),
+                value: None,
+            },
+        });
+        self
+    }
+
+    /// Adds a custom rule that images must satisfy.
+    ///
+    /// The rule is given the image reference and its metadata and returns the
+    /// reason the image violates the rule (if it does).
+    pub fn rule(
+        mut self,
+        rule: impl Fn(&str, &ImageMetadata) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.rules.push(Arc::new(rule));
+        self
+    }
+
+    /// Determines if the policy accepts every image.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty() && self.rules.is_empty()
+    }
+
+    /// Checks an image against the policy.
+    ///
+    /// Returns [`Error::Rejected`] with every reason the image violates the
+    /// policy if it does.
+    pub fn check(&self, image: &str, metadata: &ImageMetadata) -> Result<()> {
+        let mut reasons = Vec::new();
+        for required in &self.labels {
+            match (metadata.label(&required.key), &required.value) {
+                (None, _) => reasons.push(format!("missing label `{key}`", key = required.key)),
+                (Some(actual), Some(value)) if actual != value => reasons.push(format!(
+                    "label `{key}` is `{actual}` rather than `{value}`",
+                    key = required.key
+                )),
+                _ => {}
+            }
+        }
+
+        reasons.extend(self.rules.iter().filter_map(|rule| rule(image, metadata)));
+
+        if reasons.is_empty() {
+            return Ok(());
+        }
+
+        Err(Error::Rejected {
+            image: image.to_string(),
+            reasons,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_labels() {
+        let metadata = ImageMetadata {
+            labels: [
+                (
+                    String::from("org.opencontainers.image.source"),
+                    String::from("https://example.com/repo"),
+                ),
+                (String::from("approved"), String::from("false")),
+            ]
+            .into(),
+            ..Default::default()
+        };
+
+        assert!(ImagePolicy::new().check("image", &metadata).is_ok());
+        assert!(
+            ImagePolicy::new()
+                .require_label("org.opencontainers.image.source")
+                .check("image", &metadata)
+                .is_ok()
+        );
+
+        let policy = ImagePolicy::new()
+            .require_label("approved=true")
+            .require_label("owner")
+            .rule(|image, _| Some(format!("`{image}` is not allowed")));
+        match policy.check("image", &metadata) {
+            Err(Error::Rejected { image, reasons }) => {
+                assert_eq!(image, "image");
+                assert_eq!(
+                    reasons,
+                    [
+                        "label `approved` is `false` rather than `true`",
+                        "missing label `owner`",
+                        "`image` is not allowed"
+                    ]
+                );
+            }
+            result => panic!("unexpected result `{result:?}`"),
+        }
+    }
+}
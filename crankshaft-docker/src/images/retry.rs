@@ -0,0 +1,214 @@
+//! Retrying image pulls.
+
+use std::time::Duration;
+
+use rand::Rng as _;
+
+use crate::Error;
+
+/// The default maximum number of attempts to pull an image.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// The default delay before the first retry of a pull.
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The default maximum delay between retries of a pull.
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Messages within pull errors that indicate the pull will never succeed.
+const PERMANENT_ERRORS: &[&str] = &[
+    "not found",
+    "manifest unknown",
+    "unauthorized",
+    "denied",
+    "invalid reference format",
+];
+
+/// Messages within pull errors that indicate the registry is rate limiting
+/// pulls.
+const RATE_LIMIT_ERRORS: &[&str] = &["toomanyrequests", "rate limit"];
+
+/// The policy for retrying failed image pulls.
+///
+/// The delay before each retry grows exponentially from the initial backoff
+/// (doubling with every attempt) up to the maximum backoff. When jitter is
+/// enabled, each delay is scaled by a random factor between `0.5` and `1.0` so
+/// that many clients retrying at once do not overwhelm a registry together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts (including the first).
+    max_attempts: u32,
+
+    /// The delay before the first retry.
+    initial_backoff: Duration,
+
+    /// The maximum delay between retries.
+    max_backoff: Duration,
+
+    /// Whether to apply random jitter to the delays.
+    jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn never() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Sets the maximum number of attempts (including the first).
+    ///
+    /// A value of zero is treated as one.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the maximum delay between retries.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Sets whether to apply random jitter to the delays.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Gets the maximum number of attempts (including the first).
+    pub fn attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Gets the delay before the given retry (where `1` is the first retry).
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let delay = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff);
+
+        if self.jitter {
+            delay.mul_f64(rand::rng().random_range(0.5..=1.0))
+        } else {
+            delay
+        }
+    }
+
+    /// Determines if a failed pull should be retried.
+    ///
+    /// Failures to connect to the daemon or registry, timeouts, server errors,
+    /// and rate limiting are considered retryable. Failures that indicate the
+    /// image does not exist or cannot be accessed are not.
+    pub fn is_retryable(error: &Error) -> bool {
+        use bollard::errors::Error as Bollard;
+
+        match error {
+            Error::Docker(Bollard::DockerResponseServerError {
+                status_code,
+                message,
+            }) => {
+                (*status_code >= 500 || *status_code == 408 || *status_code == 429)
+                    && !is_permanent(message)
+            }
+            Error::Docker(Bollard::DockerStreamError { error }) => !is_permanent(error),
+            Error::Docker(
+                Bollard::RequestTimeoutError
+                | Bollard::IOError { .. }
+                | Bollard::HyperResponseError { .. },
+            ) => true,
+            _ => false,
+        }
+    }
+
+    /// Determines if a pull failed because the registry is rate limiting
+    /// pulls.
+    pub(crate) fn is_rate_limited(error: &Error) -> bool {
+        use bollard::errors::Error as Bollard;
+
+        let message = match error {
+            Error::Docker(Bollard::DockerResponseServerError {
+                status_code: 429, ..
+            }) => return true,
+            Error::Docker(Bollard::DockerResponseServerError { message, .. }) => message,
+            Error::Docker(Bollard::DockerStreamError { error }) => error,
+            _ => return false,
+        };
+
+        let message = message.to_lowercase();
+        RATE_LIMIT_ERRORS.iter().any(|m| message.contains(m))
+    }
+}
+
+/// Determines if an error message indicates a pull will never succeed.
+fn is_permanent(message: &str) -> bool {
+    let message = message.to_lowercase();
+    PERMANENT_ERRORS.iter().any(|m| message.contains(m))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff() {
+        let policy = RetryPolicy::default().jitter(false);
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(10), DEFAULT_MAX_BACKOFF);
+        assert_eq!(policy.backoff(u32::MAX), DEFAULT_MAX_BACKOFF);
+
+        let policy = RetryPolicy::default();
+        for retry in 1..10 {
+            let delay = policy.backoff(retry);
+            let max = RetryPolicy::default().jitter(false).backoff(retry);
+            assert!(delay >= max / 2 && delay <= max);
+        }
+    }
+
+    #[test]
+    fn retryable() {
+        let server = |status_code, message: &str| {
+            Error::Docker(bollard::errors::Error::DockerResponseServerError {
+                status_code,
+                message: message.to_string(),
+            })
+        };
+
+        assert!(RetryPolicy::is_retryable(&server(503, "unavailable")));
+        assert!(RetryPolicy::is_retryable(&server(429, "toomanyrequests")));
+        assert!(!RetryPolicy::is_retryable(&server(404, "not found")));
+        assert!(RetryPolicy::is_rate_limited(&server(429, "")));
+        assert!(RetryPolicy::is_rate_limited(&server(
+            500,
+            "toomanyrequests: You have reached your pull rate limit"
+        )));
+        assert!(!RetryPolicy::is_rate_limited(&server(503, "unavailable")));
+        assert!(!RetryPolicy::is_retryable(&server(500, "manifest unknown")));
+        assert!(RetryPolicy::is_retryable(&Error::Docker(
+            bollard::errors::Error::DockerStreamError {
+                error: String::from("net/http: TLS handshake timeout"),
+            }
+        )));
+        assert!(!RetryPolicy::is_retryable(&Error::Message(String::from(
+            "oops"
+        ))));
+    }
+}
@@ -0,0 +1,146 @@
+//! Alternative sources that images are pulled from.
+
+use std::fmt;
+use std::str::FromStr;
+
+use bollard::query_parameters::TagImageOptions;
+use tracing::debug;
+
+use super::ImageSource;
+use super::InvalidImageSource;
+use super::load_image;
+use crate::Docker;
+use crate::Error;
+use crate::Result;
+
+/// A source that an image is pulled from in place of its own registry.
+///
+/// Sources are written either as an [`ImageSource`] (e.g.,
+/// `docker-archive:/images/ubuntu.tar`) or as the reference of the image in
+/// another registry (e.g., `mirror.internal/library/ubuntu:22.04`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PullSource {
+    /// An archive or OCI image layout loaded without contacting a registry.
+    Archive(ImageSource),
+
+    /// A reference to the image in a registry (e.g., a mirror).
+    Registry(String),
+}
+
+impl FromStr for PullSource {
+    type Err = InvalidImageSource;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let transport = s.split_once(':').map(|(transport, _)| transport);
+        match transport {
+            Some("docker-archive" | "oci-archive" | "oci") => Ok(Self::Archive(s.parse()?)),
+            _ if s.is_empty() => Err(InvalidImageSource(s.to_string())),
+            _ => Ok(Self::Registry(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for PullSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Archive(source) => write!(f, "{source}"),
+            Self::Registry(reference) => write!(f, "{reference}"),
+        }
+    }
+}
+
+/// Splits an image reference into its repository and tag.
+///
+/// References without a tag have a tag of `latest`.
+fn split_tag(image: &str) -> (&str, &str) {
+    match image.rsplit_once(':') {
+        Some((repo, tag)) if !tag.contains('/') => (repo, tag),
+        _ => (image, "latest"),
+    }
+}
+
+/// Tags an image within the Docker daemon with the given reference.
+pub(crate) async fn tag_image(docker: &Docker, source: &str, image: &str) -> Result<()> {
+    let (repo, tag) = split_tag(image);
+    debug!("tagging image `{source}` as `{image}`");
+
+    docker
+        .inner()
+        .tag_image(
+            source,
+            Some(TagImageOptions {
+                repo: Some(repo.to_string()),
+                tag: Some(tag.to_string()),
+            }),
+        )
+        .await
+        .map_err(Error::Docker)
+}
+
+/// Loads an image from an archive and tags it with the given reference.
+///
+/// The archive must either contain the image with the reference or contain a
+/// single image.
+pub(crate) async fn load_as(docker: &Docker, source: &ImageSource, image: &str) -> Result<()> {
+    let loaded = load_image(docker, source).await?;
+    if loaded.iter().any(|l| l == image) {
+        return Ok(());
+    }
+
+    match loaded.as_slice() {
+        [loaded] => tag_image(docker, loaded, image).await,
+        _ => Err(Error::Message(format!(
+            "`{source}` does not contain image `{image}` (loaded {images})",
+            images = if loaded.is_empty() {
+                String::from("no images")
+            } else {
+                loaded
+                    .iter()
+                    .map(|l| format!("`{l}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn parses_sources() {
+        assert_eq!(
+            "docker-archive:/images/ubuntu.tar"
+                .parse::<PullSource>()
+                .unwrap(),
+            PullSource::Archive(ImageSource::DockerArchive(PathBuf::from(
+                "/images/ubuntu.tar"
+            )))
+        );
+        assert_eq!(
+            "mirror.internal:5000/library/ubuntu:22.04"
+                .parse::<PullSource>()
+                .unwrap(),
+            PullSource::Registry(String::from("mirror.internal:5000/library/ubuntu:22.04"))
+        );
+        assert!("oci:".parse::<PullSource>().is_err());
+        assert!("".parse::<PullSource>().is_err());
+    }
+
+    #[test]
+    fn splits_tags() {
+        assert_eq!(split_tag("ubuntu:22.04"), ("ubuntu", "22.04"));
+        assert_eq!(split_tag("ubuntu"), ("ubuntu", "latest"));
+        assert_eq!(
+            split_tag("localhost:5000/ubuntu"),
+            ("localhost:5000/ubuntu", "latest")
+        );
+        assert_eq!(
+            split_tag("localhost:5000/ubuntu:1.0"),
+            ("localhost:5000/ubuntu", "1.0")
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! The disk usage of images and their eviction.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bollard::query_parameters::RemoveImageOptions;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::Docker;
+use crate::Error;
+use crate::Result;
+
+/// The disk usage of an image stored in the Docker daemon.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct ImageUsage {
+    /// The content-addressable id of the image.
+    pub id: String,
+
+    /// The tags that reference the image.
+    pub tags: Vec<String>,
+
+    /// The size of the image in bytes.
+    ///
+    /// Layers shared with other images are counted for each image.
+    pub size: u64,
+
+    /// When the image was created (in seconds since the Unix epoch).
+    pub created: i64,
+
+    /// When the image was last used (in seconds since the Unix epoch).
+    ///
+    /// An image is used when it is pulled, tagged, or a container is created
+    /// from it. Images with running containers are used as of now.
+    pub last_used: i64,
+
+    /// The number of containers (running or not) created from the image.
+    pub containers: usize,
+}
+
+impl ImageUsage {
+    /// Returns whether or not any container was created from the image.
+    ///
+    /// Images that are in use are never evicted.
+    pub fn in_use(&self) -> bool {
+        self.containers > 0
+    }
+}
+
+/// A policy for evicting images that are not in use.
+///
+/// Images are evicted if they have not been used for longer than the maximum
+/// age and, least recently used first, until the total size of the images is
+/// within the maximum size. A policy with no limits evicts every image that is
+/// not in use.
+#[derive(Clone, Debug, Default)]
+pub struct EvictionPolicy {
+    /// The maximum time since an image was last used.
+    max_age: Option<Duration>,
+
+    /// The maximum total size of images in bytes.
+    max_size: Option<u64>,
+}
+
+impl EvictionPolicy {
+    /// Sets the maximum time since an image was last used.
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    /// Sets the maximum total size of images in bytes.
+    ///
+    /// Images that are in use count towards the total size, so it may not be
+    /// possible to evict enough images to satisfy the limit.
+    pub fn max_size(mut self, size: u64) -> Self {
+        self.max_size = Some(size);
+        self
+    }
+
+    /// Selects the images to evict as of `now` (in seconds since the Unix
+    /// epoch).
+    ///
+    /// The images are returned least recently used first.
+    pub fn select<'a>(&self, images: &'a [ImageUsage], now: i64) -> Vec<&'a ImageUsage> {
+        let mut candidates = images.iter().filter(|i| !i.in_use()).collect::<Vec<_>>();
+        candidates.sort_by_key(|i| i.last_used);
+
+        if self.max_age.is_none() && self.max_size.is_none() {
+            return candidates;
+        }
+
+        let mut total = images.iter().map(|i| i.size).sum::<u64>();
+        candidates
+            .into_iter()
+            .filter(|image| {
+                let expired = self.max_age.is_some_and(|age| {
+                    now.saturating_sub(image.last_used)
+                        > age.as_secs().try_into().unwrap_or(i64::MAX)
+                });
+                let oversized = self.max_size.is_some_and(|max| total > max);
+                if expired || oversized {
+                    total = total.saturating_sub(image.size);
+                    return true;
+                }
+
+                false
+            })
+            .collect()
+    }
+}
+
+/// Gets the disk usage of every image stored in the Docker daemon.
+pub(crate) async fn image_usage(docker: &Docker) -> Result<Vec<ImageUsage>> {
+    let now = chrono::Utc::now().timestamp();
+
+    // The containers created from each image and when they were last used
+    let mut containers: HashMap<String, (usize, i64)> = HashMap::new();
+    for summary in docker.list_containers(true).await? {
+        let Some(image) = summary.image_id else {
+            continue;
+        };
+
+        let running = summary.state.is_some_and(|s| s.as_ref() == "running");
+        let used = if running {
+            now
+        } else {
+            summary.created.unwrap_or_default()
+        };
+        let entry = containers.entry(image).or_default();
+        entry.0 += 1;
+        entry.1 = entry.1.max(used);
+    }
+
+    let mut usage = Vec::new();
+    for image in docker.list_images().await? {
+        // Images are tagged when they are pulled, which is a use of the image
+        let tagged = docker
+            .inspect_image(&image.id)
+            .await
+            .ok()
+            .and_then(|i| i.metadata?.last_tag_time)
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(&t).ok())
+            .map(|t| t.timestamp())
+            .unwrap_or_default();
+        let (count, used) = containers.get(&image.id).copied().unwrap_or_default();
+
+        usage.push(ImageUsage {
+            tags: image.repo_tags,
+            size: image.size.try_into().unwrap_or_default(),
+            created: image.created,
+            last_used: image.created.max(tagged).max(used),
+            containers: count,
+            id: image.id,
+        });
+    }
+
+    Ok(usage)
+}
+
+/// Removes the images selected by an eviction policy from the Docker daemon.
+///
+/// Returns the images that were removed.
+pub(crate) async fn evict_images(
+    docker: &Docker,
+    policy: &EvictionPolicy,
+) -> Result<Vec<ImageUsage>> {
+    let usage = image_usage(docker).await?;
+    let selected = policy.select(&usage, chrono::Utc::now().timestamp());
+
+    let mut evicted = Vec::with_capacity(selected.len());
+    for image in selected {
+        debug!("evicting image `{id}`", id = image.id);
+        // Forcing the removal untags images referenced by multiple tags; images
+        // with containers are never selected
+        docker
+            .inner()
+            .remove_image(
+                &image.id,
+                Some(RemoveImageOptions {
+                    force: true,
+                    ..Default::default()
+                }),
+                None,
+            )
+            .await
+            .map_err(Error::Docker)?;
+        evicted.push(image.clone());
+    }
+
+    Ok(evicted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(id: &str, size: u64, last_used: i64, containers: usize) -> ImageUsage {
+        ImageUsage {
+            id: id.to_string(),
+            tags: Vec::new(),
+            size,
+            created: 0,
+            last_used,
+            containers,
+        }
+    }
+
+    #[test]
+    fn selects_images() {
+        let images = [
+            image("a", 100, 50, 0),
+            image("b", 100, 10, 0),
+            image("c", 100, 0, 1),
+            image("d", 100, 90, 0),
+        ];
+        let ids = |selected: Vec<&ImageUsage>| {
+            selected
+                .into_iter()
+                .map(|i| i.id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ids(EvictionPolicy::default().select(&images, 100)),
+            ["b", "a", "d"]
+        );
+        assert_eq!(
+            ids(EvictionPolicy::default()
+                .max_age(Duration::from_secs(40))
+                .select(&images, 100)),
+            ["b", "a"]
+        );
+        assert_eq!(
+            ids(EvictionPolicy::default().max_size(250).select(&images, 100)),
+            ["b", "a"]
+        );
+        assert!(
+            EvictionPolicy::default()
+                .max_size(400)
+                .select(&images, 100)
+                .is_empty()
+        );
+    }
+}
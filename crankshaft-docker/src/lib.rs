@@ -1,21 +1,87 @@
 //! A Docker client that uses [`bollard`].
 
+use std::collections::HashMap;
+use std::path::Path;
 use std::path::PathBuf;
 
+use bollard::query_parameters::ListContainersOptions;
 use bollard::query_parameters::ListNodesOptions;
+use bollard::query_parameters::RemoveVolumeOptions;
+use bollard::secret::ContainerSummary;
 use bollard::secret::ImageDeleteResponseItem;
+use bollard::secret::ImageInspect;
 use bollard::secret::ImageSummary;
 
+pub mod bind;
+pub mod capabilities;
+pub mod certs;
 pub mod container;
+pub mod diagnosis;
+pub mod encoding;
+pub mod env;
 pub mod images;
+pub mod locale;
+pub mod namespaces;
+pub mod pool;
+pub mod scheduling;
+pub mod security;
 pub mod service;
+pub mod tmpfs;
+pub mod validate;
+pub mod version;
+pub mod volume;
 
 use bollard::secret::Node;
 use bollard::secret::SystemInfo;
+use bollard::secret::VolumeCreateOptions;
 use thiserror::Error;
 
+pub use crate::bind::Bind;
+pub use crate::capabilities::Capabilities;
 pub use crate::container::Container;
+pub use crate::container::ExecOptions;
+pub use crate::container::ExecOutput;
+pub use crate::container::Heartbeat;
+pub use crate::container::OutputStream;
+pub use crate::container::Report;
+pub use crate::container::Setting;
+pub use crate::container::SettingSource;
+pub use crate::container::Tail;
+pub use crate::container::Template;
+pub use crate::container::Usage;
+pub use crate::diagnosis::Diagnosis;
+pub use crate::encoding::Encoding;
+pub use crate::images::BuildOptions;
+pub use crate::images::EvictionPolicy;
+pub use crate::images::ImageCache;
+pub use crate::images::ImageMetadata;
+pub use crate::images::ImagePolicy;
+pub use crate::images::ImageSource;
+pub use crate::images::ImageUsage;
+pub use crate::images::InvalidImageSource;
+pub use crate::images::PullLimits;
+pub use crate::images::PullOptions;
+pub use crate::images::PullProgress;
+pub use crate::images::PullSource;
+pub use crate::images::RegistryAuth;
+pub use crate::images::RetryPolicy;
+pub use crate::images::digest;
+pub use crate::images::normalize_arch;
+pub use crate::images::pin;
 use crate::images::*;
+pub use crate::locale::Locale;
+pub use crate::namespaces::Namespace;
+pub use crate::namespaces::NamespaceOptions;
+pub use crate::pool::Pool;
+pub use crate::pool::PoolOptions;
+pub use crate::scheduling::SchedulingOptions;
+pub use crate::security::SecurityOptions;
+pub use crate::tmpfs::Tmpfs;
+pub use crate::validate::ValidationError;
+pub use crate::version::InvalidVersion;
+pub use crate::version::Requirement;
+pub use crate::version::Version;
+pub use crate::volume::Volume;
 
 /// A global error within this crate.
 #[derive(Error, Debug)]
@@ -26,9 +92,37 @@ pub enum Error {
     /// A required value was missing for a builder field.
     #[error("missing required builder field `{0}`")]
     MissingBuilderField(&'static str),
+    /// A builder failed validation.
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
     /// An error from a message.
     #[error("{0}")]
     Message(String),
+    /// A container was killed for exceeding its memory limit.
+    #[error("container `{0}` was killed for exceeding its memory limit")]
+    OutOfMemory(String),
+    /// A version or version requirement could not be parsed.
+    #[error(transparent)]
+    InvalidVersion(#[from] InvalidVersion),
+    /// The Docker daemon does not satisfy a version requirement.
+    #[error("Docker daemon version `{version}` does not satisfy the requirement `{requirement}`")]
+    UnsupportedVersion {
+        /// The version of the Docker daemon.
+        version: Version,
+        /// The requirement that was not satisfied.
+        requirement: Requirement,
+    },
+    /// A container was removed before it could be waited on.
+    #[error("container `{0}` was removed while it was running")]
+    Vanished(String),
+    /// An image was rejected by an image policy.
+    #[error("image `{image}` was rejected by the image policy: {}", reasons.join("; "))]
+    Rejected {
+        /// The image reference.
+        image: String,
+        /// The reasons the image violates the policy.
+        reasons: Vec<String>,
+    },
 }
 
 /// A [`Result`](std::result::Result) with an [`Error`](enum@Error);
@@ -85,10 +179,142 @@ impl Docker {
     ///
     /// * Confirming that the image already exists there, or
     /// * Pulling the image from the remote repository.
+    ///
+    /// Pulls use the default [`PullOptions`].
     pub async fn ensure_image(&self, image: impl Into<String>) -> Result<()> {
         ensure_image(self, image).await
     }
 
+    /// Ensures that an image exists in the Docker daemon, reporting the
+    /// progress of any pull to the provided callback.
+    ///
+    /// The callback is invoked for every update received from the Docker
+    /// daemon while the image is being pulled. If the image already exists,
+    /// the callback is never invoked.
+    ///
+    /// See [`Self::ensure_image()`] for more details.
+    pub async fn ensure_image_with_progress(
+        &self,
+        image: impl Into<String>,
+        progress: impl FnMut(&PullProgress),
+    ) -> Result<()> {
+        ensure_image_with(self, image, &PullOptions::default(), progress).await?;
+        Ok(())
+    }
+
+    /// Ensures that an image exists in the Docker daemon, pulling with the
+    /// provided options and reporting the progress of any pull to the
+    /// provided callback.
+    ///
+    /// Returns the source that the image was pulled from (see
+    /// [`PullOptions::sources()`]), or `None` if it already existed.
+    ///
+    /// See [`Self::ensure_image_with_progress()`] for more details.
+    pub async fn ensure_image_with(
+        &self,
+        image: impl Into<String>,
+        options: &PullOptions,
+        progress: impl FnMut(&PullProgress),
+    ) -> Result<Option<PullSource>> {
+        ensure_image_with(self, image, options, progress).await
+    }
+
+    /// Builds an image from a build context directory and tags it.
+    ///
+    /// The build context is archived and sent to the Docker daemon, which
+    /// builds the image from the Dockerfile within it. Intermediate containers
+    /// are always removed.
+    ///
+    /// Returns the id of the built image.
+    pub async fn build_image(
+        &self,
+        context: impl AsRef<Path>,
+        tag: impl AsRef<str>,
+        options: &BuildOptions,
+    ) -> Result<String> {
+        build_image(self, context.as_ref(), tag.as_ref(), options).await
+    }
+
+    /// Loads the images within an archive or OCI image layout into the Docker
+    /// daemon without contacting a registry.
+    ///
+    /// This allows hosts without network access to use images exported
+    /// elsewhere (e.g., with `docker save` or `skopeo copy`). Loading OCI
+    /// image layouts requires Docker 25.0 or later.
+    ///
+    /// Returns the references (or ids, for untagged images) of the loaded
+    /// images.
+    pub async fn load_image(&self, source: &ImageSource) -> Result<Vec<String>> {
+        load_image(self, source).await
+    }
+
+    /// Creates an [`ImageCache`] backed by this client.
+    ///
+    /// The cache de-duplicates concurrent attempts to ensure the same image
+    /// exists.
+    pub fn image_cache(&self) -> ImageCache {
+        ImageCache::new(self.clone())
+    }
+
+    /// Inspects an image within the Docker daemon.
+    ///
+    /// The image must already exist within the Docker daemon (see
+    /// [`Self::ensure_image()`]).
+    pub async fn inspect_image(&self, image: impl AsRef<str>) -> Result<ImageInspect> {
+        inspect_image(self, image.as_ref()).await
+    }
+
+    /// Resolves an image reference to a reference pinned to the digest the
+    /// image currently has in its registry (e.g., `ubuntu:22.04` to
+    /// `ubuntu@sha256:...`), using the credentials of the provided options.
+    ///
+    /// References that are already pinned to a digest are returned unchanged.
+    /// The image does not need to exist within the Docker daemon.
+    pub async fn resolve_image(
+        &self,
+        image: impl AsRef<str>,
+        options: &PullOptions,
+    ) -> Result<String> {
+        resolve_image(self, image.as_ref(), options).await
+    }
+
+    /// Reads a file from an image without running a container.
+    ///
+    /// The image must already exist within the Docker daemon (see
+    /// [`Self::ensure_image()`]). See [`ImageCache::read_file()`] for a cached
+    /// alternative.
+    pub async fn read_image_file(
+        &self,
+        image: impl AsRef<str>,
+        path: impl AsRef<str>,
+    ) -> Result<Vec<u8>> {
+        read_image_file(self, image.as_ref(), path.as_ref()).await
+    }
+
+    /// Unpacks the filesystem of an image into a directory on the host without
+    /// running a container.
+    ///
+    /// This allows the contents of an image to be inspected (e.g., when
+    /// debugging a task). The directory is created if it does not exist.
+    ///
+    /// The image must already exist within the Docker daemon (see
+    /// [`Self::ensure_image()`]).
+    pub async fn unpack_image(
+        &self,
+        image: impl AsRef<str>,
+        destination: impl AsRef<Path>,
+    ) -> Result<()> {
+        unpack_image(self, image.as_ref(), destination.as_ref()).await
+    }
+
+    /// Gets the typed metadata of an image within the Docker daemon.
+    ///
+    /// The image must already exist within the Docker daemon (see
+    /// [`Self::ensure_image()`]).
+    pub async fn image_metadata(&self, image: impl AsRef<str>) -> Result<ImageMetadata> {
+        self.inspect_image(image).await.map(Into::into)
+    }
+
     /// Removes an image from the Docker daemon.
     pub async fn remove_image<T: AsRef<str>, U: AsRef<str>>(
         &self,
@@ -98,6 +324,20 @@ impl Docker {
         remove_image(self, name, tag).await
     }
 
+    /// Gets the disk usage of every image stored in the Docker daemon along
+    /// with when each image was last used.
+    pub async fn image_usage(&self) -> Result<Vec<ImageUsage>> {
+        image_usage(self).await
+    }
+
+    /// Removes the images that are not in use and are selected by an eviction
+    /// policy from the Docker daemon.
+    ///
+    /// Returns the images that were removed.
+    pub async fn evict_images(&self, policy: &EvictionPolicy) -> Result<Vec<ImageUsage>> {
+        evict_images(self, policy).await
+    }
+
     /// Removes all images from the Docker daemon.
     pub async fn remove_all_images(&self) -> Result<Vec<ImageDeleteResponseItem>> {
         remove_all_images(self).await
@@ -128,6 +368,37 @@ impl Docker {
         Container::new(self.0.clone(), id.into(), stdout, stderr)
     }
 
+    /// Lists the containers known to the Docker daemon.
+    ///
+    /// If `all` is `false`, only running containers are returned.
+    pub async fn list_containers(&self, all: bool) -> Result<Vec<ContainerSummary>> {
+        self.0
+            .list_containers(Some(ListContainersOptions {
+                all,
+                ..Default::default()
+            }))
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Lists all containers (running or not) with the given label.
+    ///
+    /// The label is either a key (e.g., `owner`) or a key and value (e.g.,
+    /// `owner=crankshaft`).
+    pub async fn list_labeled_containers(
+        &self,
+        label: impl Into<String>,
+    ) -> Result<Vec<ContainerSummary>> {
+        self.0
+            .list_containers(Some(ListContainersOptions {
+                all: true,
+                filters: Some(HashMap::from([(String::from("label"), vec![label.into()])])),
+                ..Default::default()
+            }))
+            .await
+            .map_err(Into::into)
+    }
+
     //----------------------------------------------------------------------------------
     // Nodes
     //----------------------------------------------------------------------------------
@@ -154,6 +425,41 @@ impl Docker {
         service::Builder::new(self.0.clone())
     }
 
+    /// Creates a [`Pool`] of warm containers that commands are executed
+    /// within.
+    pub fn pool(&self, options: PoolOptions) -> Pool {
+        Pool::new(self.clone(), options)
+    }
+
+    //----------------------------------------------------------------------------------
+    // Volumes
+    //----------------------------------------------------------------------------------
+
+    /// Creates a named volume (if it does not already exist).
+    ///
+    /// Volumes persist the changes made to them by containers (see
+    /// [`Volume`]) until they are removed.
+    pub async fn create_volume(&self, name: impl Into<String>) -> Result<()> {
+        self.0
+            .create_volume(VolumeCreateOptions {
+                name: Some(name.into()),
+                ..Default::default()
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Removes a named volume.
+    ///
+    /// Fails if the volume is in use by a container.
+    pub async fn remove_volume(&self, name: impl AsRef<str>) -> Result<()> {
+        self.0
+            .remove_volume(name.as_ref(), None::<RemoveVolumeOptions>)
+            .await
+            .map_err(Into::into)
+    }
+
     //----------------------------------------------------------------------------------
     // System
     //----------------------------------------------------------------------------------
@@ -162,6 +468,35 @@ impl Docker {
     pub async fn info(&self) -> Result<SystemInfo> {
         self.0.info().await.map_err(Into::into)
     }
+
+    /// Gets the resource limits supported by the Docker daemon.
+    pub async fn capabilities(&self) -> Result<Capabilities> {
+        Ok(Capabilities::from(&self.info().await?))
+    }
+
+    /// Gets the version of the Docker daemon.
+    pub async fn version(&self) -> Result<Version> {
+        let version = self.0.version().await?.version.unwrap_or_default();
+        Ok(version.parse()?)
+    }
+
+    /// Ensures the version of the Docker daemon satisfies a requirement (e.g.,
+    /// `>=24.0`), returning the version if it does.
+    ///
+    /// A requirement without a comparison operator is treated as a minimum
+    /// version.
+    pub async fn require_version(&self, requirement: &str) -> Result<Version> {
+        let requirement: Requirement = requirement.parse()?;
+        let version = self.version().await?;
+        if !requirement.matches(&version) {
+            return Err(Error::UnsupportedVersion {
+                version,
+                requirement,
+            });
+        }
+
+        Ok(version)
+    }
 }
 
 #[cfg(test)]
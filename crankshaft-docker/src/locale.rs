@@ -0,0 +1,152 @@
+//! Locale and timezone configuration for containers.
+
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// The timezone used within a container when one is not otherwise configured.
+pub const DEFAULT_TIMEZONE: &str = "UTC";
+
+/// The language used within a container when one is not otherwise
+/// configured.
+///
+/// `C.UTF-8` is available in nearly every base image and guarantees a
+/// byte-wise collation order.
+pub const DEFAULT_LANG: &str = "C.UTF-8";
+
+/// The locale and timezone of a container.
+///
+/// The environment of a container is resolved in the following order, with
+/// later values taking precedence:
+///
+/// 1. The defaults ([`DEFAULT_TIMEZONE`] and [`DEFAULT_LANG`]).
+/// 2. The `TZ`, `LANG`, and `LC_*` variables of the host, if inheritance is
+///    enabled (see [`Locale::inherit()`]).
+/// 3. The values explicitly set on the [`Locale`].
+///
+/// Any environment variables set directly on a container builder take
+/// precedence over all of the above.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Locale {
+    /// The timezone (`TZ`).
+    timezone: Option<String>,
+
+    /// The language (`LANG`).
+    lang: Option<String>,
+
+    /// The locale categories (`LC_*`).
+    categories: IndexMap<String, String>,
+
+    /// Whether to inherit the locale and timezone of the host.
+    inherit: bool,
+}
+
+impl Locale {
+    /// Sets the timezone (`TZ`).
+    pub fn timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.timezone = Some(timezone.into());
+        self
+    }
+
+    /// Sets the language (`LANG`).
+    pub fn lang(mut self, lang: impl Into<String>) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    /// Sets a locale category (e.g., `LC_COLLATE` or `LC_ALL`).
+    pub fn category(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.categories.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets whether the `TZ`, `LANG`, and `LC_*` variables of the host are
+    /// inherited.
+    ///
+    /// Defaults to `false`.
+    pub fn inherit(mut self, inherit: bool) -> Self {
+        self.inherit = inherit;
+        self
+    }
+
+    /// Resolves the environment variables for the locale.
+    pub fn env(&self) -> IndexMap<String, String> {
+        self.resolve(std::env::vars())
+    }
+
+    /// Resolves the environment variables for the locale against the given
+    /// host environment.
+    fn resolve(
+        &self,
+        host: impl IntoIterator<Item = (String, String)>,
+    ) -> IndexMap<String, String> {
+        let mut env = IndexMap::new();
+        env.insert(String::from("TZ"), String::from(DEFAULT_TIMEZONE));
+        env.insert(String::from("LANG"), String::from(DEFAULT_LANG));
+
+        if self.inherit {
+            env.extend(
+                host.into_iter()
+                    .filter(|(k, _)| k == "TZ" || k == "LANG" || k.starts_with("LC_")),
+            );
+        }
+
+        if let Some(timezone) = &self.timezone {
+            env.insert(String::from("TZ"), timezone.clone());
+        }
+
+        if let Some(lang) = &self.lang {
+            env.insert(String::from("LANG"), lang.clone());
+        }
+
+        env.extend(self.categories.clone());
+        env
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host() -> Vec<(String, String)> {
+        [
+            ("TZ", "America/Chicago"),
+            ("LANG", "en_US.UTF-8"),
+            ("LC_COLLATE", "en_US.UTF-8"),
+            ("HOME", "/root"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn defaults() {
+        let env = Locale::default().resolve(host());
+        assert_eq!(env.len(), 2);
+        assert_eq!(env["TZ"], DEFAULT_TIMEZONE);
+        assert_eq!(env["LANG"], DEFAULT_LANG);
+    }
+
+    #[test]
+    fn inherit() {
+        let env = Locale::default().inherit(true).resolve(host());
+        assert_eq!(env.len(), 3);
+        assert_eq!(env["TZ"], "America/Chicago");
+        assert_eq!(env["LANG"], "en_US.UTF-8");
+        assert_eq!(env["LC_COLLATE"], "en_US.UTF-8");
+    }
+
+    #[test]
+    fn explicit() {
+        let env = Locale::default()
+            .inherit(true)
+            .timezone("Europe/Berlin")
+            .category("LC_COLLATE", "C")
+            .resolve(host());
+        assert_eq!(env["TZ"], "Europe/Berlin");
+        assert_eq!(env["LANG"], "en_US.UTF-8");
+        assert_eq!(env["LC_COLLATE"], "C");
+    }
+}
@@ -0,0 +1,117 @@
+//! Namespace isolation options for containers.
+
+use bollard::secret::HostConfig;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Whether a container has its own namespace or shares the host's.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Namespace {
+    /// The container has its own (private) namespace.
+    #[default]
+    Private,
+
+    /// The container shares the host's namespace.
+    Host,
+}
+
+/// Options for which namespaces a container is isolated in and whether it
+/// runs an init process.
+///
+/// By default, a container has its own PID, IPC, and UTS namespaces and the
+/// Docker daemon decides whether to run an init process. Each namespace may
+/// instead be shared with the host individually (e.g., sharing the PID
+/// namespace lets a task's tools inspect processes on the host).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NamespaceOptions {
+    /// The PID namespace.
+    pid: Namespace,
+
+    /// The IPC namespace.
+    ipc: Namespace,
+
+    /// The UTS namespace (i.e., the hostname and domain name).
+    uts: Namespace,
+
+    /// Whether to run an init process that forwards signals and reaps
+    /// processes.
+    init: Option<bool>,
+}
+
+impl NamespaceOptions {
+    /// Sets the PID namespace.
+    pub fn pid(mut self, pid: Namespace) -> Self {
+        self.pid = pid;
+        self
+    }
+
+    /// Sets the IPC namespace.
+    pub fn ipc(mut self, ipc: Namespace) -> Self {
+        self.ipc = ipc;
+        self
+    }
+
+    /// Sets the UTS namespace.
+    ///
+    /// Sharing the host's UTS namespace is incompatible with setting the
+    /// container's hostname.
+    pub fn uts(mut self, uts: Namespace) -> Self {
+        self.uts = uts;
+        self
+    }
+
+    /// Sets whether to run an init process as PID 1 within the container.
+    ///
+    /// Defaults to the Docker daemon's configuration.
+    pub fn init(mut self, init: bool) -> Self {
+        self.init = Some(init);
+        self
+    }
+
+    /// Applies the options to a container's host configuration.
+    ///
+    /// Private namespaces leave the host configuration's mode unset, so that
+    /// the Docker daemon's default applies.
+    pub(crate) fn apply(&self, host_config: &mut HostConfig) {
+        for (namespace, mode) in [
+            (self.pid, &mut host_config.pid_mode),
+            (self.ipc, &mut host_config.ipc_mode),
+            (self.uts, &mut host_config.uts_mode),
+        ] {
+            if namespace == Namespace::Host {
+                *mode = Some(String::from("host"));
+            }
+        }
+
+        if let Some(init) = self.init {
+            host_config.init = Some(init);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_to_host_config() {
+        let mut host_config = HostConfig::default();
+        NamespaceOptions::default().apply(&mut host_config);
+        assert_eq!(host_config, HostConfig::default());
+
+        let options: NamespaceOptions =
+            serde_json::from_str(r#"{ "pid": "host", "init": false }"#).unwrap();
+        assert_eq!(
+            options,
+            NamespaceOptions::default().pid(Namespace::Host).init(false)
+        );
+
+        options.ipc(Namespace::Host).apply(&mut host_config);
+        assert_eq!(host_config.pid_mode.as_deref(), Some("host"));
+        assert_eq!(host_config.ipc_mode.as_deref(), Some("host"));
+        assert_eq!(host_config.uts_mode, None);
+        assert_eq!(host_config.init, Some(false));
+    }
+}
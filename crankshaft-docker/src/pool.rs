@@ -0,0 +1,375 @@
+//! Pools of warm containers that commands are executed within.
+//!
+//! Creating and starting a container for every command dominates the runtime
+//! of workflows with thousands of very short commands. A [`Pool`] instead
+//! keeps a number of containers (instances) per image running idle and
+//! executes each command within one of them (see [`Container::exec_with()`]),
+//! so that the cost of starting a container is paid once per instance rather
+//! than once per command.
+//!
+//! Instances are removed once they have been idle for longer than the idle
+//! timeout or have executed the maximum number of commands, so that state
+//! left behind by commands does not accumulate indefinitely.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use tokio::sync::Semaphore;
+use tracing::debug;
+use tracing::warn;
+
+use crate::Container;
+use crate::Docker;
+use crate::ExecOptions;
+use crate::ExecOutput;
+use crate::Result;
+use crate::container::Builder;
+
+/// The default number of instances kept per image.
+const DEFAULT_SIZE: usize = 1;
+
+/// A function that configures the builder of an instance.
+type Configure = dyn Fn(Builder) -> Builder + Send + Sync;
+
+/// Options for a [`Pool`].
+#[derive(Clone, Debug)]
+pub struct PoolOptions {
+    /// The maximum number of instances per image.
+    size: usize,
+
+    /// How long an instance may be idle before it is removed.
+    idle_timeout: Option<Duration>,
+
+    /// The maximum number of commands an instance executes before it is
+    /// removed.
+    max_uses: Option<usize>,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_SIZE,
+            idle_timeout: None,
+            max_uses: None,
+        }
+    }
+}
+
+impl PoolOptions {
+    /// Sets the maximum number of instances per image (i.e., the number of
+    /// commands using an image that are executed concurrently).
+    ///
+    /// Defaults to `1`.
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = size.max(1);
+        self
+    }
+
+    /// Sets how long an instance may be idle before it is removed.
+    ///
+    /// Defaults to no timeout.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum number of commands an instance executes before it is
+    /// removed (and replaced by a new instance when next needed).
+    ///
+    /// Defaults to no maximum.
+    pub fn max_uses(mut self, max_uses: usize) -> Self {
+        self.max_uses = Some(max_uses.max(1));
+        self
+    }
+}
+
+/// A running container of a pool.
+struct Instance {
+    /// The container.
+    container: Container,
+
+    /// The number of commands the instance has executed.
+    uses: usize,
+
+    /// When the instance last became idle.
+    idle_since: Instant,
+}
+
+/// The instances of a single image.
+struct Slots {
+    /// The permits for executing commands (one per instance).
+    permits: Arc<Semaphore>,
+
+    /// The idle instances.
+    idle: Mutex<Vec<Instance>>,
+}
+
+/// An instance leased to execute a command.
+///
+/// If the lease is dropped without the instance being returned (e.g., the
+/// future executing the command was dropped), the instance is removed, as the
+/// command may still be running within it.
+struct Lease(Option<Instance>);
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        if let Some(instance) = self.0.take() {
+            remove(instance);
+        }
+    }
+}
+
+/// Removes an instance in the background.
+fn remove(instance: Instance) {
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        warn!(
+            "pooled container `{id}` could not be removed",
+            id = instance.container.id()
+        );
+        return;
+    };
+
+    runtime.spawn(async move {
+        debug!(
+            "removing pooled container `{id}`",
+            id = instance.container.id()
+        );
+
+        if let Err(e) = instance.container.force_remove().await {
+            warn!(
+                "failed to remove pooled container `{id}`: {e}",
+                id = instance.container.id()
+            );
+        }
+    });
+}
+
+/// A pool of warm containers that commands are executed within.
+///
+/// See the [module documentation](crate::pool) for more details.
+///
+/// Instances run `sleep infinity`, so images must provide `sleep`. The image
+/// must exist within the Docker daemon before a command is executed with it.
+/// Idle instances are only removed when a command is executed or when
+/// [`Self::reap()`] or [`Self::close()`] is called, so pools should be closed
+/// once they are no longer needed.
+pub struct Pool {
+    /// The Docker client.
+    docker: Docker,
+
+    /// The options of the pool.
+    options: PoolOptions,
+
+    /// The function that configures the builders of instances.
+    configure: Option<Arc<Configure>>,
+
+    /// The instances keyed by image.
+    images: Mutex<HashMap<String, Arc<Slots>>>,
+
+    /// The number of instances created (used to name them).
+    created: AtomicUsize,
+}
+
+impl Pool {
+    /// Creates a new, empty pool.
+    pub fn new(docker: Docker, options: PoolOptions) -> Self {
+        Self {
+            docker,
+            options,
+            configure: None,
+            images: Default::default(),
+            created: AtomicUsize::new(0),
+        }
+    }
+
+    /// Configures the builders of instances (e.g., to add mounts or
+    /// environment variables shared by every command).
+    ///
+    /// The image and program of the builder are set by the pool.
+    pub fn configure(
+        mut self,
+        configure: impl Fn(Builder) -> Builder + Send + Sync + 'static,
+    ) -> Self {
+        self.configure = Some(Arc::new(configure));
+        self
+    }
+
+    /// Gets the instances of an image.
+    fn slots(&self, image: &str) -> Arc<Slots> {
+        self.images
+            .lock()
+            .unwrap()
+            .entry(image.to_string())
+            .or_insert_with(|| {
+                Arc::new(Slots {
+                    permits: Arc::new(Semaphore::new(self.options.size)),
+                    idle: Default::default(),
+                })
+            })
+            .clone()
+    }
+
+    /// Determines if an idle instance has expired.
+    fn expired(&self, instance: &Instance) -> bool {
+        self.options
+            .idle_timeout
+            .is_some_and(|timeout| instance.idle_since.elapsed() > timeout)
+    }
+
+    /// Starts a new instance of an image.
+    async fn start(&self, image: &str) -> Result<Instance> {
+        let name = format!(
+            "crankshaft-pool-{pid}-{n}",
+            pid = std::process::id(),
+            n = self.created.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let mut builder = self.docker.container_builder();
+        if let Some(configure) = &self.configure {
+            builder = configure(builder);
+        }
+
+        let container = builder
+            .image(image)
+            .program("sleep")
+            .arg("infinity")
+            .try_build(&name)
+            .await?;
+
+        let instance = Instance {
+            container,
+            uses: 0,
+            idle_since: Instant::now(),
+        };
+
+        if let Err(e) = instance.container.start().await {
+            remove(instance);
+            return Err(e);
+        }
+
+        debug!("started pooled container `{name}` for image `{image}`");
+        Ok(instance)
+    }
+
+    /// Executes a command within an instance of an image and waits for it to
+    /// complete.
+    ///
+    /// If every instance of the image is busy, this waits for one to become
+    /// idle. An instance is started if there is no idle instance.
+    pub async fn exec(
+        &self,
+        image: &str,
+        command: impl IntoIterator<Item = impl Into<String>>,
+        options: &ExecOptions,
+    ) -> Result<ExecOutput> {
+        let slots = self.slots(image);
+        let _permit = slots
+            .permits
+            .acquire()
+            .await
+            .expect("the semaphore is never closed");
+
+        let idle = {
+            let mut idle = slots.idle.lock().unwrap();
+            let (expired, fresh) = std::mem::take(&mut *idle)
+                .into_iter()
+                .partition::<Vec<_>, _>(|i| self.expired(i));
+            *idle = fresh;
+            expired.into_iter().for_each(remove);
+            idle.pop()
+        };
+
+        let instance = match idle {
+            Some(instance) => instance,
+            None => self.start(image).await?,
+        };
+
+        let mut lease = Lease(Some(instance));
+        let result = lease
+            .0
+            .as_ref()
+            .expect("the lease should have an instance")
+            .container
+            .exec_with(command, options)
+            .await;
+
+        let mut instance = lease.0.take().expect("the lease should have an instance");
+        instance.uses += 1;
+
+        // Instances that failed to execute a command may be broken
+        if result.is_err()
+            || self
+                .options
+                .max_uses
+                .is_some_and(|max| instance.uses >= max)
+        {
+            remove(instance);
+        } else {
+            instance.idle_since = Instant::now();
+            slots.idle.lock().unwrap().push(instance);
+        }
+
+        result
+    }
+
+    /// Removes the idle instances that have exceeded the idle timeout.
+    ///
+    /// Returns the number of instances removed.
+    pub async fn reap(&self) -> Result<usize> {
+        self.remove_idle(|i| self.expired(i)).await
+    }
+
+    /// Removes every idle instance.
+    ///
+    /// Instances that are executing a command are removed once the command
+    /// completes only if they are no longer needed (e.g., they exceeded the
+    /// maximum number of uses).
+    pub async fn close(&self) -> Result<()> {
+        self.remove_idle(|_| true).await.map(drop)
+    }
+
+    /// Removes the idle instances matching a predicate.
+    async fn remove_idle(&self, predicate: impl Fn(&Instance) -> bool) -> Result<usize> {
+        let slots = self
+            .images
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut removed = Vec::new();
+        for slots in slots {
+            let mut idle = slots.idle.lock().unwrap();
+            let (matched, kept) = std::mem::take(&mut *idle)
+                .into_iter()
+                .partition::<Vec<_>, _>(&predicate);
+            *idle = kept;
+            removed.extend(matched);
+        }
+
+        let count = removed.len();
+        for instance in removed {
+            instance.container.force_remove().await?;
+        }
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamps_options() {
+        let options = PoolOptions::default().size(0).max_uses(0);
+        assert_eq!(options.size, 1);
+        assert_eq!(options.max_uses, Some(1));
+    }
+}
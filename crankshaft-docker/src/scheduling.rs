@@ -0,0 +1,132 @@
+//! Scheduling priority options for containers.
+//!
+//! These allow low-priority containers (e.g., those of background workflows)
+//! to yield the CPUs and disks of a shared host to other work, rather than
+//! limiting the resources they may use outright.
+
+use bollard::secret::HostConfig;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// The number of CPU shares of a container with the default niceness.
+const DEFAULT_SHARES: f64 = 1024.0;
+
+/// The minimum number of CPU shares accepted by the Docker daemon.
+const MIN_SHARES: i64 = 2;
+
+/// The maximum number of CPU shares accepted by the Docker daemon.
+const MAX_SHARES: i64 = 262_144;
+
+/// The lowest (i.e., highest priority) niceness.
+const MIN_NICE: i32 = -20;
+
+/// The highest (i.e., lowest priority) niceness.
+const MAX_NICE: i32 = 19;
+
+/// The highest (i.e., lowest priority) IO priority level.
+const MAX_IO_PRIORITY: u8 = 7;
+
+/// Options for the CPU and IO scheduling priority of a container and the CPUs
+/// it may run on.
+///
+/// Docker has no equivalent of `nice` or `ionice` for a container's
+/// processes, so the options are expressed as the relative weights the
+/// container's cgroup is given when competing for CPUs and disks, mirroring
+/// how the kernel weighs processes of different niceness. As with any
+/// relative weight, they only have an effect while the host is contended.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SchedulingOptions {
+    /// The niceness (from `-20` to `19`).
+    nice: Option<i32>,
+
+    /// The IO priority level (from `0` to `7`).
+    io_priority: Option<u8>,
+
+    /// The CPUs the container may run on (e.g., `0-3,8`).
+    cpus: Option<String>,
+}
+
+impl SchedulingOptions {
+    /// Sets the niceness of the container (from `-20`, the highest priority,
+    /// to `19`, the lowest), clamping it to that range.
+    ///
+    /// Each step of niceness changes the container's CPU shares by a factor
+    /// of 1.25, as it changes the weight of a process. A CPU share set in the
+    /// host configuration is scaled rather than replaced.
+    pub fn nice(mut self, nice: i32) -> Self {
+        self.nice = Some(nice.clamp(MIN_NICE, MAX_NICE));
+        self
+    }
+
+    /// Sets the IO priority level of the container (from `0`, the highest
+    /// priority, to `7`, the lowest), clamping it to that range.
+    ///
+    /// As with the best-effort class of `ionice`, the default is `4`. Levels
+    /// are mapped onto the container's block IO weight.
+    pub fn io_priority(mut self, level: u8) -> Self {
+        self.io_priority = Some(level.min(MAX_IO_PRIORITY));
+        self
+    }
+
+    /// Sets the CPUs the container may run on (e.g., `0-3,8`), as with
+    /// `taskset`.
+    pub fn cpus(mut self, cpus: impl Into<String>) -> Self {
+        self.cpus = Some(cpus.into());
+        self
+    }
+
+    /// Applies the options to a container's host configuration.
+    pub(crate) fn apply(&self, host_config: &mut HostConfig) {
+        if let Some(nice) = self.nice {
+            let shares = host_config
+                .cpu_shares
+                .map(|shares| shares as f64)
+                .unwrap_or(DEFAULT_SHARES);
+            let shares = (shares / 1.25f64.powi(nice)).round() as i64;
+            host_config.cpu_shares = Some(shares.clamp(MIN_SHARES, MAX_SHARES));
+        }
+
+        if let Some(level) = self.io_priority {
+            // Level 0 is the maximum weight of 1000 and level 4 the default of
+            // 500
+            host_config.blkio_weight = Some(1000 - 125 * u16::from(level));
+        }
+
+        if let Some(cpus) = &self.cpus {
+            host_config.cpuset_cpus = Some(cpus.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_to_host_config() {
+        let mut host_config = HostConfig::default();
+        SchedulingOptions::default().apply(&mut host_config);
+        assert_eq!(host_config, HostConfig::default());
+
+        SchedulingOptions::default()
+            .nice(10)
+            .io_priority(7)
+            .cpus("0-3")
+            .apply(&mut host_config);
+        assert_eq!(host_config.cpu_shares, Some(110));
+        assert_eq!(host_config.blkio_weight, Some(125));
+        assert_eq!(host_config.cpuset_cpus.as_deref(), Some("0-3"));
+
+        let mut host_config = HostConfig {
+            cpu_shares: Some(2048),
+            ..Default::default()
+        };
+        SchedulingOptions::default()
+            .nice(-100)
+            .io_priority(0)
+            .apply(&mut host_config);
+        assert_eq!(host_config.cpu_shares, Some(177_636));
+        assert_eq!(host_config.blkio_weight, Some(1000));
+    }
+}
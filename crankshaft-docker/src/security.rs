@@ -0,0 +1,259 @@
+//! Security options for containers.
+
+use std::path::PathBuf;
+
+use bollard::secret::TaskSpecContainerSpecPrivileges;
+use bollard::secret::TaskSpecContainerSpecPrivilegesSeLinuxContext;
+use bollard::secret::TaskSpecContainerSpecPrivilegesSeccomp;
+use bollard::secret::TaskSpecContainerSpecPrivilegesSeccompModeEnum;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Error;
+use crate::Result;
+
+/// The seccomp profile applied to a container.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Seccomp {
+    /// The Docker daemon's default profile.
+    #[default]
+    Default,
+
+    /// No profile (i.e., all system calls are allowed).
+    Unconfined,
+
+    /// A custom profile read from a JSON file on the host.
+    Profile(PathBuf),
+}
+
+impl Seccomp {
+    /// Reads the contents of a custom profile.
+    fn read(path: &PathBuf) -> Result<String> {
+        std::fs::read_to_string(path).map_err(|e| {
+            Error::Message(format!(
+                "failed to read seccomp profile `{path}`: {e}",
+                path = path.display()
+            ))
+        })
+    }
+}
+
+/// The SELinux label applied to a container.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct Selinux {
+    /// Whether SELinux labeling is disabled.
+    disable: bool,
+
+    /// The SELinux user.
+    user: Option<String>,
+
+    /// The SELinux role.
+    role: Option<String>,
+
+    /// The SELinux type.
+    #[serde(rename = "type")]
+    ty: Option<String>,
+
+    /// The SELinux level.
+    level: Option<String>,
+}
+
+/// Options that restrict what the processes within a container may do.
+///
+/// These are intended to allow administrators to lock down the tasks run by
+/// workflows: which system calls are allowed (seccomp), which Linux
+/// capabilities are granted, and which SELinux label is applied.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SecurityOptions {
+    /// The seccomp profile.
+    seccomp: Seccomp,
+
+    /// The capabilities to add.
+    add_caps: Vec<String>,
+
+    /// The capabilities to drop.
+    drop_caps: Vec<String>,
+
+    /// The SELinux label.
+    selinux: Selinux,
+}
+
+impl SecurityOptions {
+    /// Sets the seccomp profile.
+    ///
+    /// Defaults to [`Seccomp::Default`].
+    pub fn seccomp(mut self, seccomp: Seccomp) -> Self {
+        self.seccomp = seccomp;
+        self
+    }
+
+    /// Adds a Linux capability (e.g., `SYS_PTRACE`) to grant.
+    pub fn add_cap(mut self, cap: impl Into<String>) -> Self {
+        self.add_caps.push(cap.into());
+        self
+    }
+
+    /// Adds a Linux capability (e.g., `NET_RAW` or `ALL`) to drop.
+    pub fn drop_cap(mut self, cap: impl Into<String>) -> Self {
+        self.drop_caps.push(cap.into());
+        self
+    }
+
+    /// Sets the SELinux user.
+    pub fn selinux_user(mut self, user: impl Into<String>) -> Self {
+        self.selinux.user = Some(user.into());
+        self
+    }
+
+    /// Sets the SELinux role.
+    pub fn selinux_role(mut self, role: impl Into<String>) -> Self {
+        self.selinux.role = Some(role.into());
+        self
+    }
+
+    /// Sets the SELinux type.
+    pub fn selinux_type(mut self, ty: impl Into<String>) -> Self {
+        self.selinux.ty = Some(ty.into());
+        self
+    }
+
+    /// Sets the SELinux level.
+    pub fn selinux_level(mut self, level: impl Into<String>) -> Self {
+        self.selinux.level = Some(level.into());
+        self
+    }
+
+    /// Disables SELinux labeling of the container.
+    pub fn disable_selinux(mut self) -> Self {
+        self.selinux.disable = true;
+        self
+    }
+
+    /// Gets the capabilities to add.
+    pub(crate) fn add_caps(&self) -> &[String] {
+        &self.add_caps
+    }
+
+    /// Gets the capabilities to drop.
+    pub(crate) fn drop_caps(&self) -> &[String] {
+        &self.drop_caps
+    }
+
+    /// Gets the security options of a container's host configuration.
+    pub(crate) fn security_opt(&self) -> Result<Vec<String>> {
+        let mut options = Vec::new();
+
+        match &self.seccomp {
+            Seccomp::Default => {}
+            Seccomp::Unconfined => options.push(String::from("seccomp=unconfined")),
+            Seccomp::Profile(path) => options.push(format!("seccomp={}", Seccomp::read(path)?)),
+        }
+
+        let Selinux {
+            disable,
+            user,
+            role,
+            ty,
+            level,
+        } = &self.selinux;
+
+        if *disable {
+            options.push(String::from("label=disable"));
+        }
+
+        for (key, value) in [
+            ("user", user),
+            ("role", role),
+            ("type", ty),
+            ("level", level),
+        ] {
+            if let Some(value) = value {
+                options.push(format!("label={key}:{value}"));
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Gets the privileges of a service's container.
+    pub(crate) fn privileges(&self) -> Result<TaskSpecContainerSpecPrivileges> {
+        let seccomp = match &self.seccomp {
+            Seccomp::Default => None,
+            Seccomp::Unconfined => Some(TaskSpecContainerSpecPrivilegesSeccomp {
+                mode: Some(TaskSpecContainerSpecPrivilegesSeccompModeEnum::UNCONFINED),
+                profile: None,
+            }),
+            Seccomp::Profile(path) => Some(TaskSpecContainerSpecPrivilegesSeccomp {
+                mode: Some(TaskSpecContainerSpecPrivilegesSeccompModeEnum::CUSTOM),
+                profile: Some(Seccomp::read(path)?),
+            }),
+        };
+
+        let selinux = &self.selinux;
+        let se_linux_context = (selinux != &Selinux::default()).then(|| {
+            TaskSpecContainerSpecPrivilegesSeLinuxContext {
+                disable: Some(selinux.disable),
+                user: selinux.user.clone(),
+                role: selinux.role.clone(),
+                typ: selinux.ty.clone(),
+                level: selinux.level.clone(),
+            }
+        });
+
+        Ok(TaskSpecContainerSpecPrivileges {
+            seccomp,
+            se_linux_context,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn security_opt() {
+        assert!(
+            SecurityOptions::default()
+                .security_opt()
+                .unwrap()
+                .is_empty()
+        );
+
+        let options = SecurityOptions::default()
+            .seccomp(Seccomp::Unconfined)
+            .selinux_type("container_t")
+            .selinux_level("s0:c100,c200");
+        assert_eq!(
+            options.security_opt().unwrap(),
+            [
+                "seccomp=unconfined",
+                "label=type:container_t",
+                "label=level:s0:c100,c200"
+            ]
+        );
+
+        let options =
+            SecurityOptions::default().seccomp(Seccomp::Profile(PathBuf::from("/does/not/exist")));
+        assert!(options.security_opt().is_err());
+    }
+
+    #[test]
+    fn deserialize() {
+        let options: SecurityOptions = serde_json::from_str(
+            r#"{ "seccomp": "unconfined", "drop-caps": ["ALL"], "selinux": { "type": "container_t" } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            options,
+            SecurityOptions::default()
+                .seccomp(Seccomp::Unconfined)
+                .drop_cap("ALL")
+                .selinux_type("container_t")
+        );
+    }
+}
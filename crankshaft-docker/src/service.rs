@@ -27,6 +27,7 @@ use tokio::io::AsyncWriteExt;
 use tokio::time::sleep;
 use tracing::debug;
 use tracing::info;
+use tracing::instrument;
 use tracing::trace;
 
 use crate::Error;
@@ -76,6 +77,7 @@ impl Service {
     }
 
     /// Runs a service and waits for the task execution to end.
+    #[instrument(skip_all, fields(service = %self.id, task = name))]
     pub async fn run(&self, name: &str, started: impl FnOnce()) -> Result<ExitStatus> {
         let (container_id, exit_code) = loop {
             trace!(
@@ -4,11 +4,14 @@ use std::path::PathBuf;
 
 use bollard::Docker;
 use bollard::secret::Mount;
+use bollard::secret::NetworkAttachmentConfig;
 use bollard::secret::ServiceSpec;
 use bollard::secret::ServiceSpecMode;
 use bollard::secret::ServiceSpecModeReplicated;
 use bollard::secret::TaskSpec;
 use bollard::secret::TaskSpecContainerSpec;
+use bollard::secret::TaskSpecContainerSpecDnsConfig;
+use bollard::secret::TaskSpecContainerSpecPrivileges;
 use bollard::secret::TaskSpecResources;
 use bollard::secret::TaskSpecRestartPolicy;
 use bollard::secret::TaskSpecRestartPolicyConditionEnum;
@@ -17,8 +20,16 @@ use tracing::info;
 use tracing::warn;
 
 use super::Service;
+use crate::Bind;
 use crate::Error;
+use crate::Locale;
 use crate::Result;
+use crate::SecurityOptions;
+use crate::Tmpfs;
+use crate::Volume;
+use crate::certs::CA_BUNDLE_ENV;
+use crate::certs::CA_BUNDLE_TARGET;
+use crate::validate;
 
 /// A builder for a [`Service`].
 pub struct Builder {
@@ -44,14 +55,53 @@ pub struct Builder {
     /// Environment variables.
     env: IndexMap<String, String>,
 
+    /// The environment files to read variables from.
+    env_files: Vec<PathBuf>,
+
+    /// Environment variables whose values are sensitive.
+    secret_env: IndexMap<String, String>,
+
+    /// The locale and timezone.
+    locale: Locale,
+
     /// The working directory.
     work_dir: Option<String>,
 
+    /// The user (and optionally group) to run as.
+    user: Option<String>,
+
+    /// The supplementary groups for the user.
+    groups: Vec<String>,
+
+    /// Whether to prevent processes from gaining additional privileges.
+    no_new_privileges: bool,
+
+    /// The security options.
+    security: SecurityOptions,
+
     /// The mounts for the service's task template.
     mounts: Vec<Mount>,
 
     /// The task resources for the service.
     resources: Option<TaskSpecResources>,
+
+    /// The extra `/etc/hosts` entries as pairs of hostname and IP address.
+    extra_hosts: Vec<(String, String)>,
+
+    /// The DNS servers.
+    dns: Vec<String>,
+
+    /// The DNS search domains.
+    dns_search: Vec<String>,
+
+    /// The DNS resolver options.
+    dns_options: Vec<String>,
+
+    /// The network to connect to.
+    network: Option<String>,
+
+    /// The hostname.
+    hostname: Option<String>,
 }
 
 impl Builder {
@@ -65,9 +115,22 @@ impl Builder {
             stdout: None,
             stderr: None,
             env: Default::default(),
+            env_files: Default::default(),
+            secret_env: Default::default(),
+            locale: Default::default(),
             work_dir: Default::default(),
+            user: Default::default(),
+            groups: Default::default(),
+            no_new_privileges: false,
+            security: Default::default(),
             mounts: Default::default(),
             resources: Default::default(),
+            extra_hosts: Default::default(),
+            dns: Default::default(),
+            dns_search: Default::default(),
+            dns_options: Default::default(),
+            network: Default::default(),
+            hostname: Default::default(),
         }
     }
 
@@ -111,6 +174,25 @@ impl Builder {
         self
     }
 
+    /// Sets environment variables from an environment file on the host.
+    ///
+    /// The file is read when the builder is built (see [`crate::env::parse()`]
+    /// for its format). Environment variables set with [`Self::env()`] take
+    /// precedence over those read from environment files.
+    pub fn env_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.env_files.push(path.into());
+        self
+    }
+
+    /// Sets an environment variable whose value is sensitive.
+    ///
+    /// The value is never logged. Secret environment variables take
+    /// precedence over all other environment variables.
+    pub fn secret_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.secret_env.insert(name.into(), value.into());
+        self
+    }
+
     /// Sets the file to write the container's stdout stream to.
     pub fn stdout(mut self, path: impl Into<PathBuf>) -> Self {
         self.stdout = Some(path.into());
@@ -123,12 +205,61 @@ impl Builder {
         self
     }
 
+    /// Sets the locale and timezone.
+    ///
+    /// Environment variables set with [`Self::env()`] take precedence over
+    /// those derived from the locale.
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
     /// Sets the working directory.
     pub fn work_dir(mut self, work_dir: impl Into<String>) -> Self {
         self.work_dir = Some(work_dir.into());
         self
     }
 
+    /// Sets the user (and optionally group) to run as.
+    ///
+    /// This may be a name or id, optionally followed by a group name or id
+    /// (e.g., `nobody`, `1000`, or `1000:1000`). If not set, the user
+    /// specified by the image is used.
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Runs as the root user (and group), regardless of the user specified by
+    /// the image.
+    ///
+    /// This is needed for tasks that must appear to be root within the
+    /// container (e.g., to install packages).
+    pub fn root(self) -> Self {
+        self.user("0:0")
+    }
+
+    /// Adds a supplementary group (by name or id) for the user to be a member
+    /// of.
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.groups.push(group.into());
+        self
+    }
+
+    /// Sets whether to prevent processes from gaining additional privileges
+    /// (e.g., through setuid binaries).
+    pub fn no_new_privileges(mut self, no_new_privileges: bool) -> Self {
+        self.no_new_privileges = no_new_privileges;
+        self
+    }
+
+    /// Sets the security options (e.g., the seccomp profile, capabilities,
+    /// and SELinux label).
+    pub fn security(mut self, security: SecurityOptions) -> Self {
+        self.security = security;
+        self
+    }
+
     /// Sets a mount for the service.
     pub fn mount(mut self, mount: impl Into<Mount>) -> Self {
         self.mounts.push(mount.into());
@@ -141,14 +272,135 @@ impl Builder {
         self
     }
 
+    /// Binds a host path into the service's container.
+    pub fn bind(self, source: impl Into<String>, target: impl Into<String>) -> Self {
+        self.mount(Bind::new(source, target))
+    }
+
+    /// Binds a host path into the service's container as read-only.
+    pub fn bind_ro(self, source: impl Into<String>, target: impl Into<String>) -> Self {
+        self.mount(Bind::read_only(source, target))
+    }
+
+    /// Mounts a named volume into the service's container.
+    ///
+    /// The volume is created if it does not exist and its contents persist
+    /// after the service's container is removed.
+    pub fn volume(self, name: impl Into<String>, target: impl Into<String>) -> Self {
+        self.mount(Volume::new(name, target))
+    }
+
+    /// Mounts a writable, in-memory filesystem of an optional maximum size (in
+    /// bytes) for scratch space.
+    pub fn tmpfs(self, target: impl Into<String>, size: Option<u64>) -> Self {
+        let tmpfs = Tmpfs::new(target);
+        self.mount(match size {
+            Some(size) => tmpfs.size(size),
+            None => tmpfs,
+        })
+    }
+
     /// Sets the task resources.
     pub fn resources(mut self, resources: TaskSpecResources) -> Self {
         self.resources = Some(resources);
         self
     }
 
+    /// Adds an entry to the `/etc/hosts` file of the service's containers.
+    pub fn extra_host(mut self, hostname: impl Into<String>, ip: impl Into<String>) -> Self {
+        self.extra_hosts.push((hostname.into(), ip.into()));
+        self
+    }
+
+    /// Adds a DNS server for the service to use.
+    ///
+    /// Setting a DNS server replaces the nameservers of the `/etc/resolv.conf`
+    /// file of the service's containers.
+    pub fn dns(mut self, server: impl Into<String>) -> Self {
+        self.dns.push(server.into());
+        self
+    }
+
+    /// Adds a DNS search domain for the service to use.
+    pub fn dns_search(mut self, domain: impl Into<String>) -> Self {
+        self.dns_search.push(domain.into());
+        self
+    }
+
+    /// Adds a DNS resolver option (e.g., `ndots:2`) for the service to use.
+    pub fn dns_option(mut self, option: impl Into<String>) -> Self {
+        self.dns_options.push(option.into());
+        self
+    }
+
+    /// Sets the network to connect to.
+    ///
+    /// This may be the name or id of a user-defined network or one of the
+    /// networks built into Docker (`bridge`, `host`, or `none`).
+    pub fn network(mut self, network: impl Into<String>) -> Self {
+        self.network = Some(network.into());
+        self
+    }
+
+    /// Isolates the container from the network.
+    ///
+    /// This is equivalent to connecting to the `none` network.
+    pub fn disable_network(self) -> Self {
+        self.network("none")
+    }
+
+    /// Sets the hostname of the container.
+    pub fn hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    /// Binds a CA bundle from the host into the container as read-only.
+    ///
+    /// The bundle is bound to [`CA_BUNDLE_TARGET`] and each of the variables in
+    /// [`CA_BUNDLE_ENV`] is set to point to it. The host's CA bundle can be
+    /// located with [`host_ca_bundle()`](crate::certs::host_ca_bundle).
+    pub fn ca_bundle(self, path: impl Into<String>) -> Self {
+        self.bind_ro(path, CA_BUNDLE_TARGET)
+            .envs(CA_BUNDLE_ENV.iter().map(|name| (*name, CA_BUNDLE_TARGET)))
+    }
+
+    /// Validates the builder without creating a service.
+    ///
+    /// This checks that the image and program are not empty and that paths
+    /// within the container are absolute and normalized. The sources of bind
+    /// mounts are not checked as they need only exist on the node the
+    /// service's task is scheduled to. Validation is performed automatically by
+    /// [`Self::try_build()`].
+    pub fn validate(&self) -> Result<()> {
+        let image = self
+            .image
+            .as_deref()
+            .ok_or(Error::MissingBuilderField("image"))?;
+        validate::non_empty("image", image)?;
+
+        let program = self
+            .program
+            .as_deref()
+            .ok_or(Error::MissingBuilderField("program"))?;
+        validate::non_empty("program", program)?;
+
+        if let Some(work_dir) = &self.work_dir {
+            validate::guest_path("working directory", work_dir)?;
+        }
+
+        validate::mounts(&self.mounts, false, false)?;
+
+        Ok(())
+    }
+
     /// Consumes `self` and attempts to create a Docker service.
+    ///
+    /// The builder is validated (see [`Self::validate()`]) before the service
+    /// is created.
     pub async fn try_build(self, name: impl Into<String>) -> Result<Service> {
+        self.validate()?;
+
         let image = self
             .image
             .ok_or_else(|| Error::MissingBuilderField("image"))?;
@@ -156,6 +408,35 @@ impl Builder {
             .program
             .ok_or_else(|| Error::MissingBuilderField("program"))?;
 
+        let mut env = self.locale.env();
+        for path in &self.env_files {
+            env.extend(crate::env::read(path)?);
+        }
+        env.extend(self.env);
+        env.extend(self.secret_env);
+
+        let hosts = self
+            .extra_hosts
+            .into_iter()
+            .map(|(hostname, ip)| format!("{ip} {hostname}"))
+            .collect::<Vec<_>>();
+
+        let dns_config =
+            if self.dns.is_empty() && self.dns_search.is_empty() && self.dns_options.is_empty() {
+                None
+            } else {
+                Some(TaskSpecContainerSpecDnsConfig {
+                    nameservers: Some(self.dns),
+                    search: Some(self.dns_search),
+                    options: Some(self.dns_options),
+                })
+            };
+
+        let privileges = TaskSpecContainerSpecPrivileges {
+            no_new_privileges: self.no_new_privileges.then_some(true),
+            ..self.security.privileges()?
+        };
+
         let name = name.into();
         let response = self
             .client
@@ -172,11 +453,27 @@ impl Builder {
                             command: Some(vec![program]),
                             args: Some(self.args),
                             dir: self.work_dir,
-                            env: Some(self.env.iter().map(|(k, v)| format!("{k}={v}")).collect()),
+                            user: self.user,
+                            hostname: self.hostname,
+                            groups: (!self.groups.is_empty()).then_some(self.groups),
+                            privileges: (privileges != Default::default()).then_some(privileges),
+                            capability_add: (!self.security.add_caps().is_empty())
+                                .then(|| self.security.add_caps().to_vec()),
+                            capability_drop: (!self.security.drop_caps().is_empty())
+                                .then(|| self.security.drop_caps().to_vec()),
+                            env: Some(env.iter().map(|(k, v)| format!("{k}={v}")).collect()),
                             mounts: Some(self.mounts),
+                            hosts: (!hosts.is_empty()).then_some(hosts),
+                            dns_config,
                             ..Default::default()
                         }),
                         resources: self.resources,
+                        networks: self.network.map(|network| {
+                            vec![NetworkAttachmentConfig {
+                                target: Some(network),
+                                ..Default::default()
+                            }]
+                        }),
                         restart_policy: Some(TaskSpecRestartPolicy {
                             condition: Some(TaskSpecRestartPolicyConditionEnum::NONE),
                             ..Default::default()
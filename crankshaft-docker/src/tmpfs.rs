@@ -0,0 +1,89 @@
+//! In-memory (tmpfs) mounts.
+
+use bollard::secret::Mount;
+use bollard::secret::MountTmpfsOptions;
+use bollard::secret::MountTypeEnum;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A writable, in-memory filesystem mounted into a container.
+///
+/// A tmpfs mount provides ephemeral scratch space that is discarded when the
+/// container stops and never touches the host's filesystem. It is converted
+/// into a [`Mount`] of type `tmpfs` when provided to a container or service
+/// builder.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Tmpfs {
+    /// The path within the container.
+    target: String,
+
+    /// The maximum size of the filesystem in bytes.
+    size: Option<u64>,
+
+    /// The file mode of the filesystem's root (e.g., `0o1777`).
+    mode: Option<u32>,
+}
+
+impl Tmpfs {
+    /// Creates a new, unbounded [`Tmpfs`].
+    pub fn new(target: impl Into<String>) -> Self {
+        Self {
+            target: target.into(),
+            size: None,
+            mode: None,
+        }
+    }
+
+    /// Sets the maximum size of the filesystem in bytes.
+    pub fn size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Sets the file mode of the filesystem's root.
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Gets the path within the container.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+}
+
+impl From<Tmpfs> for Mount {
+    fn from(tmpfs: Tmpfs) -> Self {
+        let options = (tmpfs.size.is_some() || tmpfs.mode.is_some()).then(|| MountTmpfsOptions {
+            size_bytes: tmpfs.size.map(|size| size.try_into().unwrap_or(i64::MAX)),
+            mode: tmpfs.mode.map(Into::into),
+            ..Default::default()
+        });
+
+        Mount {
+            target: Some(tmpfs.target),
+            typ: Some(MountTypeEnum::TMPFS),
+            tmpfs_options: options,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_mount() {
+        let mount = Mount::from(Tmpfs::new("/scratch"));
+        assert_eq!(mount.target.as_deref(), Some("/scratch"));
+        assert_eq!(mount.typ, Some(MountTypeEnum::TMPFS));
+        assert_eq!(mount.tmpfs_options, None);
+
+        let mount = Mount::from(Tmpfs::new("/scratch").size(1 << 30).mode(0o1777));
+        let options = mount.tmpfs_options.unwrap();
+        assert_eq!(options.size_bytes, Some(1 << 30));
+        assert_eq!(options.mode, Some(0o1777));
+    }
+}
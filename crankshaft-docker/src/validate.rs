@@ -0,0 +1,197 @@
+//! Validation of container and service builders.
+//!
+//! Builders are validated before any request is made to the Docker daemon so
+//! that mistakes are reported with a descriptive error rather than whatever
+//! the daemon happens to respond with.
+
+use std::path::Path;
+
+use bollard::secret::Mount;
+use bollard::secret::MountTypeEnum;
+use thiserror::Error;
+
+/// An error validating a container or service builder.
+#[derive(Debug, Error)]
+pub enum ValidationError {
+    /// A required value was empty.
+    #[error("`{0}` cannot be empty")]
+    Empty(&'static str),
+
+    /// The source of a bind mount is not an absolute path that exists on the
+    /// host.
+    #[error("bind mount source `{0}` does not exist")]
+    MissingBindSource(String),
+
+    /// The source of a bind mount exists on the host but cannot be read.
+    #[error("bind mount source `{path}` is not accessible: {reason}")]
+    InaccessibleBindSource {
+        /// The path of the source.
+        path: String,
+
+        /// The reason the source is not accessible.
+        reason: String,
+    },
+
+    /// A path within the container or on the host was not absolute.
+    #[error("{name} `{path}` must be an absolute path")]
+    RelativePath {
+        /// The name of the path (e.g., `mount target`).
+        name: &'static str,
+
+        /// The path.
+        path: String,
+    },
+
+    /// A path within the container could not be normalized.
+    #[error("{name} `{path}` cannot contain `.` or `..` components")]
+    UnnormalizablePath {
+        /// The name of the path (e.g., `mount target`).
+        name: &'static str,
+
+        /// The path.
+        path: String,
+    },
+
+    /// Two options were set that cannot be used together.
+    #[error("`{0}` cannot be set with `{1}`")]
+    Conflict(&'static str, &'static str),
+}
+
+/// Checks that a required value is not empty.
+pub(crate) fn non_empty(name: &'static str, value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(ValidationError::Empty(name));
+    }
+
+    Ok(())
+}
+
+/// Checks that a path within the container is absolute and already
+/// normalized.
+pub(crate) fn guest_path(name: &'static str, path: &str) -> Result<(), ValidationError> {
+    if !path.starts_with('/') {
+        return Err(ValidationError::RelativePath {
+            name,
+            path: path.to_string(),
+        });
+    }
+
+    if path
+        .split('/')
+        .any(|component| component == "." || component == "..")
+    {
+        return Err(ValidationError::UnnormalizablePath {
+            name,
+            path: path.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks that a path on the host is absolute.
+pub(crate) fn host_path(name: &'static str, path: &Path) -> Result<(), ValidationError> {
+    if !path.is_absolute() {
+        return Err(ValidationError::RelativePath {
+            name,
+            path: path.display().to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks that an existing path can be read by this process.
+///
+/// Directories must be listable and files must be openable for reading.
+fn accessible(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        std::fs::read_dir(path).map(drop)
+    } else {
+        std::fs::File::open(path).map(drop)
+    }
+}
+
+/// Checks the targets of mounts and, if `local` is set, that the sources of
+/// bind mounts exist on this host and are accessible.
+///
+/// Missing sources are allowed if `create` is set, as they will be created
+/// before the container is.
+///
+/// The sources of bind mounts for services are not checked as they must exist
+/// on the node the service's task is scheduled to rather than this host.
+pub(crate) fn mounts<'a>(
+    mounts: impl IntoIterator<Item = &'a Mount>,
+    local: bool,
+    create: bool,
+) -> Result<(), ValidationError> {
+    for mount in mounts {
+        if let Some(target) = &mount.target {
+            guest_path("mount target", target)?;
+        }
+
+        if local && mount.typ == Some(MountTypeEnum::BIND) {
+            if let Some(source) = &mount.source {
+                let path = Path::new(source);
+                if !path.is_absolute() {
+                    return Err(ValidationError::MissingBindSource(source.clone()));
+                }
+
+                if !path.exists() {
+                    if create {
+                        continue;
+                    }
+
+                    return Err(ValidationError::MissingBindSource(source.clone()));
+                }
+
+                accessible(path).map_err(|e| ValidationError::InaccessibleBindSource {
+                    path: source.clone(),
+                    reason: e.to_string(),
+                })?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guest_paths() {
+        assert!(guest_path("work directory", "/mnt/work").is_ok());
+        assert!(matches!(
+            guest_path("work directory", "work"),
+            Err(ValidationError::RelativePath { .. })
+        ));
+        assert!(matches!(
+            guest_path("work directory", "/mnt/../etc"),
+            Err(ValidationError::UnnormalizablePath { .. })
+        ));
+    }
+
+    #[test]
+    fn bind_sources() {
+        let bind = |source: &str| -> Mount { crate::Bind::new(source, "/mnt").into() };
+
+        let existing = bind(env!("CARGO_MANIFEST_DIR"));
+        assert!(mounts([&existing], true, false).is_ok());
+
+        let missing = bind("/does/not/exist");
+        assert!(matches!(
+            mounts([&missing], true, false),
+            Err(ValidationError::MissingBindSource(_))
+        ));
+        assert!(mounts([&missing], true, true).is_ok());
+        assert!(mounts([&missing], false, false).is_ok());
+
+        let relative = bind("relative");
+        assert!(matches!(
+            mounts([&relative], true, true),
+            Err(ValidationError::MissingBindSource(_))
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+//! Versions of the Docker daemon.
+//!
+//! Docker versions are parsed leniently: only the leading numeric components
+//! are considered (e.g., `24.0.7-ce` is `24.0.7`) and missing components are
+//! treated as zero (e.g., `25` is `25.0.0`).
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+use thiserror::Error;
+
+/// An error parsing a version or version requirement.
+#[derive(Debug, Error)]
+#[error(
+    "invalid version `{0}`: expected a version such as `24.0.7`, optionally prefixed by `>=`, \
+     `>`, `<=`, `<`, or `=`"
+)]
+pub struct InvalidVersion(String);
+
+/// A version of the Docker daemon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct Version {
+    /// The major version.
+    pub major: u64,
+
+    /// The minor version.
+    pub minor: u64,
+
+    /// The patch version.
+    pub patch: u64,
+}
+
+impl Version {
+    /// Creates a new [`Version`].
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl FromStr for Version {
+    type Err = InvalidVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut components = s
+            .trim()
+            .trim_start_matches('v')
+            .split('.')
+            .map_while(|part| {
+                let end = part
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(part.len());
+                part[..end].parse::<u64>().ok()
+            });
+
+        let major = components
+            .next()
+            .ok_or_else(|| InvalidVersion(s.to_string()))?;
+
+        Ok(Self {
+            major,
+            minor: components.next().unwrap_or_default(),
+            patch: components.next().unwrap_or_default(),
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A requirement on a version (e.g., `>=24.0`).
+///
+/// A version without a comparison operator is treated as a minimum version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Requirement {
+    /// The orderings of a version relative to [`Self::version`] that satisfy
+    /// the requirement.
+    orderings: &'static [Ordering],
+
+    /// The version being compared against.
+    version: Version,
+}
+
+impl Requirement {
+    /// Determines if a version satisfies the requirement.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.orderings.contains(&version.cmp(&self.version))
+    }
+}
+
+impl FromStr for Requirement {
+    type Err = InvalidVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (orderings, version): (&'static [Ordering], _) =
+            if let Some(version) = s.strip_prefix(">=") {
+                (&[Ordering::Greater, Ordering::Equal], version)
+            } else if let Some(version) = s.strip_prefix("<=") {
+                (&[Ordering::Less, Ordering::Equal], version)
+            } else if let Some(version) = s.strip_prefix('>') {
+                (&[Ordering::Greater], version)
+            } else if let Some(version) = s.strip_prefix('<') {
+                (&[Ordering::Less], version)
+            } else if let Some(version) = s.strip_prefix('=') {
+                (&[Ordering::Equal], version)
+            } else {
+                (&[Ordering::Greater, Ordering::Equal], s)
+            };
+
+        Ok(Self {
+            orderings,
+            version: version.parse().map_err(|_| InvalidVersion(s.to_string()))?,
+        })
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operator = match self.orderings {
+            [Ordering::Greater, Ordering::Equal] => ">=",
+            [Ordering::Less, Ordering::Equal] => "<=",
+            [Ordering::Greater] => ">",
+            [Ordering::Less] => "<",
+            _ => "=",
+        };
+
+        write!(f, "{operator}{}", self.version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!("27.3.1".parse::<Version>().unwrap(), Version::new(27, 3, 1));
+        assert_eq!(
+            "24.0.7-ce".parse::<Version>().unwrap(),
+            Version::new(24, 0, 7)
+        );
+        assert_eq!("v25".parse::<Version>().unwrap(), Version::new(25, 0, 0));
+        assert_eq!("20.10".parse::<Version>().unwrap(), Version::new(20, 10, 0));
+        assert!("dev".parse::<Version>().is_err());
+        assert!("".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn matches_requirements() {
+        let version = Version::new(24, 0, 7);
+        let matches = |requirement: &str| {
+            requirement
+                .parse::<Requirement>()
+                .unwrap()
+                .matches(&version)
+        };
+
+        assert!(matches(">=24"));
+        assert!(matches("20.10"));
+        assert!(matches(">23.0.9"));
+        assert!(matches("<=24.0.7"));
+        assert!(matches("=24.0.7"));
+        assert!(!matches(">=25"));
+        assert!(!matches("<24"));
+        assert!(">=x".parse::<Requirement>().is_err());
+        assert_eq!(
+            ">=24.0".parse::<Requirement>().unwrap().to_string(),
+            ">=24.0.0"
+        );
+    }
+}
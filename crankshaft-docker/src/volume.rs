@@ -0,0 +1,83 @@
+//! Named volume mounts.
+
+use bollard::secret::Mount;
+use bollard::secret::MountTypeEnum;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A named volume mounted into a container.
+///
+/// Unlike the container's own filesystem, the contents of a volume persist
+/// after the container is removed, allowing state to be carried across
+/// containers without rebuilding images. A volume is created by the Docker
+/// daemon if it does not exist when the container is created (see also
+/// [`Docker::create_volume()`](crate::Docker::create_volume)).
+///
+/// A volume is converted into a [`Mount`] of type `volume` when provided to a
+/// container or service builder.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Volume {
+    /// The name of the volume.
+    name: String,
+
+    /// The path within the container.
+    target: String,
+
+    /// Whether or not the volume is read-only.
+    #[serde(default)]
+    read_only: bool,
+}
+
+impl Volume {
+    /// Creates a new, writable [`Volume`].
+    pub fn new(name: impl Into<String>, target: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            target: target.into(),
+            read_only: false,
+        }
+    }
+
+    /// Sets whether or not the volume is read-only.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Gets the name of the volume.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the path within the container.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+}
+
+impl From<Volume> for Mount {
+    fn from(volume: Volume) -> Self {
+        Mount {
+            source: Some(volume.name),
+            target: Some(volume.target),
+            typ: Some(MountTypeEnum::VOLUME),
+            read_only: Some(volume.read_only),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_mount() {
+        let mount = Mount::from(Volume::new("state", "/state").read_only(true));
+        assert_eq!(mount.source.as_deref(), Some("state"));
+        assert_eq!(mount.target.as_deref(), Some("/state"));
+        assert_eq!(mount.typ, Some(MountTypeEnum::VOLUME));
+        assert_eq!(mount.read_only, Some(true));
+    }
+}
@@ -0,0 +1,192 @@
+//! Audit logs of the tasks run by the engine.
+//!
+//! When enabled (see [`Engine::with_audit()`](crate::Engine::with_audit)), an
+//! [`Entry`] is recorded for every task that runs, capturing exactly what was
+//! run, where, when, and with what result. This allows administrators to audit
+//! what workflows actually ran on their infrastructure.
+
+use std::fmt;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::path::Path;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use nonempty::NonEmpty;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::service::runner::backend::TaskRunError;
+
+/// An execution of a task as recorded in an audit log.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct Invocation {
+    /// The image the execution ran within.
+    pub image: String,
+
+    /// The digest of the image (if it could be determined).
+    pub digest: Option<String>,
+
+    /// The program and arguments of the execution.
+    pub command: Vec<String>,
+}
+
+/// An entry in an audit log.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct Entry {
+    /// The name of the task (if it has one).
+    pub task: Option<String>,
+
+    /// The type of backend that ran the task.
+    pub backend: String,
+
+    /// The executions of the task.
+    pub executions: Vec<Invocation>,
+
+    /// When the task started running (in milliseconds since the Unix epoch).
+    pub started: u64,
+
+    /// When the task finished running (in milliseconds since the Unix epoch).
+    pub ended: u64,
+
+    /// The exit codes of the executions that ran.
+    ///
+    /// An exit code is `None` if the execution was terminated by a signal.
+    pub exit_codes: Vec<Option<i32>>,
+
+    /// The error that the task failed with (if it failed).
+    pub error: Option<String>,
+}
+
+impl Entry {
+    /// Sets the end time and result of the entry.
+    pub(crate) fn finish(&mut self, result: &Result<NonEmpty<ExitStatus>, TaskRunError>) {
+        self.ended = now();
+
+        let statuses = match result {
+            Ok(statuses)
+            | Err(TaskRunError::NonZeroExit { statuses, .. })
+            | Err(TaskRunError::OutOfMemory { statuses, .. }) => Some(statuses),
+            _ => None,
+        };
+
+        if let Some(statuses) = statuses {
+            self.exit_codes = statuses.iter().map(ExitStatus::code).collect();
+        }
+
+        if let Err(e) = result {
+            self.error = Some(format!("{e:#}"));
+        }
+    }
+}
+
+/// Gets the current time in milliseconds since the Unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// A callback that receives audit log entries.
+type Callback = dyn Fn(&Entry) + Send + Sync;
+
+/// The destination of audit log entries.
+enum Inner {
+    /// Entries are appended as JSON lines to a file.
+    File(Mutex<File>),
+
+    /// Entries are passed to a callback.
+    Callback(Box<Callback>),
+}
+
+/// A destination for audit log entries.
+#[derive(Clone)]
+pub struct Sink(Arc<Inner>);
+
+impl Sink {
+    /// Creates a sink that appends entries to a file as JSON lines.
+    ///
+    /// The file is created if it does not exist.
+    pub fn file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self(Arc::new(Inner::File(Mutex::new(file)))))
+    }
+
+    /// Creates a sink that passes entries to a callback.
+    pub fn callback(callback: impl Fn(&Entry) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(Inner::Callback(Box::new(callback))))
+    }
+
+    /// Records an entry.
+    ///
+    /// Failures to write to an audit log file are logged rather than failing
+    /// the task.
+    pub(crate) fn record(&self, entry: &Entry) {
+        match &*self.0 {
+            Inner::File(file) => {
+                let result = serde_json::to_string(entry)
+                    .map_err(std::io::Error::from)
+                    .and_then(|line| writeln!(file.lock().unwrap(), "{line}"));
+
+                if let Err(e) = result {
+                    warn!("failed to write to the audit log: {e}");
+                }
+            }
+            Inner::Callback(callback) => callback(entry),
+        }
+    }
+}
+
+impl fmt::Debug for Sink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &*self.0 {
+            Inner::File(_) => write!(f, "Sink::File"),
+            Inner::Callback(_) => write!(f, "Sink::Callback"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let sink = Sink::file(&path).unwrap();
+
+        let mut entry = Entry {
+            task: Some(String::from("hello")),
+            backend: String::from("docker"),
+            executions: vec![Invocation {
+                image: String::from("ubuntu:22.04"),
+                digest: None,
+                command: vec![String::from("echo"), String::from("hi")],
+            }],
+            started: 1,
+            ended: 0,
+            exit_codes: Vec::new(),
+            error: None,
+        };
+        entry.finish(&Err(TaskRunError::Canceled));
+        sink.record(&entry);
+        sink.record(&entry);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines = contents.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+
+        let value: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(value["task"], "hello");
+        assert_eq!(value["executions"][0]["command"][1], "hi");
+        assert_eq!(value["error"], "the task has been canceled");
+    }
+}
@@ -0,0 +1,265 @@
+//! Accounting of the time the tasks of a group run for against a budget.
+//!
+//! Tasks are assigned to a group (e.g., a run of a workflow) with
+//! [`Task::group()`](crate::Task::group). As each attempt of a task finishes,
+//! its wall time and the resource-seconds it requested (the CPU cores and GiB
+//! of RAM it requested multiplied by its wall time) are added to its group's
+//! usage. Once a group has exhausted its [`Budget`], a warning is emitted and,
+//! if the budget is enforced, new tasks submitted under the group are refused
+//! with a [`BudgetExhausted`] error. Tasks that are already waiting or
+//! running are left to complete.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use bon::Builder;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::task::Resources;
+
+/// The error returned when a task is submitted under a group that has
+/// exhausted its enforced budget.
+///
+/// Callers can downcast the error returned from
+/// [`Runner::spawn()`](crate::service::Runner::spawn) or
+/// [`Runner::submit()`](crate::service::Runner::submit) to this type.
+#[derive(Debug, thiserror::Error)]
+#[error("the budget of group `{group}` is exhausted ({exceeded})")]
+pub struct BudgetExhausted {
+    /// The group whose budget is exhausted.
+    pub group: String,
+
+    /// A description of the limit that was exceeded.
+    pub exceeded: String,
+}
+
+/// A budget of the time the tasks of a group may run for.
+///
+/// Every limit is optional; a budget is exhausted once any of its limits is
+/// reached.
+#[derive(Builder, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[builder(builder_type = Builder)]
+pub struct Budget {
+    /// The total wall time (in seconds) of the group's tasks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wall_seconds: Option<f64>,
+
+    /// The total CPU core-seconds requested by the group's tasks.
+    ///
+    /// Tasks that do not request CPUs are counted as requesting one core.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cpu_seconds: Option<f64>,
+
+    /// The total GiB-seconds of RAM requested by the group's tasks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ram_gib_seconds: Option<f64>,
+
+    /// Whether new tasks are refused once the budget is exhausted.
+    ///
+    /// When `false`, exhausting the budget only emits a warning. Defaults to
+    /// `true`.
+    #[serde(default = "enforce")]
+    #[builder(default = true)]
+    enforce: bool,
+}
+
+/// Returns the default of whether budgets are enforced.
+fn enforce() -> bool {
+    true
+}
+
+impl Budget {
+    /// Gets the total wall time (in seconds) of the group's tasks (if it is
+    /// limited).
+    pub fn wall_seconds(&self) -> Option<f64> {
+        self.wall_seconds
+    }
+
+    /// Gets the total CPU core-seconds requested by the group's tasks (if it
+    /// is limited).
+    pub fn cpu_seconds(&self) -> Option<f64> {
+        self.cpu_seconds
+    }
+
+    /// Gets the total GiB-seconds of RAM requested by the group's tasks (if it
+    /// is limited).
+    pub fn ram_gib_seconds(&self) -> Option<f64> {
+        self.ram_gib_seconds
+    }
+
+    /// Gets whether new tasks are refused once the budget is exhausted.
+    pub fn enforce(&self) -> bool {
+        self.enforce
+    }
+
+    /// Determines which limit of the budget the given usage has reached (if
+    /// any).
+    pub fn exceeded(&self, usage: &Usage) -> Option<String> {
+        [
+            (
+                "wall time",
+                self.wall_seconds,
+                usage.wall.as_secs_f64(),
+                "s",
+            ),
+            ("CPU time", self.cpu_seconds, usage.cpu_seconds, " core-s"),
+            (
+                "RAM time",
+                self.ram_gib_seconds,
+                usage.ram_gib_seconds,
+                " GiB-s",
+            ),
+        ]
+        .into_iter()
+        .find_map(|(name, limit, used, unit)| {
+            let limit = limit?;
+            (used >= limit).then(|| {
+                format!("{name} of {used:.0}{unit} has reached the limit of {limit:.0}{unit}")
+            })
+        })
+    }
+}
+
+/// The time the tasks of a group have run for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct Usage {
+    /// The number of task attempts that have finished.
+    pub attempts: u64,
+
+    /// The total wall time of the attempts.
+    pub wall: Duration,
+
+    /// The total CPU core-seconds requested by the attempts.
+    pub cpu_seconds: f64,
+
+    /// The total GiB-seconds of RAM requested by the attempts.
+    pub ram_gib_seconds: f64,
+}
+
+/// The account of a single group.
+#[derive(Debug, Default)]
+struct Account {
+    /// The budget of the group (if it has one).
+    budget: Option<Budget>,
+
+    /// The usage of the group.
+    usage: Usage,
+
+    /// Whether a warning has been emitted for exhausting the budget.
+    warned: bool,
+}
+
+impl Account {
+    /// Emits a warning the first time the group's budget is found to be
+    /// exhausted.
+    ///
+    /// Returns the limit that was exceeded (if any).
+    fn check(&mut self, group: &str) -> Option<String> {
+        let exceeded = self.budget.as_ref()?.exceeded(&self.usage)?;
+        if !self.warned {
+            self.warned = true;
+            warn!("the budget of group `{group}` is exhausted: {exceeded}");
+        }
+
+        Some(exceeded)
+    }
+}
+
+/// The accounts of every group, shared by the runners of an engine.
+#[derive(Debug, Default)]
+pub(crate) struct Budgets(Mutex<HashMap<String, Account>>);
+
+impl Budgets {
+    /// Sets the budget of a group, replacing any previous budget.
+    ///
+    /// The group's usage so far is kept.
+    pub(crate) fn set(&self, group: impl Into<String>, budget: Budget) {
+        let mut accounts = self.0.lock().unwrap();
+        let account = accounts.entry(group.into()).or_default();
+        account.budget = Some(budget);
+        account.warned = false;
+    }
+
+    /// Gets the usage of a group.
+    pub(crate) fn usage(&self, group: &str) -> Usage {
+        self.0
+            .lock()
+            .unwrap()
+            .get(group)
+            .map(|account| account.usage)
+            .unwrap_or_default()
+    }
+
+    /// Checks that a new task may be submitted under a group.
+    pub(crate) fn admit(&self, group: &str) -> Result<(), BudgetExhausted> {
+        let mut accounts = self.0.lock().unwrap();
+        let Some(account) = accounts.get_mut(group) else {
+            return Ok(());
+        };
+
+        match account.check(group) {
+            Some(exceeded) if account.budget.as_ref().is_some_and(Budget::enforce) => {
+                Err(BudgetExhausted {
+                    group: group.to_string(),
+                    exceeded,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Records an attempt of a task of a group that ran for the given wall
+    /// time with the given requested resources.
+    pub(crate) fn record(&self, group: &str, wall: Duration, resources: Option<&Resources>) {
+        let cpu = resources.and_then(Resources::cpu).unwrap_or(1.0);
+        let ram = resources.and_then(Resources::ram).unwrap_or_default();
+
+        let mut accounts = self.0.lock().unwrap();
+        let account = accounts.entry(group.to_string()).or_default();
+        account.usage.attempts += 1;
+        account.usage.wall += wall;
+        account.usage.cpu_seconds += cpu * wall.as_secs_f64();
+        account.usage.ram_gib_seconds += ram * wall.as_secs_f64();
+        account.check(group);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exhausts_budgets() {
+        let budgets = Budgets::default();
+        let resources = Resources::builder().cpu(2.0).ram(4.0).build();
+
+        // Groups without a budget are only accounted
+        budgets.record("a", Duration::from_secs(10), Some(&resources));
+        assert!(budgets.admit("a").is_ok());
+        assert_eq!(budgets.usage("a").cpu_seconds, 20.0);
+        assert_eq!(budgets.usage("a").ram_gib_seconds, 40.0);
+
+        budgets.set("a", Budget::builder().cpu_seconds(30.0).build());
+        assert!(budgets.admit("a").is_ok());
+        budgets.record("a", Duration::from_secs(10), None);
+        assert_eq!(budgets.usage("a").attempts, 2);
+        let e = budgets.admit("a").unwrap_err();
+        assert_eq!(e.group, "a");
+        assert_eq!(
+            e.exceeded,
+            "CPU time of 30 core-s has reached the limit of 30 core-s"
+        );
+
+        // Budgets that are not enforced only warn
+        budgets.set(
+            "a",
+            Budget::builder().wall_seconds(1.0).enforce(false).build(),
+        );
+        assert!(budgets.admit("a").is_ok());
+    }
+}
@@ -0,0 +1,127 @@
+//! Events emitted as tasks move through their lifecycle.
+//!
+//! When enabled (see [`Engine::with_events()`](crate::Engine::with_events)),
+//! an [`Event`] is sent for every state transition of every task so that
+//! monitoring tools can react to them without polling.
+
+use std::process::ExitStatus;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use nonempty::NonEmpty;
+use tokio::sync::broadcast;
+
+use crate::service::runner::backend::TaskRunError;
+
+/// The sending half of a channel of task events.
+pub type Sender = broadcast::Sender<Event>;
+
+/// The next identifier to assign to a task.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Gets a unique identifier for a task.
+pub(crate) fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// An event in the lifecycle of a task.
+///
+/// Every event carries the identifier the engine assigned to the task, which
+/// is unique for the lifetime of the process, along with the task's name (if
+/// it has one).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// The task was submitted and is waiting to run.
+    Queued {
+        /// The identifier of the task.
+        id: u64,
+
+        /// The name of the task.
+        name: Option<String>,
+
+        /// The type of backend the task was submitted to.
+        backend: String,
+    },
+
+    /// The runner has capacity for the task and handed it to the backend.
+    Running {
+        /// The identifier of the task.
+        id: u64,
+
+        /// The name of the task.
+        name: Option<String>,
+    },
+
+    /// The backend reported that the task's first execution has started.
+    Started {
+        /// The identifier of the task.
+        id: u64,
+
+        /// The name of the task.
+        name: Option<String>,
+    },
+
+    /// The task finished running.
+    Exited {
+        /// The identifier of the task.
+        id: u64,
+
+        /// The name of the task.
+        name: Option<String>,
+
+        /// The exit codes of the executions that ran.
+        ///
+        /// An exit code is `None` if the execution was terminated by a
+        /// signal.
+        exit_codes: Vec<Option<i32>>,
+
+        /// The error that the task failed with (if it failed).
+        error: Option<String>,
+    },
+}
+
+impl Event {
+    /// Creates an [`Event::Exited`] from the result of a task.
+    pub(crate) fn exited(
+        id: u64,
+        name: Option<String>,
+        result: &Result<NonEmpty<ExitStatus>, TaskRunError>,
+    ) -> Self {
+        let exit_codes = match result {
+            Ok(statuses)
+            | Err(TaskRunError::NonZeroExit { statuses, .. })
+            | Err(TaskRunError::OutOfMemory { statuses, .. }) => {
+                statuses.iter().map(ExitStatus::code).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        Self::Exited {
+            id,
+            name,
+            exit_codes,
+            error: result.as_ref().err().map(|e| format!("{e:#}")),
+        }
+    }
+
+    /// Gets the identifier of the task the event is for.
+    pub fn id(&self) -> u64 {
+        match self {
+            Self::Queued { id, .. }
+            | Self::Running { id, .. }
+            | Self::Started { id, .. }
+            | Self::Exited { id, .. } => *id,
+        }
+    }
+
+    /// Gets the name of the task the event is for (if it has one).
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Queued { name, .. }
+            | Self::Running { name, .. }
+            | Self::Started { name, .. }
+            | Self::Exited { name, .. } => name.as_deref(),
+        }
+    }
+}
@@ -1,18 +1,32 @@
 //! The engine that powers Crankshaft.
 
+use std::sync::Arc;
+
 use anyhow::Result;
 use crankshaft_config::backend::Config;
+use crankshaft_config::telemetry::Telemetry;
 use indexmap::IndexMap;
 use tokio_util::sync::CancellationToken;
 use tracing::debug;
 
+pub mod audit;
+pub mod budget;
+pub mod events;
+pub mod logs;
+pub mod metrics;
+pub mod scatter;
 pub mod service;
+#[cfg(feature = "store")]
+pub mod store;
+pub mod stream;
 pub mod task;
+pub mod telemetry;
 
 pub use task::Task;
 
 use crate::service::Runner;
 use crate::service::runner::Backend;
+use crate::service::runner::QueueStats;
 use crate::service::runner::TaskHandle;
 
 /// A workflow execution engine.
@@ -20,17 +34,166 @@ use crate::service::runner::TaskHandle;
 pub struct Engine {
     /// The task runner(s).
     runners: IndexMap<String, Runner>,
+
+    /// The budgets of the groups of tasks shared by the runners.
+    budgets: Arc<budget::Budgets>,
+
+    /// The cancellation tokens of the groups of tasks shared by the runners.
+    groups: Arc<service::runner::Groups>,
+
+    /// The usage statistics counters (if telemetry is enabled).
+    telemetry: Option<Arc<telemetry::Counters>>,
+
+    /// The audit log sink (if auditing is enabled).
+    audit: Option<audit::Sink>,
+
+    /// The sender of task events (if events are enabled).
+    events: Option<events::Sender>,
+
+    /// The store of task attempts (if the store is enabled).
+    #[cfg(feature = "store")]
+    store: Option<store::Store>,
+
+    /// Whether the call cache is enabled.
+    #[cfg(feature = "store")]
+    call_cache: bool,
+
+    /// Whether identical tasks are coalesced while they are waiting or
+    /// running.
+    deduplicate: bool,
 }
 
 impl Engine {
     /// Adds a [`Backend`] to the engine.
     pub async fn with(mut self, config: Config) -> Result<Self> {
-        let (name, kind, max_tasks, defaults) = config.into_parts();
-        let runner = Runner::initialize(kind, max_tasks, defaults).await?;
+        let name = config.name().to_string();
+        let mut runner = Runner::initialize(config).await?;
+        runner.telemetry = self.telemetry.clone();
+        runner.audit = self.audit.clone();
+        runner.events = self.events.clone();
+        runner.budgets = self.budgets.clone();
+        runner.groups = self.groups.clone();
+        if self.deduplicate {
+            runner.inflight = Some(Default::default());
+        }
+        #[cfg(feature = "store")]
+        {
+            runner.store = self.store.clone().map(|store| (store, name.clone()));
+            runner.call_cache = self.call_cache;
+        }
         self.runners.insert(name, runner);
         Ok(self)
     }
 
+    /// Enables reporting anonymous usage statistics.
+    ///
+    /// Only the number of tasks run per backend type and the number of failed
+    /// tasks per failure category are reported (see [`telemetry::Report`]).
+    /// Reporting is never enabled unless this method is called.
+    ///
+    /// This must be called from within a Tokio runtime.
+    pub fn with_telemetry(mut self, config: Telemetry) -> Self {
+        let counters = telemetry::spawn(config);
+
+        for runner in self.runners.values_mut() {
+            runner.telemetry = Some(counters.clone());
+        }
+
+        self.telemetry = Some(counters);
+        self
+    }
+
+    /// Enables recording an audit log entry for every task that runs.
+    ///
+    /// Each entry records the task's executions (including the digests of
+    /// their images, where the backend can determine them), when the task
+    /// started and finished running, and its result (see [`audit::Entry`]).
+    pub fn with_audit(mut self, sink: audit::Sink) -> Self {
+        for runner in self.runners.values_mut() {
+            runner.audit = Some(sink.clone());
+        }
+
+        self.audit = Some(sink);
+        self
+    }
+
+    /// Enables sending an event for every state transition of every task.
+    ///
+    /// Events are broadcast to every receiver subscribed to the sender (see
+    /// [`events::Event`]); events are dropped if there are no receivers.
+    pub fn with_events(mut self, sender: events::Sender) -> Self {
+        for runner in self.runners.values_mut() {
+            runner.events = Some(sender.clone());
+        }
+
+        self.events = Some(sender);
+        self
+    }
+
+    /// Enables recording every attempt to run a task in a persistent store.
+    ///
+    /// Each attempt is recorded when it starts running and updated when it
+    /// finishes (see [`store::Attempt`]).
+    #[cfg(feature = "store")]
+    pub fn with_store(mut self, store: store::Store) -> Self {
+        for (name, runner) in &mut self.runners {
+            runner.store = Some((store.clone(), name.clone()));
+        }
+
+        self.store = Some(store);
+        self
+    }
+
+    /// Enables coalescing identical tasks while they are waiting or running.
+    ///
+    /// A task submitted to a runner while an identical task (see
+    /// [`Task::content_hash()`]) with the same retry policy and group is
+    /// waiting or running on the same runner is not run; instead, it completes
+    /// with the result of the identical task.
+    /// This prevents duplicate work when a workflow retries or restarts
+    /// branches that are still running.
+    ///
+    /// A coalesced task relays the output and state transitions of the task it
+    /// was coalesced onto. Canceling a coalesced task does not cancel the task
+    /// it was coalesced onto. If that task is canceled or preempted, one of the
+    /// tasks coalesced onto it runs in its place; otherwise, they complete
+    /// with its result (e.g., if it times out, so do they).
+    pub fn with_deduplication(mut self) -> Self {
+        for runner in self.runners.values_mut() {
+            runner.inflight = Some(Default::default());
+        }
+
+        self.deduplicate = true;
+        self
+    }
+
+    /// Enables the call cache.
+    ///
+    /// Before a task runs, a key is computed from the digests of its images,
+    /// the commands and environment of its executions, the checksums of its
+    /// inputs, and the URLs of its outputs. If an attempt with the same key
+    /// previously succeeded, its exit statuses are returned instead of running
+    /// the task again.
+    ///
+    /// A previous result is only reused while its outputs are unchanged: the
+    /// outputs written to local URLs must still exist, and files must still
+    /// have the checksums they had when the attempt finished. Outputs written
+    /// to remote URLs are assumed to be unchanged.
+    ///
+    /// Tasks whose images are not pinned to (or resolvable to) a digest, or
+    /// that have remote or directory inputs without a checksum, are always
+    /// run. The call cache has no effect unless a store is enabled (see
+    /// [`Self::with_store()`]).
+    #[cfg(feature = "store")]
+    pub fn with_call_cache(mut self) -> Self {
+        for runner in self.runners.values_mut() {
+            runner.call_cache = true;
+        }
+
+        self.call_cache = true;
+        self
+    }
+
     /// Gets the names of the runners.
     pub fn runners(&self) -> impl Iterator<Item = &str> {
         self.runners.keys().map(|key| key.as_ref())
@@ -40,8 +203,8 @@ impl Engine {
     ///
     /// The `cancellation` token can be used to gracefully cancel the task.
     ///
-    /// A [`TaskHandle`] is returned, which contains a channel that can be
-    /// awaited for the result of the job.
+    /// A [`TaskHandle`] is returned, which can be awaited for the result of
+    /// the task, polled for its status, or used to cancel it.
     pub fn spawn(
         &self,
         name: impl AsRef<str>,
@@ -66,6 +229,123 @@ impl Engine {
         backend.spawn(task, token)
     }
 
+    /// Submits a [`Task`] to be executed, waiting (up to the backend's
+    /// configured queue timeout) for space in the backend's queue if it is
+    /// full.
+    ///
+    /// See [`Self::spawn()`] for more details.
+    pub async fn submit(
+        &self,
+        name: impl AsRef<str>,
+        task: Task,
+        token: CancellationToken,
+    ) -> Result<TaskHandle> {
+        let name = name.as_ref();
+        let backend = self
+            .runners
+            .get(name)
+            .unwrap_or_else(|| panic!("backend not found: {name}"));
+
+        backend.submit(task, token).await
+    }
+
+    /// Resolves the images of a [`Task`] to references pinned to the digests
+    /// the images currently have in a backend, so that every task of a
+    /// workflow run uses the same image bits.
+    ///
+    /// This should be called before the task is submitted. See
+    /// [`Runner::resolve()`] for more details.
+    pub async fn resolve(&self, name: impl AsRef<str>, task: &mut Task) -> Result<()> {
+        let name = name.as_ref();
+        let backend = self
+            .runners
+            .get(name)
+            .unwrap_or_else(|| panic!("backend not found: {name}"));
+
+        backend.resolve(task).await
+    }
+
+    /// Reattaches to the tasks left running in a backend by a previous process
+    /// (e.g., one that crashed), returning the name and a handle of each task.
+    ///
+    /// See [`Runner::reattach()`] for more details.
+    pub async fn reattach(&self, name: impl AsRef<str>) -> Result<Vec<(String, TaskHandle)>> {
+        let name = name.as_ref();
+        let backend = self
+            .runners
+            .get(name)
+            .unwrap_or_else(|| panic!("backend not found: {name}"));
+
+        backend.reattach().await
+    }
+
+    /// Resubmits the tasks that a previous process (e.g., one that crashed)
+    /// left queued or running in a backend, as recorded in the store,
+    /// returning the name and a handle of each task.
+    ///
+    /// See [`Runner::recover()`] for more details.
+    #[cfg(feature = "store")]
+    pub async fn recover(
+        &self,
+        name: impl AsRef<str>,
+        max_attempts: u32,
+    ) -> Result<Vec<(Option<String>, TaskHandle)>> {
+        let name = name.as_ref();
+        let backend = self
+            .runners
+            .get(name)
+            .unwrap_or_else(|| panic!("backend not found: {name}"));
+
+        backend.recover(max_attempts).await
+    }
+
+    /// Shuts down every backend.
+    ///
+    /// Waiting tasks are canceled and running tasks are canceled and waited on
+    /// so that no containers are left running (see [`Runner::shutdown()`]).
+    pub async fn shutdown(&self) {
+        futures::future::join_all(self.runners.values().map(Runner::shutdown)).await;
+    }
+
+    /// Cancels a group of tasks (e.g., a run of a workflow).
+    ///
+    /// Every task of the group that is waiting to run is canceled, every
+    /// running task of the group is canceled (stopping its containers, as
+    /// with canceling its token), and new tasks submitted under the group are
+    /// refused with a [`GroupCanceled`](service::runner::GroupCanceled)
+    /// error. Canceled tasks finish with [`TaskRunError::Canceled`], which is
+    /// recorded in the store (if it is enabled).
+    ///
+    /// Returns `false` if the group was already canceled.
+    ///
+    /// [`TaskRunError::Canceled`]: service::runner::backend::TaskRunError::Canceled
+    pub fn cancel_group(&self, group: impl AsRef<str>) -> bool {
+        self.groups.cancel(group.as_ref())
+    }
+
+    /// Sets the budget of a group of tasks (e.g., a run of a workflow),
+    /// replacing any previous budget of the group.
+    ///
+    /// The group's usage so far counts against the budget (see the
+    /// [`budget`] module).
+    pub fn set_budget(&self, group: impl Into<String>, budget: budget::Budget) {
+        self.budgets.set(group, budget);
+    }
+
+    /// Gets the time the tasks of a group have run for.
+    pub fn budget_usage(&self, group: impl AsRef<str>) -> budget::Usage {
+        self.budgets.usage(group.as_ref())
+    }
+
+    /// Gets the statistics of a backend's queue.
+    ///
+    /// Returns `None` if there is no backend with the given name.
+    pub fn queue_stats(&self, name: impl AsRef<str>) -> Option<QueueStats> {
+        self.runners
+            .get(name.as_ref())
+            .map(|runner| runner.queue_stats())
+    }
+
     /// Starts an instrumentation loop.
     #[cfg(tokio_unstable)]
     pub fn start_instrument(delay_ms: u64) {
@@ -0,0 +1,278 @@
+//! Live output of running tasks.
+//!
+//! Backends that support it send each line written to the stdout and stderr
+//! of a task's executions while the task runs; callers follow them with
+//! [`TaskHandle::follow_stdout()`](crate::service::runner::TaskHandle::follow_stdout)
+//! and
+//! [`TaskHandle::follow_stderr()`](crate::service::runner::TaskHandle::follow_stderr)
+//! (e.g., to show the live logs of in-flight tasks).
+//!
+//! Backends may also report samples of the resources a task has consumed so
+//! far, which callers read with
+//! [`TaskHandle::usage()`](crate::service::runner::TaskHandle::usage).
+
+use std::time::Duration;
+
+use futures::Stream;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+
+/// The number of lines buffered for each follower before the oldest are
+/// dropped.
+const CAPACITY: usize = 1024;
+
+/// An output stream of a task's executions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputStream {
+    /// The stdout stream.
+    Stdout,
+
+    /// The stderr stream.
+    Stderr,
+}
+
+/// A line of output written by an execution of a task.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Line {
+    /// The index of the execution that wrote the line.
+    pub execution: usize,
+
+    /// The text of the line (without its line ending).
+    pub text: String,
+}
+
+/// A sample of the resources consumed by a running execution of a task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Usage {
+    /// The index of the execution the sample is for.
+    pub execution: usize,
+
+    /// The wall-clock time the execution has been running for.
+    pub elapsed: Duration,
+
+    /// The total CPU time consumed by the execution so far.
+    pub cpu: Option<Duration>,
+
+    /// The peak memory usage of the execution so far, in bytes.
+    pub max_memory: Option<u64>,
+}
+
+impl Usage {
+    /// Creates a sample of the resources consumed by an execution.
+    pub fn new(
+        execution: usize,
+        elapsed: Duration,
+        cpu: Option<Duration>,
+        max_memory: Option<u64>,
+    ) -> Self {
+        Self {
+            execution,
+            elapsed,
+            cpu,
+            max_memory,
+        }
+    }
+}
+
+/// The sending half of the output of a task.
+///
+/// Backends send the lines of a task's output through the sender while the
+/// task runs; the output ends once every sender has been dropped.
+#[derive(Clone, Debug)]
+pub struct Sender {
+    /// The sender of the lines of output.
+    lines: broadcast::Sender<(OutputStream, Line)>,
+
+    /// The sender of the latest sample of resource usage.
+    usage: watch::Sender<Option<Usage>>,
+}
+
+impl Sender {
+    /// Creates a sender with no followers.
+    pub fn new() -> Self {
+        Self {
+            lines: broadcast::Sender::new(CAPACITY),
+            usage: watch::Sender::new(None),
+        }
+    }
+
+    /// Sends a line written by the given execution to the given stream.
+    ///
+    /// The line is discarded if no one is following the stream.
+    pub fn send(&self, stream: OutputStream, execution: usize, text: impl Into<String>) {
+        if self.lines.receiver_count() > 0 {
+            // Sending only fails if there are no receivers
+            let _ = self.lines.send((
+                stream,
+                Line {
+                    execution,
+                    text: text.into(),
+                },
+            ));
+        }
+    }
+
+    /// Reports a sample of the resources consumed by an execution, replacing
+    /// the previous sample.
+    pub fn report(&self, usage: Usage) {
+        self.usage.send_replace(Some(usage));
+    }
+
+    /// Downgrades the sender so that it does not keep the output open.
+    pub(crate) fn downgrade(&self) -> Follower {
+        Follower {
+            lines: self.lines.downgrade(),
+            usage: self.usage.subscribe(),
+        }
+    }
+}
+
+impl Default for Sender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A handle for following the output of a task that does not keep the output
+/// open.
+#[derive(Clone, Debug)]
+pub(crate) struct Follower {
+    /// The sender of the lines of output.
+    lines: broadcast::WeakSender<(OutputStream, Line)>,
+
+    /// The receiver of the latest sample of resource usage.
+    usage: watch::Receiver<Option<Usage>>,
+}
+
+impl Follower {
+    /// Follows the lines written to the given stream from now on.
+    ///
+    /// Lines dropped because the follower fell behind are skipped. The stream
+    /// ends once the task has finished running (immediately if it already
+    /// has).
+    pub(crate) fn follow(&self, stream: OutputStream) -> impl Stream<Item = Line> + use<> {
+        let rx = self.lines.upgrade().map(|sender| sender.subscribe());
+        futures::stream::unfold(rx, move |rx| async move {
+            let mut rx = rx?;
+            loop {
+                match rx.recv().await {
+                    Ok((s, line)) if s == stream => return Some((line, Some(rx))),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// Gets the latest sample of resource usage (if any were reported).
+    ///
+    /// The last sample remains available after the task has finished.
+    pub(crate) fn usage(&self) -> Option<Usage> {
+        *self.usage.borrow()
+    }
+
+    /// Relays the output and resource usage of the task to another sender
+    /// until the task has finished running.
+    pub(crate) async fn relay(&self, sender: &Sender) {
+        let Some(mut lines) = self.lines.upgrade().map(|sender| sender.subscribe()) else {
+            return;
+        };
+
+        let mut usage = self.usage.clone();
+        usage.mark_changed();
+
+        loop {
+            tokio::select! {
+                line = lines.recv() => match line {
+                    Ok((stream, line)) => sender.send(stream, line.execution, line.text),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+                Ok(()) = usage.changed() => {
+                    if let Some(sample) = *usage.borrow_and_update() {
+                        sender.report(sample);
+                    }
+                }
+            }
+        }
+
+        // The last sample may have been reported as the task finished
+        let sample = *usage.borrow();
+        if let Some(sample) = sample {
+            sender.report(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt as _;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn follows_output() {
+        let sender = Sender::new();
+        let follower = sender.downgrade();
+
+        // Lines sent before following are not seen
+        sender.send(OutputStream::Stdout, 0, "before");
+
+        let stdout = follower.follow(OutputStream::Stdout);
+        let stderr = follower.follow(OutputStream::Stderr);
+        sender.send(OutputStream::Stdout, 0, "hello");
+        sender.send(OutputStream::Stderr, 0, "oops");
+        sender.send(OutputStream::Stdout, 1, "world");
+        drop(sender);
+
+        let stdout = stdout.collect::<Vec<_>>().await;
+        assert_eq!(
+            stdout
+                .iter()
+                .map(|l| (l.execution, l.text.as_str()))
+                .collect::<Vec<_>>(),
+            [(0, "hello"), (1, "world")]
+        );
+        assert_eq!(stderr.collect::<Vec<_>>().await.len(), 1);
+
+        // The output has ended
+        assert_eq!(
+            follower
+                .follow(OutputStream::Stdout)
+                .collect::<Vec<_>>()
+                .await,
+            []
+        );
+
+        let sender = Sender::new();
+        let follower = sender.downgrade();
+        assert_eq!(follower.usage(), None);
+
+        let usage = Usage::new(0, Duration::from_secs(1), None, Some(1024));
+        sender.report(usage);
+        drop(sender);
+        assert_eq!(follower.usage(), Some(usage));
+    }
+
+    #[tokio::test]
+    async fn relays_output() {
+        let leader = Sender::new();
+        let followed = leader.downgrade();
+        let sender = Sender::new();
+        let relayed = sender.downgrade();
+        let stdout = relayed.follow(OutputStream::Stdout);
+
+        let usage = Usage::new(0, Duration::from_secs(1), None, Some(1024));
+        tokio::join!(followed.relay(&sender), async move {
+            leader.send(OutputStream::Stdout, 0, "hello");
+            leader.report(usage);
+        });
+        drop(sender);
+
+        assert_eq!(stdout.map(|l| l.text).collect::<Vec<_>>().await, ["hello"]);
+        assert_eq!(relayed.usage(), Some(usage));
+    }
+}
@@ -0,0 +1,260 @@
+//! Metrics for monitoring the activity of backends.
+//!
+//! Metrics are recorded for the lifetime of the process and can be rendered
+//! in the Prometheus text exposition format with [`render()`] (e.g., to serve
+//! from a `/metrics` endpoint). Recording a metric is only a few atomic
+//! operations, so metrics that are never rendered cost next to nothing.
+
+use std::fmt::Write;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+/// The upper bounds (in seconds) of the buckets of each histogram.
+const BUCKETS: [f64; 10] = [0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 900.0, 3600.0];
+
+/// A histogram of durations.
+#[derive(Debug)]
+struct Histogram {
+    /// The number of observations less than or equal to each bucket's bound.
+    buckets: [AtomicU64; BUCKETS.len()],
+
+    /// The sum of all observations (in microseconds).
+    sum: AtomicU64,
+
+    /// The number of observations.
+    count: AtomicU64,
+}
+
+impl Histogram {
+    /// Creates an empty histogram.
+    const fn new() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; BUCKETS.len()],
+            sum: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// Records an observed duration.
+    fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            if secs <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        self.sum
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Gets the number of observations.
+    fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Writes the histogram in the Prometheus text exposition format.
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{le=\"{bound}\"}} {}",
+                bucket.load(Ordering::Relaxed)
+            );
+        }
+
+        let count = self.count();
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {count}");
+        let _ = writeln!(
+            out,
+            "{name}_sum {}",
+            self.sum.load(Ordering::Relaxed) as f64 / 1_000_000.0
+        );
+        let _ = writeln!(out, "{name}_count {count}");
+    }
+}
+
+/// The metrics recorded by the engine.
+#[derive(Debug)]
+pub(crate) struct Metrics {
+    /// The number of tasks started.
+    tasks_started: AtomicU64,
+
+    /// The number of tasks that completed successfully.
+    tasks_succeeded: AtomicU64,
+
+    /// The number of tasks that failed (including tasks that were canceled,
+    /// preempted, or timed out).
+    tasks_failed: AtomicU64,
+
+    /// The number of tasks currently running.
+    tasks_running: AtomicU64,
+
+    /// The durations of task runs.
+    task_duration: Histogram,
+
+    /// The durations of image pulls.
+    pull_duration: Histogram,
+
+    /// The number of images found in an image cache.
+    image_cache_hits: AtomicU64,
+
+    /// The number of images not found in an image cache.
+    image_cache_misses: AtomicU64,
+}
+
+impl Metrics {
+    /// Creates a set of metrics with nothing recorded.
+    const fn new() -> Self {
+        Self {
+            tasks_started: AtomicU64::new(0),
+            tasks_succeeded: AtomicU64::new(0),
+            tasks_failed: AtomicU64::new(0),
+            tasks_running: AtomicU64::new(0),
+            task_duration: Histogram::new(),
+            pull_duration: Histogram::new(),
+            image_cache_hits: AtomicU64::new(0),
+            image_cache_misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Records that a task started running.
+    pub(crate) fn task_started(&self) {
+        self.tasks_started.fetch_add(1, Ordering::Relaxed);
+        self.tasks_running.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that a task finished running.
+    pub(crate) fn task_finished(&self, duration: Duration, succeeded: bool) {
+        self.tasks_running.fetch_sub(1, Ordering::Relaxed);
+        self.task_duration.observe(duration);
+
+        let counter = if succeeded {
+            &self.tasks_succeeded
+        } else {
+            &self.tasks_failed
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the duration of an image pull.
+    pub(crate) fn image_pulled(&self, duration: Duration) {
+        self.pull_duration.observe(duration);
+    }
+
+    /// Records a lookup of an image in an image cache.
+    pub(crate) fn image_cache_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.image_cache_hits
+        } else {
+            &self.image_cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub(crate) fn render(&self) -> String {
+        let mut out = String::new();
+
+        for (name, kind, help, value) in [
+            (
+                "crankshaft_tasks_started_total",
+                "counter",
+                "The number of tasks started.",
+                &self.tasks_started,
+            ),
+            (
+                "crankshaft_tasks_succeeded_total",
+                "counter",
+                "The number of tasks that completed successfully.",
+                &self.tasks_succeeded,
+            ),
+            (
+                "crankshaft_tasks_failed_total",
+                "counter",
+                "The number of tasks that failed.",
+                &self.tasks_failed,
+            ),
+            (
+                "crankshaft_tasks_running",
+                "gauge",
+                "The number of tasks currently running.",
+                &self.tasks_running,
+            ),
+            (
+                "crankshaft_image_cache_hits_total",
+                "counter",
+                "The number of images found in an image cache.",
+                &self.image_cache_hits,
+            ),
+            (
+                "crankshaft_image_cache_misses_total",
+                "counter",
+                "The number of images not found in an image cache.",
+                &self.image_cache_misses,
+            ),
+        ] {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            let _ = writeln!(out, "{name} {}", value.load(Ordering::Relaxed));
+        }
+
+        self.task_duration.render(
+            &mut out,
+            "crankshaft_task_duration_seconds",
+            "The durations of task runs.",
+        );
+        self.pull_duration.render(
+            &mut out,
+            "crankshaft_image_pull_duration_seconds",
+            "The durations of image pulls.",
+        );
+
+        out
+    }
+}
+
+/// The metrics of the process.
+static METRICS: Metrics = Metrics::new();
+
+/// Gets the metrics recorded by the engine.
+pub(crate) fn global() -> &'static Metrics {
+    &METRICS
+}
+
+/// Renders the metrics recorded by the engine in the Prometheus text
+/// exposition format.
+pub fn render() -> String {
+    METRICS.render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders() {
+        let metrics = Metrics::new();
+        metrics.task_started();
+        metrics.task_started();
+        metrics.task_finished(Duration::from_millis(300), true);
+        metrics.image_cache_lookup(false);
+        metrics.image_pulled(Duration::from_secs(20));
+
+        let out = metrics.render();
+        assert!(out.contains("# TYPE crankshaft_tasks_started_total counter\n"));
+        assert!(out.contains("crankshaft_tasks_started_total 2\n"));
+        assert!(out.contains("crankshaft_tasks_succeeded_total 1\n"));
+        assert!(out.contains("crankshaft_tasks_running 1\n"));
+        assert!(out.contains("crankshaft_image_cache_misses_total 1\n"));
+        assert!(out.contains("crankshaft_task_duration_seconds_bucket{le=\"0.1\"} 0\n"));
+        assert!(out.contains("crankshaft_task_duration_seconds_bucket{le=\"0.5\"} 1\n"));
+        assert!(out.contains("crankshaft_task_duration_seconds_sum 0.3\n"));
+        assert!(out.contains("crankshaft_image_pull_duration_seconds_bucket{le=\"10\"} 0\n"));
+        assert!(out.contains("crankshaft_image_pull_duration_seconds_count 1\n"));
+    }
+}
@@ -0,0 +1,258 @@
+//! Scattering tasks and gathering their results.
+//!
+//! A common pattern is to _scatter_ a number of independent tasks and then run
+//! a single _gather_ task over their outputs. [`Engine::scatter_gather()`]
+//! handles spawning the scattered tasks, waiting on them, applying a
+//! [`Policy`] when some of them fail, and spawning the gather task.
+
+use std::future::Future;
+use std::process::ExitStatus;
+
+use anyhow::Result;
+use futures::StreamExt as _;
+use futures::stream::FuturesUnordered;
+use nonempty::NonEmpty;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::Engine;
+use crate::Task;
+use crate::service::runner::backend::TaskRunError;
+use crate::service::runner::join;
+
+/// The policy applied when one or more scattered tasks fail.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Policy {
+    /// Cancel any outstanding scattered tasks upon the first failure and do
+    /// not run the gather task.
+    #[default]
+    FailFast,
+
+    /// Wait for all scattered tasks to complete and only run the gather task
+    /// if every scattered task succeeded.
+    RequireAll,
+
+    /// Wait for all scattered tasks to complete and run the gather task over
+    /// the scattered tasks that succeeded.
+    Partial,
+}
+
+/// A scattered task along with its result.
+#[derive(Debug)]
+pub struct Scattered {
+    /// The scattered task.
+    pub task: Task,
+
+    /// The result of running the scattered task.
+    pub result: Result<NonEmpty<ExitStatus>, TaskRunError>,
+}
+
+impl Scattered {
+    /// Returns whether or not the scattered task succeeded.
+    ///
+    /// A scattered task succeeds when every one of its executions exited
+    /// successfully.
+    pub fn succeeded(&self) -> bool {
+        join::succeeded(&self.result)
+    }
+}
+
+/// The result of a scatter and gather.
+#[derive(Debug)]
+pub struct Gathered {
+    /// The scattered tasks in the order in which they were provided.
+    pub scattered: Vec<Scattered>,
+
+    /// The result of the gather task.
+    ///
+    /// This is `None` if the gather task was not run, either because the
+    /// [`Policy`] prevented it or because the gather callback did not produce
+    /// a task.
+    pub gathered: Option<Result<NonEmpty<ExitStatus>, TaskRunError>>,
+}
+
+impl Engine {
+    /// Scatters a set of tasks to the named backend and then runs a gather
+    /// task over their results.
+    ///
+    /// The `gather` callback receives the scattered tasks that are eligible
+    /// for gathering under the provided [`Policy`] and returns the gather task
+    /// (typically wiring the outputs of the scattered tasks into the inputs of
+    /// the gather task). Returning `None` skips the gather task.
+    ///
+    /// The `token` cancels both the scattered tasks and the gather task.
+    pub async fn scatter_gather(
+        &self,
+        name: impl AsRef<str>,
+        tasks: impl IntoIterator<Item = Task>,
+        policy: Policy,
+        token: CancellationToken,
+        gather: impl FnOnce(&[&Scattered]) -> Option<Task>,
+    ) -> Result<Gathered> {
+        let name = name.as_ref();
+        let scatter_token = token.child_token();
+
+        let tasks = tasks.into_iter().collect::<Vec<_>>();
+        let pending = tasks
+            .iter()
+            .cloned()
+            .enumerate()
+            .map(|(i, task)| {
+                let handle = self.spawn(name, task, scatter_token.clone())?;
+                Ok(async move { (i, handle.wait().await) })
+            })
+            .collect::<Result<FuturesUnordered<_>>>()
+            // Cancel the tasks already scattered if any task fails to be spawned
+            .inspect_err(|_| scatter_token.cancel())?;
+
+        debug!("scattered {count} task(s)", count = tasks.len());
+
+        let scattered = wait(policy, &tasks, pending, &scatter_token).await;
+        let gathered = match eligible(policy, &scattered).and_then(|eligible| gather(&eligible)) {
+            Some(task) => Some(self.spawn(name, task, token)?.wait().await),
+            None => {
+                debug!("skipping the gather task");
+                None
+            }
+        };
+
+        Ok(Gathered {
+            scattered,
+            gathered,
+        })
+    }
+}
+
+/// Waits on the results of the scattered tasks.
+///
+/// Under [`Policy::FailFast`], `token` is canceled upon the first failure.
+async fn wait(
+    policy: Policy,
+    tasks: &[Task],
+    mut pending: FuturesUnordered<
+        impl Future<Output = (usize, Result<NonEmpty<ExitStatus>, TaskRunError>)>,
+    >,
+    token: &CancellationToken,
+) -> Vec<Scattered> {
+    let mut results = (0..tasks.len()).map(|_| None).collect::<Vec<_>>();
+
+    while let Some((i, result)) = pending.next().await {
+        let scattered = Scattered {
+            task: tasks[i].clone(),
+            result,
+        };
+
+        if !scattered.succeeded() && policy == Policy::FailFast && !token.is_cancelled() {
+            debug!("a scattered task failed; canceling outstanding scattered tasks");
+            token.cancel();
+        }
+
+        results[i] = Some(scattered);
+    }
+
+    // SAFETY: every spawned task reports exactly one result above.
+    results.into_iter().map(|result| result.unwrap()).collect()
+}
+
+/// Determines the scattered tasks that are eligible for gathering under a
+/// policy.
+///
+/// Returns `None` if the gather task should not be run.
+fn eligible(policy: Policy, scattered: &[Scattered]) -> Option<Vec<&Scattered>> {
+    match policy {
+        Policy::FailFast | Policy::RequireAll if !scattered.iter().all(Scattered::succeeded) => {
+            None
+        }
+        Policy::Partial => Some(scattered.iter().filter(|s| s.succeeded()).collect()),
+        _ => Some(scattered.iter().collect()),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::process::ExitStatusExt as _;
+
+    use futures::FutureExt as _;
+    use futures::future::BoxFuture;
+
+    use super::*;
+
+    /// The result of a scattered task.
+    type Outcome = (usize, Result<NonEmpty<ExitStatus>, TaskRunError>);
+
+    fn tasks(count: usize) -> Vec<Task> {
+        (0..count)
+            .map(|i| {
+                Task::builder()
+                    .name(format!("task-{i}"))
+                    .executions(NonEmpty::new(
+                        crate::task::Execution::builder()
+                            .image("ubuntu:22.04")
+                            .program("true")
+                            .build(),
+                    ))
+                    .build()
+            })
+            .collect()
+    }
+
+    fn succeeds(i: usize) -> BoxFuture<'static, Outcome> {
+        async move { (i, Ok(NonEmpty::new(ExitStatus::from_raw(0)))) }.boxed()
+    }
+
+    fn fails(i: usize) -> BoxFuture<'static, Outcome> {
+        async move { (i, Err(TaskRunError::Other(anyhow::anyhow!("failed")))) }.boxed()
+    }
+
+    /// A task that runs until it is canceled.
+    fn runs(i: usize, token: &CancellationToken) -> BoxFuture<'static, Outcome> {
+        let token = token.clone();
+        async move {
+            token.cancelled().await;
+            (i, Err(TaskRunError::Canceled))
+        }
+        .boxed()
+    }
+
+    #[tokio::test]
+    async fn fail_fast_cancels_outstanding_tasks() {
+        let token = CancellationToken::new();
+        let pending = FuturesUnordered::from_iter([fails(0), runs(1, &token), succeeds(2)]);
+        let scattered = wait(Policy::FailFast, &tasks(3), pending, &token).await;
+
+        assert!(token.is_cancelled());
+        assert!(matches!(scattered[1].result, Err(TaskRunError::Canceled)));
+        assert!(scattered[2].succeeded());
+        assert!(eligible(Policy::FailFast, &scattered).is_none());
+    }
+
+    #[tokio::test]
+    async fn require_all_waits_for_every_task() {
+        let token = CancellationToken::new();
+        let pending = FuturesUnordered::from_iter([fails(0), succeeds(1)]);
+        let scattered = wait(Policy::RequireAll, &tasks(2), pending, &token).await;
+
+        assert!(!token.is_cancelled());
+        assert!(scattered[1].succeeded());
+        assert!(eligible(Policy::RequireAll, &scattered).is_none());
+
+        let pending = FuturesUnordered::from_iter([succeeds(0), succeeds(1)]);
+        let scattered = wait(Policy::RequireAll, &tasks(2), pending, &token).await;
+        assert_eq!(eligible(Policy::RequireAll, &scattered).unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn partial_gathers_successful_tasks() {
+        let token = CancellationToken::new();
+        let pending = FuturesUnordered::from_iter([succeeds(0), fails(1), succeeds(2)]);
+        let scattered = wait(Policy::Partial, &tasks(3), pending, &token).await;
+
+        assert!(!token.is_cancelled());
+        let names = eligible(Policy::Partial, &scattered)
+            .unwrap()
+            .iter()
+            .map(|s| s.task.name().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["task-0", "task-2"]);
+    }
+}
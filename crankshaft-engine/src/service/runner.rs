@@ -1,44 +1,502 @@
 //! Task runner services.
 
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::future::IntoFuture;
 use std::process::ExitStatus;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
 
+use anyhow::Context as _;
 use anyhow::Result;
+use crankshaft_config::backend::Config;
 use crankshaft_config::backend::Defaults;
 use crankshaft_config::backend::Kind;
+#[cfg(doc)]
+use crankshaft_config::backend::Oversized;
+use crankshaft_config::backend::Parts;
+use crankshaft_config::backend::Queue;
+use futures::FutureExt as _;
+use futures::Stream;
+use futures::future::BoxFuture;
 use nonempty::NonEmpty;
+use tokio::select;
+use tokio::sync::OwnedSemaphorePermit;
 use tokio::sync::Semaphore;
 use tokio::sync::oneshot::Receiver;
+use tokio::sync::watch;
 use tokio_util::sync::CancellationToken;
+use tokio_util::sync::DropGuard;
+use tokio_util::task::TaskTracker;
+use tracing::Instrument as _;
+use tracing::info_span;
 use tracing::trace;
+use tracing::warn;
 
+mod adaptive;
 pub mod backend;
+mod groups;
+pub mod join;
+mod scheduler;
+mod status;
 
 pub use backend::Backend;
+pub use groups::GroupCanceled;
+pub(crate) use groups::Groups;
+pub use status::TaskStatus;
 
 use crate::Task;
+use crate::audit;
+use crate::budget::Budgets;
+use crate::events;
+use crate::events::Event;
+use crate::logs;
+use crate::logs::Line;
+use crate::logs::OutputStream;
+use crate::logs::Usage;
+use crate::metrics;
 use crate::service::name::GeneratorIterator;
 use crate::service::name::UniqueAlphanumeric;
+use crate::service::runner::backend::chaos;
 use crate::service::runner::backend::docker;
 use crate::service::runner::backend::generic;
 use crate::service::runner::backend::tes;
+use crate::service::runner::scheduler::Request;
+use crate::service::runner::scheduler::Scheduler;
+use crate::task::Gpus;
+use crate::telemetry::Counters;
 
 /// The size of the name buffer.
 const NAME_BUFFER_LEN: usize = 4096;
 
+/// The error returned when a task is submitted to a runner whose queue is
+/// full.
+///
+/// Callers can downcast the error returned from [`Runner::spawn()`] or
+/// [`Runner::submit()`] to this type to distinguish backpressure from other
+/// failures.
+#[derive(Debug, thiserror::Error)]
+#[error("the task queue is full ({depth} tasks queued)")]
+pub struct QueueFull {
+    /// The number of tasks queued when the task was rejected.
+    pub depth: usize,
+}
+
+/// The error returned when a task requests more resources than the capacity
+/// of a runner that rejects such tasks (see [`Oversized::Reject`]).
+///
+/// Callers can downcast the error returned from [`Runner::spawn()`] or
+/// [`Runner::submit()`] to this type.
+#[derive(Debug, thiserror::Error)]
+#[error("the task requests {requested} {resource}, but the runner's capacity is {capacity}")]
+pub struct ExceedsCapacity {
+    /// The resource that the task requests too much of (e.g., `CPUs`).
+    pub resource: &'static str,
+
+    /// The amount of the resource requested.
+    pub requested: f64,
+
+    /// The capacity of the runner for the resource.
+    pub capacity: f64,
+}
+
+/// Statistics about the queue of a runner.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct QueueStats {
+    /// The number of tasks waiting to run.
+    pub depth: usize,
+
+    /// The number of tasks rejected because the queue was full.
+    pub rejected: u64,
+}
+
+/// The queue of tasks waiting to run.
+#[derive(Debug, Default)]
+struct TaskQueue {
+    /// The slots of the queue, if it is bounded.
+    slots: Option<Arc<Semaphore>>,
+
+    /// How long asynchronous submissions wait for a slot.
+    timeout: Option<Duration>,
+
+    /// The number of tasks waiting to run.
+    depth: AtomicUsize,
+
+    /// The number of tasks rejected because the queue was full.
+    rejected: AtomicU64,
+}
+
+impl TaskQueue {
+    /// Creates a new task queue from the given limits.
+    fn new(config: Option<Queue>) -> Self {
+        match config {
+            Some(config) => Self {
+                slots: Some(Arc::new(Semaphore::new(config.max_depth()))),
+                timeout: config.timeout().map(Duration::from_millis),
+                ..Default::default()
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// Records a rejected task and returns the error to report.
+    fn reject(&self) -> anyhow::Error {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        QueueFull {
+            depth: self.depth.load(Ordering::Relaxed),
+        }
+        .into()
+    }
+
+    /// Reserves a slot in the queue without waiting.
+    fn try_reserve(&self) -> Result<Option<OwnedSemaphorePermit>> {
+        match &self.slots {
+            Some(slots) => match slots.clone().try_acquire_owned() {
+                Ok(slot) => Ok(Some(slot)),
+                Err(_) => Err(self.reject()),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Reserves a slot in the queue, waiting up to the configured timeout for
+    /// one to become available.
+    async fn reserve(&self) -> Result<Option<OwnedSemaphorePermit>> {
+        match (&self.slots, self.timeout) {
+            (Some(slots), Some(timeout)) => {
+                match tokio::time::timeout(timeout, slots.clone().acquire_owned()).await {
+                    Ok(slot) => Ok(Some(slot?)),
+                    Err(_) => Err(self.reject()),
+                }
+            }
+            _ => self.try_reserve(),
+        }
+    }
+
+    /// Gets the statistics of the queue.
+    fn stats(&self) -> QueueStats {
+        QueueStats {
+            depth: self.depth.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The result of a task.
+type TaskResult = Result<NonEmpty<ExitStatus>, backend::TaskRunError>;
+
+/// Copies the result of a task for a task coalesced onto it.
+///
+/// Errors that cannot be cloned are copied as their messages.
+fn duplicate(result: &TaskResult) -> TaskResult {
+    use backend::TaskRunError;
+
+    match result {
+        Ok(statuses) => Ok(statuses.clone()),
+        Err(TaskRunError::Canceled) => Err(TaskRunError::Canceled),
+        Err(TaskRunError::Preempted) => Err(TaskRunError::Preempted),
+        Err(TaskRunError::NonZeroExit {
+            index,
+            statuses,
+            tail,
+        }) => Err(TaskRunError::NonZeroExit {
+            index: *index,
+            statuses: statuses.clone(),
+            tail: tail.clone(),
+        }),
+        Err(TaskRunError::OutOfMemory { index, statuses }) => Err(TaskRunError::OutOfMemory {
+            index: *index,
+            statuses: statuses.clone(),
+        }),
+        Err(TaskRunError::TimedOut(timeout)) => Err(TaskRunError::TimedOut(*timeout)),
+        Err(TaskRunError::Other(e)) => Err(TaskRunError::Other(anyhow::anyhow!("{e:#}"))),
+    }
+}
+
+/// A task that is waiting or running along with the tasks coalesced onto it.
+#[derive(Debug)]
+pub(crate) struct Coalesced {
+    /// The follower of the task's output.
+    logs: logs::Follower,
+
+    /// The receiver of the status of the task.
+    status: watch::Receiver<TaskStatus>,
+
+    /// The senders of the results of the tasks coalesced onto the task.
+    waiters: Vec<tokio::sync::oneshot::Sender<TaskResult>>,
+}
+
+/// The tasks that are waiting or running keyed by their content hashes (see
+/// [`Task::content_hash()`]) combined with their retry policies and groups.
+pub(crate) type InFlight = Arc<Mutex<HashMap<String, Coalesced>>>;
+
+/// A task that other identical tasks may be coalesced onto while it is
+/// waiting or running.
+///
+/// The task stops accepting other tasks when this is dropped; if it is
+/// dropped without a result, one of the tasks coalesced onto it runs instead
+/// (see [`Follower::wait()`]).
+struct Leader {
+    /// The tasks that are waiting or running.
+    inflight: InFlight,
+
+    /// The content hash of the task.
+    hash: String,
+}
+
+impl Leader {
+    /// Sends the result of the task to the tasks coalesced onto it.
+    ///
+    /// The tasks coalesced onto a task that was canceled or preempted are not
+    /// sent its result; one of them runs instead.
+    fn finish(self, result: &TaskResult) {
+        let coalesced = self.inflight.lock().unwrap().remove(&self.hash);
+        if matches!(
+            result,
+            Err(backend::TaskRunError::Canceled | backend::TaskRunError::Preempted)
+        ) {
+            return;
+        }
+
+        for waiter in coalesced.into_iter().flat_map(|c| c.waiters) {
+            let _ = waiter.send(duplicate(result));
+        }
+    }
+}
+
+impl Drop for Leader {
+    fn drop(&mut self) {
+        self.inflight.lock().unwrap().remove(&self.hash);
+    }
+}
+
+/// A task coalesced onto an identical task that is waiting or running.
+struct Follower {
+    /// The receiver of the result of the identical task.
+    result: tokio::sync::oneshot::Receiver<TaskResult>,
+
+    /// The follower of the identical task's output.
+    logs: logs::Follower,
+
+    /// The receiver of the status of the identical task.
+    status: watch::Receiver<TaskStatus>,
+}
+
+impl Follower {
+    /// Waits on the result of the task this task was coalesced onto.
+    ///
+    /// While waiting, the output of that task is relayed to `logs` and its
+    /// transitions to running and started are emitted as this task's events.
+    ///
+    /// Returns `None` if that task finished without a result to share (e.g.,
+    /// because it was canceled), in which case this task has to run instead.
+    async fn wait(
+        self,
+        id: u64,
+        name: &Option<String>,
+        logs: &logs::Sender,
+        emit: &impl Fn(Event),
+        token: &CancellationToken,
+    ) -> Option<TaskResult> {
+        let Self {
+            mut result,
+            logs: followed,
+            mut status,
+        } = self;
+
+        let relay = followed.relay(logs);
+        tokio::pin!(relay);
+        let mut relayed = false;
+        let mut running = false;
+        let mut started = false;
+        status.mark_changed();
+
+        loop {
+            select! {
+                // Transitions are emitted before the result is received
+                biased;
+
+                Ok(()) = status.changed() => {
+                    let leader = match &*status.borrow_and_update() {
+                        TaskStatus::Running { started, .. } => Some(*started),
+                        _ => None,
+                    };
+
+                    if leader.is_some() && !running {
+                        running = true;
+                        emit(Event::Running { id, name: name.clone() });
+                    }
+
+                    if leader == Some(true) && !started {
+                        started = true;
+                        emit(Event::Started { id, name: name.clone() });
+                    }
+                }
+                result = &mut result => {
+                    if !relayed {
+                        relay.await;
+                    }
+
+                    return result.ok();
+                }
+                _ = token.cancelled() => return Some(Err(backend::TaskRunError::Canceled)),
+                _ = &mut relay, if !relayed => relayed = true,
+            }
+        }
+    }
+}
+
+/// The role of a task among the identical tasks that are waiting or running.
+enum Role {
+    /// The task runs and identical tasks are coalesced onto it.
+    Leader(Leader),
+
+    /// The task is coalesced onto an identical task.
+    Follower(Follower),
+}
+
+/// Coalesces a task onto an identical task that is waiting or running or, if
+/// there is none, makes it the task that identical tasks are coalesced onto.
+fn coalesce(
+    inflight: &InFlight,
+    hash: &str,
+    logs: &logs::Sender,
+    status: &watch::Receiver<TaskStatus>,
+) -> Role {
+    let mut tasks = inflight.lock().unwrap();
+    match tasks.get_mut(hash) {
+        Some(coalesced) => {
+            let (waiter, result) = tokio::sync::oneshot::channel();
+            coalesced.waiters.push(waiter);
+            Role::Follower(Follower {
+                result,
+                logs: coalesced.logs.clone(),
+                status: coalesced.status.clone(),
+            })
+        }
+        None => {
+            tasks.insert(
+                hash.to_string(),
+                Coalesced {
+                    logs: logs.downgrade(),
+                    status: status.clone(),
+                    waiters: Vec::new(),
+                },
+            );
+            Role::Leader(Leader {
+                inflight: inflight.clone(),
+                hash: hash.to_string(),
+            })
+        }
+    }
+}
+
 /// A spawned task handle.
 #[derive(Debug)]
-pub struct TaskHandle(Receiver<Result<NonEmpty<ExitStatus>, backend::TaskRunError>>);
+pub struct TaskHandle {
+    /// The receiver for the result of the task.
+    rx: Receiver<Result<NonEmpty<ExitStatus>, backend::TaskRunError>>,
+
+    /// The cancellation token for the task.
+    ///
+    /// This is a child of the token provided when the task was spawned.
+    token: CancellationToken,
+
+    /// The guard that cancels the task when the handle is dropped (if
+    /// enabled).
+    guard: Option<DropGuard>,
+
+    /// The follower of the task's output.
+    logs: logs::Follower,
+
+    /// The receiver of the status of the task.
+    status: watch::Receiver<TaskStatus>,
+}
 
 impl TaskHandle {
+    /// Gets the current status of the task.
+    pub fn status(&self) -> TaskStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Gets the latest sample of the resources consumed by the task's
+    /// executions while it runs.
+    ///
+    /// Returns `None` if no sample has been reported yet or the task's backend
+    /// does not report resource usage.
+    pub fn usage(&self) -> Option<Usage> {
+        self.logs.usage()
+    }
+
+    /// Cancels the task.
+    ///
+    /// Only this task is canceled; the token provided when the task was
+    /// spawned is unaffected.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Gets the cancellation token for the task.
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Cancels the task if the handle is dropped before the task completes
+    /// (e.g., while unwinding from a panic).
+    ///
+    /// By default, dropping a handle leaves the task running.
+    pub fn cancel_on_drop(mut self) -> Self {
+        self.guard = Some(self.token.clone().drop_guard());
+        self
+    }
+
+    /// Follows the lines written to the stdout of the task's executions while
+    /// it runs.
+    ///
+    /// Only lines written after this is called are included; the stream ends
+    /// once the task has finished running. If the task's backend does not
+    /// support following output (see [`Backend::run_with_logs()`]), the stream
+    /// ends without any lines.
+    pub fn follow_stdout(&self) -> impl Stream<Item = Line> + use<> {
+        self.logs.follow(OutputStream::Stdout)
+    }
+
+    /// Follows the lines written to the stderr of the task's executions while
+    /// it runs.
+    ///
+    /// See [`Self::follow_stdout()`] for more details.
+    pub fn follow_stderr(&self) -> impl Stream<Item = Line> + use<> {
+        self.logs.follow(OutputStream::Stderr)
+    }
+
     /// Consumes the task handle and waits for the task to complete.
     ///
     /// Returns the exit statuses of the task's executors.
     pub async fn wait(self) -> Result<NonEmpty<ExitStatus>, backend::TaskRunError> {
-        self.0
-            .await
-            .map_err(|e| backend::TaskRunError::Other(e.into()))?
+        let result = self.rx.await;
+
+        // The task is complete, so there is nothing left to cancel
+        if let Some(guard) = self.guard {
+            guard.disarm();
+        }
+
+        result.map_err(|e| backend::TaskRunError::Other(e.into()))?
+    }
+}
+
+impl IntoFuture for TaskHandle {
+    type IntoFuture = BoxFuture<'static, Self::Output>;
+    type Output = TaskResult;
+
+    /// Waits for the task to complete (see [`TaskHandle::wait()`]).
+    fn into_future(self) -> Self::IntoFuture {
+        self.wait().boxed()
     }
 }
 
@@ -48,42 +506,152 @@ pub struct Runner {
     /// The task runner itself.
     backend: Arc<dyn Backend>,
 
-    /// The task lock.
-    lock: Arc<tokio::sync::Semaphore>,
+    /// The scheduler that starts tasks as the runner has capacity for them.
+    scheduler: Arc<Scheduler>,
+
+    /// The queue of tasks waiting to be scheduled.
+    queue: Arc<TaskQueue>,
 
     /// The unique name generator for tasks without names being sent to backends
     /// that may need names.
     name_generator: Arc<Mutex<GeneratorIterator<UniqueAlphanumeric>>>,
+
+    /// The usage statistics counters (if telemetry is enabled).
+    pub(crate) telemetry: Option<Arc<Counters>>,
+
+    /// The audit log sink (if auditing is enabled).
+    pub(crate) audit: Option<audit::Sink>,
+
+    /// The sender of task events (if events are enabled).
+    pub(crate) events: Option<events::Sender>,
+
+    /// The store of task attempts and the name of the runner within it (if
+    /// the store is enabled).
+    #[cfg(feature = "store")]
+    pub(crate) store: Option<(crate::store::Store, String)>,
+
+    /// Whether the results of previous attempts in the store are reused for
+    /// identical tasks.
+    #[cfg(feature = "store")]
+    pub(crate) call_cache: bool,
+
+    /// The tasks that identical tasks are coalesced onto (if de-duplication is
+    /// enabled).
+    pub(crate) inflight: Option<InFlight>,
+
+    /// The names of the tasks that have been reattached to.
+    reattached: Mutex<HashSet<String>>,
+
+    /// The references pinned to digests that image references were resolved
+    /// to keyed by image reference.
+    ///
+    /// A reference the backend cannot resolve is resolved to `None`.
+    resolved: Mutex<HashMap<String, Arc<tokio::sync::OnceCell<Option<String>>>>>,
+
+    /// The budgets of the groups of tasks.
+    pub(crate) budgets: Arc<Budgets>,
+
+    /// The cancellation tokens of the groups of tasks.
+    pub(crate) groups: Arc<Groups>,
+
+    /// The token canceled when the runner shuts down.
+    shutdown: CancellationToken,
+
+    /// The tracker of the runner's spawned tasks.
+    tasks: TaskTracker,
 }
 
 impl Runner {
-    /// Creates a new [`Runner`].
-    pub async fn initialize(
-        config: Kind,
-        max_tasks: usize,
-        defaults: Option<Defaults>,
-    ) -> Result<Self> {
-        let backend = match config {
-            Kind::Docker(config) => {
-                let backend = docker::Backend::initialize_default_with(config).await?;
-                Arc::new(backend) as Arc<dyn Backend>
-            }
-            Kind::Generic(config) => {
-                let backend = generic::Backend::initialize(config, defaults).await?;
-                Arc::new(backend)
+    /// Creates a new [`Runner`] from the configuration of a backend.
+    ///
+    /// If the configuration is adaptive, the number of concurrently running
+    /// tasks is adapted to the load of the host (never exceeding its maximum
+    /// number of tasks).
+    ///
+    /// If the configuration has chaos settings, faults are injected into the
+    /// tasks run by the backend.
+    ///
+    /// If the configuration has queue limits, the number of tasks waiting to
+    /// run is limited; otherwise, it is unbounded.
+    ///
+    /// If the configuration has a capacity, tasks are only started once the
+    /// resources they request fit within the capacity. Waiting tasks are
+    /// started in order of priority (see [`Task::priority()`]).
+    ///
+    /// If the configuration has a fallback, it is initialized instead if the
+    /// backend fails to initialize.
+    pub async fn initialize(config: Config) -> Result<Self> {
+        let Parts {
+            kind,
+            max_tasks,
+            defaults,
+            adaptive,
+            chaos,
+            queue,
+            capacity,
+            fallback,
+            ..
+        } = config.into_parts();
+        let backend = match (Self::backend(kind, defaults.clone()).await, fallback) {
+            (Ok(backend), _) => backend,
+            (Err(e), Some(fallback)) => {
+                warn!("failed to initialize backend, using its fallback instead: {e:#}");
+                Self::backend(fallback, defaults)
+                    .await
+                    .context("failed to initialize the fallback backend")?
             }
-            Kind::TES(config) => Arc::new(tes::Backend::initialize(config)),
+            (Err(e), None) => return Err(e),
+        };
+
+        let backend = match chaos {
+            Some(chaos) => Arc::new(chaos::Backend::new(backend, chaos)?),
+            None => backend,
         };
 
         let generator = UniqueAlphanumeric::default_with_expected_generations(NAME_BUFFER_LEN);
+        let lock = Arc::new(Semaphore::new(max_tasks));
+
+        if let Some(adaptive) = adaptive {
+            adaptive::spawn(adaptive, &lock, max_tasks);
+        }
 
         Ok(Self {
             backend,
-            lock: Arc::new(Semaphore::new(max_tasks)),
+            scheduler: Arc::new(Scheduler::new(lock, capacity)),
+            queue: Arc::new(TaskQueue::new(queue)),
             name_generator: Arc::new(Mutex::new(GeneratorIterator::new(
                 generator,
                 NAME_BUFFER_LEN,
             ))),
+            telemetry: None,
+            audit: None,
+            events: None,
+            #[cfg(feature = "store")]
+            store: None,
+            #[cfg(feature = "store")]
+            call_cache: false,
+            inflight: None,
+            reattached: Mutex::default(),
+            resolved: Mutex::default(),
+            budgets: Default::default(),
+            groups: Default::default(),
+            shutdown: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+        })
+    }
+
+    /// Initializes a backend.
+    async fn backend(config: Kind, defaults: Option<Defaults>) -> Result<Arc<dyn Backend>> {
+        Ok(match config {
+            Kind::Docker(config) => {
+                let backend = docker::Backend::initialize_default_with(config).await?;
+                Arc::new(backend)
+            }
+            Kind::Generic(config) => {
+                let backend = generic::Backend::initialize(config, defaults).await?;
+                Arc::new(backend)
+            }
+            Kind::TES(config) => Arc::new(tes::Backend::initialize(config)),
         })
     }
 
@@ -94,12 +662,312 @@ impl Runner {
     /// executions collection.
     ///
     /// The `cancellation` token can be used to gracefully cancel the task.
-    pub fn spawn(&self, mut task: Task, token: CancellationToken) -> anyhow::Result<TaskHandle> {
+    ///
+    /// If the runner's queue is full, the task is rejected immediately with a
+    /// [`QueueFull`] error.
+    ///
+    /// Tasks with invalid resource requests (see
+    /// [`Resources::validate()`](crate::task::Resources::validate)) are
+    /// rejected before being queued, as are tasks that request more than the
+    /// runner's capacity if it rejects them (see [`ExceedsCapacity`]) and
+    /// tasks of a group that has been canceled (see [`GroupCanceled`]) or
+    /// has exhausted its budget (see
+    /// [`BudgetExhausted`](crate::budget::BudgetExhausted)).
+    ///
+    /// The tasks of a group are canceled when the group is canceled, as if
+    /// their `cancellation` tokens were.
+    pub fn spawn(&self, task: Task, token: CancellationToken) -> anyhow::Result<TaskHandle> {
+        if let Some(resources) = &task.resources {
+            resources.validate()?;
+        }
+
+        self.scheduler.check(&Request::new(&task))?;
+        if let Some(group) = &task.group {
+            self.groups.admit(group)?;
+            self.budgets.admit(group)?;
+        }
+
+        let slot = self.queue.try_reserve()?;
+        self.spawn_queued(slot, task, token)
+    }
+
+    /// Submits a task to be executed by the backend, waiting for space in the
+    /// runner's queue if it is full.
+    ///
+    /// The wait is bounded by the queue's configured timeout; if no timeout is
+    /// configured or it elapses, the task is rejected with a [`QueueFull`]
+    /// error.
+    ///
+    /// See [`Self::spawn()`] for more details.
+    pub async fn submit(&self, task: Task, token: CancellationToken) -> anyhow::Result<TaskHandle> {
+        if let Some(resources) = &task.resources {
+            resources.validate()?;
+        }
+
+        self.scheduler.check(&Request::new(&task))?;
+        if let Some(group) = &task.group {
+            self.groups.admit(group)?;
+            self.budgets.admit(group)?;
+        }
+
+        let slot = self.queue.reserve().await?;
+        self.spawn_queued(slot, task, token)
+    }
+
+    /// Resolves the images of a task's executions to references pinned to the
+    /// digests the images currently have (see [`Backend::resolve_image()`]).
+    ///
+    /// Each image reference is only resolved once for the lifetime of the
+    /// runner, so every task that uses it (e.g., every task of a scatter) runs
+    /// the exact same image even if its tag is moved while a workflow runs.
+    /// When the store is enabled, each resolution is recorded in it. The images
+    /// of backends that cannot resolve image references are left unchanged.
+    pub async fn resolve(&self, task: &mut Task) -> Result<()> {
+        for execution in task.executions.iter_mut() {
+            let cell = self
+                .resolved
+                .lock()
+                .unwrap()
+                .entry(execution.image.clone())
+                .or_default()
+                .clone();
+
+            let resolved = cell
+                .get_or_try_init(|| async {
+                    let resolved = self.backend.resolve_image(&execution.image).await?;
+
+                    #[cfg(feature = "store")]
+                    if let (Some(resolved), Some((store, runner))) = (&resolved, &self.store) {
+                        store.resolved(runner, &execution.image, resolved);
+                    }
+
+                    anyhow::Ok(resolved)
+                })
+                .await?;
+
+            if let Some(resolved) = resolved {
+                execution.image = resolved.clone();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reattaches to the tasks left running by a previous process (e.g., one
+    /// that crashed), returning the name and a handle of each task.
+    ///
+    /// Waiting on a handle recovers the result of the task once it completes.
+    /// Reattached tasks do not count towards the runner's limits on running
+    /// tasks. Only backends that support it reattach to tasks (see
+    /// [`Backend::reattach()`]).
+    pub async fn reattach(&self) -> Result<Vec<(String, TaskHandle)>> {
+        if self.shutdown.is_cancelled() {
+            anyhow::bail!("the runner has shut down");
+        }
+
+        let reattached = self.backend.reattach().await?;
+        self.reattached
+            .lock()
+            .unwrap()
+            .extend(reattached.iter().map(|r| r.name.clone()));
+
+        // The unfinished attempts of the reattached tasks are finished once the
+        // tasks complete
+        #[cfg(feature = "store")]
+        let unfinished = match &self.store {
+            Some((store, _)) => store.unfinished().await?,
+            None => Vec::new(),
+        };
+
+        Ok(reattached
+            .into_iter()
+            .map(|reattached| {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                let (status, status_rx) = watch::channel(TaskStatus::Running {
+                    since: Instant::now(),
+                    started: true,
+                });
+                let token = self.shutdown.child_token();
+                let wait = (reattached.wait)(token.clone());
+
+                #[cfg(feature = "store")]
+                let attempts = match &self.store {
+                    Some((store, runner)) => unfinished
+                        .iter()
+                        .filter(|a| {
+                            a.runner == *runner && a.task.as_deref() == Some(&reattached.name)
+                        })
+                        .map(|a| (a.id, store.clone()))
+                        .collect(),
+                    None => Vec::new(),
+                };
+
+                self.tasks.spawn(async move {
+                    let result = wait.await;
+                    #[cfg(feature = "store")]
+                    for (attempt, store) in attempts {
+                        store.finished(attempt, &result);
+                    }
+
+                    // The identifier of the event is not part of the status
+                    status.send_modify(|s| s.apply(&Event::exited(0, None, &result)));
+                    let _ = tx.send(result);
+                });
+
+                let handle = TaskHandle {
+                    rx,
+                    token,
+                    guard: None,
+                    logs: logs::Sender::new().downgrade(),
+                    status: status_rx,
+                };
+                (reattached.name, handle)
+            })
+            .collect())
+    }
+
+    /// Resubmits the tasks recorded in the store that were queued or running
+    /// in the runner when a previous process exited without finishing them
+    /// (e.g., because it crashed), returning the name and a handle of each
+    /// resubmitted task.
+    ///
+    /// Each resubmitted task is a new attempt: its attempt number is one more
+    /// than that of the interrupted attempt (which is treated as the first
+    /// attempt if it had no number). Interrupted attempts are recorded as
+    /// finished so that they are only resubmitted once; those that would
+    /// exceed `max_attempts`, or whose secrets were redacted from the store,
+    /// are not resubmitted.
+    ///
+    /// Tasks that have been reattached to (see [`Self::reattach()`]) are not
+    /// resubmitted, so this should be called after reattaching. Nothing is
+    /// resubmitted if the store is not enabled.
+    #[cfg(feature = "store")]
+    pub async fn recover(&self, max_attempts: u32) -> Result<Vec<(Option<String>, TaskHandle)>> {
+        let Some((store, runner)) = &self.store else {
+            return Ok(Vec::new());
+        };
+
+        let unfinished = store
+            .unfinished()
+            .await?
+            .into_iter()
+            .filter(|a| &a.runner == runner)
+            .filter(|a| {
+                a.task
+                    .as_ref()
+                    .is_none_or(|name| !self.reattached.lock().unwrap().contains(name))
+            })
+            .collect::<Vec<_>>();
+
+        let mut handles = Vec::new();
+        for attempt in unfinished {
+            let next = attempt.attempt.unwrap_or(1) + 1;
+            let task = match attempt.task()? {
+                Some(_) if next > max_attempts => {
+                    warn!(
+                        "not resubmitting interrupted task attempt {id}: the task has reached its \
+                         maximum of {max_attempts} attempts",
+                        id = attempt.id
+                    );
+                    store
+                        .interrupted(attempt.id, "the attempt was interrupted")
+                        .await?;
+                    continue;
+                }
+                Some(mut task) => {
+                    task.attempt = Some(next);
+                    task
+                }
+                None => {
+                    warn!(
+                        "not resubmitting interrupted task attempt {id}: its secrets were not \
+                         stored",
+                        id = attempt.id
+                    );
+                    store
+                        .interrupted(attempt.id, "the attempt was interrupted")
+                        .await?;
+                    continue;
+                }
+            };
+
+            store
+                .interrupted(
+                    attempt.id,
+                    &format!("the attempt was interrupted and resubmitted as attempt {next}"),
+                )
+                .await?;
+
+            let handle = self.submit(task, self.shutdown.child_token()).await?;
+            handles.push((attempt.task, handle));
+        }
+
+        Ok(handles)
+    }
+
+    /// Gets the statistics of the runner's queue.
+    pub fn queue_stats(&self) -> QueueStats {
+        self.queue.stats()
+    }
+
+    /// Shuts down the runner.
+    ///
+    /// Tasks waiting to run are canceled. Running tasks are canceled and
+    /// waited on so that the backend can clean up after them (e.g., stopping
+    /// and removing their containers) before this returns. Tasks spawned
+    /// after the runner has shut down are rejected.
+    pub async fn shutdown(&self) {
+        self.shutdown.cancel();
+        self.tasks.close();
+        self.tasks.wait().await;
+    }
+
+    /// Spawns a task that holds the given slot in the queue until it starts
+    /// running.
+    fn spawn_queued(
+        &self,
+        slot: Option<OwnedSemaphorePermit>,
+        mut task: Task,
+        token: CancellationToken,
+    ) -> anyhow::Result<TaskHandle> {
         trace!(backend = ?self.backend, task = ?task);
 
+        if self.shutdown.is_cancelled() {
+            anyhow::bail!("the runner has shut down");
+        }
+
         let (tx, rx) = tokio::sync::oneshot::channel();
         let backend = self.backend.clone();
-        let lock = self.lock.clone();
+        let scheduler = self.scheduler.clone();
+        let request = Request::new(&task);
+        let queue = self.queue.clone();
+        let telemetry = self.telemetry.clone();
+        let audit = self.audit.clone();
+        let events = self.events.clone();
+        let budgets = self.budgets.clone();
+        let shutdown = self.shutdown.clone();
+        let (token, group_guard) = match &task.group {
+            Some(group) => {
+                let (token, guard) = self.groups.link(group, &token);
+                (token, Some(guard))
+            }
+            None => (token.child_token(), None),
+        };
+        let timeout = task.timeout;
+        let error_on_nonzero = task
+            .executions
+            .iter()
+            .map(|e| e.error_on_nonzero)
+            .collect::<Vec<_>>();
+        let logs = logs::Sender::new();
+        let (status, status_rx) = watch::channel(TaskStatus::Queued);
+        let handle = TaskHandle {
+            rx,
+            token: token.clone(),
+            guard: None,
+            logs: logs.downgrade(),
+            status: status_rx,
+        };
 
         if backend.default_name() == "docker" && task.name.is_none() {
             let mut generator = self.name_generator.lock().unwrap();
@@ -107,19 +975,538 @@ impl Runner {
             task.name = Some(generator.next().unwrap());
         }
 
-        tokio::spawn(async move {
-            let _permit = lock.acquire().await?;
-            let result = backend.clone().run(task, None, token)?.await;
+        let id = events::next_id();
+
+        // The attempt is recorded before the task is identified so that its
+        // specification can be resubmitted as a new attempt
+        #[cfg(feature = "store")]
+        let store = match &self.store {
+            Some((store, runner)) => {
+                let record = crate::store::Record::new(&task, id, runner, backend.default_name())?;
+                Some((store.queued(record), store.clone()))
+            }
+            None => None,
+        };
+
+        #[cfg(feature = "store")]
+        let call_cache = self.call_cache;
+
+        task.identify(id);
+
+        // Keep the task as specified for a retry, whose placeholders are expanded
+        // with its own attempt number
+        let spec = task.retry.as_ref().map(|_| task.clone());
+
+        // Placeholders are expanded after the attempt is recorded so that
+        // resubmitted attempts are expanded with their own attempt numbers
+        if let Err(e) = task.interpolate(id) {
+            let e = anyhow::Error::from(e).context("failed to interpolate the task's variables");
+            #[cfg(feature = "store")]
+            if let Some((attempt, store)) = store {
+                let result = Err(backend::TaskRunError::Other(anyhow::anyhow!("{e:#}")));
+                self.tasks.spawn(async move {
+                    if let Some(attempt) = attempt.await {
+                        store.finished(attempt, &result);
+                    }
+                });
+            }
+
+            return Err(e);
+        }
+
+        let mut entry = audit.as_ref().map(|_| audit::Entry {
+            task: task.name.clone(),
+            backend: backend.default_name().to_string(),
+            executions: task
+                .executions
+                .iter()
+                .map(|e| audit::Invocation {
+                    image: e.image.clone(),
+                    digest: None,
+                    command: std::iter::once(e.program.clone())
+                        .chain(e.args.iter().cloned())
+                        .collect(),
+                })
+                .collect(),
+            started: 0,
+            ended: 0,
+            exit_codes: Vec::new(),
+            error: None,
+        });
+
+        let name = task.name.clone();
+        let emit = move |event: Event| {
+            status.send_modify(|s| s.apply(&event));
+            if let Some(events) = &events {
+                // Sending only fails if there are no receivers
+                let _ = events.send(event);
+            }
+        };
+
+        emit(Event::Queued {
+            id,
+            name: name.clone(),
+            backend: backend.default_name().to_string(),
+        });
+
+        // Coalesce the task onto an identical task that is waiting or running
+        let coalescing = self
+            .inflight
+            .clone()
+            .map(|inflight| (inflight, task.coalescing_key()));
+        let status_rx = handle.status.clone();
+        let mut role = coalescing
+            .as_ref()
+            .map(|(inflight, key)| coalesce(inflight, key, &logs, &status_rx));
+
+        if let Some(Role::Follower(_)) = &role {
+            trace!("coalescing task {id} onto an identical task");
+        } else {
+            queue.depth.fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.tasks.spawn(async move {
+            let _group_guard = group_guard;
+
+            #[cfg(feature = "store")]
+            let store = match store {
+                Some((attempt, store)) => attempt.await.map(|attempt| (attempt, store)),
+                None => None,
+            };
+
+            // Wait on the identical tasks this task is coalesced onto until one
+            // shares its result or this task has to run instead
+            let mut slot = slot;
+            let leader = loop {
+                let follower = match role {
+                    Some(Role::Follower(follower)) => follower,
+                    Some(Role::Leader(leader)) => break Some(leader),
+                    None => break None,
+                };
+
+                // Tasks coalesced onto others do not hold slots in the queue
+                drop(slot.take());
+                if let Some(result) = follower.wait(id, &name, &logs, &emit, &token).await {
+                    drop(logs);
+                    emit(Event::exited(id, name, &result));
+                    #[cfg(feature = "store")]
+                    if let Some((attempt, store)) = &store {
+                        store.finished(*attempt, &result);
+                    }
+
+                    let _ = tx.send(result);
+                    return Ok(());
+                }
+
+                trace!("running task {id} as the task it was coalesced onto did not complete");
+                // SAFETY: only tasks that can be coalesced follow others
+                let (inflight, key) = coalescing.as_ref().unwrap();
+                role = Some(coalesce(inflight, key, &logs, &status_rx));
+                if let Some(Role::Leader(_)) = &role {
+                    queue.depth.fetch_add(1, Ordering::Relaxed);
+                }
+            };
+
+            // The outputs of a result that can be reused are checksummed once the
+            // task finishes
+            #[cfg(feature = "store")]
+            let outputs = call_cache.then(|| {
+                task.outputs()
+                    .map(|o| o.url().to_string())
+                    .collect::<Vec<_>>()
+            });
+
+            // Reuse the result of a previous attempt of an identical task
+            #[cfg(feature = "store")]
+            if let (true, Some((attempt, store))) = (call_cache, &store) {
+                match crate::store::cache::key(&task, &*backend).await {
+                    Ok(Some(key)) => {
+                        store.keyed(*attempt, &key);
+                        match store.lookup(&key).await {
+                            Ok(Some((source, statuses))) => {
+                                trace!("reusing the result of attempt {source} for task {id}");
+                                queue.depth.fetch_sub(1, Ordering::Relaxed);
+                                drop(slot);
+
+                                store.reused(*attempt, source, &statuses);
+                                let result = Ok(statuses);
+                                emit(Event::exited(id, name, &result));
+                                if let Some(leader) = leader {
+                                    leader.finish(&result);
+                                }
+
+                                let _ = tx.send(result);
+                                return Ok(());
+                            }
+                            Ok(None) => {}
+                            Err(e) => warn!("failed to look up the call cache: {e}"),
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("failed to compute the call cache key of task {id}: {e:#}"),
+                }
+            }
+
+            let admission = select! {
+                admission = scheduler.admit(request) => Some(admission),
+                _ = token.cancelled() => None,
+                _ = shutdown.cancelled() => None,
+            };
+            queue.depth.fetch_sub(1, Ordering::Relaxed);
+            drop(slot);
+
+            let mut admitted = None;
+            let result = 'run: {
+                let admission = match admission {
+                    Some(Ok(admission)) => admitted.insert(admission),
+                    // Tasks still waiting to run when they are canceled (e.g., with
+                    // their group) or the runner shuts down are canceled, and tasks
+                    // that cannot be admitted fail; neither ran, so neither is audited
+                    Some(Err(e)) => {
+                        entry = None;
+                        break 'run Err(backend::TaskRunError::Other(e));
+                    }
+                    None => {
+                        entry = None;
+                        break 'run Err(backend::TaskRunError::Canceled);
+                    }
+                };
+
+                if let (Some(devices), Some(resources)) =
+                    (admission.gpus(), task.resources.as_mut())
+                {
+                    resources.gpus = Some(Gpus::Devices(devices.to_vec()));
+                }
+
+                if let Some(entry) = &mut entry {
+                    entry.started = audit::now();
+                }
+
+                #[cfg(feature = "store")]
+                if let Some((attempt, store)) = &store {
+                    store.started(*attempt);
+                }
+
+                emit(Event::Running {
+                    id,
+                    name: name.clone(),
+                });
+
+                let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+                let started = {
+                    let emit = emit.clone();
+                    let name = name.clone();
+                    async move {
+                        if started_rx.await.is_ok() {
+                            emit(Event::Started { id, name });
+                        }
+                    }
+                };
+                tokio::spawn(started);
+
+                let retry = task.retry.clone();
+                let mut spec = spec;
+                let mut started_tx = Some(started_tx);
+                let mut attempts = 0;
+                loop {
+                    attempts += 1;
+
+                    let images = task
+                        .executions
+                        .iter()
+                        .map(|e| e.image.as_str())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    let span = info_span!(
+                        "task",
+                        id,
+                        name = task.name.as_deref(),
+                        backend = backend.default_name(),
+                        images,
+                        attempt = task.attempt,
+                        trace_id = task.trace_context.as_ref().map(|c| c.trace_id())
+                    );
+                    // Each attempt has its own token so that an attempt that timed
+                    // out can be retried
+                    let attempt_token = token.child_token();
+                    let group = task.group.clone().map(|g| (g, task.resources.clone()));
+                    let run_started = Instant::now();
+                    metrics::global().task_started();
+                    let mut run = match backend.clone().run_with_logs(
+                        task,
+                        started_tx.take(),
+                        attempt_token.clone(),
+                        logs.clone(),
+                    ) {
+                        Ok(run) => run,
+                        // A task the backend fails to start fails like any other
+                        Err(e) => async move { Err(backend::TaskRunError::Other(e)) }.boxed(),
+                    }
+                    .instrument(span);
+                    let deadline = async move {
+                        match timeout {
+                            Some(timeout) => {
+                                tokio::time::sleep(timeout).await;
+                                timeout
+                            }
+                            None => std::future::pending().await,
+                        }
+                    };
+                    let result = select! {
+                        result = &mut run => result,
+                        timeout = deadline => {
+                            attempt_token.cancel();
+
+                            // Wait for the backend to clean up after the cancellation
+                            let _ = run.await;
+                            Err(backend::TaskRunError::TimedOut(timeout))
+                        }
+                        _ = shutdown.cancelled() => {
+                            // The backend cleans up after the cancellation (e.g., by
+                            // stopping the task's containers) before returning
+                            attempt_token.cancel();
+                            run.await
+                        }
+                    };
+
+                    // Fail the task if an execution that treats a non-zero exit as an
+                    // error has done so
+                    let result = result.and_then(|statuses| {
+                        match statuses
+                            .iter()
+                            .zip(&error_on_nonzero)
+                            .position(|(status, error)| *error && !status.success())
+                        {
+                            Some(index) => Err(backend::TaskRunError::NonZeroExit {
+                                index,
+                                statuses,
+                                tail: None,
+                            }),
+                            None => Ok(statuses),
+                        }
+                    });
+
+                    metrics::global().task_finished(run_started.elapsed(), result.is_ok());
+                    if let Some(telemetry) = &telemetry {
+                        telemetry.record(backend.default_name(), &result);
+                    }
+
+                    if let Some((group, resources)) = &group {
+                        budgets.record(group, run_started.elapsed(), resources.as_ref());
+                    }
+
+                    // Retry the task if its policy allows it
+                    let (Some(retry), Some(spec), Err(e)) = (&retry, spec.as_mut(), &result) else {
+                        break result;
+                    };
+
+                    if !retry.retries(attempts, e)
+                        || token.is_cancelled()
+                        || shutdown.is_cancelled()
+                    {
+                        break result;
+                    }
+
+                    let delay = retry.delay(attempts);
+                    warn!(
+                        "task {id} failed on attempt {attempts} and will be retried in {delay:?}: \
+                         {e:#}"
+                    );
+
+                    // The attempt's resources are released while it waits to be
+                    // retried
+                    admitted = None;
+                    select! {
+                        biased;
+
+                        _ = token.cancelled() => break Err(backend::TaskRunError::Canceled),
+                        _ = shutdown.cancelled() => break Err(backend::TaskRunError::Canceled),
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+
+                    retry.prepare(spec, e);
+                    task = spec.clone();
+                    if let Err(e) = task.interpolate(id) {
+                        break Err(backend::TaskRunError::Other(
+                            anyhow::Error::from(e)
+                                .context("failed to interpolate the task's variables"),
+                        ));
+                    }
+
+                    // The next attempt may request more memory than the last, so it
+                    // is admitted again
+                    let request = Request::new(&task);
+                    if let Err(e) = scheduler.check(&request) {
+                        break Err(backend::TaskRunError::Other(e.into()));
+                    }
+
+                    let admission = select! {
+                        admission = scheduler.admit(request) => admission,
+                        _ = token.cancelled() => break Err(backend::TaskRunError::Canceled),
+                        _ = shutdown.cancelled() => break Err(backend::TaskRunError::Canceled),
+                    };
+                    let admission = match admission {
+                        Ok(admission) => admitted.insert(admission),
+                        Err(e) => break Err(backend::TaskRunError::Other(e)),
+                    };
+
+                    if let (Some(devices), Some(resources)) =
+                        (admission.gpus(), task.resources.as_mut())
+                    {
+                        resources.gpus = Some(Gpus::Devices(devices.to_vec()));
+                    }
+                }
+            };
+
+            // The output of the task ends once every sender has been dropped
+            drop(logs);
+
+            emit(Event::exited(id, name, &result));
+
+            #[cfg(feature = "store")]
+            if let Some((attempt, store)) = &store {
+                if let (Some(outputs), Ok(_)) = (&outputs, &result) {
+                    store.checksummed(*attempt, crate::store::cache::checksums(outputs).await);
+                }
+
+                store.finished(*attempt, &result);
+            }
+
+            if let (Some(audit), Some(mut entry)) = (&audit, entry) {
+                entry.finish(&result);
+                for invocation in &mut entry.executions {
+                    invocation.digest = backend.image_digest(&invocation.image).await;
+                }
+
+                audit.record(&entry);
+            }
+
+            if let Some(leader) = leader {
+                leader.finish(&result);
+            }
 
             // NOTE: if the send does not succeed, that is almost certainly
             // because the receiver was dropped. That is a relatively standard
             // practice if you don't specifically _want_ to keep a handle to the
             // returned result, so we ignore any errors related to that.
             let _ = tx.send(result);
-            drop(_permit);
+            drop(admitted);
             anyhow::Ok(())
         });
 
-        Ok(TaskHandle(rx))
+        Ok(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_queue() {
+        let queue = TaskQueue::new(Some(Queue::builder().max_depth(1).build()));
+
+        let slot = queue.try_reserve().unwrap();
+        assert!(slot.is_some());
+
+        let err = queue.try_reserve().unwrap_err();
+        assert!(err.downcast_ref::<QueueFull>().is_some());
+        assert_eq!(queue.stats().rejected, 1);
+
+        drop(slot);
+        assert!(queue.try_reserve().unwrap().is_some());
+    }
+
+    #[test]
+    fn unbounded_queue() {
+        let queue = TaskQueue::new(None);
+        for _ in 0..100 {
+            assert!(queue.try_reserve().unwrap().is_none());
+        }
+        assert_eq!(queue.stats(), QueueStats::default());
+    }
+
+    #[test]
+    fn fans_out_results() {
+        let inflight = InFlight::default();
+        let logs = logs::Sender::new();
+        let (_, status) = watch::channel(TaskStatus::Queued);
+
+        let Role::Leader(leader) = coalesce(&inflight, "hash", &logs, &status) else {
+            panic!("the first task should lead");
+        };
+        let Role::Follower(mut follower) = coalesce(&inflight, "hash", &logs, &status) else {
+            panic!("an identical task should follow");
+        };
+
+        leader.finish(&Err(backend::TaskRunError::Other(anyhow::anyhow!(
+            "failed"
+        ))));
+
+        let result = follower.result.try_recv().unwrap();
+        assert_eq!(result.unwrap_err().to_string(), "failed");
+        assert!(inflight.lock().unwrap().is_empty());
+
+        // The result of a canceled task is not shared
+        let Role::Leader(leader) = coalesce(&inflight, "hash", &logs, &status) else {
+            panic!("the first task should lead");
+        };
+        let Role::Follower(mut follower) = coalesce(&inflight, "hash", &logs, &status) else {
+            panic!("an identical task should follow");
+        };
+        leader.finish(&Err(backend::TaskRunError::Canceled));
+        assert!(matches!(
+            follower.result.try_recv(),
+            Err(tokio::sync::oneshot::error::TryRecvError::Closed)
+        ));
+
+        // Dropping a leader stops it from accepting other tasks
+        let role = coalesce(&inflight, "hash", &logs, &status);
+        assert!(matches!(role, Role::Leader(_)));
+        drop(role);
+        assert!(inflight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn followers_relay_leaders() {
+        let inflight = InFlight::default();
+        let leader_logs = logs::Sender::new();
+        let (leader_status, status) = watch::channel(TaskStatus::Queued);
+        let Role::Leader(leader) = coalesce(&inflight, "hash", &leader_logs, &status) else {
+            panic!("the first task should lead");
+        };
+        let Role::Follower(follower) = coalesce(&inflight, "hash", &leader_logs, &status) else {
+            panic!("an identical task should follow");
+        };
+
+        let events = Mutex::new(Vec::new());
+        let emit = |event: Event| events.lock().unwrap().push(event);
+        let logs = logs::Sender::new();
+        let token = CancellationToken::new();
+        let wait = follower.wait(1, &None, &logs, &emit, &token);
+        let lead = async move {
+            tokio::task::yield_now().await;
+            leader_status.send_modify(|s| s.apply(&Event::Running { id: 0, name: None }));
+            tokio::task::yield_now().await;
+            leader_status.send_modify(|s| s.apply(&Event::Started { id: 0, name: None }));
+            tokio::task::yield_now().await;
+
+            // A canceled leader does not share its result
+            drop(leader_logs);
+            leader.finish(&Err(backend::TaskRunError::Canceled));
+        };
+
+        let (result, ()) = tokio::join!(wait, lead);
+        assert!(result.is_none());
+        assert!(matches!(
+            events.lock().unwrap().as_slice(),
+            [Event::Running { id: 1, .. }, Event::Started { id: 1, .. }]
+        ));
+
+        // The follower runs instead of the canceled leader
+        assert!(matches!(
+            coalesce(&inflight, "hash", &logs, &status),
+            Role::Leader(_)
+        ));
     }
 }
@@ -0,0 +1,320 @@
+//! Load-aware adaptation of the number of concurrently running tasks.
+
+use std::sync::Arc;
+use std::sync::Weak;
+use std::time::Duration;
+
+use crankshaft_config::backend::Adaptive;
+use tokio::select;
+use tokio::sync::Semaphore;
+use tokio::sync::SemaphorePermit;
+use tracing::debug;
+use tracing::warn;
+
+/// The path to the load average information.
+const LOADAVG_PATH: &str = "/proc/loadavg";
+
+/// The path to the CPU pressure stall information.
+const CPU_PRESSURE_PATH: &str = "/proc/pressure/cpu";
+
+/// Parses the one minute load average from the contents of `/proc/loadavg`.
+fn parse_loadavg(contents: &str) -> Option<f64> {
+    contents.split_whitespace().next()?.parse().ok()
+}
+
+/// Parses the ten second `some` average from the contents of
+/// `/proc/pressure/cpu`.
+fn parse_cpu_pressure(contents: &str) -> Option<f64> {
+    contents
+        .lines()
+        .find(|line| line.starts_with("some "))?
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("avg10="))?
+        .parse()
+        .ok()
+}
+
+/// Samples the host and determines whether it is under pressure.
+///
+/// Returns `None` if none of the configured load metrics could be read.
+fn under_pressure(config: &Adaptive, cpus: f64) -> Option<bool> {
+    let mut sampled = false;
+    let mut pressured = false;
+
+    if let Some(max) = config.max_load_per_cpu() {
+        if let Some(load) = std::fs::read_to_string(LOADAVG_PATH)
+            .ok()
+            .as_deref()
+            .and_then(parse_loadavg)
+        {
+            sampled = true;
+            pressured |= load / cpus > max;
+        }
+    }
+
+    if let Some(max) = config.max_cpu_pressure() {
+        if let Some(pressure) = std::fs::read_to_string(CPU_PRESSURE_PATH)
+            .ok()
+            .as_deref()
+            .and_then(parse_cpu_pressure)
+        {
+            sampled = true;
+            pressured |= pressure > max;
+        }
+    }
+
+    sampled.then_some(pressured)
+}
+
+/// The permits withheld from a semaphore to adapt to the load of the host.
+///
+/// Withheld permits are forgotten rather than held so that the semaphore can
+/// be dropped while permits are withheld.
+#[derive(Debug)]
+struct Adapter {
+    /// The number of permits withheld from the semaphore.
+    withheld: usize,
+
+    /// The number of permits to withhold once they are released, as every
+    /// permit was in use when they were to be withheld.
+    pending: usize,
+
+    /// The maximum number of permits that may be withheld.
+    max_withheld: usize,
+
+    /// The number of permits of the semaphore when none are withheld.
+    max_tasks: usize,
+
+    /// Whether a warning has been emitted for being unable to sample the host.
+    warned: bool,
+}
+
+impl Adapter {
+    /// Adapts the permits available in `semaphore` to a sample of the host.
+    fn adapt(&mut self, semaphore: &Semaphore, pressure: Option<bool>) {
+        match pressure {
+            Some(true) if self.withheld + self.pending < self.max_withheld => {
+                match semaphore.try_acquire() {
+                    Ok(permit) => self.withhold(permit),
+                    // Every permit is in use, so one is withheld once released
+                    Err(_) => self.pending += 1,
+                }
+            }
+            Some(false) => {
+                if self.pending > 0 {
+                    self.pending -= 1;
+                } else if self.withheld > 0 {
+                    semaphore.add_permits(1);
+                    self.withheld -= 1;
+                    debug!(
+                        "host pressure has subsided; increased concurrency to {tasks} task(s)",
+                        tasks = self.max_tasks - self.withheld
+                    );
+                }
+            }
+            Some(true) => {}
+            None => {
+                // Without load information, run at full concurrency
+                semaphore.add_permits(self.withheld);
+                self.withheld = 0;
+                self.pending = 0;
+
+                if !self.warned {
+                    warn!(
+                        "unable to read host load information; concurrency will not be adapted to \
+                         host load"
+                    );
+                    self.warned = true;
+                }
+            }
+        }
+    }
+
+    /// Withholds a permit acquired from the semaphore.
+    fn withhold(&mut self, permit: SemaphorePermit<'_>) {
+        permit.forget();
+        self.withheld += 1;
+        debug!(
+            "host is under pressure; reduced concurrency to {tasks} task(s)",
+            tasks = self.max_tasks - self.withheld
+        );
+    }
+}
+
+/// Spawns a task that adapts the permits available in `semaphore` to the load
+/// of the host.
+///
+/// While the host is under pressure, a permit is withheld from the semaphore
+/// on each sample (down to the configured minimum number of tasks); if every
+/// permit is in use, the permit is withheld once a task releases it. Once the
+/// pressure subsides, a withheld permit is returned on each sample. Every
+/// withheld permit is returned if the host's load cannot be sampled.
+///
+/// The spawned task exits when the semaphore is dropped.
+pub(crate) fn spawn(config: Adaptive, semaphore: &Arc<Semaphore>, max_tasks: usize) {
+    let interval = Duration::from_secs(config.interval());
+    let max_withheld = max_tasks.saturating_sub(config.min_tasks().max(1));
+    let cpus = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1) as f64;
+
+    tokio::spawn(run(
+        Arc::downgrade(semaphore),
+        interval,
+        Adapter {
+            withheld: 0,
+            pending: 0,
+            max_withheld,
+            max_tasks,
+            warned: false,
+        },
+        move || under_pressure(&config, cpus),
+    ));
+}
+
+/// Samples the host with `sample` on every interval and adapts the permits
+/// available in `semaphore` until the semaphore is dropped.
+async fn run(
+    semaphore: Weak<Semaphore>,
+    interval: Duration,
+    mut adapter: Adapter,
+    mut sample: impl FnMut() -> Option<bool>,
+) {
+    let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+
+    while let Some(semaphore) = semaphore.upgrade() {
+        select! {
+            _ = ticks.tick() => adapter.adapt(&semaphore, sample()),
+            // Withhold the permits still to be withheld as tasks release them
+            permit = semaphore.acquire(), if adapter.pending > 0 => {
+                if let Ok(permit) = permit {
+                    adapter.pending -= 1;
+                    adapter.withhold(permit);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_loadavg() {
+        assert_eq!(parse_loadavg("0.52 0.58 0.59 2/1011 12345\n"), Some(0.52));
+        assert_eq!(parse_loadavg(""), None);
+    }
+
+    #[test]
+    fn parses_cpu_pressure() {
+        let contents = "some avg10=12.50 avg60=3.00 avg300=1.00 total=123456\nfull avg10=0.00 \
+                        avg60=0.00 avg300=0.00 total=0\n";
+        assert_eq!(parse_cpu_pressure(contents), Some(12.5));
+        assert_eq!(parse_cpu_pressure("full avg10=1.00\n"), None);
+    }
+
+    #[test]
+    fn adapts_permits() {
+        let semaphore = Semaphore::new(4);
+        let mut adapter = Adapter {
+            withheld: 0,
+            pending: 0,
+            max_withheld: 2,
+            max_tasks: 4,
+            warned: false,
+        };
+
+        for _ in 0..3 {
+            adapter.adapt(&semaphore, Some(true));
+        }
+        assert_eq!(semaphore.available_permits(), 2);
+
+        adapter.adapt(&semaphore, Some(false));
+        assert_eq!(semaphore.available_permits(), 3);
+
+        // Every withheld permit is returned once the host cannot be sampled
+        adapter.adapt(&semaphore, Some(true));
+        adapter.adapt(&semaphore, None);
+        assert_eq!(semaphore.available_permits(), 4);
+        assert_eq!(adapter.withheld, 0);
+
+        // Permits in use are withheld once they are released
+        let permits = semaphore.try_acquire_many(4).unwrap();
+        adapter.adapt(&semaphore, Some(true));
+        assert_eq!(adapter.pending, 1);
+        adapter.adapt(&semaphore, Some(false));
+        assert_eq!(adapter.pending, 0);
+        drop(permits);
+    }
+
+    #[tokio::test]
+    async fn withholds_permits_in_use() {
+        let semaphore = Arc::new(Semaphore::new(2));
+        let permits = semaphore.clone().try_acquire_many_owned(2).unwrap();
+        let adapter = Adapter {
+            withheld: 0,
+            pending: 0,
+            max_withheld: 1,
+            max_tasks: 2,
+            warned: false,
+        };
+        let handle = tokio::spawn(run(
+            Arc::downgrade(&semaphore),
+            Duration::from_millis(1),
+            adapter,
+            || Some(true),
+        ));
+
+        // Every permit is held by a task while the host is under pressure
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(permits);
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while semaphore.available_permits() != 1 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // No more than the maximum number of permits is withheld
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(semaphore.available_permits(), 1);
+
+        drop(semaphore);
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn exits_when_semaphore_dropped() {
+        let semaphore = Arc::new(Semaphore::new(4));
+        let adapter = Adapter {
+            withheld: 0,
+            pending: 0,
+            max_withheld: 3,
+            max_tasks: 4,
+            warned: false,
+        };
+        let handle = tokio::spawn(run(
+            Arc::downgrade(&semaphore),
+            Duration::from_millis(1),
+            adapter,
+            || Some(true),
+        ));
+
+        while semaphore.available_permits() > 1 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        // The task exits even though it is withholding permits
+        drop(semaphore);
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::fmt::Debug;
 use std::process::ExitStatus;
+use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -11,13 +12,16 @@ use tokio::sync::oneshot;
 use tokio_util::sync::CancellationToken;
 
 use crate::Task;
+use crate::logs;
 
+pub mod chaos;
 pub mod docker;
 pub mod generic;
 pub mod tes;
 
 /// Represents an error that may occur when running a task.
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum TaskRunError {
     /// The task has been canceled.
     #[error("the task has been canceled")]
@@ -28,17 +32,161 @@ pub enum TaskRunError {
     /// tasks.
     #[error("the task has been preempted")]
     Preempted,
+    /// An execution of the task that treats a non-zero exit as an error has
+    /// exited with a non-zero status.
+    ///
+    /// The statuses of the executions that ran are included, along with the
+    /// end of the failed execution's output if the backend kept it.
+    #[error(
+        "execution {index} of the task exited with {status}{tail}",
+        status = statuses[*index],
+        tail = tail.as_ref().map(|t| format!("\n{t}")).unwrap_or_default()
+    )]
+    NonZeroExit {
+        /// The index of the execution that exited with a non-zero status.
+        index: usize,
+        /// The exit statuses of the executions that ran.
+        statuses: NonEmpty<ExitStatus>,
+        /// The end of the failed execution's stdout and stderr (if kept).
+        tail: Option<String>,
+    },
+    /// An execution of the task was killed for exceeding its memory limit.
+    ///
+    /// This error is only returned from backends that can detect when an
+    /// execution runs out of memory; other backends report such executions as
+    /// having exited with a non-zero status (see [`Self::signal()`]).
+    #[error(
+        "execution {index} of the task was killed for exceeding its memory limit ({status})",
+        status = statuses[*index]
+    )]
+    OutOfMemory {
+        /// The index of the execution that ran out of memory.
+        index: usize,
+        /// The exit statuses of the executions that ran.
+        statuses: NonEmpty<ExitStatus>,
+    },
+    /// The task exceeded its wall-clock limit and has been canceled.
+    #[error("the task timed out after {0:?}")]
+    TimedOut(Duration),
     /// Another error occurred while running the task.
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
+impl TaskRunError {
+    /// Gets the signal that likely terminated the failed execution of the
+    /// task (if any).
+    ///
+    /// See [`signal()`] for how the signal is determined.
+    pub fn signal(&self) -> Option<i32> {
+        match self {
+            Self::NonZeroExit {
+                index, statuses, ..
+            }
+            | Self::OutOfMemory { index, statuses } => signal(&statuses[*index]),
+            _ => None,
+        }
+    }
+
+    /// Determines if the task failed because the backend detected that an
+    /// execution was killed for exceeding its memory limit.
+    ///
+    /// An execution killed with `SIGKILL` is not assumed to have run out of
+    /// memory, as it may have been killed for another reason (e.g., by a
+    /// timeout or by its runtime).
+    pub fn out_of_memory(&self) -> bool {
+        matches!(self, Self::OutOfMemory { .. })
+    }
+}
+
+/// Gets the signal that likely terminated an execution (if any).
+///
+/// Containers terminated by a signal exit with a code of `128` plus the
+/// signal's number, so the signal is inferred from such codes when the
+/// status does not report a signal directly (a program may also exit with
+/// such a code itself).
+pub fn signal(status: &ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(status) {
+        return Some(signal);
+    }
+
+    // Only the standard signals (1 to 31) are considered
+    status
+        .code()
+        .filter(|code| (129..=159).contains(code))
+        .map(|code| code - 128)
+}
+
+/// The result of a task run by a previous process.
+type Recovery = BoxFuture<'static, Result<NonEmpty<ExitStatus>, TaskRunError>>;
+
+/// A task left running by a previous process (e.g., one that crashed) that a
+/// backend has reattached to.
+#[non_exhaustive]
+pub struct Reattached {
+    /// The name of the task.
+    pub name: String,
+
+    /// Waits for the task to complete, recovering its result.
+    ///
+    /// The task is stopped if the given token is canceled.
+    pub wait: Box<dyn FnOnce(CancellationToken) -> Recovery + Send>,
+}
+
+impl Reattached {
+    /// Creates a reattached task.
+    pub fn new(
+        name: impl Into<String>,
+        wait: impl FnOnce(CancellationToken) -> Recovery + Send + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            wait: Box::new(wait),
+        }
+    }
+}
+
+impl Debug for Reattached {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reattached")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
 /// An execution backend.
 #[async_trait]
 pub trait Backend: Debug + Send + Sync + 'static {
     /// Gets the default name for the backend.
     fn default_name(&self) -> &'static str;
 
+    /// Gets the digest of an image, if the backend is able to determine it.
+    ///
+    /// This is used to record the exact image that a task ran within (e.g.,
+    /// in an audit log). By default, no digest is reported.
+    async fn image_digest(&self, _image: &str) -> Option<String> {
+        None
+    }
+
+    /// Resolves an image reference to a reference pinned to the digest the
+    /// image currently has (e.g., `ubuntu:22.04` to `ubuntu@sha256:...`).
+    ///
+    /// Returns `None` if the backend is unable to resolve image references,
+    /// which is the default.
+    async fn resolve_image(&self, _image: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Reattaches to the tasks left running by a previous process (e.g., one
+    /// that crashed) so that their results are recovered rather than lost.
+    ///
+    /// Each task is only reattached to once. By default, no tasks are
+    /// reattached to.
+    async fn reattach(&self) -> Result<Vec<Reattached>> {
+        Ok(Vec::new())
+    }
+
     /// Runs a task in a backend.
     ///
     /// The optional `started` channel is notified when the first execution of
@@ -52,4 +200,23 @@ pub trait Backend: Debug + Send + Sync + 'static {
         started: Option<oneshot::Sender<()>>,
         token: CancellationToken,
     ) -> Result<BoxFuture<'static, Result<NonEmpty<ExitStatus>, TaskRunError>>>;
+
+    /// Runs a task in a backend, sending the lines of its output to `logs`
+    /// while it runs.
+    ///
+    /// The output of the task ends once `logs` is dropped, so the returned
+    /// future should own it until the task completes.
+    ///
+    /// By default, the output is not sent and the task is run with
+    /// [`Self::run()`].
+    fn run_with_logs(
+        &self,
+        task: Task,
+        started: Option<oneshot::Sender<()>>,
+        token: CancellationToken,
+        logs: logs::Sender,
+    ) -> Result<BoxFuture<'static, Result<NonEmpty<ExitStatus>, TaskRunError>>> {
+        drop(logs);
+        self.run(task, started, token)
+    }
 }
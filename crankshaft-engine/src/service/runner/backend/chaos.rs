@@ -0,0 +1,239 @@
+//! A backend that injects faults into the tasks of another backend.
+
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt as _;
+#[cfg(windows)]
+use std::os::windows::process::ExitStatusExt as _;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use anyhow::ensure;
+use async_trait::async_trait;
+use crankshaft_config::backend::Chaos;
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use nonempty::NonEmpty;
+use rand::Rng as _;
+use tokio::select;
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use super::Reattached;
+use super::TaskRunError;
+use crate::Task;
+use crate::logs;
+
+/// The exit code reported for a task simulated to be killed due to running out
+/// of memory.
+///
+/// This is the exit code of a process killed by `SIGKILL` (i.e., `128 + 9`),
+/// which is what the kernel's out-of-memory killer sends.
+const OOM_EXIT_CODE: i32 = 137;
+
+/// A fault that may be injected into a task.
+#[derive(Debug, PartialEq, Eq)]
+enum Fault {
+    /// The task fails with an injected error.
+    Failure,
+    /// The task is reported as killed due to running out of memory.
+    Oom,
+}
+
+/// A backend that injects faults into the tasks of another backend.
+#[derive(Debug)]
+pub struct Backend {
+    /// The backend that runs the tasks.
+    inner: Arc<dyn crate::Backend>,
+
+    /// The fault injection settings.
+    config: Chaos,
+}
+
+impl Backend {
+    /// Creates a new backend that injects faults into the tasks of the given
+    /// backend.
+    pub fn new(inner: Arc<dyn crate::Backend>, config: Chaos) -> Result<Self> {
+        ensure!(
+            (0.0..=1.0).contains(&config.failure_rate()),
+            "chaos failure rate must be between 0.0 and 1.0"
+        );
+        ensure!(
+            (0.0..=1.0).contains(&config.oom_rate()),
+            "chaos OOM rate must be between 0.0 and 1.0"
+        );
+
+        Ok(Self { inner, config })
+    }
+
+    /// Chooses the fault (if any) to inject given a random sample in `[0, 1)`.
+    fn fault(&self, sample: f64) -> Option<Fault> {
+        if sample < self.config.failure_rate() {
+            Some(Fault::Failure)
+        } else if sample < self.config.failure_rate() + self.config.oom_rate() {
+            Some(Fault::Oom)
+        } else {
+            None
+        }
+    }
+
+    /// Runs a task in the inner backend, injecting a fault if one is drawn.
+    fn run_chaotically(
+        &self,
+        task: Task,
+        started: Option<oneshot::Sender<()>>,
+        token: CancellationToken,
+        logs: Option<logs::Sender>,
+    ) -> Result<BoxFuture<'static, Result<NonEmpty<ExitStatus>, TaskRunError>>> {
+        let mut rng = rand::rng();
+        let latency = self
+            .config
+            .max_latency()
+            .map(|max| Duration::from_millis(rng.random_range(0..=max)));
+        let fault = self.fault(rng.random());
+
+        let name = task
+            .name
+            .clone()
+            .unwrap_or_else(|| String::from("<unnamed>"));
+        let run = match fault {
+            Some(_) => None,
+            None => Some(match logs {
+                Some(logs) => self
+                    .inner
+                    .run_with_logs(task, started, token.clone(), logs)?,
+                None => self.inner.run(task, started, token.clone())?,
+            }),
+        };
+
+        Ok(async move {
+            if let Some(latency) = latency {
+                info!("chaos: delaying task `{name}` by {latency:?}");
+
+                select! {
+                    biased;
+
+                    _ = token.cancelled() => return Err(TaskRunError::Canceled),
+                    _ = tokio::time::sleep(latency) => {}
+                }
+            }
+
+            match (fault, run) {
+                (Some(Fault::Failure), _) => {
+                    info!("chaos: injecting a failure into task `{name}`");
+                    Err(TaskRunError::Other(anyhow!(
+                        "injected failure for task `{name}`"
+                    )))
+                }
+                (Some(Fault::Oom), _) => {
+                    info!("chaos: simulating an out-of-memory kill of task `{name}`");
+
+                    // See WEXITSTATUS from wait(2) to explain the shift
+                    #[cfg(unix)]
+                    let status = ExitStatus::from_raw(OOM_EXIT_CODE << 8);
+
+                    #[cfg(windows)]
+                    let status = ExitStatus::from_raw(OOM_EXIT_CODE as u32);
+
+                    Err(TaskRunError::OutOfMemory {
+                        index: 0,
+                        statuses: NonEmpty::new(status),
+                    })
+                }
+                (None, Some(run)) => run.await,
+                (None, None) => unreachable!("the task should run without a fault"),
+            }
+        }
+        .boxed())
+    }
+}
+
+#[async_trait]
+impl crate::Backend for Backend {
+    fn default_name(&self) -> &'static str {
+        self.inner.default_name()
+    }
+
+    async fn image_digest(&self, image: &str) -> Option<String> {
+        self.inner.image_digest(image).await
+    }
+
+    async fn resolve_image(&self, image: &str) -> Result<Option<String>> {
+        self.inner.resolve_image(image).await
+    }
+
+    async fn reattach(&self) -> Result<Vec<Reattached>> {
+        self.inner.reattach().await
+    }
+
+    fn run(
+        &self,
+        task: Task,
+        started: Option<oneshot::Sender<()>>,
+        token: CancellationToken,
+    ) -> Result<BoxFuture<'static, Result<NonEmpty<ExitStatus>, TaskRunError>>> {
+        self.run_chaotically(task, started, token, None)
+    }
+
+    fn run_with_logs(
+        &self,
+        task: Task,
+        started: Option<oneshot::Sender<()>>,
+        token: CancellationToken,
+        logs: logs::Sender,
+    ) -> Result<BoxFuture<'static, Result<NonEmpty<ExitStatus>, TaskRunError>>> {
+        self.run_chaotically(task, started, token, Some(logs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Noop;
+
+    #[async_trait]
+    impl crate::Backend for Noop {
+        fn default_name(&self) -> &'static str {
+            "noop"
+        }
+
+        fn run(
+            &self,
+            _: Task,
+            _: Option<oneshot::Sender<()>>,
+            _: CancellationToken,
+        ) -> Result<BoxFuture<'static, Result<NonEmpty<ExitStatus>, TaskRunError>>> {
+            Ok(async { Ok(NonEmpty::new(ExitStatus::default())) }.boxed())
+        }
+    }
+
+    fn backend(failure_rate: f64, oom_rate: f64) -> Result<Backend> {
+        Backend::new(
+            Arc::new(Noop),
+            Chaos::builder()
+                .failure_rate(failure_rate)
+                .oom_rate(oom_rate)
+                .build(),
+        )
+    }
+
+    #[test]
+    fn faults() {
+        let backend = backend(0.25, 0.5).unwrap();
+        assert_eq!(backend.fault(0.0), Some(Fault::Failure));
+        assert_eq!(backend.fault(0.3), Some(Fault::Oom));
+        assert_eq!(backend.fault(0.75), None);
+        assert_eq!(backend.fault(0.9), None);
+    }
+
+    #[test]
+    fn invalid_rates() {
+        assert!(backend(1.5, 0.0).is_err());
+        assert!(backend(0.0, -0.1).is_err());
+    }
+}
@@ -1,15 +1,23 @@
 //! A Docker backend.
 
+use std::collections::HashSet;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::ExitStatus;
 use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
 
 use anyhow::Context;
 use anyhow::Result;
 use anyhow::anyhow;
 use anyhow::bail;
 use async_trait::async_trait;
+use bollard::secret::ContainerSummaryStateEnum;
 use bollard::secret::HostConfig;
 use bollard::secret::LocalNodeState;
 use bollard::secret::Mount;
@@ -17,10 +25,27 @@ use bollard::secret::MountTypeEnum;
 use bollard::secret::NodeSpecAvailabilityEnum;
 use bollard::secret::NodeState;
 use crankshaft_config::backend::docker::Config;
+use crankshaft_config::backend::docker::OutputEncoding;
+use crankshaft_docker::Capabilities;
 use crankshaft_docker::Container;
 use crankshaft_docker::Docker;
+use crankshaft_docker::Encoding;
+use crankshaft_docker::ImageCache;
+use crankshaft_docker::ImagePolicy;
+use crankshaft_docker::Namespace;
+use crankshaft_docker::NamespaceOptions;
+use crankshaft_docker::OutputStream;
+use crankshaft_docker::PullLimits;
+use crankshaft_docker::PullOptions;
+use crankshaft_docker::PullSource;
+use crankshaft_docker::RegistryAuth;
+use crankshaft_docker::RetryPolicy;
+use crankshaft_docker::SchedulingOptions;
+use crankshaft_docker::certs::host_ca_bundle;
+use crankshaft_docker::normalize_arch;
 use crankshaft_docker::service::Service;
 use futures::FutureExt;
+use futures::StreamExt as _;
 use futures::future::BoxFuture;
 use nonempty::NonEmpty;
 use tempfile::TempDir;
@@ -29,10 +54,72 @@ use tokio::sync::oneshot;
 use tokio_util::sync::CancellationToken;
 use tracing::debug;
 use tracing::info;
+use tracing::warn;
 
+use super::Reattached;
 use super::TaskRunError;
 use crate::Task;
+use crate::logs;
+use crate::metrics;
 use crate::task::Input;
+use crate::task::Output;
+use crate::task::Resources as TaskResources;
+use crate::task::input::Contents;
+use crate::task::input::download;
+use crate::task::output::Type as OutputType;
+
+mod hostenv;
+mod logdir;
+mod preflight;
+mod script;
+mod workdir;
+
+use logdir::LogDir;
+pub use preflight::InsufficientDisk;
+use workdir::GUEST_WORK_DIR;
+use workdir::WorkDir;
+
+/// The maximum number of inputs that are fetched concurrently for a task.
+const MAX_CONCURRENT_INPUT_FETCHES: usize = 8;
+
+/// The interval at which the resource usage of running containers is
+/// reported.
+const USAGE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The label set on the containers created by the Docker backend.
+///
+/// The value of the label identifies the process that created the container.
+pub const INSTANCE_LABEL: &str = "crankshaft.instance";
+
+/// The identifier of this process used as the value of [`INSTANCE_LABEL`].
+static INSTANCE: LazyLock<String> = LazyLock::new(|| uuid::Uuid::new_v4().to_string());
+
+/// The label set to the name of the task a container runs.
+const TASK_LABEL: &str = "crankshaft.task";
+
+/// The label set to the path on the host that a container's stdout is written
+/// to (if any).
+const STDOUT_LABEL: &str = "crankshaft.stdout";
+
+/// The label set to the path on the host that a container's stderr is written
+/// to (if any).
+const STDERR_LABEL: &str = "crankshaft.stderr";
+
+/// The label set to the path of the working directory of a container's task
+/// (if it has one).
+const WORK_DIR_LABEL: &str = "crankshaft.work-dir";
+
+/// The containers of interrupted tasks that this process has reattached to.
+static REATTACHED: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(Default::default);
+
+/// Converts an error from the Docker client, adding its diagnosis (if any) as
+/// context so that the failure is reported with a hint for fixing it.
+fn diagnosed(e: crankshaft_docker::Error) -> anyhow::Error {
+    match e.diagnosis() {
+        Some(diagnosis) => anyhow::Error::from(e).context(diagnosis),
+        None => e.into(),
+    }
+}
 
 /// Represents resource information about a Docker swarm.
 #[derive(Debug, Default, Clone, Copy)]
@@ -128,10 +215,18 @@ impl Resources {
 pub struct Backend {
     /// A handle to the inner docker client.
     client: Docker,
+    /// The cache of images that have been ensured to exist.
+    images: ImageCache,
     /// Configuration for the backend.
     config: Config,
     /// The available resources reported by Docker.
     resources: Resources,
+    /// The resource limits supported by the local Docker daemon.
+    capabilities: Capabilities,
+    /// The root directory of the Docker daemon (if it is on the local host).
+    docker_root: Option<PathBuf>,
+    /// The number of executions that have used a deprecated image.
+    deprecated_image_uses: Arc<AtomicU64>,
 }
 
 impl Backend {
@@ -149,6 +244,19 @@ impl Backend {
             .await
             .context("failed to retrieve local Docker daemon information")?;
 
+        if config.script_wrapper() && config.work_dir_root().is_none() {
+            bail!(
+                "the Docker backend's `script-wrapper` option requires `work-dir-root` to be set"
+            );
+        }
+
+        if let Some(requirement) = config.min_version() {
+            client
+                .require_version(requirement)
+                .await
+                .context("the local Docker daemon is not supported")?;
+        }
+
         // Check to see if the daemon is part of an active swarm or not
         // If the daemon is part of a swarm, but the node is not active or a manager, we
         // can't spawn tasks
@@ -286,11 +394,94 @@ impl Backend {
             }
         };
 
-        Ok(Self {
+        // Probe the resource limits the daemon supports so that unsupported
+        // limits can be adjusted rather than failing (or being silently
+        // ignored) when containers are created
+        let capabilities = Capabilities::from(&info);
+        let unsupported = capabilities.unsupported();
+        if !unsupported.is_empty() && matches!(resources, Resources::Local(_)) {
+            warn!(
+                "the local Docker daemon does not support {unsupported}; these limits will not be \
+                 applied to tasks",
+                unsupported = unsupported.join(", ")
+            );
+        }
+
+        if let Some(arch) = config.arch() {
+            let requested = normalize_arch(arch)
+                .with_context(|| format!("unsupported architecture `{arch}`"))?;
+
+            if let (Resources::Local(_), Some(host)) = (&resources, &info.architecture) {
+                let normalized = normalize_arch(host).unwrap_or(host);
+                if normalized != requested {
+                    bail!(
+                        "the Docker backend is configured to pull images for architecture \
+                         `{requested}`, but the local Docker daemon's architecture is `{host}`"
+                    );
+                }
+            }
+        }
+
+        let mut limits = PullLimits::new();
+        if let Some(max) = config.max_concurrent_pulls() {
+            limits = limits.max_concurrent(max);
+        }
+        if let Some(max) = config.max_concurrent_pulls_per_registry() {
+            limits = limits.max_concurrent_per_registry(max);
+        }
+
+        let mut options = PullOptions::default()
+            .retry_policy(RetryPolicy::default().max_attempts(config.max_pull_attempts()))
+            .limits(limits);
+        if let Some(arch) = config.arch() {
+            options = options.arch(arch);
+        }
+        if let Some(auth) = RegistryAuth::from_env() {
+            options = options.auth(auth);
+        }
+        for (image, sources) in config.image_sources() {
+            let sources = sources
+                .iter()
+                .map(|s| s.parse::<PullSource>())
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("invalid sources for image `{image}`"))?;
+            options = options.sources(image, sources);
+        }
+
+        // The daemon's root directory is only checked for free space if it is
+        // on this host
+        let docker_root = info
+            .docker_root_dir
+            .map(PathBuf::from)
+            .filter(|root| root.is_dir());
+
+        let policy = config
+            .required_labels()
+            .iter()
+            .fold(ImagePolicy::new(), |policy, label| {
+                policy.require_label(label)
+            });
+
+        let backend = Self {
+            images: client
+                .image_cache()
+                .with_pull_options(options)
+                .with_policy(policy),
             client,
             config,
             resources,
-        })
+            capabilities,
+            docker_root,
+            deprecated_image_uses: Default::default(),
+        };
+
+        if backend.config.reconcile() {
+            if let Err(e) = backend.reconcile().await {
+                warn!("failed to clean up after interrupted tasks: {e:#}");
+            }
+        }
+
+        Ok(backend)
     }
 
     /// Attempts to initialize a new Docker [`Backend`] with the default
@@ -306,6 +497,124 @@ impl Backend {
     pub fn resources(&self) -> &Resources {
         &self.resources
     }
+
+    /// Gets the number of executions that have used a deprecated image (see
+    /// [`Config::deprecated_images()`]).
+    pub fn deprecated_image_uses(&self) -> u64 {
+        self.deprecated_image_uses.load(Ordering::Relaxed)
+    }
+
+    /// Gets the namespace isolation options of the containers of tasks.
+    fn namespaces(&self) -> NamespaceOptions {
+        let namespace = |host| {
+            if host {
+                Namespace::Host
+            } else {
+                Namespace::Private
+            }
+        };
+
+        let options = NamespaceOptions::default()
+            .pid(namespace(self.config.host_pid()))
+            .ipc(namespace(self.config.host_ipc()))
+            .uts(namespace(self.config.host_uts()));
+
+        match self.config.init() {
+            Some(init) => options.init(init),
+            None => options,
+        }
+    }
+
+    /// Gets the scheduling priority options of the containers of tasks.
+    fn scheduling(&self) -> SchedulingOptions {
+        let mut options = SchedulingOptions::default();
+        if let Some(nice) = self.config.nice() {
+            options = options.nice(nice);
+        }
+
+        if let Some(level) = self.config.io_priority() {
+            options = options.io_priority(level);
+        }
+
+        if let Some(cpus) = self.config.cpuset() {
+            options = options.cpus(cpus);
+        }
+
+        options
+    }
+
+    /// Removes the containers and working directories left behind by tasks
+    /// that were interrupted (e.g., by a crash of a previous process).
+    ///
+    /// Containers labeled with [`INSTANCE_LABEL`] by another process are
+    /// removed by force, as are the working directories of tasks that did not
+    /// complete (see [`Config::work_dir_root()`]). As containers are matched
+    /// by their label alone, this must not be called while another process
+    /// runs tasks against the same Docker daemon.
+    ///
+    /// If [`Config::reattach()`] is set, containers that are still running
+    /// (and their working directories) are kept so that they can be
+    /// reattached to (see [`Backend::reattach()`](crate::Backend::reattach)).
+    ///
+    /// Returns the number of containers and working directories removed.
+    pub async fn reconcile(&self) -> Result<(usize, usize)> {
+        let containers = self
+            .client
+            .list_labeled_containers(INSTANCE_LABEL)
+            .await
+            .context("failed to list containers")?;
+
+        let mut removed = 0;
+        for summary in containers {
+            let owner = summary
+                .labels
+                .as_ref()
+                .and_then(|labels| labels.get(INSTANCE_LABEL));
+            let id = match summary.id {
+                Some(id) if owner != Some(&*INSTANCE) => id,
+                _ => continue,
+            };
+
+            if self.config.reattach() && summary.state == Some(ContainerSummaryStateEnum::RUNNING) {
+                if let Some(path) = summary.labels.as_ref().and_then(|l| l.get(WORK_DIR_LABEL)) {
+                    workdir::retain(PathBuf::from(path));
+                }
+
+                continue;
+            }
+
+            if REATTACHED.lock().unwrap().contains(&id) {
+                continue;
+            }
+
+            info!("removing container `{id}` of an interrupted task");
+            self.client
+                .container_from_name(&id, None, None)
+                .force_remove()
+                .await
+                .with_context(|| format!("failed to remove container `{id}`"))?;
+            removed += 1;
+        }
+
+        let work_dirs = match self.config.work_dir_root() {
+            Some(root) => {
+                let root = root.to_path_buf();
+                tokio::task::spawn_blocking(move || workdir::reconcile(&root))
+                    .await
+                    .context("failed to remove working directories")??
+            }
+            None => Vec::new(),
+        };
+
+        for path in &work_dirs {
+            info!(
+                "removed working directory `{path}` of an interrupted task",
+                path = path.display()
+            );
+        }
+
+        Ok((removed, work_dirs.len()))
+    }
 }
 
 #[async_trait]
@@ -314,11 +623,125 @@ impl crate::Backend for Backend {
         "docker"
     }
 
+    async fn reattach(&self) -> Result<Vec<Reattached>> {
+        let containers = self
+            .client
+            .list_labeled_containers(INSTANCE_LABEL)
+            .await
+            .context("failed to list containers")?;
+
+        let mut reattached = Vec::new();
+        for summary in containers {
+            let labels = summary.labels.unwrap_or_default();
+            let id = match summary.id {
+                Some(id)
+                    if labels.get(INSTANCE_LABEL) != Some(&*INSTANCE)
+                        && summary.state == Some(ContainerSummaryStateEnum::RUNNING) =>
+                {
+                    id
+                }
+                _ => continue,
+            };
+
+            // Only reattach to each container once
+            if !REATTACHED.lock().unwrap().insert(id.clone()) {
+                continue;
+            }
+
+            let name = labels
+                .get(TASK_LABEL)
+                .cloned()
+                .unwrap_or_else(|| id.clone());
+            let container = self.client.container_from_name(
+                &id,
+                labels.get(STDOUT_LABEL).map(PathBuf::from),
+                labels.get(STDERR_LABEL).map(PathBuf::from),
+            );
+            let mut work_dir = labels
+                .get(WORK_DIR_LABEL)
+                .map(|path| WorkDir::adopt(PathBuf::from(path), self.config.preserve_work_dirs()));
+            let cleanup = self.config.cleanup();
+            let grace = self.config.stop_grace_period().map(Duration::from_secs);
+
+            info!("reattaching to container `{id}` of interrupted task `{name}`");
+            reattached.push(Reattached::new(name, move |token: CancellationToken| {
+                async move {
+                    let result = select! {
+                        biased;
+
+                        _ = token.cancelled() => {
+                            if let Some(grace) = grace {
+                                if let Err(e) = container.stop(Some(grace)).await {
+                                    warn!("failed to stop container `{id}`: {e:#}");
+                                }
+                            }
+
+                            Err(TaskRunError::Canceled)
+                        }
+                        status = container.wait() => status
+                            .with_context(|| format!("failed to wait for container `{id}`"))
+                            .map_err(TaskRunError::Other),
+                    };
+
+                    if let (Ok(status), Some(work_dir)) = (&result, &mut work_dir) {
+                        if status.success() {
+                            work_dir.succeed();
+                        }
+                    }
+
+                    if cleanup || token.is_cancelled() {
+                        if let Err(e) = container.force_remove().await {
+                            warn!("failed to remove container `{id}`: {e:#}");
+                        }
+                    }
+
+                    REATTACHED.lock().unwrap().remove(&id);
+                    drop(work_dir);
+                    result.map(NonEmpty::new)
+                }
+                .boxed()
+            }));
+        }
+
+        Ok(reattached)
+    }
+
+    async fn image_digest(&self, image: &str) -> Option<String> {
+        if let Some(digest) = crankshaft_docker::digest(image) {
+            return Some(digest.to_string());
+        }
+
+        let inspect = self.client.inspect_image(image).await.ok()?;
+        inspect
+            .repo_digests?
+            .into_iter()
+            .find_map(|d| d.split_once('@').map(|(_, digest)| digest.to_string()))
+    }
+
+    async fn resolve_image(&self, image: &str) -> Result<Option<String>> {
+        let pinned = self
+            .images
+            .resolve(image)
+            .await
+            .with_context(|| format!("failed to resolve image `{image}`"))?;
+        Ok(Some(pinned))
+    }
+
     fn run(
+        &self,
+        task: Task,
+        started: Option<oneshot::Sender<()>>,
+        token: CancellationToken,
+    ) -> Result<BoxFuture<'static, Result<NonEmpty<ExitStatus>, TaskRunError>>> {
+        self.run_with_logs(task, started, token, logs::Sender::new())
+    }
+
+    fn run_with_logs(
         &self,
         task: Task,
         mut started: Option<oneshot::Sender<()>>,
         token: CancellationToken,
+        logs: logs::Sender,
     ) -> Result<BoxFuture<'static, Result<NonEmpty<ExitStatus>, TaskRunError>>> {
         // Helper for cleanup
         enum Cleaner {
@@ -330,10 +753,19 @@ impl crate::Backend for Backend {
 
         impl Cleaner {
             /// Runs cleanup.
-            async fn cleanup(&self, canceled: bool) -> Result<()> {
+            ///
+            /// The container of a canceled task is given `grace` to exit
+            /// before it is removed by force.
+            async fn cleanup(&self, canceled: bool, grace: Option<Duration>) -> Result<()> {
                 match self {
                     Self::Container(container) => {
                         if canceled {
+                            if let Some(grace) = grace {
+                                if let Err(e) = container.stop(Some(grace)).await {
+                                    warn!("failed to stop container: {e:#}");
+                                }
+                            }
+
                             container
                                 .force_remove()
                                 .await
@@ -353,14 +785,121 @@ impl crate::Backend for Backend {
         }
 
         let client = self.client.clone();
+        let images = self.images.clone();
         let cleanup = self.config.cleanup();
+        let stop_grace_period = self.config.stop_grace_period().map(Duration::from_secs);
+        let output_tail = match self.config.output_tail() {
+            0 => None,
+            kib => Some(kib as usize * 1024),
+        };
+        let namespaces = self.namespaces();
+        let scheduling = self.scheduling();
+        let output_encoding = match self.config.output_encoding() {
+            OutputEncoding::Utf8 => Encoding::Utf8,
+            OutputEncoding::Latin1 => Encoding::Latin1,
+            OutputEncoding::Escaped => Encoding::Escaped,
+        };
+        let image_defaults = self.config.image_defaults();
+        let deprecated_images = self.config.deprecated_images().clone();
+        let require_digests = self.config.require_digests();
+        let trusted_digests = self.config.trusted_digests().to_vec();
+        let input_cache = self.config.input_cache().map(Path::to_path_buf);
+        let input_cache_max_size = self
+            .config
+            .input_cache_max_size()
+            .map(|size| (size * 1024.0 * 1024.0 * 1024.0) as u64);
+        let input_cache_max_age = self.config.input_cache_max_age().map(Duration::from_secs);
+        let temp_dir_root = self.config.temp_dir_root().map(Path::to_path_buf);
+        let work_dir_root = self.config.work_dir_root().map(Path::to_path_buf);
+        let preserve_work_dirs = self.config.preserve_work_dirs();
+        let script_wrapper = self.config.script_wrapper();
+        let logs_root = self.config.logs_root().map(Path::to_path_buf);
+        let host_env = hostenv::collect(self.config.pass_env());
+        let min_free_disk = self
+            .config
+            .min_free_disk()
+            .map(preflight::gib_to_bytes)
+            .unwrap_or_default();
+        let docker_root = self.docker_root.clone();
+        let deprecated_image_uses = self.deprecated_image_uses.clone();
         let resources = self.resources;
+        let capabilities = self.capabilities;
+
+        let ca_bundle = if self.config.ca_bundle() {
+            let path = host_ca_bundle();
+            if path.is_none() {
+                warn!("a CA bundle could not be located on the host and will not be bound");
+            }
+
+            path
+        } else {
+            None
+        };
 
         Ok(async move {
-            let tempdir = TempDir::new().context("failed to create temporary directory for mounts")?;
+            // Fail early if a directory the task uses does not have enough free
+            // space; the disk space requested by the task is written to its
+            // working directory (or, without one, its containers' writable
+            // layers within the daemon's root directory)
+            let disk = task
+                .resources
+                .as_ref()
+                .and_then(|r| r.disk())
+                .map(preflight::gib_to_bytes)
+                .unwrap_or_default();
+            let temp_dir = temp_dir_root.clone().unwrap_or_else(std::env::temp_dir);
+            let mut checks = vec![("temporary directory root", temp_dir, min_free_disk)];
+            if let Some(input_cache) = &input_cache {
+                checks.push(("input cache", input_cache.clone(), min_free_disk));
+            }
+            if let Some(root) = &work_dir_root {
+                checks.push(("working directory root", root.clone(), min_free_disk + disk));
+            }
+            if let Some(root) = docker_root {
+                let disk = if work_dir_root.is_some() { 0 } else { disk };
+                checks.push(("Docker root directory", root, min_free_disk + disk));
+            }
+            for (purpose, path, required) in checks {
+                preflight::check(purpose, &path, required).map_err(anyhow::Error::from)?;
+            }
+
+            let tempdir = match &temp_dir_root {
+                Some(root) => {
+                    tokio::fs::create_dir_all(root).await.with_context(|| {
+                        format!("failed to create temporary directory root `{root}`", root = root.display())
+                    })?;
+                    TempDir::new_in(root)
+                }
+                None => TempDir::new(),
+            }
+            .context("failed to create temporary directory for mounts")?;
 
             let mut mounts = Vec::new();
-            add_input_mounts(task.inputs, tempdir.path(), &mut mounts).await?;
+            let _pins = add_input_mounts(
+                task.inputs,
+                tempdir.path(),
+                input_cache.as_deref(),
+                &mut mounts,
+            )
+            .await?;
+
+            // Evict from the input cache only once this task's inputs are pinned
+            if let Some(input_cache) = input_cache.clone().filter(|_| {
+                input_cache_max_size.is_some() || input_cache_max_age.is_some()
+            }) {
+                let evicted = tokio::task::spawn_blocking(move || {
+                    download::evict(&input_cache, input_cache_max_size, input_cache_max_age)
+                })
+                .await
+                .context("failed to evict from the input cache")?;
+
+                match evicted {
+                    Ok(0) => {}
+                    Ok(bytes) => debug!("evicted {bytes} bytes from the input cache"),
+                    Err(e) => warn!("failed to evict from the input cache: {e:#}"),
+                }
+            }
+
             add_shared_mounts(task.volumes, tempdir.path(), &mut mounts)?;
             let mut outputs = Vec::new();
 
@@ -368,17 +907,110 @@ impl crate::Backend for Backend {
                     .name
                     .context("task requires a name to run on the Docker backend")?;
 
-            for execution in task.executions {
+            let mut task_dir = work_dir_root
+                .as_deref()
+                .map(|root| WorkDir::create(root, &name, preserve_work_dirs))
+                .transpose()?;
+
+            if let Some(task_dir) = &task_dir {
+                mounts.push(task_dir.mount());
+            }
+
+            let log_dir = logs_root
+                .as_deref()
+                .map(|root| LogDir::create(root, &name))
+                .transpose()?;
+
+            // Outputs other than the stdout and stderr of executions are
+            // collected from the containers once they exit
+            let collected = task
+                .outputs
+                .iter()
+                .filter(|o| {
+                    !task.executions.iter().any(|e| {
+                        e.stdout.as_deref() == Some(o.path.as_str())
+                            || e.stderr.as_deref() == Some(o.path.as_str())
+                    })
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            for (index, execution) in task.executions.into_iter().enumerate() {
                 if token.is_cancelled() {
                     return Err(TaskRunError::Canceled);
                 }
 
+                let error_on_nonzero = execution.error_on_nonzero;
+
+                // Propagate the task's trace context into the container
+                let mut env = host_env.clone();
+                env.extend(execution.env);
+                if let Some(context) = &task.trace_context {
+                    env.extend(context.env().map(|(name, value)| (name.to_string(), value.to_string())));
+                }
+
+                if let Some(deprecation) = deprecated_images.get(&execution.image) {
+                    deprecated_image_uses.fetch_add(1, Ordering::Relaxed);
+                    warn!(
+                        task = name,
+                        image = execution.image,
+                        replacement = deprecation.replacement(),
+                        message = deprecation.message(),
+                        "task `{name}` is using deprecated image `{image}`{replacement}",
+                        image = execution.image,
+                        replacement = deprecation
+                            .replacement()
+                            .map(|r| format!("; use `{r}` instead"))
+                            .unwrap_or_default()
+                    );
+                }
+
                 // First ensure the execution's image exists
-                client
-                    .ensure_image(&execution.image)
+                let cached = images.contains(&execution.image);
+                metrics::global().image_cache_lookup(cached);
+
+                let pull = Instant::now();
+                images
+                    .ensure(&execution.image)
                     .await
+                    .map_err(diagnosed)
                     .with_context(|| format!("failed to pull image `{image}`", image = execution.image))?;
 
+                if !cached {
+                    metrics::global().image_pulled(pull.elapsed());
+
+                    if let Some(source) = images.source(&execution.image) {
+                        info!(
+                            task = name,
+                            image = execution.image,
+                            source = %source,
+                            "pulled image `{image}` from `{source}`",
+                            image = execution.image
+                        );
+                    }
+                }
+
+                verify_image(&client, &execution.image, require_digests, &trusted_digests).await?;
+
+                // Apply any resource defaults from the image's labels before the task's resources
+                let requested = if image_defaults {
+                    let labels = client
+                        .inspect_image(&execution.image)
+                        .await
+                        .with_context(|| format!("failed to inspect image `{image}`", image = execution.image))?
+                        .config
+                        .and_then(|config| config.labels)
+                        .unwrap_or_default();
+
+                    match (TaskResources::from_labels(&labels), &task.resources) {
+                        (Some(defaults), Some(resources)) => Some(defaults.apply(resources)),
+                        (Some(defaults), None) => Some(defaults),
+                        (None, resources) => resources.clone(),
+                    }
+                } else {
+                    task.resources.clone()
+                };
+
                 // Look for the path where the caller wants stdout saved to
                 let stdout = execution.stdout.as_ref().and_then(|p| {
                     let url = task.outputs.iter().find_map(|o| if o.path == *p {
@@ -422,14 +1054,18 @@ impl crate::Backend for Backend {
                 }).transpose()?;
 
                 // Check to see if we should use the service API for running the task
-                let (result, cleaner) = if resources.use_service() {
+                let (mut result, cleaner) = if resources.use_service() {
                     let mut builder = client
                         .service_builder()
                         .image(execution.image)
                         .program(execution.program)
                         .args(execution.args)
-                        .envs(execution.env)
-                        .resources(task.resources.as_ref().map(Into::into).unwrap_or_default());
+                        .envs(env)
+                        .resources(requested.as_ref().map(Into::into).unwrap_or_default());
+
+                    for (name, value) in &execution.secret_env {
+                        builder = builder.secret_env(name, value.expose());
+                    }
 
                     if let Some(stdout) = stdout {
                         builder = builder.stdout(stdout);
@@ -443,7 +1079,11 @@ impl crate::Backend for Backend {
                         builder = builder.work_dir(work_dir);
                     }
 
-                    let service = Arc::new(builder.try_build(&name).await.map_err(|e| TaskRunError::Other(e.into()))?);
+                    if let Some(ca_bundle) = &ca_bundle {
+                        builder = builder.ca_bundle(ca_bundle.display().to_string());
+                    }
+
+                    let service = Arc::new(builder.try_build(&name).await.map_err(|e| TaskRunError::Other(diagnosed(e)))?);
                     let started = started.take();
 
                     select! {
@@ -454,37 +1094,134 @@ impl crate::Backend for Backend {
                             (Err(TaskRunError::Canceled), Cleaner::Service(service))
                         }
                         res = service.run(&name, || if let Some(started) = started { started.send(()).ok(); }) => {
-                            (res.context("failed to run Docker service").map_err(TaskRunError::Other), Cleaner::Service(service))
+                            (res.map_err(diagnosed).context("failed to run Docker service").map_err(TaskRunError::Other), Cleaner::Service(service))
                         }
                     }
                 } else {
-                   let mut builder = client
+                    let mut host_config = HostConfig {
+                        mounts: Some(mounts.clone()),
+                        ..requested.as_ref().map(|r| r.into()).unwrap_or_default()
+                    };
+
+                    for adjustment in capabilities.apply(&mut host_config) {
+                        warn!("task `{name}`: {adjustment} (not supported by the Docker daemon)");
+                    }
+
+                    // Write streams that are not otherwise saved to the log
+                    // directory
+                    let log_exec = log_dir.as_ref().map(|d| d.execution(index)).transpose()?;
+                    let stdout = stdout.or_else(|| log_exec.as_ref().map(|d| d.join("stdout")));
+                    let stderr = stderr.or_else(|| log_exec.as_ref().map(|d| d.join("stderr")));
+
+                    // Run the command through a wrapper script in the task's
+                    // working directory if configured
+                    let (program, args) = match task_dir.as_ref().filter(|_| script_wrapper) {
+                        Some(task_dir) => {
+                            let script = script::write(
+                                task_dir.path(),
+                                index,
+                                &execution.program,
+                                &execution.args,
+                                &env,
+                            )?;
+                            (script::INTERPRETER.to_string(), vec![script])
+                        }
+                        None => (execution.program, execution.args),
+                    };
+
+                    let mut builder = client
                         .container_builder()
                         .image(execution.image)
-                        .program(execution.program)
-                        .args(execution.args)
-                        .envs(execution.env)
-                        .host_config(HostConfig {
-                            mounts: Some(mounts.clone()),
-                            ..task.resources.as_ref().map(|r| r.into()).unwrap_or_default()
-                        });
+                        .program(program)
+                        .args(args)
+                        .envs(env)
+                        .label(INSTANCE_LABEL, INSTANCE.as_str())
+                        .label(TASK_LABEL, &name)
+                        .host_config(host_config);
+
+                    for (name, value) in &execution.secret_env {
+                        builder = builder.secret_env(name, value.expose());
+                    }
 
+                    // Record where the task's state lives so that another
+                    // process can reattach to the container
                     if let Some(stdout) = stdout {
-                        builder = builder.stdout(stdout);
+                        builder = builder
+                            .label(STDOUT_LABEL, stdout.display().to_string())
+                            .stdout(stdout);
                     }
 
                     if let Some(stderr) = stderr {
-                        builder = builder.stderr(stderr);
+                        builder = builder
+                            .label(STDERR_LABEL, stderr.display().to_string())
+                            .stderr(stderr);
                     }
 
-                    if let Some(work_dir) = execution.work_dir {
+                    if let Some(task_dir) = &task_dir {
+                        builder = builder.label(WORK_DIR_LABEL, task_dir.path().display().to_string());
+                    }
+
+                    if let Some(work_dir) = execution
+                        .work_dir
+                        .or_else(|| task_dir.as_ref().map(|_| GUEST_WORK_DIR.to_string()))
+                    {
                         builder = builder.work_dir(work_dir);
                     }
 
+                    if let Some(ca_bundle) = &ca_bundle {
+                        builder = builder.ca_bundle(ca_bundle.display().to_string());
+                    }
+
+                    if let Some(bytes) = output_tail {
+                        builder = builder.tail(bytes);
+                    }
+
+                    if let Some(hostname) = &task.hostname {
+                        builder = builder.hostname(hostname);
+                    }
+
+                    // Pipe stdin from the host file bound at the path
+                    if let Some(stdin) = &execution.stdin {
+                        let path = host_path(&mounts, stdin).with_context(|| {
+                            format!("stdin `{stdin}` is not within an input or the working directory of task `{name}`")
+                        })?;
+                        let file = tokio::fs::File::open(&path).await.with_context(|| {
+                            format!("failed to open stdin `{path}`", path = path.display())
+                        })?;
+                        builder = builder.stdin(file);
+                    }
+
+                    // Containers abandoned while running are stopped with
+                    // the same grace period as canceled tasks
+                    builder = builder
+                        .namespaces(namespaces.clone())
+                        .scheduling(scheduling.clone())
+                        .output_encoding(output_encoding)
+                        .stop_timeout(stop_grace_period.unwrap_or_default());
+
+                    let sender = logs.clone();
+                    builder = builder.on_line(move |stream, line| {
+                        let stream = match stream {
+                            OutputStream::Stdout => logs::OutputStream::Stdout,
+                            OutputStream::Stderr => logs::OutputStream::Stderr,
+                        };
+                        sender.send(stream, index, line);
+                    });
+
+                    let sender = logs.clone();
+                    builder = builder.heartbeat(USAGE_INTERVAL, move |heartbeat| {
+                        sender.report(logs::Usage::new(
+                            index,
+                            heartbeat.elapsed,
+                            heartbeat.cpu,
+                            heartbeat.max_memory,
+                        ));
+                    });
+
                     let container = Arc::new(
                         builder
                             .try_build(name.clone())
-                            .await.map_err(|e| TaskRunError::Other(e.into()))?,
+                            .await.map_err(|e| TaskRunError::Other(diagnosed(e)))?,
                     );
 
                     let started = started.take();
@@ -497,16 +1234,94 @@ impl crate::Backend for Backend {
                             (Err(TaskRunError::Canceled), Cleaner::Container(container))
                         }
                         res = container.run(&name, || if let Some(started) = started { started.send(()).ok(); }) => {
-                            (res.context("failed to run Docker container").map_err(TaskRunError::Other), Cleaner::Container(container))
+                            if let Some(usage) = container.usage() {
+                                info!(
+                                    wall = ?usage.wall,
+                                    cpu = ?usage.cpu,
+                                    max_memory = ?usage.max_memory,
+                                    "task `{name}` resource usage"
+                                );
+                            }
+
+                            (res.map_err(diagnosed).context("failed to run Docker container").map_err(TaskRunError::Other), Cleaner::Container(container))
                         }
                     }
                 };
 
+                // A failure to collect the outputs fails the execution once its
+                // container has been cleaned up
+                if let (Ok(_), Cleaner::Container(container)) = (&result, &cleaner) {
+                    if let Err(e) = collect_outputs(container, &collected, &name).await {
+                        result = Err(TaskRunError::Other(e));
+                    }
+                }
+
+                if let (Some(log_dir), Cleaner::Container(container)) = (&log_dir, &cleaner) {
+                    if let Some(report) = container.report() {
+                        if let Err(e) = log_dir.write(index, &report) {
+                            warn!("failed to write the logs of task `{name}`: {e:#}");
+                        }
+                    }
+                }
+
+                // Keep the end of a failed execution's output for the error
+                let tail = match (&result, &cleaner) {
+                    (Ok(status), Cleaner::Container(container)) if !status.success() => container
+                        .tail()
+                        .filter(|tail| !tail.is_empty())
+                        .map(|tail| tail.to_string()),
+                    _ => None,
+                };
+
+                // Executions killed for running out of memory are reported
+                // distinctly so that they can be retried with more memory
+                let oom_status = match (&result, &cleaner) {
+                    (Err(TaskRunError::Other(e)), Cleaner::Container(container))
+                        if matches!(
+                            e.downcast_ref::<crankshaft_docker::Error>(),
+                            Some(crankshaft_docker::Error::OutOfMemory(_))
+                        ) =>
+                    {
+                        container.report().map(|report| report.status)
+                    }
+                    _ => None,
+                };
+
                 if cleanup {
-                    cleaner.cleanup(token.is_cancelled()).await?;
+                    cleaner.cleanup(token.is_cancelled(), stop_grace_period).await?;
+                }
+
+                if let Some(status) = oom_status {
+                    outputs.push(status);
+                    return Err(TaskRunError::OutOfMemory {
+                        index,
+                        // SAFETY: the status of this execution was just pushed
+                        statuses: NonEmpty::from_vec(outputs).unwrap(),
+                    });
+                }
+
+                let status = result?;
+                outputs.push(status);
+
+                // Stop running executions if a non-zero exit is an error
+                if error_on_nonzero && !status.success() {
+                    return Err(TaskRunError::NonZeroExit {
+                        index,
+                        // SAFETY: the status of this execution was just pushed
+                        statuses: NonEmpty::from_vec(outputs).unwrap(),
+                        tail,
+                    });
+                }
+
+                if let Some(tail) = tail {
+                    debug!("task `{name}` execution exited with {status}\n{tail}");
                 }
+            }
 
-                outputs.push(result?);
+            if let Some(task_dir) = &mut task_dir {
+                if outputs.iter().all(ExitStatus::success) {
+                    task_dir.succeed();
+                }
             }
 
             // SAFETY: each task _must_ have at least one execution, so at least one
@@ -522,17 +1337,42 @@ impl crate::Backend for Backend {
 /// Bind mounts are created for any input specified as a path.
 ///
 /// For inputs not specified by a path, the contents are fetched and written to
-/// a file within the provided temporary directory.
+/// a file within the provided temporary directory (or, for remote inputs, the
+/// input cache directory if one is provided). Up to
+/// [`MAX_CONCURRENT_INPUT_FETCHES`] inputs are fetched concurrently; the mounts
+/// are added in the order of the inputs.
+///
+/// Inputs downloaded into the input cache directory are pinned so that they
+/// are not evicted while the task runs; the pins are returned.
 ///
 /// Errors may be returned if an input's contents could not be fetched.
 async fn add_input_mounts(
     inputs: Vec<Input>,
     temp_dir: &Path,
+    cache_dir: Option<&Path>,
     mounts: &mut Vec<Mount>,
-) -> Result<()> {
-    for input in inputs {
-        let target = input.path;
-        let source = input.contents.fetch(temp_dir).await?;
+) -> Result<Vec<download::Pin>> {
+    let pins = cache_dir
+        .map(|dir| {
+            inputs
+                .iter()
+                .filter_map(|input| match input.contents() {
+                    Contents::Url(url) => Some(download::Pin::new(download::path(url, dir))),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut fetches = futures::stream::iter(inputs)
+        .map(|input| async move {
+            let source = input.fetch(temp_dir, cache_dir).await?;
+            anyhow::Ok((input.path, input.read_only, source))
+        })
+        .buffered(MAX_CONCURRENT_INPUT_FETCHES);
+
+    while let Some(result) = fetches.next().await {
+        let (target, read_only, source) = result?;
 
         mounts.push(Mount {
             target: Some(target),
@@ -545,14 +1385,146 @@ async fn add_input_mounts(
                     .to_string(),
             ),
             typ: Some(MountTypeEnum::BIND),
-            read_only: Some(input.read_only),
+            read_only: Some(read_only),
             ..Default::default()
         });
     }
 
+    Ok(pins)
+}
+
+/// Verifies that an image is trusted to run.
+///
+/// If `require_digests` is set, the image must be pinned to a digest. If
+/// `trusted_digests` is not empty, the digest of the image (either the digest
+/// it is pinned to or one of its repository digests) must be in the list.
+async fn verify_image(
+    client: &Docker,
+    image: &str,
+    require_digests: bool,
+    trusted_digests: &[String],
+) -> Result<()> {
+    let pinned = crankshaft_docker::digest(image);
+    if require_digests && pinned.is_none() {
+        bail!("image `{image}` is not pinned to a digest");
+    }
+
+    if trusted_digests.is_empty() {
+        return Ok(());
+    }
+
+    let digests = match pinned {
+        Some(digest) => vec![digest.to_string()],
+        None => client
+            .inspect_image(image)
+            .await
+            .with_context(|| format!("failed to inspect image `{image}`"))?
+            .repo_digests
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|d| d.split_once('@').map(|(_, digest)| digest.to_string()))
+            .collect(),
+    };
+
+    if !digests.iter().any(|d| trusted_digests.contains(d)) {
+        bail!("image `{image}` does not have a trusted digest");
+    }
+
     Ok(())
 }
 
+/// Collects the outputs of a task from the container of one of its
+/// executions.
+///
+/// Each output that exists within the container is copied to its `file` URL,
+/// replacing the output collected from any previous execution. Outputs that
+/// do not exist within the container or that have a URL with an unsupported
+/// scheme are skipped. The size and (for files) SHA-256 checksum of each
+/// collected output are logged.
+async fn collect_outputs(container: &Container, outputs: &[Output], task: &str) -> Result<()> {
+    for output in outputs {
+        let destination = match output.url.scheme() {
+            "file" => output.url.to_file_path().map_err(|_| {
+                anyhow!(
+                    "output URL `{url}` has a file scheme but cannot be represented as a file path",
+                    url = output.url
+                )
+            })?,
+            scheme => {
+                warn!(
+                    "output `{path}` of task `{task}` cannot be collected: unsupported scheme \
+                     `{scheme}` for output URL `{url}`",
+                    path = output.path,
+                    url = output.url
+                );
+                continue;
+            }
+        };
+
+        let Some(size) = container
+            .copy_from(&output.path, &destination)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to collect output `{path}` of task `{task}`",
+                    path = output.path
+                )
+            })?
+        else {
+            debug!(
+                "output `{path}` of task `{task}` does not exist in container `{id}`",
+                path = output.path,
+                id = container.id()
+            );
+            continue;
+        };
+
+        let checksum = match output.ty {
+            OutputType::File => Some(download::sha256(&destination).await?),
+            OutputType::Directory => None,
+        };
+
+        info!(
+            task,
+            path = output.path,
+            url = %output.url,
+            size,
+            checksum,
+            "collected output `{path}` of task `{task}`",
+            path = output.path
+        );
+    }
+
+    Ok(())
+}
+
+/// Gets the path on the host of a path within a container from the bind
+/// mounts of the container.
+///
+/// The bind mount with the longest target containing the path is used.
+fn host_path(mounts: &[Mount], path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    mounts
+        .iter()
+        .filter(|m| m.typ == Some(MountTypeEnum::BIND))
+        .filter_map(|m| {
+            let target = m.target.as_deref()?;
+            let source = Path::new(m.source.as_deref()?);
+            let rest = path.strip_prefix(target).ok()?;
+
+            // Joining an empty path would add a trailing separator
+            let path = if rest.as_os_str().is_empty() {
+                source.to_path_buf()
+            } else {
+                source.join(rest)
+            };
+
+            Some((target.len(), path))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, path)| path)
+}
+
 /// Gets the shared mounts (if any exist) from the shared volumes in a [`Task`]
 /// (via [`Task::shared_volumes()`]).
 fn add_shared_mounts(volumes: Vec<String>, tempdir: &Path, mounts: &mut Vec<Mount>) -> Result<()> {
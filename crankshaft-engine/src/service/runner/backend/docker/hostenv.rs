@@ -0,0 +1,76 @@
+//! Passing host environment variables through to containers.
+//!
+//! Containers do not inherit the environment of the host, which strips
+//! context that some tools need (e.g., the variables a scheduler sets for the
+//! job a runner is executing within). The variables matching a configured set
+//! of patterns are instead copied into each container's environment.
+
+use indexmap::IndexMap;
+
+/// Determines if a variable name matches a pattern.
+///
+/// A `*` in the pattern matches any sequence of characters; all other
+/// characters match exactly.
+fn matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+
+    // The first part is anchored to the start of the name
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        // There was no `*`, so the pattern must match the whole name
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    // The last part is anchored to the end of the name
+    rest.ends_with(last)
+}
+
+/// Collects the host environment variables that match any of the given
+/// patterns.
+///
+/// Variables whose names or values are not valid UTF-8 are skipped.
+pub(crate) fn collect(patterns: &[String]) -> IndexMap<String, String> {
+    if patterns.is_empty() {
+        return IndexMap::new();
+    }
+
+    let mut env = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(name, _)| patterns.iter().any(|p| matches(p, name)))
+        .collect::<IndexMap<_, _>>();
+
+    // Sort so that containers see the variables in a stable order
+    env.sort_keys();
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_patterns() {
+        assert!(matches("HOME", "HOME"));
+        assert!(!matches("HOME", "HOMEDIR"));
+        assert!(matches("SLURM_*", "SLURM_JOB_ID"));
+        assert!(matches("SLURM_*", "SLURM_"));
+        assert!(!matches("SLURM_*", "MY_SLURM_JOB_ID"));
+        assert!(matches("*_PROXY", "HTTPS_PROXY"));
+        assert!(matches("*", "ANYTHING"));
+        assert!(matches("A*B*C", "AxxBxxC"));
+        assert!(!matches("A*B*C", "AxxCxxB"));
+        assert!(!matches("AB*BA", "ABA"));
+    }
+}
@@ -0,0 +1,204 @@
+//! Structured log directories of task attempts.
+//!
+//! When a logs root is configured, each attempt of a task is given a directory
+//! at `<root>/<task>/attempt-<n>` that records what each of its executions ran
+//! and how it went, so that failures can be investigated after the fact:
+//!
+//! ```text
+//! <root>/<task>/attempt-1/<execution>/
+//! ├── cmd.json        # the image and command that ran
+//! ├── stdout          # the stdout stream
+//! ├── stderr          # the stderr stream
+//! ├── exit_code       # the exit code (empty if killed by a signal)
+//! ├── timing.json     # when the execution started and ended
+//! └── resources.json  # the resources the execution consumed
+//! ```
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use anyhow::Result;
+use crankshaft_docker::Report;
+use serde::Serialize;
+
+/// The record of what an execution ran.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Command<'a> {
+    /// The image reference.
+    image: &'a str,
+
+    /// The id of the image that ran.
+    image_id: Option<&'a str>,
+
+    /// The program and arguments that ran.
+    command: &'a [String],
+}
+
+/// The record of when an execution ran.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Timing {
+    /// When the execution started (in seconds since the Unix epoch).
+    started: f64,
+
+    /// When the execution ended (in seconds since the Unix epoch).
+    ended: f64,
+
+    /// How long the execution ran for (in seconds).
+    wall: f64,
+}
+
+/// The record of the resources an execution consumed.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Resources {
+    /// The CPU time consumed (in seconds).
+    cpu: Option<f64>,
+
+    /// The peak memory usage (in bytes).
+    max_memory: Option<u64>,
+}
+
+/// Gets the number of seconds since the Unix epoch of a time.
+fn epoch_secs(time: SystemTime) -> f64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+/// The log directory of a single attempt of a task.
+#[derive(Debug)]
+pub(crate) struct LogDir {
+    /// The path to the directory.
+    path: PathBuf,
+}
+
+impl LogDir {
+    /// Creates the log directory of the next attempt of a task within the
+    /// given root.
+    pub(crate) fn create(root: &Path, task: &str) -> Result<Self> {
+        let parent = root.join(task);
+        std::fs::create_dir_all(&parent).with_context(|| {
+            format!(
+                "failed to create log directory `{parent}`",
+                parent = parent.display()
+            )
+        })?;
+
+        // Creating the directory fails if another attempt claimed the number
+        for attempt in 1.. {
+            let path = parent.join(format!("attempt-{attempt}"));
+            match std::fs::create_dir(&path) {
+                Ok(()) => return Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!(
+                            "failed to create log directory `{path}`",
+                            path = path.display()
+                        )
+                    });
+                }
+            }
+        }
+
+        unreachable!("there are always more attempt numbers")
+    }
+
+    /// Creates the directory of an execution.
+    ///
+    /// Returns the path of the directory.
+    pub(crate) fn execution(&self, index: usize) -> Result<PathBuf> {
+        let path = self.path.join(index.to_string());
+        std::fs::create_dir_all(&path).with_context(|| {
+            format!(
+                "failed to create log directory `{path}`",
+                path = path.display()
+            )
+        })?;
+        Ok(path)
+    }
+
+    /// Writes the records of an execution from the report of its container.
+    ///
+    /// Streams that were written elsewhere (e.g., to an output of the task)
+    /// are copied into the directory.
+    pub(crate) fn write(&self, index: usize, report: &Report) -> Result<()> {
+        let dir = self.execution(index)?;
+        let write = |name: &str, contents: Vec<u8>| {
+            let path = dir.join(name);
+            std::fs::write(&path, contents)
+                .with_context(|| format!("failed to write `{path}`", path = path.display()))
+        };
+
+        write(
+            "cmd.json",
+            serde_json::to_vec_pretty(&Command {
+                image: &report.image,
+                image_id: report.image_id.as_deref(),
+                command: &report.command,
+            })?,
+        )?;
+        write(
+            "exit_code",
+            report
+                .status
+                .code()
+                .map(|code| code.to_string())
+                .unwrap_or_default()
+                .into_bytes(),
+        )?;
+        write(
+            "timing.json",
+            serde_json::to_vec_pretty(&Timing {
+                started: epoch_secs(report.started),
+                ended: epoch_secs(report.ended),
+                wall: report.usage.wall.as_secs_f64(),
+            })?,
+        )?;
+        write(
+            "resources.json",
+            serde_json::to_vec_pretty(&Resources {
+                cpu: report.usage.cpu.map(|cpu| cpu.as_secs_f64()),
+                max_memory: report.usage.max_memory,
+            })?,
+        )?;
+
+        for (name, source) in [("stdout", &report.stdout), ("stderr", &report.stderr)] {
+            let path = dir.join(name);
+            if let Some(source) = source.as_ref().filter(|s| **s != path) {
+                std::fs::copy(source, &path).with_context(|| {
+                    format!(
+                        "failed to copy `{source}` to `{path}`",
+                        source = source.display(),
+                        path = path.display()
+                    )
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_attempts() {
+        let root = tempfile::tempdir().unwrap();
+
+        let first = LogDir::create(root.path(), "task").unwrap();
+        let second = LogDir::create(root.path(), "task").unwrap();
+        assert_eq!(first.path, root.path().join("task/attempt-1"));
+        assert_eq!(second.path, root.path().join("task/attempt-2"));
+        assert_eq!(
+            second.execution(0).unwrap(),
+            root.path().join("task/attempt-2/0")
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Checks of the free disk space of the directories used by tasks.
+//!
+//! A task that runs out of disk space part way through (e.g., while pulling
+//! its image or writing its outputs) fails with an error that rarely points at
+//! the cause. Checking the free space of each directory the task uses before
+//! it runs fails the task early with an [`InsufficientDisk`] error instead.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use thiserror::Error;
+use tracing::debug;
+
+/// The number of bytes in a gibibyte.
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// An error for a directory without enough free disk space for a task.
+#[derive(Debug, Error)]
+#[error(
+    "insufficient disk space for the {purpose} `{path}`: {required:.2} GiB is required but only \
+     {available:.2} GiB is available",
+    path = path.display(),
+    required = *required as f64 / GIB,
+    available = *available as f64 / GIB
+)]
+#[non_exhaustive]
+pub struct InsufficientDisk {
+    /// What the directory is used for (e.g., `working directory root`).
+    pub purpose: &'static str,
+
+    /// The path of the directory.
+    pub path: PathBuf,
+
+    /// The disk space required (in bytes).
+    pub required: u64,
+
+    /// The disk space available (in bytes).
+    pub available: u64,
+}
+
+/// Converts a size in GiB to bytes.
+pub(crate) fn gib_to_bytes(gib: f64) -> u64 {
+    (gib * GIB) as u64
+}
+
+/// Gets the disk space (in bytes) available to unprivileged users on the
+/// filesystem containing a path.
+///
+/// If the path does not exist, the space of its nearest existing ancestor is
+/// returned. Returns `None` if the space cannot be determined.
+fn available(path: &Path) -> Option<u64> {
+    let path = path.ancestors().find(|p| p.exists())?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt as _;
+
+        let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+
+        // SAFETY: the path is a valid C string and `statvfs` only writes to
+        // the provided buffer, which is only read if it succeeded.
+        let stat = unsafe {
+            if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+                return None;
+            }
+
+            stat.assume_init()
+        };
+
+        #[allow(clippy::unnecessary_cast)]
+        Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Checks that a directory has at least the required disk space (in bytes)
+/// available.
+///
+/// The check passes if nothing is required or the available space cannot be
+/// determined.
+pub(crate) fn check(
+    purpose: &'static str,
+    path: &Path,
+    required: u64,
+) -> Result<(), InsufficientDisk> {
+    if required == 0 {
+        return Ok(());
+    }
+
+    let Some(available) = available(path) else {
+        debug!(
+            "could not determine the disk space available for the {purpose} `{path}`",
+            path = path.display()
+        );
+        return Ok(());
+    };
+
+    if available < required {
+        return Err(InsufficientDisk {
+            purpose,
+            path: path.to_path_buf(),
+            required,
+            available,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn checks_available_space() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(check("temporary directory root", dir.path(), 0).is_ok());
+        assert!(
+            check(
+                "temporary directory root",
+                &dir.path().join("missing/dir"),
+                1
+            )
+            .is_ok()
+        );
+
+        let e = check("temporary directory root", dir.path(), u64::MAX).unwrap_err();
+        assert_eq!(e.required, u64::MAX);
+        assert!(
+            e.to_string()
+                .starts_with("insufficient disk space for the temporary directory root")
+        );
+    }
+}
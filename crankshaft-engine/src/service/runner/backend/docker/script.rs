@@ -0,0 +1,102 @@
+//! Wrapper scripts for the commands of executions.
+//!
+//! Rather than running an execution's program directly, the command can be
+//! written to a Bash script within the task's working directory that is run
+//! inside the container. The program is written verbatim (so it may contain
+//! pipes or several statements) followed by its quoted arguments, and the
+//! script runs with `set -euo pipefail` and records the command's exit code to
+//! a file next to it.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::Context;
+use anyhow::Result;
+use indexmap::IndexMap;
+
+use super::workdir::GUEST_WORK_DIR;
+
+/// The interpreter that wrapper scripts are run with.
+pub(crate) const INTERPRETER: &str = "bash";
+
+/// Quotes a value for Bash.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Renders the wrapper script of a command.
+fn render(
+    program: &str,
+    args: &[String],
+    env: &IndexMap<String, String>,
+    exit_code: &str,
+) -> String {
+    let mut script = String::from("#!/usr/bin/env bash\nset -euo pipefail\n");
+
+    // The path of the exit code is always a plain path within the working
+    // directory, so it does not need quoting
+    writeln!(script, "trap 'echo $? > {exit_code}' EXIT").unwrap();
+
+    for (name, value) in env {
+        writeln!(script, "export {name}={value}", value = quote(value)).unwrap();
+    }
+
+    script.push_str(program);
+    for arg in args {
+        script.push(' ');
+        script.push_str(&quote(arg));
+    }
+
+    script.push('\n');
+    script
+}
+
+/// Writes the wrapper script of an execution into the working directory of
+/// its task.
+///
+/// The values of secret environment variables are not written; they are
+/// provided to the container's environment instead.
+///
+/// Returns the path of the script within the container.
+pub(crate) fn write(
+    dir: &Path,
+    index: usize,
+    program: &str,
+    args: &[String],
+    env: &IndexMap<String, String>,
+) -> Result<String> {
+    let name = format!(".command-{index}.sh");
+    let exit_code = format!("{GUEST_WORK_DIR}/.exitcode-{index}");
+    let path = dir.join(&name);
+
+    std::fs::write(&path, render(program, args, env, &exit_code)).with_context(|| {
+        format!(
+            "failed to write command script `{path}`",
+            path = path.display()
+        )
+    })?;
+
+    Ok(format!("{GUEST_WORK_DIR}/{name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_scripts() {
+        let env = IndexMap::from([(String::from("GREETING"), String::from("it's me"))]);
+        let script = render(
+            "grep -c foo input.txt | tee count.txt",
+            &[String::from("a b")],
+            &env,
+            "/mnt/task/.exitcode-0",
+        );
+
+        assert_eq!(
+            script,
+            "#!/usr/bin/env bash\nset -euo pipefail\ntrap 'echo $? > /mnt/task/.exitcode-0' \
+             EXIT\nexport GREETING='it'\\''s me'\ngrep -c foo input.txt | tee count.txt 'a b'\n"
+        );
+    }
+}
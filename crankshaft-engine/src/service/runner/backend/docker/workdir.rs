@@ -0,0 +1,290 @@
+//! Working directories of tasks run by the Docker backend.
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use anyhow::Result;
+use bollard::secret::Mount;
+use bollard::secret::MountTypeEnum;
+use crankshaft_config::backend::docker::PreserveWorkDirs;
+use tracing::info;
+use tracing::warn;
+
+/// The path within containers that working directories are bound to.
+pub(crate) const GUEST_WORK_DIR: &str = "/mnt/task";
+
+/// The suffix of the marker file created next to a working directory while
+/// its task runs.
+const ACTIVE_SUFFIX: &str = ".active";
+
+/// The working directories of the tasks running in this process.
+static ACTIVE: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(Default::default);
+
+/// Gets the path to the marker file of a working directory.
+fn marker(path: &Path) -> PathBuf {
+    let mut marker = OsString::from(path.as_os_str());
+    marker.push(ACTIVE_SUFFIX);
+    marker.into()
+}
+
+/// The working directory of a single attempt of a task.
+///
+/// The directory is removed when dropped unless it should be preserved (see
+/// [`PreserveWorkDirs`]).
+#[derive(Debug)]
+pub(crate) struct WorkDir {
+    /// The path to the directory on the host.
+    path: PathBuf,
+
+    /// When to preserve the directory.
+    preserve: PreserveWorkDirs,
+
+    /// Whether the task succeeded.
+    succeeded: bool,
+}
+
+impl WorkDir {
+    /// Creates a unique working directory for a task within the given root.
+    pub(crate) fn create(root: &Path, task: &str, preserve: PreserveWorkDirs) -> Result<Self> {
+        std::fs::create_dir_all(root).with_context(|| {
+            format!(
+                "failed to create working directory root `{root}`",
+                root = root.display()
+            )
+        })?;
+
+        let path = tempfile::Builder::new()
+            .prefix(&format!("{task}-"))
+            .tempdir_in(root)
+            .with_context(|| {
+                format!(
+                    "failed to create working directory for task `{task}` in `{root}`",
+                    root = root.display()
+                )
+            })?
+            .keep();
+
+        // The marker distinguishes the directory of a task that was
+        // interrupted (e.g., by a crash) from one preserved once it completed
+        std::fs::File::create(marker(&path)).with_context(|| {
+            format!(
+                "failed to create marker for working directory `{path}`",
+                path = path.display()
+            )
+        })?;
+        ACTIVE.lock().unwrap().insert(path.clone());
+
+        Ok(Self {
+            path,
+            preserve,
+            succeeded: false,
+        })
+    }
+
+    /// Adopts the working directory of a task started by another process
+    /// (e.g., one that was interrupted while the task ran).
+    pub(crate) fn adopt(path: PathBuf, preserve: PreserveWorkDirs) -> Self {
+        retain(path.clone());
+        Self {
+            path,
+            preserve,
+            succeeded: false,
+        }
+    }
+
+    /// Gets the path to the directory on the host.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the mount that binds the directory into a container.
+    pub(crate) fn mount(&self) -> Mount {
+        Mount {
+            target: Some(GUEST_WORK_DIR.to_string()),
+            source: Some(self.path.display().to_string()),
+            typ: Some(MountTypeEnum::BIND),
+            read_only: Some(false),
+            ..Default::default()
+        }
+    }
+
+    /// Marks the task as having succeeded.
+    pub(crate) fn succeed(&mut self) {
+        self.succeeded = true;
+    }
+
+    /// Determines whether the directory should be preserved.
+    fn preserved(&self) -> bool {
+        match self.preserve {
+            PreserveWorkDirs::Never => false,
+            PreserveWorkDirs::OnFailure => !self.succeeded,
+            PreserveWorkDirs::Always => true,
+        }
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        ACTIVE.lock().unwrap().remove(&self.path);
+        if let Err(e) = std::fs::remove_file(marker(&self.path)) {
+            warn!(
+                "failed to remove marker for working directory `{path}`: {e}",
+                path = self.path.display()
+            );
+        }
+
+        if self.preserved() {
+            info!(
+                "preserving working directory `{path}`",
+                path = self.path.display()
+            );
+            return;
+        }
+
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            warn!(
+                "failed to remove working directory `{path}`: {e}",
+                path = self.path.display()
+            );
+        }
+    }
+}
+
+/// Keeps the working directory at `path` from being removed by [`reconcile()`]
+/// (e.g., because its task is still running in a container).
+pub(crate) fn retain(path: PathBuf) {
+    ACTIVE.lock().unwrap().insert(path);
+}
+
+/// Removes the working directories in `root` of tasks that were interrupted
+/// before they completed (e.g., by a crash).
+///
+/// The directories of tasks running in this process and directories
+/// preserved once their task completed are kept.
+///
+/// Returns the paths of the removed directories.
+pub(crate) fn reconcile(root: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| {
+                format!(
+                    "failed to read working directory root `{root}`",
+                    root = root.display()
+                )
+            });
+        }
+    };
+
+    let mut removed = Vec::new();
+    for entry in entries {
+        let marker = entry?.path();
+        let path = match marker
+            .to_str()
+            .and_then(|name| name.strip_suffix(ACTIVE_SUFFIX))
+        {
+            Some(path) => PathBuf::from(path),
+            None => continue,
+        };
+
+        if ACTIVE.lock().unwrap().contains(&path) {
+            continue;
+        }
+
+        if path.exists() {
+            std::fs::remove_dir_all(&path).with_context(|| {
+                format!(
+                    "failed to remove stale working directory `{path}`",
+                    path = path.display()
+                )
+            })?;
+        }
+
+        std::fs::remove_file(&marker).with_context(|| {
+            format!(
+                "failed to remove marker `{marker}`",
+                marker = marker.display()
+            )
+        })?;
+        removed.push(path);
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preserves_on_failure() {
+        let root = tempfile::tempdir().unwrap();
+
+        let dir = WorkDir::create(root.path(), "failed", PreserveWorkDirs::OnFailure).unwrap();
+        let failed = dir.path.clone();
+        drop(dir);
+        assert!(failed.exists());
+
+        let mut dir = WorkDir::create(root.path(), "ok", PreserveWorkDirs::OnFailure).unwrap();
+        let succeeded = dir.path.clone();
+        assert_ne!(failed, succeeded);
+        dir.succeed();
+        drop(dir);
+        assert!(!succeeded.exists());
+    }
+
+    #[test]
+    fn reconciles_interrupted() {
+        let root = tempfile::tempdir().unwrap();
+
+        let preserved =
+            WorkDir::create(root.path(), "preserved", PreserveWorkDirs::Always).unwrap();
+        let preserved_path = preserved.path.clone();
+        drop(preserved);
+
+        let running = WorkDir::create(root.path(), "running", PreserveWorkDirs::Never).unwrap();
+
+        // Simulate a task interrupted before its working directory was dropped
+        let interrupted =
+            WorkDir::create(root.path(), "interrupted", PreserveWorkDirs::Never).unwrap();
+        let interrupted_path = interrupted.path.clone();
+        ACTIVE.lock().unwrap().remove(&interrupted_path);
+        std::mem::forget(interrupted);
+
+        assert_eq!(
+            reconcile(root.path()).unwrap(),
+            [interrupted_path.as_path()]
+        );
+        assert!(!interrupted_path.exists());
+        assert!(preserved_path.exists());
+        assert!(running.path.exists());
+        assert!(reconcile(root.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn adopts_reattached() {
+        let root = tempfile::tempdir().unwrap();
+
+        // Simulate a task whose container is still running after its process
+        // was interrupted
+        let dir = WorkDir::create(root.path(), "reattached", PreserveWorkDirs::Never).unwrap();
+        let path = dir.path.clone();
+        ACTIVE.lock().unwrap().remove(&path);
+        std::mem::forget(dir);
+
+        retain(path.clone());
+        assert!(reconcile(root.path()).unwrap().is_empty());
+
+        let mut dir = WorkDir::adopt(path.clone(), PreserveWorkDirs::OnFailure);
+        dir.succeed();
+        drop(dir);
+        assert!(!path.exists());
+        assert!(!marker(&path).exists());
+    }
+}
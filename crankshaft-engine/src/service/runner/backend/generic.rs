@@ -9,8 +9,12 @@ use std::time::Duration;
 
 use anyhow::Context as _;
 use anyhow::Result;
+use bollard::secret::HostConfig;
+use bollard::secret::Mount;
+use bollard::secret::MountTypeEnum;
 use crankshaft_config::backend::Defaults;
 use crankshaft_config::backend::generic::Config;
+use crankshaft_config::backend::generic::driver::Locale;
 use futures::FutureExt;
 use futures::future::BoxFuture;
 use nonempty::NonEmpty;
@@ -22,8 +26,12 @@ use tracing::warn;
 
 use super::TaskRunError;
 use crate::Task;
+use crate::logs;
 use crate::service::runner::backend::generic::driver::Driver;
+use crate::task::Execution;
+use crate::task::Input;
 use crate::task::Resources;
+use crate::task::input::Contents;
 
 pub mod driver;
 
@@ -91,6 +99,62 @@ impl Backend {
     }
 }
 
+/// Renders an execution as a `docker run` command.
+///
+/// The command is rendered with the same flag model as the Docker backend:
+/// inputs specified as paths are bound into the container, and the resource
+/// limits are applied. Inputs specified by URL or literal contents cannot be
+/// bound and are skipped.
+fn docker_run(
+    execution: &Execution,
+    inputs: &[Input],
+    resources: Option<&Resources>,
+) -> Result<String> {
+    let mut mounts = Vec::new();
+    for input in inputs {
+        match input.contents() {
+            Contents::Path(path) => mounts.push(Mount {
+                source: Some(path.display().to_string()),
+                target: Some(input.path().to_string()),
+                typ: Some(MountTypeEnum::BIND),
+                read_only: Some(input.read_only()),
+                ..Default::default()
+            }),
+            _ => warn!(
+                "input `{path}` is not a path on the host and cannot be bound into the container",
+                path = input.path()
+            ),
+        }
+    }
+
+    // The client is only used to create the builder; no requests are made
+    let mut builder = crankshaft_docker::Docker::with_http_defaults()?
+        .container_builder()
+        .image(&execution.image)
+        .program(&execution.program)
+        .args(&execution.args)
+        .envs(&execution.env)
+        .host_config(HostConfig {
+            mounts: Some(mounts),
+            ..resources.map(Into::into).unwrap_or_default()
+        });
+
+    for (name, value) in &execution.secret_env {
+        builder = builder.secret_env(name, value.expose());
+    }
+
+    if let Some(work_dir) = &execution.work_dir {
+        builder = builder.work_dir(work_dir);
+    }
+
+    let args = builder.run_args()?;
+    Ok(shlex::try_join(
+        ["docker", "run", "--rm"]
+            .into_iter()
+            .chain(args.iter().map(String::as_str)),
+    )?)
+}
+
 impl crate::Backend for Backend {
     /// Gets the default name for the backend.
     fn default_name(&self) -> &'static str {
@@ -99,19 +163,38 @@ impl crate::Backend for Backend {
 
     /// Runs a task in a backend.
     fn run(
+        &self,
+        task: Task,
+        started: Option<oneshot::Sender<()>>,
+        token: CancellationToken,
+    ) -> Result<BoxFuture<'static, Result<NonEmpty<ExitStatus>, TaskRunError>>> {
+        self.run_with_logs(task, started, token, logs::Sender::new())
+    }
+
+    /// Runs a task in a backend, sending the output of each execution's submit
+    /// command as it is written.
+    fn run_with_logs(
         &self,
         task: Task,
         mut started: Option<oneshot::Sender<()>>,
         token: CancellationToken,
+        logs: logs::Sender,
     ) -> Result<BoxFuture<'static, Result<NonEmpty<ExitStatus>, TaskRunError>>> {
         let driver = self.driver.clone();
         let config = self.config.clone();
 
-        let default_substitutions = self
-            .resolve_resources(task.resources.as_ref())
+        let resources = self.resolve_resources(task.resources.as_ref());
+        let mut default_substitutions = resources
+            .as_ref()
             .map(|resources| resources.to_hashmap())
             .unwrap_or_default();
 
+        if let Some(Locale::SSH(ssh)) = config.driver().locale() {
+            if let Some(cache_dir) = ssh.cache_dir() {
+                default_substitutions.insert("cache_dir".into(), cache_dir.to_owned().into());
+            }
+        }
+
         Ok(async move {
             let mut statuses = Vec::new();
             let job_id_regex = config
@@ -123,21 +206,32 @@ impl crate::Backend for Backend {
                 })
                 .transpose()?;
 
-            for execution in task.executions {
+            for (index, execution) in task.executions.into_iter().enumerate() {
                 if token.is_cancelled() {
                     return Err(TaskRunError::Canceled);
                 }
 
+                let error_on_nonzero = execution.error_on_nonzero;
+
                 // TODO(clay): this will warn every time for now. We need to
                 // change the model of how tasks are done internally to remove
                 // this need.
-                warn!(
-                    "generic backends do not support images; as such, the directive to use a `{}` \
-                     image will be ignored",
-                    execution.image
-                );
+                if !config.uses_image() {
+                    warn!(
+                        "the submit command of the generic backend does not reference \
+                         `~{{image}}`; as such, the directive to use a `{}` image will be ignored",
+                        execution.image
+                    );
+                }
 
                 let mut substitutions = default_substitutions.clone();
+                if config.uses_docker_run() {
+                    let command = docker_run(&execution, &task.inputs, resources.as_ref())
+                        .context("failed to render the `docker run` command")?;
+                    substitutions.insert("docker_run".into(), command.into());
+                }
+
+                substitutions.insert("image".into(), execution.image.into());
 
                 if substitutions
                     .insert(
@@ -164,8 +258,9 @@ impl crate::Backend for Backend {
                 let submit = config
                     .resolve_submit(&substitutions)
                     .context("failed to resolve submit command")?;
+                let logs = logs.clone();
                 let output = driver
-                    .run(submit)
+                    .run_streaming(submit, move |stream, line| logs.send(stream, index, line))
                     .await
                     .context("failed to run submit command")?;
 
@@ -236,6 +331,11 @@ impl crate::Backend for Backend {
                         statuses.push(output.status);
                     }
                 }
+
+                // Stop running executions if a non-zero exit is an error
+                if error_on_nonzero && statuses.last().is_some_and(|s| !s.success()) {
+                    break;
+                }
             }
 
             // SAFETY: each task _must_ have at least one execution, so at least one
@@ -245,3 +345,34 @@ impl crate::Backend for Backend {
         .boxed())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_docker_run() {
+        let execution = Execution::builder()
+            .image("ubuntu:latest")
+            .program("cat")
+            .args(vec![String::from("/in/data.txt")])
+            .work_dir("/work")
+            .build();
+        let input = Input::builder()
+            .contents(Contents::Path("/data/data.txt".into()))
+            .path("/in/data.txt")
+            .ty(crate::task::input::Type::File)
+            .build();
+        let resources = Resources::builder().cpu_limit(2.0).build();
+
+        let command = docker_run(&execution, &[input], Some(&resources)).unwrap();
+        assert!(
+            command.starts_with("docker run --rm '--entrypoint=' '--workdir=/work'"),
+            "{command}"
+        );
+        assert!(command.contains(
+            "'--mount=type=bind,source=/data/data.txt,target=/in/data.txt,readonly' '--cpus=2'"
+        ));
+        assert!(command.ends_with("ubuntu:latest cat /in/data.txt"));
+    }
+}
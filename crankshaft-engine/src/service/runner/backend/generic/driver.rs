@@ -1,6 +1,6 @@
 //! Command drivers in a generic backend.
 
-use std::io::Read as _;
+use std::io::BufRead as _;
 #[cfg(unix)]
 use std::os::unix::process::ExitStatusExt;
 #[cfg(windows)]
@@ -21,12 +21,19 @@ use rand::Rng as _;
 use ssh2::Channel;
 use ssh2::Session;
 use thiserror::Error;
+use tokio::io::AsyncBufReadExt as _;
+use tokio::io::AsyncRead;
 use tokio::net::TcpStream;
 use tokio::process::Command;
 use tracing::debug;
 use tracing::error;
 use tracing::trace;
 
+use crate::logs::OutputStream;
+
+/// A callback invoked with each line of output written by a command.
+type LineCallback = dyn Fn(OutputStream, &str) + Send + Sync;
+
 /// An error related to a [`Driver`].
 #[derive(Error, Debug)]
 pub enum Error {
@@ -112,12 +119,34 @@ impl Driver {
     /// are intended to be returned directly to the user in the calling binary
     /// (i.e., the errors are typically unrecoverable).
     pub async fn run(&self, command: impl Into<String>) -> Result<Output> {
-        let command = command.into();
+        self.run_command(command.into(), None).await
+    }
+
+    /// Runs a shell command within the configuration locale, invoking the
+    /// given callback with each line of its output as it is written.
+    ///
+    /// The output is also collected and returned as with [`Self::run()`].
+    /// Over SSH, stdout is streamed as it is written and stderr is streamed
+    /// once stdout has been closed.
+    pub async fn run_streaming(
+        &self,
+        command: impl Into<String>,
+        on_line: impl Fn(OutputStream, &str) + Send + Sync + 'static,
+    ) -> Result<Output> {
+        self.run_command(command.into(), Some(Arc::new(on_line)))
+            .await
+    }
 
+    /// Runs a shell command, optionally streaming its output.
+    async fn run_command(
+        &self,
+        command: String,
+        on_line: Option<Arc<LineCallback>>,
+    ) -> Result<Output> {
         match &self.transport {
-            Transport::Local => run_local_command(command, &self.config).await,
+            Transport::Local => run_local_command(command, &self.config, on_line).await,
             Transport::SSH(session) => {
-                run_ssh_command(session.clone(), &self.config, command).await
+                run_ssh_command(session.clone(), &self.config, command, on_line).await
             }
         }
     }
@@ -137,30 +166,104 @@ impl Driver {
 // Local Execution //
 //=================//
 
+/// Reads the output of a local command, invoking the callback (if there is
+/// one) with each line.
+async fn read_output(
+    reader: impl AsyncRead + Unpin,
+    stream: OutputStream,
+    on_line: Option<Arc<LineCallback>>,
+) -> std::io::Result<Vec<u8>> {
+    let mut reader = tokio::io::BufReader::new(reader);
+    let mut output = Vec::new();
+
+    loop {
+        let start = output.len();
+        if reader.read_until(b'\n', &mut output).await? == 0 {
+            return Ok(output);
+        }
+
+        if let Some(on_line) = &on_line {
+            let line = String::from_utf8_lossy(&output[start..]);
+            on_line(stream, line.trim_end_matches(['\r', '\n']));
+        }
+    }
+}
+
 /// Runs a command in a local context.
-async fn run_local_command(command: String, config: &Config) -> Result<Output> {
+async fn run_local_command(
+    command: String,
+    config: &Config,
+    on_line: Option<Arc<LineCallback>>,
+) -> Result<Output> {
     trace!("executing local command: `{command}`");
 
     // NOTE: this is cloned because `default()` is only implemented on the owned
     // [`Locale`] type (not a reference).
-    let command = match config.shell().unwrap_or_default() {
-        Shell::Bash => Command::new("/usr/bin/env")
-            .args(["bash", "-c", &command])
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn(),
-        Shell::Sh => Command::new("/usr/bin/env")
-            .args(["sh", "-c", &command])
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn(),
-    }
-    .context("spawning the local command")?;
+    let mut command = match config.shell().unwrap_or_default() {
+        Shell::Bash => {
+            let mut cmd = Command::new("/usr/bin/env");
+            cmd.args(["bash", "-c", &command]);
+            cmd
+        }
+        Shell::Sh => {
+            let mut cmd = Command::new("/usr/bin/env");
+            cmd.args(["sh", "-c", &command]);
+            cmd
+        }
+    };
 
-    command
-        .wait_with_output()
-        .await
-        .context("executing the local command")
+    // Spawn the command in its own process group so that the entire process
+    // tree can be killed if the command is canceled
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let mut child = command
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("spawning the local command")?;
+
+    let mut group = ProcessGroup(child.id());
+
+    // SAFETY: both streams were piped when the command was spawned.
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let (stdout, stderr, status) = tokio::try_join!(
+        read_output(stdout, OutputStream::Stdout, on_line.clone()),
+        read_output(stderr, OutputStream::Stderr, on_line),
+        child.wait(),
+    )
+    .context("executing the local command")?;
+
+    // The command has exited, so there is no need to kill the process group
+    group.0 = None;
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// The process group of a local command.
+///
+/// The process group is killed when dropped; this occurs when the future
+/// running the local command is dropped before the command exits (e.g., when
+/// a task is canceled).
+struct ProcessGroup(Option<u32>);
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(id) = self.0 {
+            trace!("killing process group {id}");
+
+            // SAFETY: `killpg` has no memory safety requirements; the id is
+            // that of a process group created when the command was spawned.
+            unsafe {
+                libc::killpg(id as libc::pid_t, libc::SIGKILL);
+            }
+        }
+    }
 }
 
 //===============//
@@ -192,8 +295,41 @@ async fn create_ssh_transport(config: &ssh::Config) -> Result<Transport> {
         .map_err(Error::SSH2)
         .context("performing the SSH handshake")?;
 
-    // Connect to the SSH agent and authenticate within the current
-    // session.
+    let username = config
+        .username()
+        .map(ToOwned::to_owned)
+        .unwrap_or_else(whoami::username);
+
+    // Authenticate with the configured private key or, if there isn't one,
+    // with the identity within the SSH agent.
+    debug!("authenticating SSH session");
+
+    match config.key() {
+        Some(key) => {
+            trace!("authenticating with the private key `{}`", key.display());
+            sess.userauth_pubkey_file(&username, None, key, None)
+                .map_err(Error::SSH2)
+                .with_context(|| {
+                    format!(
+                        "authenticating with username `{username}` and private key `{key}`",
+                        key = key.display()
+                    )
+                })?;
+        }
+        None => authenticate_with_agent(&sess, &username)?,
+    }
+
+    if sess.authenticated() {
+        debug!("authentication successful");
+        Ok(Transport::SSH(Arc::new(sess)))
+    } else {
+        error!("authentication failed!");
+        bail!("failed authentication")
+    }
+}
+
+/// Authenticates an SSH session with the identity within the SSH agent.
+fn authenticate_with_agent(sess: &Session, username: &str) -> Result<()> {
     debug!("retrieving identities from the SSH agent");
 
     trace!("initializing the SSH agent");
@@ -236,42 +372,16 @@ async fn create_ssh_transport(config: &ssh::Config) -> Result<Transport> {
         key.comment()
     );
 
-    // Authenticate the SSH session.
-    debug!("authenticating SSH session");
-
-    if let Some(username) = config.username() {
-        agent
-            .userauth(username, key)
-            .map_err(Error::SSH2)
-            .with_context(|| {
-                format!(
-                    "authenticating with username `{}` and identity `{}`",
-                    username,
-                    key.comment()
-                )
-            })?;
-    } else {
-        let username = whoami::username();
-
-        agent
-            .userauth(&username, key)
-            .map_err(Error::SSH2)
-            .with_context(|| {
-                format!(
-                    "authenticating with username `{}` and identity `{}`",
-                    username,
-                    key.comment()
-                )
-            })?;
-    }
-
-    if sess.authenticated() {
-        debug!("authentication successful");
-        Ok(Transport::SSH(Arc::new(sess)))
-    } else {
-        error!("authentication failed!");
-        bail!("failed authentication")
-    }
+    agent
+        .userauth(username, key)
+        .map_err(Error::SSH2)
+        .with_context(|| {
+            format!(
+                "authenticating with username `{}` and identity `{}`",
+                username,
+                key.comment()
+            )
+        })
 }
 
 /// The minimum amount of waiting time.
@@ -317,11 +427,37 @@ fn channel_session_with_backoff(
     unreachable!()
 }
 
+/// Reads the output of a remote command, invoking the callback (if there is
+/// one) with each line.
+fn read_remote_output(
+    reader: impl std::io::Read,
+    stream: OutputStream,
+    on_line: Option<&LineCallback>,
+) -> std::io::Result<Vec<u8>> {
+    let mut reader = std::io::BufReader::new(reader);
+    let mut output = Vec::new();
+
+    loop {
+        let start = output.len();
+        if reader.read_until(b'\n', &mut output)? == 0 {
+            return Ok(output);
+        }
+
+        let line = String::from_utf8_lossy(&output[start..]);
+        let line = line.trim_end_matches(['\r', '\n']);
+        trace!("{stream:?}: {line}");
+        if let Some(on_line) = on_line {
+            on_line(stream, line);
+        }
+    }
+}
+
 /// Runs a remote command over SSH.
 async fn run_ssh_command(
     session: Arc<ssh2::Session>,
     config: &Config,
     command: String,
+    on_line: Option<Arc<LineCallback>>,
 ) -> Result<Output> {
     let max_attempts = config.max_attempts();
 
@@ -343,29 +479,16 @@ async fn run_ssh_command(
 
         // Read the entire output that was written to the channel.
         trace!("reading the stdout of the command");
-        let mut stdout = Vec::new();
-        channel
-            .read_to_end(&mut stdout)
+        let stdout = read_remote_output(&mut channel, OutputStream::Stdout, on_line.as_deref())
             .map_err(Error::Io)
             .context("reading the stdout of the command over SSH")?;
 
-        for line in String::from_utf8_lossy(&stdout).lines() {
-            trace!("stdout: {line}");
-        }
-
         // Read the entire stderr that was written to the channel.
         trace!("reading the stderr of the command");
-        let mut stderr = Vec::new();
-        channel
-            .stderr()
-            .read_to_end(&mut stderr)
+        let stderr = read_remote_output(channel.stderr(), OutputStream::Stderr, on_line.as_deref())
             .map_err(Error::Io)
             .context("reading the stderr of the command over SSH")?;
 
-        for line in String::from_utf8_lossy(&stderr).lines() {
-            trace!("stderr: {line}");
-        }
-
         // Getting the exit code.
         let status = channel
             .exit_status()
@@ -410,3 +533,45 @@ async fn run_ssh_command(
         .map_err(Error::Join)
         .context("running an SSH command")?
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn streams_local_output() {
+        let driver = Driver::initialize(Config::default()).await.unwrap();
+
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let output = {
+            let lines = lines.clone();
+            driver
+                .run_streaming(
+                    "echo one; echo two >&2; printf three",
+                    move |stream, line| {
+                        lines.lock().unwrap().push((stream, line.to_string()));
+                    },
+                )
+                .await
+                .unwrap()
+        };
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"one\nthree");
+        assert_eq!(output.stderr, b"two\n");
+
+        let mut lines = lines.lock().unwrap().clone();
+        lines.sort_by_key(|(stream, _)| *stream == OutputStream::Stderr);
+        assert_eq!(
+            lines,
+            [
+                (OutputStream::Stdout, String::from("one")),
+                (OutputStream::Stdout, String::from("three")),
+                (OutputStream::Stderr, String::from("two")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Cancellation of groups of tasks.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use tokio::select;
+use tokio_util::sync::CancellationToken;
+use tokio_util::sync::DropGuard;
+
+/// The error returned when a task is submitted under a group that has been
+/// canceled.
+///
+/// Callers can downcast the error returned from [`Runner::spawn()`] or
+/// [`Runner::submit()`] to this type.
+///
+/// [`Runner::spawn()`]: super::Runner::spawn
+/// [`Runner::submit()`]: super::Runner::submit
+#[derive(Debug, thiserror::Error)]
+#[error("group `{group}` has been canceled")]
+pub struct GroupCanceled {
+    /// The group that was canceled.
+    pub group: String,
+}
+
+/// The cancellation tokens of every group, shared by the runners of an
+/// engine.
+#[derive(Debug, Default)]
+pub(crate) struct Groups(Mutex<HashMap<String, CancellationToken>>);
+
+impl Groups {
+    /// Gets the token of a group, creating it if the group has none.
+    fn token(&self, group: &str) -> CancellationToken {
+        self.0
+            .lock()
+            .unwrap()
+            .entry(group.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Cancels a group.
+    ///
+    /// Returns `false` if the group was already canceled.
+    pub(crate) fn cancel(&self, group: &str) -> bool {
+        let token = self.token(group);
+        let canceled = !token.is_cancelled();
+        token.cancel();
+        canceled
+    }
+
+    /// Checks that a new task may be submitted under a group.
+    pub(crate) fn admit(&self, group: &str) -> Result<(), GroupCanceled> {
+        match self.0.lock().unwrap().get(group) {
+            Some(token) if token.is_cancelled() => Err(GroupCanceled {
+                group: group.to_string(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Creates the token of a task of a group, which is canceled when either
+    /// the group or the given token is canceled.
+    ///
+    /// The returned guard must be held for as long as the task runs; dropping
+    /// it stops watching the given token.
+    pub(crate) fn link(
+        &self,
+        group: &str,
+        token: &CancellationToken,
+    ) -> (CancellationToken, DropGuard) {
+        let linked = self.token(group).child_token();
+        let done = CancellationToken::new();
+
+        tokio::spawn({
+            let linked = linked.clone();
+            let token = token.clone();
+            let done = done.clone();
+            async move {
+                select! {
+                    _ = token.cancelled() => linked.cancel(),
+                    _ = linked.cancelled() => {}
+                    _ = done.cancelled() => {}
+                }
+            }
+        });
+
+        (linked, done.drop_guard())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancels_groups() {
+        let groups = Groups::default();
+        let token = CancellationToken::new();
+        let (first, _guard) = groups.link("a", &token);
+        let (other, _other_guard) = groups.link("b", &token.child_token());
+        assert!(groups.admit("a").is_ok());
+
+        assert!(groups.cancel("a"));
+        assert!(!groups.cancel("a"));
+        assert!(first.is_cancelled());
+        assert!(!other.is_cancelled());
+        assert_eq!(groups.admit("a").unwrap_err().group, "a");
+        assert!(groups.admit("b").is_ok());
+
+        // Tasks are still canceled by their own tokens
+        token.cancel();
+        tokio::time::timeout(std::time::Duration::from_secs(5), other.cancelled())
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+//! Combinators for waiting on multiple [`TaskHandle`]s.
+
+use std::process::ExitStatus;
+
+use futures::StreamExt as _;
+use futures::stream::FuturesUnordered;
+use nonempty::NonEmpty;
+
+use super::TaskHandle;
+use super::backend::TaskRunError;
+
+/// The result of a task.
+type TaskResult = Result<NonEmpty<ExitStatus>, TaskRunError>;
+
+/// Returns whether or not every execution of a task exited successfully.
+pub(crate) fn succeeded(result: &TaskResult) -> bool {
+    result
+        .as_ref()
+        .map(|statuses| statuses.iter().all(|status| status.success()))
+        .unwrap_or(false)
+}
+
+/// Waits for every task to complete.
+///
+/// The results are returned in the same order as the provided handles.
+pub async fn join_all(handles: impl IntoIterator<Item = TaskHandle>) -> Vec<TaskResult> {
+    futures::future::join_all(handles.into_iter().map(TaskHandle::wait)).await
+}
+
+/// Waits for the first task to complete and cancels the remaining tasks.
+///
+/// Returns the index of the first task to complete along with its result or
+/// `None` if no handles were provided.
+pub async fn select_any(
+    handles: impl IntoIterator<Item = TaskHandle>,
+) -> Option<(usize, TaskResult)> {
+    let (tokens, mut pending): (Vec<_>, FuturesUnordered<_>) = handles
+        .into_iter()
+        .enumerate()
+        .map(|(i, handle)| {
+            (
+                handle.token().clone(),
+                async move { (i, handle.wait().await) },
+            )
+        })
+        .unzip();
+
+    let first = pending.next().await;
+
+    for token in tokens {
+        token.cancel();
+    }
+
+    first
+}
+
+/// Waits for the first task to complete successfully and cancels the remaining
+/// tasks.
+///
+/// A task completes successfully when every one of its executions exits
+/// successfully.
+///
+/// Returns the index of the first successful task along with its exit
+/// statuses or `None` if no task succeeded.
+pub async fn first_success(
+    handles: impl IntoIterator<Item = TaskHandle>,
+) -> Option<(usize, NonEmpty<ExitStatus>)> {
+    let (tokens, mut pending): (Vec<_>, FuturesUnordered<_>) = handles
+        .into_iter()
+        .enumerate()
+        .map(|(i, handle)| {
+            (
+                handle.token().clone(),
+                async move { (i, handle.wait().await) },
+            )
+        })
+        .unzip();
+
+    let mut winner = None;
+    while let Some((i, result)) = pending.next().await {
+        if succeeded(&result) {
+            // SAFETY: we just checked that the result is successful.
+            winner = Some((i, result.unwrap()));
+            break;
+        }
+    }
+
+    for token in tokens {
+        token.cancel();
+    }
+
+    winner
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::future::Future;
+    use std::os::unix::process::ExitStatusExt as _;
+    use std::time::Duration;
+
+    use tokio::sync::oneshot;
+    use tokio::sync::watch;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::logs;
+    use crate::service::runner::TaskStatus;
+
+    /// Creates a handle to a task that runs `run` with the task's token.
+    fn handle<F>(run: impl FnOnce(CancellationToken) -> F) -> TaskHandle
+    where
+        F: Future<Output = TaskResult> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let token = CancellationToken::new();
+        let run = run(token.clone());
+        tokio::spawn(async move {
+            let _ = tx.send(run.await);
+        });
+
+        TaskHandle {
+            rx,
+            token,
+            guard: None,
+            logs: logs::Sender::new().downgrade(),
+            status: watch::channel(TaskStatus::Queued).1,
+        }
+    }
+
+    /// A task that succeeds after a delay.
+    fn succeeds(delay: u64) -> TaskHandle {
+        handle(|_| async move {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Ok(NonEmpty::new(ExitStatus::from_raw(0)))
+        })
+    }
+
+    /// A task that fails after a delay.
+    fn fails(delay: u64) -> TaskHandle {
+        handle(|_| async move {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Err(TaskRunError::Other(anyhow::anyhow!("failed")))
+        })
+    }
+
+    /// A task that runs until it is canceled.
+    fn runs() -> TaskHandle {
+        handle(|token| async move {
+            token.cancelled().await;
+            Err(TaskRunError::Canceled)
+        })
+    }
+
+    #[tokio::test]
+    async fn joins_all() {
+        let results = join_all([succeeds(10), fails(0)]).await;
+        assert!(succeeded(&results[0]));
+        assert!(matches!(results[1], Err(TaskRunError::Other(_))));
+        assert!(join_all([]).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn selects_any() {
+        let loser = runs();
+        let token = loser.token().clone();
+        let (i, result) = select_any([loser, succeeds(0)]).await.unwrap();
+        assert_eq!(i, 1);
+        assert!(succeeded(&result));
+        assert!(token.is_cancelled());
+
+        // The first task to complete is selected even if it failed
+        let loser = succeeds(1000);
+        let token = loser.token().clone();
+        let (i, result) = select_any([loser, fails(0)]).await.unwrap();
+        assert_eq!(i, 1);
+        assert!(matches!(result, Err(TaskRunError::Other(_))));
+        assert!(token.is_cancelled());
+
+        assert!(select_any([]).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn finds_first_success() {
+        let loser = runs();
+        let token = loser.token().clone();
+        let (i, statuses) = first_success([fails(0), loser, succeeds(10)])
+            .await
+            .unwrap();
+        assert_eq!(i, 2);
+        assert!(statuses.first().success());
+        assert!(token.is_cancelled());
+
+        assert!(first_success([fails(0), fails(10)]).await.is_none());
+        assert!(first_success([]).await.is_none());
+    }
+}
@@ -0,0 +1,578 @@
+//! Scheduling of tasks against the capacity of a runner.
+//!
+//! Tasks waiting to run are started in order of priority (and then in the
+//! order they were submitted). A task is only started once a permit for a
+//! concurrent task is available and, if the runner has a configured
+//! [`Capacity`], once the CPUs and memory it requests fit within the capacity
+//! not already requested by running tasks. If the capacity lists GPU
+//! devices, tasks requesting GPUs are also only started once enough devices
+//! are free, and are assigned devices that no other running task is using.
+//!
+//! Tasks are started strictly in order: a task that does not yet fit blocks
+//! the tasks behind it so that tasks requesting large amounts of resources
+//! are not starved by a stream of smaller tasks.
+//!
+//! A task that requests more than the capacity either runs once nothing else
+//! is running or, if the capacity rejects such tasks, is rejected before it
+//! is queued (see [`Scheduler::check()`]).
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use crankshaft_config::backend::Capacity;
+use crankshaft_config::backend::Oversized;
+use tokio::select;
+use tokio::sync::Notify;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+
+use super::ExceedsCapacity;
+use crate::Task;
+use crate::task::Gpus;
+
+/// A request to start a task.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Request {
+    /// The priority of the task.
+    priority: i32,
+
+    /// The number of CPUs requested by the task.
+    cpu: f64,
+
+    /// The amount of RAM (in GiB) requested by the task.
+    ram: f64,
+
+    /// The GPUs requested by the task.
+    gpus: Option<Gpus>,
+}
+
+impl Request {
+    /// Creates a request to start the given task.
+    ///
+    /// Tasks that do not request CPUs or memory are treated as requesting
+    /// none.
+    pub(crate) fn new(task: &Task) -> Self {
+        let resources = task.resources.as_ref();
+        Self {
+            priority: task.priority,
+            cpu: resources.and_then(|r| r.cpu()).unwrap_or_default(),
+            ram: resources.and_then(|r| r.ram()).unwrap_or_default(),
+            gpus: resources.and_then(|r| r.gpus()).cloned(),
+        }
+    }
+}
+
+/// A task waiting to be started.
+#[derive(Debug)]
+struct Waiter {
+    /// The request to start the task.
+    request: Request,
+
+    /// The order in which the task was submitted.
+    sequence: u64,
+
+    /// The sender for the task's admission.
+    tx: oneshot::Sender<Admission>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Higher priorities first, then earlier submissions first
+        self.request
+            .priority
+            .cmp(&other.request.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+/// The state of a scheduler.
+#[derive(Debug, Default)]
+struct State {
+    /// The tasks waiting to be started.
+    waiting: BinaryHeap<Waiter>,
+
+    /// The number of tasks submitted so far.
+    submitted: u64,
+
+    /// The number of CPUs requested by running tasks.
+    cpu: f64,
+
+    /// The amount of RAM (in GiB) requested by running tasks.
+    ram: f64,
+
+    /// The GPU devices in use by running tasks.
+    gpus: HashSet<String>,
+}
+
+/// The GPU devices claimed for a request.
+#[derive(Debug, Default)]
+struct Devices {
+    /// The devices claimed.
+    claimed: Vec<String>,
+
+    /// The devices assigned to the task, if the task requested a number of
+    /// GPUs rather than specific devices.
+    assigned: Option<Vec<String>>,
+}
+
+/// Determines if a request fits within the remaining capacity for a
+/// resource.
+///
+/// A request that exceeds the total capacity fits once nothing else is
+/// using the resource so that it can still (eventually) run.
+fn fits(requested: f64, used: f64, capacity: Option<f64>) -> bool {
+    match capacity {
+        Some(capacity) => used == 0.0 || used + requested <= capacity,
+        None => true,
+    }
+}
+
+/// Determines which GPU devices to claim for a request, if the request fits
+/// within the devices not in use.
+///
+/// Requests for GPUs are not tracked if no devices are available. As with
+/// other resources, a request for more GPUs than are available fits once no
+/// devices are in use.
+fn claim_gpus(
+    request: Option<&Gpus>,
+    in_use: &HashSet<String>,
+    available: &[String],
+) -> Option<Devices> {
+    let request = match request {
+        Some(request) if !available.is_empty() => request,
+        _ => return Some(Devices::default()),
+    };
+
+    match request {
+        Gpus::Count(count) => {
+            let count = *count as usize;
+            if count > available.len() {
+                return in_use.is_empty().then(|| Devices {
+                    claimed: available.to_vec(),
+                    assigned: None,
+                });
+            }
+
+            let free = available
+                .iter()
+                .filter(|device| !in_use.contains(*device))
+                .take(count)
+                .cloned()
+                .collect::<Vec<_>>();
+
+            (free.len() == count).then(|| Devices {
+                claimed: free.clone(),
+                assigned: Some(free),
+            })
+        }
+        Gpus::Devices(devices) => {
+            if devices.iter().any(|device| in_use.contains(device)) {
+                return None;
+            }
+
+            Some(Devices {
+                claimed: devices
+                    .iter()
+                    .filter(|device| available.contains(device))
+                    .cloned()
+                    .collect(),
+                assigned: None,
+            })
+        }
+    }
+}
+
+/// The state shared between a scheduler, its dispatcher, and its admissions.
+#[derive(Debug)]
+struct Shared {
+    /// The state of the scheduler.
+    state: Mutex<State>,
+
+    /// The capacity of the runner.
+    capacity: Capacity,
+
+    /// Notified when a task is submitted or the resources of a task are
+    /// released.
+    notify: Notify,
+}
+
+impl Shared {
+    /// Takes the next waiting task if it fits within the remaining capacity,
+    /// claiming its requested resources.
+    fn take(self: &Arc<Self>) -> Option<(Waiter, Claim)> {
+        let mut state = self.state.lock().unwrap();
+        let next = state.waiting.peek()?;
+        if !fits(next.request.cpu, state.cpu, self.capacity.cpu())
+            || !fits(next.request.ram, state.ram, self.capacity.ram())
+        {
+            return None;
+        }
+
+        let devices = claim_gpus(
+            next.request.gpus.as_ref(),
+            &state.gpus,
+            self.capacity.gpus(),
+        )?;
+
+        let waiter = state.waiting.pop().unwrap();
+        state.cpu += waiter.request.cpu;
+        state.ram += waiter.request.ram;
+        state.gpus.extend(devices.claimed.iter().cloned());
+
+        let claim = Claim {
+            shared: self.clone(),
+            cpu: waiter.request.cpu,
+            ram: waiter.request.ram,
+            gpus: devices.claimed,
+            assigned: devices.assigned,
+        };
+
+        Some((waiter, claim))
+    }
+}
+
+/// The resources claimed by a running task.
+///
+/// The resources are released when dropped.
+#[derive(Debug)]
+struct Claim {
+    /// The state of the scheduler.
+    shared: Arc<Shared>,
+
+    /// The number of CPUs claimed.
+    cpu: f64,
+
+    /// The amount of RAM (in GiB) claimed.
+    ram: f64,
+
+    /// The GPU devices claimed.
+    gpus: Vec<String>,
+
+    /// The GPU devices assigned to the task, if any.
+    assigned: Option<Vec<String>>,
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            state.cpu = (state.cpu - self.cpu).max(0.0);
+            state.ram = (state.ram - self.ram).max(0.0);
+            for device in &self.gpus {
+                state.gpus.remove(device);
+            }
+        }
+
+        self.shared.notify.notify_one();
+    }
+}
+
+/// The admission of a task to run.
+///
+/// The task's permit and claimed resources are released when dropped.
+#[derive(Debug)]
+pub(crate) struct Admission {
+    /// The permit for the task to run concurrently.
+    permit: OwnedSemaphorePermit,
+
+    /// The resources claimed by the task.
+    claim: Claim,
+}
+
+impl Admission {
+    /// Gets the GPU devices assigned to the task.
+    ///
+    /// This is only set when the task requested a number of GPUs and the
+    /// runner's capacity lists its GPU devices.
+    pub(crate) fn gpus(&self) -> Option<&[String]> {
+        self.claim.assigned.as_deref()
+    }
+}
+
+/// A scheduler of the tasks of a runner.
+#[derive(Debug)]
+pub(crate) struct Scheduler {
+    /// The state shared with the dispatcher and admissions.
+    shared: Arc<Shared>,
+
+    /// The token for stopping the dispatcher.
+    token: CancellationToken,
+}
+
+impl Scheduler {
+    /// Creates a scheduler that starts tasks as permits become available in
+    /// `lock` and as they fit within the given capacity.
+    ///
+    /// This must be called from within a Tokio runtime.
+    pub(crate) fn new(lock: Arc<Semaphore>, capacity: Option<Capacity>) -> Self {
+        let shared = Arc::new(Shared {
+            state: Default::default(),
+            capacity: capacity.unwrap_or_default(),
+            notify: Notify::new(),
+        });
+
+        let token = CancellationToken::new();
+        tokio::spawn(dispatch(shared.clone(), lock, token.clone()));
+        Self { shared, token }
+    }
+
+    /// Checks that a request does not exceed the capacity if the capacity
+    /// rejects such requests.
+    pub(crate) fn check(&self, request: &Request) -> Result<(), ExceedsCapacity> {
+        let capacity = &self.shared.capacity;
+        if capacity.oversized() != Oversized::Reject {
+            return Ok(());
+        }
+
+        let gpus = match &request.gpus {
+            Some(Gpus::Count(count)) => *count as f64,
+            Some(Gpus::Devices(devices)) => devices.len() as f64,
+            None => 0.0,
+        };
+        let available = (!capacity.gpus().is_empty()).then(|| capacity.gpus().len() as f64);
+
+        for (resource, requested, capacity) in [
+            ("CPUs", request.cpu, capacity.cpu()),
+            ("GiB of RAM", request.ram, capacity.ram()),
+            ("GPUs", gpus, available),
+        ] {
+            if let Some(capacity) = capacity.filter(|capacity| requested > *capacity) {
+                return Err(ExceedsCapacity {
+                    resource,
+                    requested,
+                    capacity,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Waits for a task to be admitted to run.
+    pub(crate) async fn admit(&self, request: Request) -> Result<Admission> {
+        let (tx, rx) = oneshot::channel();
+
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            let sequence = state.submitted;
+            state.submitted += 1;
+            state.waiting.push(Waiter {
+                request,
+                sequence,
+                tx,
+            });
+        }
+
+        self.shared.notify.notify_one();
+        rx.await
+            .map_err(|_| anyhow!("the runner's scheduler has shut down"))
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
+
+/// Dispatches waiting tasks as permits and capacity become available.
+async fn dispatch(shared: Arc<Shared>, lock: Arc<Semaphore>, token: CancellationToken) {
+    loop {
+        let mut permit = select! {
+            _ = token.cancelled() => return,
+            permit = lock.clone().acquire_owned() => match permit {
+                Ok(permit) => permit,
+                Err(_) => return,
+            },
+        };
+
+        loop {
+            let notified = shared.notify.notified();
+
+            if let Some((waiter, claim)) = shared.take() {
+                match waiter.tx.send(Admission { permit, claim }) {
+                    Ok(()) => break,
+                    // The task is no longer waiting, so give its permit to
+                    // the next task
+                    Err(admission) => {
+                        permit = admission.permit;
+                        continue;
+                    }
+                }
+            }
+
+            select! {
+                _ = token.cancelled() => return,
+                _ = notified => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Creates a request with the given priority and resources.
+    fn request(priority: i32, cpu: f64, ram: f64) -> Request {
+        Request {
+            priority,
+            cpu,
+            ram,
+            gpus: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn admits_by_priority() {
+        let lock = Arc::new(Semaphore::new(1));
+        let scheduler = Arc::new(Scheduler::new(lock, None));
+
+        let first = scheduler.admit(request(0, 0.0, 0.0)).await.unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        for priority in [1, 5, 3] {
+            let scheduler = scheduler.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let admission = scheduler.admit(request(priority, 0.0, 0.0)).await.unwrap();
+                tx.send(priority).unwrap();
+                drop(admission);
+            });
+        }
+
+        // Let the tasks queue up behind the first task
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(first);
+
+        let mut order = Vec::new();
+        for _ in 0..3 {
+            order.push(rx.recv().await.unwrap());
+        }
+
+        assert_eq!(order, [5, 3, 1]);
+    }
+
+    #[tokio::test]
+    async fn packs_against_capacity() {
+        let lock = Arc::new(Semaphore::new(10));
+        let capacity = Capacity::builder().cpu(4.0).build();
+        let scheduler = Scheduler::new(lock, Some(capacity));
+
+        let a = scheduler.admit(request(0, 3.0, 0.0)).await.unwrap();
+        let b = scheduler.admit(request(0, 1.0, 0.0)).await.unwrap();
+
+        // The capacity is exhausted until a task finishes
+        let waiting = scheduler.admit(request(0, 2.0, 0.0));
+        tokio::pin!(waiting);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut waiting)
+                .await
+                .is_err()
+        );
+
+        drop(b);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut waiting)
+                .await
+                .is_err()
+        );
+
+        drop(a);
+        waiting.await.unwrap();
+
+        // Requests larger than the capacity still run (alone)
+        scheduler.admit(request(0, 8.0, 0.0)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_requests() {
+        let lock = Arc::new(Semaphore::new(10));
+        let capacity = Capacity::builder()
+            .cpu(4.0)
+            .ram(8.0)
+            .gpus(vec![String::from("0")])
+            .oversized(Oversized::Reject)
+            .build();
+        let scheduler = Scheduler::new(lock.clone(), Some(capacity));
+
+        assert!(scheduler.check(&request(0, 4.0, 8.0)).is_ok());
+        let err = scheduler.check(&request(0, 2.0, 16.0)).unwrap_err();
+        assert_eq!(err.resource, "GiB of RAM");
+        assert!(
+            scheduler
+                .check(&Request {
+                    gpus: Some(Gpus::Count(2)),
+                    ..Default::default()
+                })
+                .is_err()
+        );
+
+        // Oversized requests are only rejected if the capacity says so
+        let scheduler = Scheduler::new(lock, Some(Capacity::builder().cpu(4.0).build()));
+        assert!(scheduler.check(&request(0, 8.0, 0.0)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn assigns_distinct_gpus() {
+        let lock = Arc::new(Semaphore::new(10));
+        let capacity = Capacity::builder()
+            .gpus(vec![
+                String::from("0"),
+                String::from("1"),
+                String::from("2"),
+            ])
+            .build();
+        let scheduler = Scheduler::new(lock, Some(capacity));
+
+        let gpus = |gpus: Gpus| Request {
+            gpus: Some(gpus),
+            ..Default::default()
+        };
+
+        let a = scheduler.admit(gpus(Gpus::Count(2))).await.unwrap();
+        assert_eq!(a.gpus().unwrap(), ["0", "1"]);
+
+        let b = scheduler.admit(gpus(Gpus::Count(1))).await.unwrap();
+        assert_eq!(b.gpus().unwrap(), ["2"]);
+
+        // No devices are free until a task finishes
+        let waiting = scheduler.admit(gpus(Gpus::Devices(vec![String::from("1")])));
+        tokio::pin!(waiting);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut waiting)
+                .await
+                .is_err()
+        );
+
+        drop(a);
+        let c = waiting.await.unwrap();
+        assert!(c.gpus().is_none());
+
+        let d = scheduler.admit(gpus(Gpus::Count(1))).await.unwrap();
+        assert_eq!(d.gpus().unwrap(), ["0"]);
+    }
+}
@@ -0,0 +1,101 @@
+//! The status of spawned tasks.
+
+use std::time::Instant;
+
+use crate::events::Event;
+
+/// The status of a spawned task.
+///
+/// See [`TaskHandle::status()`](super::TaskHandle::status).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TaskStatus {
+    /// The task is waiting to run.
+    Queued,
+
+    /// The runner has handed the task to the backend.
+    Running {
+        /// When the task was handed to the backend.
+        since: Instant,
+
+        /// Whether the backend has reported that the task's first execution
+        /// has started.
+        started: bool,
+    },
+
+    /// The task finished running.
+    Finished {
+        /// The exit codes of the executions that ran.
+        ///
+        /// An exit code is `None` if the execution was terminated by a
+        /// signal.
+        exit_codes: Vec<Option<i32>>,
+
+        /// The error that the task failed with (if it failed).
+        error: Option<String>,
+    },
+}
+
+impl TaskStatus {
+    /// Returns whether or not the task has finished running.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Finished { .. })
+    }
+
+    /// Applies an event in the lifecycle of the task to the status.
+    pub(crate) fn apply(&mut self, event: &Event) {
+        match event {
+            Event::Queued { .. } => *self = Self::Queued,
+            Event::Running { .. } => {
+                *self = Self::Running {
+                    since: Instant::now(),
+                    started: false,
+                }
+            }
+            Event::Started { .. } => {
+                if let Self::Running { started, .. } = self {
+                    *started = true;
+                }
+            }
+            Event::Exited {
+                exit_codes, error, ..
+            } => {
+                *self = Self::Finished {
+                    exit_codes: exit_codes.clone(),
+                    error: error.clone(),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_events() {
+        let mut status = TaskStatus::Queued;
+
+        status.apply(&Event::Running { id: 0, name: None });
+        assert!(matches!(status, TaskStatus::Running { started: false, .. }));
+
+        status.apply(&Event::Started { id: 0, name: None });
+        assert!(matches!(status, TaskStatus::Running { started: true, .. }));
+
+        status.apply(&Event::Exited {
+            id: 0,
+            name: None,
+            exit_codes: vec![Some(0)],
+            error: None,
+        });
+        assert!(status.is_finished());
+        assert_eq!(
+            status,
+            TaskStatus::Finished {
+                exit_codes: vec![Some(0)],
+                error: None,
+            }
+        );
+    }
+}
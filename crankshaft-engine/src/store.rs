@@ -0,0 +1,990 @@
+//! A persistent store of task attempts.
+//!
+//! When enabled (see [`Engine::with_store()`](crate::Engine::with_store)), an
+//! [`Attempt`] is recorded in an embedded SQLite database for every task that
+//! is submitted: its specification, the commands of its executions, when it
+//! was queued, started, and finished running, its result, and where its output
+//! was written. Attempts are recorded as soon as they are queued, so attempts
+//! that never finished (e.g., because the process crashed) can be found
+//! afterwards with [`Store::unfinished()`] and resubmitted with
+//! [`Engine::recover()`](crate::Engine::recover).
+//!
+//! The store also backs the call cache (see
+//! [`Engine::with_call_cache()`](crate::Engine::with_call_cache)): the key of
+//! every cacheable attempt is recorded so that a later task with the same key
+//! reuses the result of the attempt instead of running again.
+//!
+//! The store also records the digest that each image reference was resolved
+//! to (see [`Engine::resolve()`](crate::Engine::resolve)), so the exact images
+//! a workflow ran can be determined afterwards.
+//!
+//! The database is accessed on a dedicated thread so that its synchronous I/O
+//! never blocks the async runtime.
+//!
+//! This module requires the `store` feature.
+
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt as _;
+#[cfg(windows)]
+use std::os::windows::process::ExitStatusExt as _;
+use std::path::Path;
+use std::process::ExitStatus;
+
+use futures::FutureExt as _;
+use futures::future::BoxFuture;
+use nonempty::NonEmpty;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension as _;
+use rusqlite::Row;
+use rusqlite::params;
+use rusqlite::types::Type;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tracing::warn;
+
+use crate::Task;
+use crate::audit;
+use crate::service::runner::backend::TaskRunError;
+
+pub(crate) mod cache;
+
+/// The value that secret environment variables are replaced with in stored
+/// task specifications.
+const REDACTED: &str = "<redacted>";
+
+/// The schema of the store.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS attempts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id INTEGER NOT NULL,
+    runner TEXT NOT NULL,
+    task TEXT,
+    attempt INTEGER,
+    backend TEXT NOT NULL,
+    spec TEXT NOT NULL,
+    redacted INTEGER NOT NULL,
+    commands TEXT NOT NULL,
+    stdout TEXT NOT NULL,
+    stderr TEXT NOT NULL,
+    outputs TEXT NOT NULL,
+    queued INTEGER NOT NULL,
+    started INTEGER,
+    ended INTEGER,
+    exit_codes TEXT,
+    error TEXT,
+    key TEXT,
+    cached INTEGER,
+    task_group TEXT,
+    canceled INTEGER,
+    checksums TEXT
+);
+CREATE INDEX IF NOT EXISTS attempts_task ON attempts (task);
+CREATE INDEX IF NOT EXISTS attempts_key ON attempts (key);
+CREATE INDEX IF NOT EXISTS attempts_group ON attempts (task_group);
+CREATE TABLE IF NOT EXISTS images (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    runner TEXT NOT NULL,
+    image TEXT NOT NULL,
+    resolved TEXT NOT NULL,
+    time INTEGER NOT NULL
+);
+";
+
+/// The columns selected when reading attempts.
+const COLUMNS: &str = "id, run_id, runner, task, attempt, backend, spec, redacted, commands, \
+                       stdout, stderr, outputs, queued, started, ended, exit_codes, error, key, \
+                       cached, task_group, canceled, checksums";
+
+/// An error accessing the store.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The database could not be accessed.
+    #[error(transparent)]
+    Database(#[from] rusqlite::Error),
+
+    /// A stored value could not be serialized or deserialized.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    /// The thread accessing the database could not be spawned.
+    #[error("failed to spawn the thread of the store")]
+    Thread(#[source] std::io::Error),
+
+    /// The thread accessing the database exited (e.g., by panicking).
+    #[error("the thread of the store has exited")]
+    Closed,
+}
+
+/// A job run against the database on the thread of the store.
+type Job = Box<dyn FnOnce(&Connection) + Send>;
+
+/// An attempt to run a task as recorded in the store.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct Attempt {
+    /// The identifier of the attempt in the store.
+    pub id: i64,
+
+    /// The identifier the engine assigned to the task when it ran.
+    pub run_id: u64,
+
+    /// The name of the runner the task was submitted to.
+    pub runner: String,
+
+    /// The name of the task (if it has one).
+    pub task: Option<String>,
+
+    /// The attempt number of the task (if it has one).
+    pub attempt: Option<u32>,
+
+    /// The type of backend that ran the task.
+    pub backend: String,
+
+    /// The specification of the task, with the values of secret environment
+    /// variables redacted.
+    pub spec: serde_json::Value,
+
+    /// Whether the values of any secret environment variables were redacted
+    /// from the specification.
+    ///
+    /// Such attempts cannot be resubmitted from the store.
+    pub redacted: bool,
+
+    /// The program and arguments of each execution of the task.
+    pub commands: Vec<Vec<String>>,
+
+    /// The path that each execution's stdout was written to (if any).
+    pub stdout: Vec<Option<String>>,
+
+    /// The path that each execution's stderr was written to (if any).
+    pub stderr: Vec<Option<String>>,
+
+    /// The URLs that the task's outputs were written to.
+    pub outputs: Vec<String>,
+
+    /// When the attempt was queued (in milliseconds since the Unix epoch).
+    pub queued: u64,
+
+    /// When the attempt started running (in milliseconds since the Unix
+    /// epoch).
+    ///
+    /// This is `None` if the attempt never started running.
+    pub started: Option<u64>,
+
+    /// When the attempt finished running (in milliseconds since the Unix
+    /// epoch).
+    ///
+    /// This is `None` if the attempt is still waiting or running, or never
+    /// finished.
+    pub ended: Option<u64>,
+
+    /// The exit codes of the executions that ran (if the attempt finished).
+    ///
+    /// An exit code is `None` if the execution was terminated by a signal.
+    pub exit_codes: Option<Vec<Option<i32>>>,
+
+    /// The error that the attempt failed with (if it failed).
+    pub error: Option<String>,
+
+    /// The call cache key of the task (if the call cache is enabled and the
+    /// task can be cached).
+    pub key: Option<String>,
+
+    /// The identifier of the attempt whose result was reused by the call
+    /// cache instead of running the task (if any).
+    pub cached: Option<i64>,
+
+    /// The group the task belongs to (if it has one).
+    pub group: Option<String>,
+
+    /// Whether the attempt was canceled (e.g., with its group).
+    pub canceled: bool,
+
+    /// The checksums of the task's outputs when the attempt finished (if its
+    /// result can be reused by the call cache).
+    ///
+    /// A checksum is `None` if the output is not a file written to a local
+    /// URL.
+    pub checksums: Option<Vec<Option<String>>>,
+}
+
+/// The resolution of an image reference to a digest as recorded in the store.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct Resolution {
+    /// The name of the runner that resolved the image reference.
+    pub runner: String,
+
+    /// The image reference (e.g., `ubuntu:22.04`).
+    pub image: String,
+
+    /// The reference pinned to a digest that the image reference was resolved
+    /// to (e.g., `ubuntu@sha256:...`).
+    pub resolved: String,
+
+    /// When the image reference was resolved (in milliseconds since the Unix
+    /// epoch).
+    pub time: u64,
+}
+
+impl Attempt {
+    /// Reads an attempt from a row of the store.
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        /// Parses a JSON column that may be `NULL`.
+        fn json<T: DeserializeOwned>(row: &Row<'_>, index: usize) -> rusqlite::Result<Option<T>> {
+            row.get::<_, Option<String>>(index)?
+                .map(|text| {
+                    serde_json::from_str(&text).map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e))
+                    })
+                })
+                .transpose()
+        }
+
+        /// Parses a JSON column that is never `NULL`.
+        fn required<T: DeserializeOwned>(row: &Row<'_>, index: usize) -> rusqlite::Result<T> {
+            json(row, index)?.ok_or(rusqlite::Error::InvalidColumnType(
+                index,
+                String::from("NULL"),
+                Type::Null,
+            ))
+        }
+
+        Ok(Self {
+            id: row.get(0)?,
+            run_id: row.get::<_, i64>(1)? as u64,
+            runner: row.get(2)?,
+            task: row.get(3)?,
+            attempt: row.get(4)?,
+            backend: row.get(5)?,
+            spec: required(row, 6)?,
+            redacted: row.get(7)?,
+            commands: required(row, 8)?,
+            stdout: required(row, 9)?,
+            stderr: required(row, 10)?,
+            outputs: required(row, 11)?,
+            queued: row.get::<_, i64>(12)? as u64,
+            started: row.get::<_, Option<i64>>(13)?.map(|started| started as u64),
+            ended: row.get::<_, Option<i64>>(14)?.map(|ended| ended as u64),
+            exit_codes: json(row, 15)?,
+            error: row.get(16)?,
+            key: row.get(17)?,
+            cached: row.get(18)?,
+            group: row.get(19)?,
+            canceled: row.get::<_, Option<bool>>(20)?.unwrap_or_default(),
+            checksums: json(row, 21)?,
+        })
+    }
+
+    /// Gets whether the attempt finished running.
+    pub fn finished(&self) -> bool {
+        self.ended.is_some()
+    }
+
+    /// Gets the task of the attempt from its specification.
+    ///
+    /// Returns `None` if the values of secret environment variables were
+    /// redacted from the specification.
+    pub fn task(&self) -> Result<Option<Task>, Error> {
+        if self.redacted {
+            return Ok(None);
+        }
+
+        Ok(Some(serde_json::from_value(self.spec.clone())?))
+    }
+}
+
+/// The details of an attempt recorded when it is queued.
+#[derive(Clone, Debug)]
+pub(crate) struct Record {
+    /// The identifier the engine assigned to the task.
+    run_id: u64,
+
+    /// The name of the runner the task was submitted to.
+    runner: String,
+
+    /// The name of the task.
+    task: Option<String>,
+
+    /// The attempt number of the task.
+    attempt: Option<u32>,
+
+    /// The type of backend running the task.
+    backend: String,
+
+    /// The serialized specification of the task.
+    spec: String,
+
+    /// Whether secrets were redacted from the specification.
+    redacted: bool,
+
+    /// The serialized commands of the task's executions.
+    commands: String,
+
+    /// The serialized stdout paths of the task's executions.
+    stdout: String,
+
+    /// The serialized stderr paths of the task's executions.
+    stderr: String,
+
+    /// The serialized URLs of the task's outputs.
+    outputs: String,
+
+    /// The group of the task.
+    group: Option<String>,
+}
+
+impl Record {
+    /// Creates a record of an attempt to run the given task.
+    ///
+    /// The record must be created before the task is identified so that the
+    /// stored specification can be resubmitted as a new attempt.
+    pub(crate) fn new(
+        task: &Task,
+        run_id: u64,
+        runner: &str,
+        backend: &str,
+    ) -> Result<Self, Error> {
+        let mut spec = serde_json::to_value(task)?;
+        let mut redacted = false;
+        if let Some(executions) = spec
+            .get_mut("executions")
+            .and_then(serde_json::Value::as_array_mut)
+        {
+            for secrets in executions
+                .iter_mut()
+                .filter_map(|e| e.get_mut("secret-env"))
+                .filter_map(serde_json::Value::as_object_mut)
+            {
+                for value in secrets.values_mut() {
+                    *value = REDACTED.into();
+                    redacted = true;
+                }
+            }
+        }
+
+        Ok(Self {
+            run_id,
+            runner: runner.to_string(),
+            task: task.name.clone(),
+            attempt: task.attempt,
+            backend: backend.to_string(),
+            spec: spec.to_string(),
+            redacted,
+            commands: serde_json::to_string(
+                &task
+                    .executions
+                    .iter()
+                    .map(|e| {
+                        std::iter::once(e.program.as_str())
+                            .chain(e.args.iter().map(String::as_str))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>(),
+            )?,
+            stdout: serde_json::to_string(
+                &task
+                    .executions
+                    .iter()
+                    .map(|e| e.stdout())
+                    .collect::<Vec<_>>(),
+            )?,
+            stderr: serde_json::to_string(
+                &task
+                    .executions
+                    .iter()
+                    .map(|e| e.stderr())
+                    .collect::<Vec<_>>(),
+            )?,
+            outputs: serde_json::to_string(&task.outputs().map(|o| o.url()).collect::<Vec<_>>())?,
+            group: task.group.clone(),
+        })
+    }
+}
+
+/// A persistent store of task attempts.
+///
+/// Jobs are run against the database in the order they are sent, so a query
+/// sees every attempt recorded before it. The database is closed once every
+/// clone of the store has been dropped.
+#[derive(Clone, Debug)]
+pub struct Store(mpsc::UnboundedSender<Job>);
+
+impl Store {
+    /// Opens the store in the database at the given path.
+    ///
+    /// The database is created if it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::initialize(Connection::open(path)?)
+    }
+
+    /// Opens a store that is kept in memory.
+    ///
+    /// The attempts recorded in the store are lost when it is dropped.
+    pub fn in_memory() -> Result<Self, Error> {
+        Self::initialize(Connection::open_in_memory()?)
+    }
+
+    /// Creates the schema of the store if it does not already exist and
+    /// spawns the thread that accesses the database.
+    fn initialize(connection: Connection) -> Result<Self, Error> {
+        connection.execute_batch(SCHEMA)?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<Job>();
+        std::thread::Builder::new()
+            .name(String::from("crankshaft-store"))
+            .spawn(move || {
+                while let Some(job) = rx.blocking_recv() {
+                    job(&connection);
+                }
+            })
+            .map_err(Error::Thread)?;
+
+        Ok(Self(tx))
+    }
+
+    /// Sends a job to run against the database, returning a receiver of its
+    /// result.
+    fn send<T: Send + 'static>(
+        &self,
+        job: impl FnOnce(&Connection) -> Result<T, Error> + Send + 'static,
+    ) -> oneshot::Receiver<Result<T, Error>> {
+        let (tx, rx) = oneshot::channel();
+        // If the thread has exited, the job is dropped along with its sender
+        let _ = self.0.send(Box::new(move |connection| {
+            let _ = tx.send(job(connection));
+        }));
+        rx
+    }
+
+    /// Runs a query against the database.
+    async fn query<T: Send + 'static>(
+        &self,
+        job: impl FnOnce(&Connection) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, Error> {
+        self.send(job).await.unwrap_or(Err(Error::Closed))
+    }
+
+    /// Writes to the database without waiting for the write to complete.
+    ///
+    /// Failures to write `what` are logged rather than failing the task.
+    fn write(
+        &self,
+        what: &'static str,
+        job: impl FnOnce(&Connection) -> Result<(), Error> + Send + 'static,
+    ) {
+        let _ = self.0.send(Box::new(move |connection| {
+            if let Err(e) = job(connection) {
+                warn!("failed to record {what} in the store: {e}");
+            }
+        }));
+    }
+
+    /// Records that an attempt was queued.
+    ///
+    /// The attempt is recorded in the order this is called, but the returned
+    /// future resolves to its identifier in the store once it has been.
+    /// Failures to write to the store are logged rather than failing the
+    /// task.
+    pub(crate) fn queued(&self, record: Record) -> BoxFuture<'static, Option<i64>> {
+        let rx = self.send(move |connection| {
+            connection.execute(
+                "INSERT INTO attempts (run_id, runner, task, attempt, backend, spec, redacted, \
+                 commands, stdout, stderr, outputs, queued, task_group) VALUES (?1, ?2, ?3, ?4, \
+                 ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                params![
+                    record.run_id as i64,
+                    record.runner,
+                    record.task,
+                    record.attempt,
+                    record.backend,
+                    record.spec,
+                    record.redacted,
+                    record.commands,
+                    record.stdout,
+                    record.stderr,
+                    record.outputs,
+                    audit::now() as i64,
+                    record.group,
+                ],
+            )?;
+            Ok(connection.last_insert_rowid())
+        });
+
+        async move {
+            match rx.await.unwrap_or(Err(Error::Closed)) {
+                Ok(id) => Some(id),
+                Err(e) => {
+                    warn!("failed to record a task attempt in the store: {e}");
+                    None
+                }
+            }
+        }
+        .boxed()
+    }
+
+    /// Records that the attempt with the given identifier started running.
+    ///
+    /// Failures to write to the store are logged rather than failing the
+    /// task.
+    pub(crate) fn started(&self, id: i64) {
+        let now = audit::now() as i64;
+        self.write("a task attempt", move |connection| {
+            connection.execute(
+                "UPDATE attempts SET started = ?1 WHERE id = ?2",
+                params![now, id],
+            )?;
+            Ok(())
+        });
+    }
+
+    /// Records the call cache key of the attempt with the given identifier.
+    ///
+    /// Failures to write to the store are logged rather than failing the
+    /// task.
+    pub(crate) fn keyed(&self, id: i64, key: &str) {
+        let key = key.to_string();
+        self.write("a task attempt", move |connection| {
+            connection.execute(
+                "UPDATE attempts SET key = ?1 WHERE id = ?2",
+                params![key, id],
+            )?;
+            Ok(())
+        });
+    }
+
+    /// Records the checksums of the outputs of the attempt with the given
+    /// identifier.
+    ///
+    /// Failures to write to the store are logged rather than failing the
+    /// task.
+    pub(crate) fn checksummed(&self, id: i64, checksums: Vec<Option<String>>) {
+        self.write("a task attempt", move |connection| {
+            connection.execute(
+                "UPDATE attempts SET checksums = ?1 WHERE id = ?2",
+                params![serde_json::to_string(&checksums)?, id],
+            )?;
+            Ok(())
+        });
+    }
+
+    /// Finds the most recent attempt with the given call cache key that
+    /// succeeded (i.e., every execution exited with a zero exit code) and
+    /// whose outputs are unchanged (see [`cache::verify()`]).
+    ///
+    /// Returns the identifier of the attempt and the statuses of its
+    /// executions.
+    pub(crate) async fn lookup(
+        &self,
+        key: &str,
+    ) -> Result<Option<(i64, NonEmpty<ExitStatus>)>, Error> {
+        let key = key.to_string();
+        let attempts = self
+            .query(move |connection| {
+                let mut statement = connection.prepare(&format!(
+                    "SELECT {COLUMNS} FROM attempts WHERE key = ?1 AND cached IS NULL AND ended \
+                     IS NOT NULL AND error IS NULL ORDER BY id DESC"
+                ))?;
+                let attempts = statement
+                    .query_map([key], Attempt::from_row)?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(attempts)
+            })
+            .await?;
+
+        for attempt in attempts {
+            let codes = attempt.exit_codes.unwrap_or_default();
+            if !codes.iter().all(|code| *code == Some(0)) {
+                continue;
+            }
+
+            if !cache::verify(&attempt.outputs, attempt.checksums.as_deref()).await {
+                continue;
+            }
+
+            if let Some(statuses) = NonEmpty::from_vec(vec![ExitStatus::from_raw(0); codes.len()]) {
+                return Ok(Some((attempt.id, statuses)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Records that the attempt with the given identifier reused the result of
+    /// the attempt `source` rather than running.
+    ///
+    /// Failures to write to the store are logged rather than failing the
+    /// task.
+    pub(crate) fn reused(&self, id: i64, source: i64, statuses: &NonEmpty<ExitStatus>) {
+        let exit_codes = statuses.iter().map(ExitStatus::code).collect::<Vec<_>>();
+        let now = audit::now() as i64;
+        self.write("a task attempt", move |connection| {
+            connection.execute(
+                "UPDATE attempts SET started = ?1, ended = ?1, exit_codes = ?2, cached = ?3 WHERE \
+                 id = ?4",
+                params![now, serde_json::to_string(&exit_codes)?, source, id],
+            )?;
+            Ok(())
+        });
+    }
+
+    /// Records that the attempt with the given identifier was interrupted
+    /// before it finished (e.g., by the process crashing) for the given
+    /// reason.
+    pub(crate) async fn interrupted(&self, id: i64, reason: &str) -> Result<(), Error> {
+        let reason = reason.to_string();
+        let now = audit::now() as i64;
+        self.query(move |connection| {
+            connection.execute(
+                "UPDATE attempts SET ended = ?1, error = ?2 WHERE id = ?3",
+                params![now, reason, id],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Records that the attempt with the given identifier finished running.
+    ///
+    /// Failures to write to the store are logged rather than failing the
+    /// task.
+    pub(crate) fn finished(&self, id: i64, result: &Result<NonEmpty<ExitStatus>, TaskRunError>) {
+        let exit_codes: Vec<_> = match result {
+            Ok(statuses)
+            | Err(TaskRunError::NonZeroExit { statuses, .. })
+            | Err(TaskRunError::OutOfMemory { statuses, .. }) => {
+                statuses.iter().map(ExitStatus::code).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        let error = result.as_ref().err().map(|e| format!("{e:#}"));
+        let canceled = matches!(result, Err(TaskRunError::Canceled));
+        let now = audit::now() as i64;
+        self.write("a task attempt", move |connection| {
+            connection.execute(
+                "UPDATE attempts SET ended = ?1, exit_codes = ?2, error = ?3, canceled = ?4 WHERE \
+                 id = ?5",
+                params![
+                    now,
+                    serde_json::to_string(&exit_codes)?,
+                    error,
+                    canceled,
+                    id
+                ],
+            )?;
+            Ok(())
+        });
+    }
+
+    /// Records that a runner resolved an image reference to a reference pinned
+    /// to a digest.
+    ///
+    /// Failures to write to the store are logged rather than failing the
+    /// task.
+    pub(crate) fn resolved(&self, runner: &str, image: &str, resolved: &str) {
+        let (runner, image, resolved) =
+            (runner.to_string(), image.to_string(), resolved.to_string());
+        let now = audit::now() as i64;
+        self.write("an image resolution", move |connection| {
+            connection.execute(
+                "INSERT INTO images (runner, image, resolved, time) VALUES (?1, ?2, ?3, ?4)",
+                params![runner, image, resolved, now],
+            )?;
+            Ok(())
+        });
+    }
+
+    /// Gets the resolutions of image references recorded in the store, oldest
+    /// first.
+    ///
+    /// If `image` is provided, only the resolutions of that image reference
+    /// are returned.
+    pub async fn resolutions(&self, image: Option<&str>) -> Result<Vec<Resolution>, Error> {
+        let image = image.map(str::to_string);
+        self.query(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT runner, image, resolved, time FROM images WHERE ?1 IS NULL OR image = ?1 \
+                 ORDER BY id",
+            )?;
+            let resolutions = statement
+                .query_map([image], |row| {
+                    Ok(Resolution {
+                        runner: row.get(0)?,
+                        image: row.get(1)?,
+                        resolved: row.get(2)?,
+                        time: row.get::<_, i64>(3)? as u64,
+                    })
+                })?
+                .collect::<Result<_, _>>()?;
+            Ok(resolutions)
+        })
+        .await
+    }
+
+    /// Gets the attempt with the given identifier (if there is one).
+    pub async fn attempt(&self, id: i64) -> Result<Option<Attempt>, Error> {
+        self.query(move |connection| {
+            Ok(connection
+                .query_row(
+                    &format!("SELECT {COLUMNS} FROM attempts WHERE id = ?1"),
+                    [id],
+                    Attempt::from_row,
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    /// Gets the attempts recorded in the store, oldest first.
+    ///
+    /// If `task` is provided, only the attempts of tasks with that name are
+    /// returned.
+    pub async fn attempts(&self, task: Option<&str>) -> Result<Vec<Attempt>, Error> {
+        let task = task.map(str::to_string);
+        self.query(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {COLUMNS} FROM attempts WHERE ?1 IS NULL OR task = ?1 ORDER BY id"
+            ))?;
+            let attempts = statement
+                .query_map([task], Attempt::from_row)?
+                .collect::<Result<_, _>>()?;
+            Ok(attempts)
+        })
+        .await
+    }
+
+    /// Gets the attempts of the tasks of a group, oldest first.
+    pub async fn group(&self, group: &str) -> Result<Vec<Attempt>, Error> {
+        let group = group.to_string();
+        self.query(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {COLUMNS} FROM attempts WHERE task_group = ?1 ORDER BY id"
+            ))?;
+            let attempts = statement
+                .query_map([group], Attempt::from_row)?
+                .collect::<Result<_, _>>()?;
+            Ok(attempts)
+        })
+        .await
+    }
+
+    /// Gets the attempts that were queued but never finished, oldest first.
+    ///
+    /// When no tasks are waiting or running, these are the attempts that were
+    /// interrupted (e.g., by the process crashing).
+    pub async fn unfinished(&self) -> Result<Vec<Attempt>, Error> {
+        self.query(|connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {COLUMNS} FROM attempts WHERE ended IS NULL ORDER BY id"
+            ))?;
+            let attempts = statement
+                .query_map([], Attempt::from_row)?
+                .collect::<Result<_, _>>()?;
+            Ok(attempts)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nonempty::NonEmpty;
+
+    use super::*;
+    use crate::task::Execution;
+    use crate::task::Output;
+    use crate::task::output;
+
+    #[tokio::test]
+    async fn records_attempts() {
+        let store = Store::in_memory().unwrap();
+        let task = Task::builder()
+            .name("hello")
+            .group("run")
+            .executions(NonEmpty::new(
+                Execution::builder()
+                    .image("ubuntu:22.04")
+                    .program("echo")
+                    .args([String::from("hi")])
+                    .stdout("/stdout")
+                    .secret_env([(String::from("TOKEN"), "hunter2".into())])
+                    .build(),
+            ))
+            .build();
+
+        let first = store
+            .queued(Record::new(&task, 7, "local", "docker").unwrap())
+            .await
+            .unwrap();
+        let second = store
+            .queued(Record::new(&task, 8, "local", "docker").unwrap())
+            .await
+            .unwrap();
+        store.started(first);
+        store.finished(first, &Err(TaskRunError::Canceled));
+
+        let attempt = store.attempt(first).await.unwrap().unwrap();
+        assert_eq!(attempt.run_id, 7);
+        assert_eq!(attempt.runner, "local");
+        assert!(attempt.started.is_some());
+        assert_eq!(attempt.task.as_deref(), Some("hello"));
+        assert_eq!(attempt.commands, [["echo", "hi"]]);
+        assert_eq!(attempt.stdout, [Some(String::from("/stdout"))]);
+        assert_eq!(attempt.stderr, [None]);
+        assert_eq!(attempt.exit_codes, Some(Vec::new()));
+        assert_eq!(attempt.error.as_deref(), Some("the task has been canceled"));
+        assert_eq!(attempt.group.as_deref(), Some("run"));
+        assert!(attempt.canceled);
+        assert!(attempt.finished());
+        assert_eq!(
+            attempt.spec["executions"][0]["secret-env"]["TOKEN"],
+            REDACTED
+        );
+        assert!(attempt.redacted);
+        assert!(attempt.task().unwrap().is_none());
+
+        assert_eq!(store.attempts(Some("hello")).await.unwrap().len(), 2);
+        assert!(store.attempts(Some("other")).await.unwrap().is_empty());
+        assert_eq!(store.group("run").await.unwrap().len(), 2);
+        assert!(store.group("other").await.unwrap().is_empty());
+        assert_eq!(
+            store
+                .unfinished()
+                .await
+                .unwrap()
+                .iter()
+                .map(|a| a.id)
+                .collect::<Vec<_>>(),
+            [second]
+        );
+        assert!(store.attempt(second + 1).await.unwrap().is_none());
+
+        store.interrupted(second, "interrupted").await.unwrap();
+        assert!(store.unfinished().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn looks_up_cached_results() {
+        let store = Store::in_memory().unwrap();
+        let task = Task::builder()
+            .executions(NonEmpty::new(
+                Execution::builder()
+                    .image("ubuntu@sha256:abc")
+                    .program("true")
+                    .build(),
+            ))
+            .build();
+        let record = Record::new(&task, 0, "local", "docker").unwrap();
+
+        let failed = store.queued(record.clone()).await.unwrap();
+        store.keyed(failed, "key");
+        store.finished(failed, &Err(TaskRunError::Canceled));
+        assert!(store.lookup("key").await.unwrap().is_none());
+
+        let succeeded = store.queued(record.clone()).await.unwrap();
+        store.keyed(succeeded, "key");
+        store.finished(succeeded, &Ok(NonEmpty::new(ExitStatus::from_raw(0))));
+        let (source, statuses) = store.lookup("key").await.unwrap().unwrap();
+        assert_eq!(source, succeeded);
+        assert!(statuses.iter().all(ExitStatus::success));
+        assert!(store.lookup("other").await.unwrap().is_none());
+
+        let reused = store.queued(record.clone()).await.unwrap();
+        store.reused(reused, source, &statuses);
+        let attempt = store.attempt(reused).await.unwrap().unwrap();
+        assert_eq!(attempt.cached, Some(succeeded));
+        assert_eq!(attempt.exit_codes, Some(vec![Some(0)]));
+        assert_eq!(store.lookup("key").await.unwrap().unwrap().0, succeeded);
+    }
+
+    #[tokio::test]
+    async fn verifies_cached_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+        std::fs::write(&path, "hello").unwrap();
+
+        let store = Store::in_memory().unwrap();
+        let task = Task::builder()
+            .executions(NonEmpty::new(
+                Execution::builder()
+                    .image("ubuntu@sha256:abc")
+                    .program("true")
+                    .build(),
+            ))
+            .outputs([Output::builder()
+                .url(url::Url::from_file_path(&path).unwrap())
+                .path("/out.txt")
+                .ty(output::Type::File)
+                .build()])
+            .build();
+        let record = Record::new(&task, 0, "local", "docker").unwrap();
+        let outputs = vec![url::Url::from_file_path(&path).unwrap().to_string()];
+
+        // Results whose outputs were never checksummed cannot be verified
+        let unverified = store.queued(record.clone()).await.unwrap();
+        store.keyed(unverified, "key");
+        store.finished(unverified, &Ok(NonEmpty::new(ExitStatus::from_raw(0))));
+        assert!(store.lookup("key").await.unwrap().is_none());
+
+        let succeeded = store.queued(record).await.unwrap();
+        store.keyed(succeeded, "key");
+        store.checksummed(succeeded, cache::checksums(&outputs).await);
+        store.finished(succeeded, &Ok(NonEmpty::new(ExitStatus::from_raw(0))));
+        assert_eq!(store.lookup("key").await.unwrap().unwrap().0, succeeded);
+
+        std::fs::write(&path, "goodbye").unwrap();
+        assert!(store.lookup("key").await.unwrap().is_none());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(store.lookup("key").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn records_resolutions() {
+        let store = Store::in_memory().unwrap();
+        store.resolved("local", "ubuntu:22.04", "ubuntu@sha256:abc");
+        store.resolved("local", "alpine", "alpine@sha256:def");
+
+        let resolutions = store.resolutions(None).await.unwrap();
+        assert_eq!(resolutions.len(), 2);
+        assert_eq!(resolutions[0].runner, "local");
+        assert_eq!(resolutions[0].resolved, "ubuntu@sha256:abc");
+
+        let resolutions = store.resolutions(Some("alpine")).await.unwrap();
+        assert_eq!(resolutions.len(), 1);
+        assert_eq!(resolutions[0].resolved, "alpine@sha256:def");
+    }
+
+    #[tokio::test]
+    async fn resubmits_specification() {
+        let store = Store::in_memory().unwrap();
+        let task = Task::builder()
+            .name("hello")
+            .attempt(2)
+            .executions(NonEmpty::new(
+                Execution::builder()
+                    .image("ubuntu:22.04")
+                    .program("echo")
+                    .env([(String::from("GREETING"), String::from("hi"))])
+                    .build(),
+            ))
+            .build();
+
+        let id = store
+            .queued(Record::new(&task, 0, "local", "docker").unwrap())
+            .await
+            .unwrap();
+        let attempt = store.attempt(id).await.unwrap().unwrap();
+        assert!(!attempt.redacted);
+        assert_eq!(attempt.started, None);
+
+        let task = attempt.task().unwrap().unwrap();
+        assert_eq!(task.name(), Some("hello"));
+        assert_eq!(task.attempt(), Some(2));
+        assert_eq!(task.executions().next().unwrap().env()["GREETING"], "hi");
+    }
+}
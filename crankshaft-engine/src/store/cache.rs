@@ -0,0 +1,199 @@
+//! Keys of the call cache.
+//!
+//! A task's key identifies everything that determines its result: the digest
+//! of each execution's image, its command, working directory, and
+//! environment, the checksums of the task's inputs, and where its outputs are
+//! written. Tasks whose result may differ between runs with the same key
+//! (e.g., those with images that are not pinned to a digest or inputs whose
+//! contents cannot be checksummed) are not cached.
+//!
+//! The outputs of a cached result are checksummed when its attempt finishes so
+//! that the result is only reused while its outputs are unchanged.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde_json::json;
+use sha2::Digest as _;
+use sha2::Sha256;
+use tracing::debug;
+use url::Url;
+
+use crate::Task;
+use crate::service::runner::Backend;
+use crate::task::RUN_ID_ENV;
+use crate::task::TASK_ATTEMPT_ENV;
+use crate::task::TASK_NAME_ENV;
+use crate::task::input::Contents;
+use crate::task::input::Type;
+use crate::task::input::download;
+
+/// Computes the call cache key of a task.
+///
+/// Returns `None` if the task cannot be cached.
+pub(crate) async fn key(task: &Task, backend: &dyn Backend) -> Result<Option<String>> {
+    let mut executions = Vec::with_capacity(task.executions.len());
+    for execution in task.executions.iter() {
+        let digest = match crankshaft_docker::digest(&execution.image) {
+            Some(digest) => Some(digest.to_string()),
+            None => backend.image_digest(&execution.image).await,
+        };
+        let Some(digest) = digest else {
+            debug!(
+                "task is not cached as the digest of image `{image}` is unknown",
+                image = execution.image
+            );
+            return Ok(None);
+        };
+
+        // The variables identifying the run differ between otherwise identical
+        // tasks
+        let env = execution
+            .env
+            .iter()
+            .filter(|(name, _)| {
+                ![TASK_NAME_ENV, TASK_ATTEMPT_ENV, RUN_ID_ENV].contains(&name.as_str())
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        // Secrets are hashed so that they are never stored
+        let secret_env = execution
+            .secret_env
+            .iter()
+            .map(|(name, value)| (name, download::hex(Sha256::digest(value.expose()))))
+            .collect::<BTreeMap<_, _>>();
+
+        executions.push(json!({
+            "digest": digest,
+            "program": execution.program,
+            "args": execution.args,
+            "work-dir": execution.work_dir,
+            "stdin": execution.stdin,
+            "stdout": execution.stdout,
+            "stderr": execution.stderr,
+            "env": env,
+            "secret-env": secret_env,
+        }));
+    }
+
+    let mut inputs = Vec::with_capacity(task.inputs.len());
+    for input in &task.inputs {
+        let checksum = match (&input.checksum, &input.contents, &input.ty) {
+            (Some(checksum), ..) => checksum.clone(),
+            (None, Contents::Literal(bytes), _) => {
+                format!("sha256:{}", download::hex(Sha256::digest(bytes)))
+            }
+            (None, Contents::Path(path), Type::File) => download::sha256(path).await?,
+            _ => {
+                debug!(
+                    "task is not cached as input `{path}` has no checksum",
+                    path = input.path
+                );
+                return Ok(None);
+            }
+        };
+
+        inputs.push(json!({
+            "path": input.path,
+            "type": input.ty,
+            "checksum": checksum,
+        }));
+    }
+
+    let outputs = task
+        .outputs
+        .iter()
+        .map(|output| {
+            json!({
+                "url": output.url.as_str(),
+                "path": output.path,
+                "type": output.ty,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let key = json!({
+        "executions": executions,
+        "inputs": inputs,
+        "outputs": outputs,
+        "volumes": task.volumes,
+    });
+
+    Ok(Some(format!(
+        "sha256:{}",
+        download::hex(Sha256::digest(key.to_string()))
+    )))
+}
+
+/// Gets the local path of the output written to the given URL (if it was
+/// written to a `file://` URL).
+fn local(url: &str) -> Option<PathBuf> {
+    Url::parse(url)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok())
+}
+
+/// Determines if a file exists at the given path.
+async fn is_file(path: &Path) -> bool {
+    tokio::fs::metadata(path)
+        .await
+        .is_ok_and(|metadata| metadata.is_file())
+}
+
+/// Computes the checksums of the outputs written to the given URLs.
+///
+/// Only files written to local URLs are checksummed; the checksum of any
+/// other output is `None`.
+pub(crate) async fn checksums(outputs: &[String]) -> Vec<Option<String>> {
+    let mut checksums = Vec::with_capacity(outputs.len());
+    for output in outputs {
+        let checksum = match local(output) {
+            Some(path) if is_file(&path).await => download::sha256(&path).await.ok(),
+            _ => None,
+        };
+
+        checksums.push(checksum);
+    }
+
+    checksums
+}
+
+/// Determines if the outputs written to the given URLs are unchanged since
+/// they were checksummed (see [`checksums()`]).
+///
+/// Local outputs must still exist, and local files must still have their
+/// recorded checksums. Outputs written elsewhere cannot be verified and are
+/// assumed to be unchanged.
+pub(crate) async fn verify(outputs: &[String], checksums: Option<&[Option<String>]>) -> bool {
+    if outputs.is_empty() {
+        return true;
+    }
+
+    let Some(checksums) = checksums.filter(|c| c.len() == outputs.len()) else {
+        return false;
+    };
+
+    for (output, checksum) in outputs.iter().zip(checksums) {
+        let Some(path) = local(output) else {
+            continue;
+        };
+
+        let unchanged = match (checksum, tokio::fs::metadata(&path).await) {
+            (Some(checksum), Ok(metadata)) if metadata.is_file() => {
+                download::sha256(&path).await.ok().as_ref() == Some(checksum)
+            }
+            (None, Ok(metadata)) => metadata.is_dir(),
+            _ => false,
+        };
+
+        if !unchanged {
+            debug!("output `{output}` of a cached result has changed");
+            return false;
+        }
+    }
+
+    true
+}
@@ -0,0 +1,281 @@
+//! Streaming submission of tasks with backpressure.
+//!
+//! Producers that generate very large numbers of tasks (e.g., the shards of a
+//! scatter over millions of records) should not have to queue every task
+//! eagerly. [`Engine::submitter()`] returns a [`Submitter`] that only accepts
+//! a task once fewer than a maximum number of submitted tasks are
+//! outstanding, along with a [`Results`] stream that yields the result of
+//! each task as it completes.
+//!
+//! A task remains outstanding until its result has been yielded by the
+//! [`Results`] stream, so the stream must be consumed (e.g., concurrently with
+//! the producer) for the submitter to accept more tasks. This bounds the
+//! memory used by both the queued tasks and their unconsumed results.
+
+use std::pin::Pin;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use std::task::ready;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use futures::FutureExt as _;
+use futures::Sink;
+use futures::Stream;
+use futures::StreamExt as _;
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+use nonempty::NonEmpty;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tokio_util::sync::PollSemaphore;
+
+use crate::Engine;
+use crate::Task;
+use crate::service::runner::TaskHandle;
+use crate::service::runner::backend::TaskRunError;
+
+/// The result of a submitted task along with its index.
+pub type Completed = (u64, Result<NonEmpty<ExitStatus>, TaskRunError>);
+
+/// A future that resolves to the result of a submitted task.
+type Pending = BoxFuture<'static, Completed>;
+
+/// A submitter of tasks to a backend that waits while too many submitted
+/// tasks are outstanding.
+///
+/// Tasks can be submitted with [`Self::submit()`] or by using the submitter
+/// as a [`Sink`] (e.g., with [`SinkExt::send_all()`](futures::SinkExt)).
+/// Each task is given an index, in the order it was submitted, that its
+/// result is yielded with.
+///
+/// Dropping (or closing) the submitter ends the [`Results`] stream once the
+/// outstanding tasks complete.
+pub struct Submitter<'a> {
+    /// The engine that tasks are submitted to.
+    engine: &'a Engine,
+
+    /// The name of the backend that tasks are submitted to.
+    name: String,
+
+    /// The permits for outstanding tasks.
+    permits: PollSemaphore,
+
+    /// The permit acquired for the next task sent to the sink.
+    permit: Option<OwnedSemaphorePermit>,
+
+    /// The sender of the results of submitted tasks.
+    tx: Option<mpsc::UnboundedSender<Pending>>,
+
+    /// The token that cancels the submitted tasks.
+    token: CancellationToken,
+
+    /// The index of the next task.
+    next: u64,
+}
+
+impl Submitter<'_> {
+    /// Gets the number of tasks that can be submitted before the submitter
+    /// waits for outstanding tasks to complete.
+    pub fn available(&self) -> usize {
+        self.permits.available_permits()
+    }
+
+    /// Submits a task, waiting until fewer than the maximum number of
+    /// submitted tasks are outstanding and for space in the backend's queue
+    /// (see [`Engine::submit()`]).
+    ///
+    /// Returns the index of the task.
+    pub async fn submit(&mut self, task: Task) -> Result<u64> {
+        let permit = match self.permit.take() {
+            Some(permit) => permit,
+            None => self.permits.clone_inner().acquire_owned().await?,
+        };
+
+        let handle = self
+            .engine
+            .submit(&self.name, task, self.token.clone())
+            .await?;
+        self.track(handle, permit)
+    }
+
+    /// Tracks the handle of a submitted task until its result is yielded.
+    fn track(&mut self, handle: TaskHandle, permit: OwnedSemaphorePermit) -> Result<u64> {
+        let index = self.next;
+        self.next += 1;
+
+        let pending = async move {
+            let result = handle.wait().await;
+            drop(permit);
+            (index, result)
+        }
+        .boxed();
+
+        self.tx
+            .as_ref()
+            .ok_or_else(|| anyhow!("the submitter has been closed"))?
+            .send(pending)
+            // The results of the task are not wanted if the stream was dropped
+            .ok();
+
+        Ok(index)
+    }
+}
+
+impl Sink<Task> for Submitter<'_> {
+    type Error = anyhow::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        if self.permit.is_none() {
+            match ready!(self.permits.poll_acquire(cx)) {
+                Some(permit) => self.permit = Some(permit),
+                None => return Poll::Ready(Err(anyhow!("the submitter has been closed"))),
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Submits a task without waiting for space in the backend's queue (see
+    /// [`Engine::spawn()`]).
+    ///
+    /// This must be preceded by a call to `poll_ready()` that returned
+    /// success.
+    fn start_send(mut self: Pin<&mut Self>, task: Task) -> Result<()> {
+        let permit = self
+            .permit
+            .take()
+            .ok_or_else(|| anyhow!("`poll_ready()` must be called before `start_send()`"))?;
+
+        let handle = self.engine.spawn(&self.name, task, self.token.clone())?;
+        self.track(handle, permit)?;
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<()>> {
+        self.tx = None;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// A stream of the results of the tasks sent to a [`Submitter`].
+///
+/// Results are yielded as tasks complete (and not necessarily in the order
+/// they were submitted) along with the index of each task. The stream ends
+/// once the submitter has been dropped (or closed) and every submitted task
+/// has completed.
+pub struct Results {
+    /// The receiver of the results of submitted tasks.
+    rx: mpsc::UnboundedReceiver<Pending>,
+
+    /// The results of the tasks that have not yet completed.
+    pending: FuturesUnordered<Pending>,
+
+    /// Whether the submitter has been dropped (or closed).
+    closed: bool,
+}
+
+impl Stream for Results {
+    type Item = Completed;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while !self.closed {
+            match self.rx.poll_recv(cx) {
+                Poll::Ready(Some(pending)) => self.pending.push(pending),
+                Poll::Ready(None) => self.closed = true,
+                Poll::Pending => break,
+            }
+        }
+
+        match ready!(self.pending.poll_next_unpin(cx)) {
+            Some(completed) => Poll::Ready(Some(completed)),
+            // The receiver wakes the stream when another task is submitted
+            None if !self.closed => Poll::Pending,
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+impl Engine {
+    /// Creates a [`Submitter`] of tasks to the named backend that allows at
+    /// most `max_outstanding` submitted tasks to be outstanding at once,
+    /// along with the stream of their results.
+    ///
+    /// The `token` cancels every task submitted with the submitter.
+    ///
+    /// See the [module documentation](crate::stream) for more details.
+    pub fn submitter(
+        &self,
+        name: impl Into<String>,
+        max_outstanding: usize,
+        token: CancellationToken,
+    ) -> (Submitter<'_>, Results) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let permits = Arc::new(Semaphore::new(max_outstanding.max(1)));
+
+        (
+            Submitter {
+                engine: self,
+                name: name.into(),
+                permits: PollSemaphore::new(permits),
+                permit: None,
+                tx: Some(tx),
+                token,
+                next: 0,
+            },
+            Results {
+                rx,
+                pending: FuturesUnordered::new(),
+                closed: false,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn yields_results_as_tasks_complete() {
+        let (tx, rx) = mpsc::unbounded_channel::<Pending>();
+        let mut results = Results {
+            rx,
+            pending: FuturesUnordered::new(),
+            closed: false,
+        };
+
+        for (index, delay) in [(0, 50), (1, 0)] {
+            tx.send(
+                async move {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    (index, Err(TaskRunError::Canceled))
+                }
+                .boxed(),
+            )
+            .unwrap();
+        }
+
+        assert_eq!(results.next().await.unwrap().0, 1);
+        assert_eq!(results.next().await.unwrap().0, 0);
+
+        // The stream waits for more tasks until the submitter is dropped
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), results.next())
+                .await
+                .is_err()
+        );
+        drop(tx);
+        assert!(results.next().await.is_none());
+    }
+}
@@ -1,24 +1,55 @@
 //! Tasks that can be run by execution runners.
 
+use std::time::Duration;
+
 use bon::Builder;
+use indexmap::IndexMap;
 use nonempty::NonEmpty;
+use serde::Deserialize;
+use serde::Serialize;
 use tes::v1::types::task::Executor;
 use tes::v1::types::task::Input as TesInput;
 use tes::v1::types::task::Output as TesOutput;
 use tes::v1::types::task::Resources as TesResources;
 
+pub mod diff;
 pub mod execution;
+mod hash;
 pub mod input;
+pub mod interpolate;
 pub mod output;
 pub mod resources;
+pub mod retry;
+pub mod runtime;
+pub mod trace;
 
+pub use diff::Difference;
 pub use execution::Execution;
+pub use execution::Secret;
 pub use input::Input;
 pub use output::Output;
+pub use resources::Gpus;
 pub use resources::Resources;
+pub use retry::RetrySpec;
+pub use runtime::RuntimeAttributes;
+pub use trace::TraceContext;
+
+/// The environment variable set to the name of a task within its executions.
+pub const TASK_NAME_ENV: &str = "CRANKSHAFT_TASK_NAME";
+
+/// The environment variable set to the attempt number of a task within its
+/// executions (if the task has one).
+pub const TASK_ATTEMPT_ENV: &str = "CRANKSHAFT_TASK_ATTEMPT";
+
+/// The environment variable set to the identifier the engine assigned to a run
+/// of a task within its executions.
+///
+/// This is the identifier reported in task [events](crate::events).
+pub const RUN_ID_ENV: &str = "CRANKSHAFT_RUN_ID";
 
 /// A task intended for execution.
-#[derive(Builder, Clone, Debug)]
+#[derive(Builder, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 #[builder(builder_type = Builder)]
 pub struct Task {
     /// An optional name.
@@ -30,10 +61,12 @@ pub struct Task {
     pub(crate) description: Option<String>,
 
     /// An optional list of [`Input`]s.
+    #[serde(default)]
     #[builder(into, default)]
     pub(crate) inputs: Vec<Input>,
 
     /// An optional list of [`Output`]s.
+    #[serde(default)]
     #[builder(into, default)]
     pub(crate) outputs: Vec<Output>,
 
@@ -46,8 +79,73 @@ pub struct Task {
     pub(crate) executions: NonEmpty<Execution>,
 
     /// The list of volumes shared across executions in the task.
+    #[serde(default)]
     #[builder(into, default)]
     pub(crate) volumes: Vec<String>,
+
+    /// An optional wall-clock limit for the task.
+    ///
+    /// The limit starts once the task begins running (and not while the task
+    /// waits for the runner to have capacity for it).
+    ///
+    /// When serialized, the limit is expressed in seconds.
+    #[serde(default, with = "seconds")]
+    pub(crate) timeout: Option<Duration>,
+
+    /// The priority of the task.
+    ///
+    /// While waiting for a runner to have capacity for them, tasks with
+    /// higher priorities are started before tasks with lower priorities.
+    /// Defaults to `0`.
+    #[serde(default)]
+    #[builder(default)]
+    pub(crate) priority: i32,
+
+    /// The distributed tracing context to propagate into the task's
+    /// containers (as the `TRACEPARENT` and `TRACESTATE` environment
+    /// variables).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub(crate) trace_context: Option<TraceContext>,
+
+    /// The hostname of the task's containers.
+    ///
+    /// Only supported by the Docker backend.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub(crate) hostname: Option<String>,
+
+    /// The attempt number of the task (e.g., when a workflow engine retries a
+    /// failed task).
+    ///
+    /// The attempt number is made available to the task's executions (see
+    /// [`TASK_ATTEMPT_ENV`]) and recorded with its traces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) attempt: Option<u32>,
+
+    /// The variables interpolated within the paths of the task's inputs and
+    /// the arguments and working directories of its executions.
+    ///
+    /// Placeholders (e.g., `${sample}` or `${task.name}`) are only expanded
+    /// when variables are set (even if there are none), which leaves the
+    /// placeholders of tasks that don't opt in (e.g., within shell scripts)
+    /// untouched. They are expanded when the task is submitted to a runner; see
+    /// [`interpolate`] for the syntax and the built-in variables.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub(crate) variables: Option<IndexMap<String, String>>,
+
+    /// The policy for automatically retrying the task if it fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) retry: Option<RetrySpec>,
+
+    /// The group the task belongs to (e.g., the run of a workflow).
+    ///
+    /// The time the tasks of a group run for is accounted against the group's
+    /// budget (see [`crate::budget`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub(crate) group: Option<String>,
 }
 
 impl Task {
@@ -101,10 +199,147 @@ impl Task {
         self.executions.push(execution.into());
     }
 
+    /// Chains an execution onto the task that runs a different program after
+    /// the last execution, sharing its image, working directory, and
+    /// environment (see [`Execution::then()`]).
+    ///
+    /// Executions run in order; if the last execution treats a non-zero exit
+    /// as an error, a failure stops the chained execution from running.
+    pub fn chain(
+        &mut self,
+        program: impl Into<String>,
+        args: impl IntoIterator<Item = impl Into<String>>,
+    ) {
+        let execution = self.executions.last().then(program, args);
+        self.executions.push(execution);
+    }
+
     /// Gets the shared volumes across executions within this task.
     pub fn shared_volumes(&self) -> impl Iterator<Item = &str> {
         self.volumes.iter().map(|v| v.as_str())
     }
+
+    /// Gets the wall-clock limit for the task (if one is specified).
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Gets the priority of the task.
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Gets the distributed tracing context of the task (if one is
+    /// specified).
+    pub fn trace_context(&self) -> Option<&TraceContext> {
+        self.trace_context.as_ref()
+    }
+
+    /// Gets the hostname of the task's containers (if one is specified).
+    pub fn hostname(&self) -> Option<&str> {
+        self.hostname.as_deref()
+    }
+
+    /// Gets the attempt number of the task (if one is specified).
+    pub fn attempt(&self) -> Option<u32> {
+        self.attempt
+    }
+
+    /// Gets the policy for automatically retrying the task (if one is
+    /// specified).
+    pub fn retry(&self) -> Option<&RetrySpec> {
+        self.retry.as_ref()
+    }
+
+    /// Gets the group the task belongs to (if one is specified).
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    /// Gets the variables interpolated within the task (if interpolation is
+    /// enabled).
+    pub fn variables(&self) -> Option<&IndexMap<String, String>> {
+        self.variables.as_ref()
+    }
+
+    /// Expands the placeholders within the paths of the task's inputs and the
+    /// arguments and working directories of its executions.
+    ///
+    /// This does nothing if the task has no variables.
+    pub(crate) fn interpolate(&mut self, run_id: u64) -> interpolate::Result<()> {
+        let Some(variables) = &self.variables else {
+            return Ok(());
+        };
+
+        let lookup = |name: &str| match name {
+            "task.name" => self.name.clone(),
+            "attempt" => self.attempt.map(|a| a.to_string()),
+            "run.id" => Some(run_id.to_string()),
+            _ => variables.get(name).cloned(),
+        };
+        let expand = |s: &str| interpolate::interpolate(s, lookup);
+
+        let mut inputs = self.inputs.clone();
+        for input in &mut inputs {
+            input.path = expand(&input.path)?;
+            if let input::Contents::Path(path) = &input.contents {
+                input.contents = input::Contents::Path(expand(&path.to_string_lossy())?.into());
+            }
+        }
+
+        let mut executions = self.executions.clone();
+        for execution in executions.iter_mut() {
+            for arg in &mut execution.args {
+                *arg = expand(arg)?;
+            }
+
+            if let Some(work_dir) = &execution.work_dir {
+                execution.work_dir = Some(expand(work_dir)?);
+            }
+        }
+
+        self.inputs = inputs;
+        self.executions = executions;
+        Ok(())
+    }
+
+    /// Increments the attempt number of the task for a retry.
+    ///
+    /// The attempt number is also set in the environment of the task's
+    /// executions.
+    pub(crate) fn next_attempt(&mut self) {
+        let attempt = self.attempt.unwrap_or(1) + 1;
+        self.attempt = Some(attempt);
+
+        for execution in self.executions.iter_mut() {
+            execution
+                .env
+                .insert(TASK_ATTEMPT_ENV.to_string(), attempt.to_string());
+        }
+    }
+
+    /// Sets the identifiers of the task in the environment of its executions
+    /// so that output within them can be correlated with the engine's records.
+    ///
+    /// Variables already set by an execution are left unchanged.
+    pub(crate) fn identify(&mut self, run_id: u64) {
+        let vars = [
+            (TASK_NAME_ENV, self.name.clone()),
+            (TASK_ATTEMPT_ENV, self.attempt.map(|a| a.to_string())),
+            (RUN_ID_ENV, Some(run_id.to_string())),
+        ];
+
+        for execution in self.executions.iter_mut() {
+            for (name, value) in &vars {
+                if let Some(value) = value {
+                    execution
+                        .env
+                        .entry(name.to_string())
+                        .or_insert_with(|| value.clone());
+                }
+            }
+        }
+    }
 }
 
 impl TryFrom<Task> for tes::v1::types::requests::Task {
@@ -119,6 +354,14 @@ impl TryFrom<Task> for tes::v1::types::requests::Task {
             resources,
             executions,
             volumes,
+            timeout: _,
+            priority: _,
+            trace_context,
+            hostname: _,
+            attempt: _,
+            variables: _,
+            retry: _,
+            group: _,
         } = task;
 
         //========//
@@ -155,7 +398,20 @@ impl TryFrom<Task> for tes::v1::types::requests::Task {
         // Executions //
         //============//
 
-        let executors = executions.map(Executor::from).into_iter().collect();
+        let mut executors = executions
+            .map(Executor::from)
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        if let Some(context) = &trace_context {
+            for executor in &mut executors {
+                executor.env.get_or_insert_default().extend(
+                    context
+                        .env()
+                        .map(|(name, value)| (name.to_string(), value.to_string())),
+                );
+            }
+        }
 
         //===========//
         // Resources //
@@ -182,3 +438,268 @@ impl TryFrom<Task> for tes::v1::types::requests::Task {
         })
     }
 }
+
+impl TryFrom<tes::v1::types::requests::Task> for Task {
+    type Error = anyhow::Error;
+
+    /// Converts a TES task into a task.
+    ///
+    /// TES tags and backend parameters have no equivalent and are ignored.
+    /// Tasks with volumes are rejected, as volumes are not yet supported.
+    fn try_from(task: tes::v1::types::requests::Task) -> Result<Self, Self::Error> {
+        if task.volumes.as_ref().is_some_and(|v| !v.is_empty()) {
+            anyhow::bail!("volumes are not yet supported within Crankshaft");
+        }
+
+        let executions = task
+            .executors
+            .into_iter()
+            .map(Execution::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            name: task.name,
+            description: task.description,
+            inputs: task
+                .inputs
+                .unwrap_or_default()
+                .into_iter()
+                .map(Input::try_from)
+                .collect::<anyhow::Result<_>>()?,
+            outputs: task
+                .outputs
+                .unwrap_or_default()
+                .into_iter()
+                .map(Output::try_from)
+                .collect::<anyhow::Result<_>>()?,
+            resources: task.resources.map(Resources::from),
+            executions: NonEmpty::from_vec(executions)
+                .ok_or_else(|| anyhow::anyhow!("a TES task must have at least one executor"))?,
+            volumes: Vec::new(),
+            timeout: None,
+            priority: 0,
+            trace_context: None,
+            hostname: None,
+            attempt: None,
+            variables: None,
+            retry: None,
+            group: None,
+        })
+    }
+}
+
+/// Serialization of an optional [`Duration`] as a number of seconds.
+mod seconds {
+    use std::time::Duration;
+
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+
+    /// Serializes the duration as a number of seconds.
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&duration.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes the duration from a number of seconds.
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(deserializer)?
+            .map(|secs| Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let json = r#"{
+            "name": "hello",
+            "inputs": [{
+                "contents": { "literal": [104, 105] },
+                "path": "/in.txt",
+                "ty": "file"
+            }],
+            "executions": [{
+                "image": "ubuntu:22.04",
+                "program": "cat",
+                "args": ["/in.txt"],
+                "env": { "FOO": "bar" },
+                "secret-env": { "TOKEN": "hunter2" }
+            }],
+            "timeout": 1.5
+        }"#;
+
+        let task: Task = serde_json::from_str(json).unwrap();
+        assert_eq!(task.name(), Some("hello"));
+        assert_eq!(task.timeout, Some(Duration::from_millis(1500)));
+        assert!(task.inputs[0].read_only());
+        assert_eq!(
+            task.executions.first().secret_env()["TOKEN"].expose(),
+            "hunter2"
+        );
+
+        let value = serde_json::to_value(&task).unwrap();
+        let task: Task = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&task).unwrap(), value);
+    }
+
+    #[test]
+    fn interpolates_variables() {
+        let json = r#"{
+            "name": "align",
+            "inputs": [{
+                "contents": { "path": "/data/${sample}.bam" },
+                "path": "/in/${sample}.bam",
+                "ty": "file"
+            }],
+            "executions": [{
+                "image": "ubuntu:22.04",
+                "program": "sh",
+                "args": ["-c", "echo $${HOME} ${task.name} ${attempt}"],
+                "work-dir": "/work/${run.id}"
+            }],
+            "attempt": 2,
+            "variables": { "sample": "NA12878" }
+        }"#;
+
+        let mut task: Task = serde_json::from_str(json).unwrap();
+        task.interpolate(7).unwrap();
+
+        let input = &task.inputs[0];
+        assert_eq!(input.path(), "/in/NA12878.bam");
+        assert!(
+            matches!(input.contents(), input::Contents::Path(p) if p.to_str() == Some("/data/NA12878.bam"))
+        );
+        let execution = task.executions.first();
+        assert_eq!(execution.args(), ["-c", "echo ${HOME} align 2"]);
+        assert_eq!(execution.work_dir(), Some("/work/7"));
+
+        // Tasks without variables are left untouched
+        let mut task: Task = serde_json::from_str(&json.replace(
+            r#""variables": { "sample": "NA12878" }"#,
+            r#""priority": 0"#,
+        ))
+        .unwrap();
+        task.interpolate(7).unwrap();
+        assert_eq!(task.inputs[0].path(), "/in/${sample}.bam");
+
+        // Undefined variables are errors
+        let mut task: Task = serde_json::from_str(json).unwrap();
+        task.variables = Some(IndexMap::new());
+        assert!(task.interpolate(7).is_err());
+    }
+
+    #[test]
+    fn chains_executions() {
+        let mut task: Task = serde_json::from_str(
+            r#"{
+                "executions": [{
+                    "image": "ubuntu:22.04",
+                    "program": "make",
+                    "work-dir": "/work",
+                    "stdout": "/work/build.log",
+                    "env": { "CC": "clang" },
+                    "error-on-nonzero": true
+                }]
+            }"#,
+        )
+        .unwrap();
+        task.chain("make", ["install"]);
+
+        let chained = task.executions.last();
+        assert_eq!(task.executions.len(), 2);
+        assert_eq!(chained.image(), "ubuntu:22.04");
+        assert_eq!(chained.args(), ["install"]);
+        assert_eq!(chained.work_dir(), Some("/work"));
+        assert_eq!(chained.stdout(), None);
+        assert_eq!(chained.env()["CC"], "clang");
+        assert!(chained.error_on_nonzero());
+    }
+
+    #[test]
+    fn from_tes() {
+        let json = r#"{
+            "name": "hello",
+            "inputs": [{ "path": "/in.txt", "content": "hi", "type": "FILE" }],
+            "outputs": [{ "path": "/out.txt", "url": "s3://bucket/out.txt", "type": "FILE" }],
+            "resources": { "cpu_cores": 2, "ram_gb": 4.0 },
+            "executors": [{
+                "image": "ubuntu:22.04",
+                "command": ["cat", "/in.txt"],
+                "stdout": "/out.txt"
+            }]
+        }"#;
+
+        let tes: tes::v1::types::requests::Task = serde_json::from_str(json).unwrap();
+        let task = Task::try_from(tes).unwrap();
+        assert_eq!(task.name(), Some("hello"));
+        assert!(matches!(task.inputs[0].contents(), input::Contents::Literal(v) if v == b"hi"));
+        assert_eq!(task.outputs[0].url(), "s3://bucket/out.txt");
+        assert_eq!(task.resources().unwrap().cpu(), Some(2.0));
+
+        let execution = task.executions.first();
+        assert_eq!(execution.program(), "cat");
+        assert_eq!(execution.args(), ["/in.txt"]);
+        assert!(execution.error_on_nonzero());
+
+        let tes: tes::v1::types::requests::Task =
+            serde_json::from_str(r#"{ "executors": [] }"#).unwrap();
+        assert!(Task::try_from(tes).is_err());
+
+        let tes: tes::v1::types::requests::Task = serde_json::from_str(
+            r#"{ "volumes": ["/data"], "executors": [{ "image": "ubuntu:22.04", "command": ["true"] }] }"#,
+        )
+        .unwrap();
+        assert!(Task::try_from(tes).is_err());
+    }
+
+    #[test]
+    fn identify() {
+        let mut task = Task::builder()
+            .name("hello")
+            .attempt(2)
+            .executions(NonEmpty::new(
+                Execution::builder()
+                    .image("ubuntu")
+                    .program("echo")
+                    .env([(RUN_ID_ENV.to_string(), String::from("mine"))])
+                    .build(),
+            ))
+            .build();
+        task.identify(7);
+
+        let env = &task.executions.head.env;
+        assert_eq!(env[TASK_NAME_ENV], "hello");
+        assert_eq!(env[TASK_ATTEMPT_ENV], "2");
+        assert_eq!(env[RUN_ID_ENV], "mine");
+    }
+
+    #[test]
+    fn next_attempt() {
+        let mut task = Task::builder()
+            .executions(NonEmpty::new(
+                Execution::builder().image("ubuntu").program("echo").build(),
+            ))
+            .build();
+        task.identify(7);
+        assert!(!task.executions.head.env.contains_key(TASK_ATTEMPT_ENV));
+
+        task.next_attempt();
+        assert_eq!(task.attempt, Some(2));
+        assert_eq!(task.executions.head.env[TASK_ATTEMPT_ENV], "2");
+
+        task.next_attempt();
+        assert_eq!(task.executions.head.env[TASK_ATTEMPT_ENV], "3");
+    }
+}
@@ -0,0 +1,519 @@
+//! Differences between tasks.
+
+use std::fmt;
+use std::fmt::Debug;
+
+use indexmap::IndexMap;
+
+use crate::Task;
+use crate::task::Execution;
+use crate::task::Input;
+use crate::task::Output;
+use crate::task::Resources;
+use crate::task::Secret;
+
+/// A difference between two tasks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Difference {
+    /// The path to the differing field (e.g., `executions[0].work-dir`).
+    ///
+    /// Fields are named as they are when the task is serialized.
+    pub path: String,
+
+    /// The value within the left task (if present).
+    pub left: Option<String>,
+
+    /// The value within the right task (if present).
+    pub right: Option<String>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{path}: {left} -> {right}",
+            path = self.path,
+            left = self.left.as_deref().unwrap_or("<none>"),
+            right = self.right.as_deref().unwrap_or("<none>")
+        )
+    }
+}
+
+/// Accumulates the differences between two tasks.
+#[derive(Default)]
+struct Differ(Vec<Difference>);
+
+impl Differ {
+    /// Compares two values by their debug representations.
+    fn value<T: Debug>(&mut self, path: impl fmt::Display, left: &T, right: &T) {
+        self.option(path, Some(left), Some(right));
+    }
+
+    /// Compares two optional values by their debug representations.
+    fn option<T: Debug>(&mut self, path: impl fmt::Display, left: Option<&T>, right: Option<&T>) {
+        let left = left.map(|v| format!("{v:?}"));
+        let right = right.map(|v| format!("{v:?}"));
+
+        if left != right {
+            self.0.push(Difference {
+                path: path.to_string(),
+                left,
+                right,
+            });
+        }
+    }
+
+    /// Compares two lists element-wise with the provided comparison.
+    fn list<T>(
+        &mut self,
+        path: &str,
+        left: &[T],
+        right: &[T],
+        mut compare: impl FnMut(&mut Self, &str, &T, &T),
+    ) where
+        T: Debug,
+    {
+        for i in 0..left.len().max(right.len()) {
+            let path = format!("{path}[{i}]");
+
+            match (left.get(i), right.get(i)) {
+                (Some(l), Some(r)) => compare(self, &path, l, r),
+                (l, r) => self.option(path, l, r),
+            }
+        }
+    }
+
+    /// Compares two maps key-wise.
+    fn map(
+        &mut self,
+        path: &str,
+        left: &IndexMap<String, String>,
+        right: &IndexMap<String, String>,
+    ) {
+        for key in left
+            .keys()
+            .chain(right.keys().filter(|k| !left.contains_key(*k)))
+        {
+            self.option(format!("{path}.{key}"), left.get(key), right.get(key));
+        }
+    }
+
+    /// Compares two maps of secrets key-wise.
+    ///
+    /// The values are compared directly, but are redacted in any reported
+    /// difference.
+    fn secrets(
+        &mut self,
+        path: &str,
+        left: &IndexMap<String, Secret>,
+        right: &IndexMap<String, Secret>,
+    ) {
+        for key in left
+            .keys()
+            .chain(right.keys().filter(|k| !left.contains_key(*k)))
+        {
+            let (l, r) = (left.get(key), right.get(key));
+            if l != r {
+                self.0.push(Difference {
+                    path: format!("{path}.{key}"),
+                    left: l.map(|v| format!("{v:?}")),
+                    right: r.map(|v| format!("{v:?}")),
+                });
+            }
+        }
+    }
+
+    /// Compares two executions.
+    fn execution(&mut self, path: &str, left: &Execution, right: &Execution) {
+        // Destructure the execution so that a new field cannot be missed
+        let Execution {
+            image,
+            program,
+            args,
+            work_dir,
+            stdin,
+            stdout,
+            stderr,
+            env,
+            secret_env,
+            error_on_nonzero,
+        } = left;
+
+        self.value(format!("{path}.image"), image, &right.image);
+        self.value(format!("{path}.program"), program, &right.program);
+        self.value(format!("{path}.args"), args, &right.args);
+        self.value(format!("{path}.work-dir"), work_dir, &right.work_dir);
+        self.value(format!("{path}.stdin"), stdin, &right.stdin);
+        self.value(format!("{path}.stdout"), stdout, &right.stdout);
+        self.value(format!("{path}.stderr"), stderr, &right.stderr);
+        self.map(&format!("{path}.env"), env, &right.env);
+        self.secrets(&format!("{path}.secret-env"), secret_env, &right.secret_env);
+        self.value(
+            format!("{path}.error-on-nonzero"),
+            error_on_nonzero,
+            &right.error_on_nonzero,
+        );
+    }
+
+    /// Compares two inputs.
+    fn input(&mut self, path: &str, left: &Input, right: &Input) {
+        let Input {
+            name,
+            description,
+            contents,
+            path: input_path,
+            ty,
+            read_only,
+            checksum,
+        } = left;
+
+        self.value(format!("{path}.name"), name, &right.name);
+        self.value(
+            format!("{path}.description"),
+            description,
+            &right.description,
+        );
+        self.value(format!("{path}.path"), input_path, &right.path);
+        self.value(format!("{path}.contents"), contents, &right.contents);
+        self.value(format!("{path}.ty"), ty, &right.ty);
+        self.value(format!("{path}.read-only"), read_only, &right.read_only);
+        self.value(format!("{path}.checksum"), checksum, &right.checksum);
+    }
+
+    /// Compares two outputs.
+    fn output(&mut self, path: &str, left: &Output, right: &Output) {
+        let Output {
+            name,
+            description,
+            url,
+            path: output_path,
+            ty,
+        } = left;
+
+        self.value(format!("{path}.name"), name, &right.name);
+        self.value(
+            format!("{path}.description"),
+            description,
+            &right.description,
+        );
+        self.value(format!("{path}.path"), output_path, &right.path);
+        self.value(format!("{path}.url"), &url.as_str(), &right.url.as_str());
+        self.value(format!("{path}.ty"), ty, &right.ty);
+    }
+
+    /// Compares two sets of resources.
+    fn resources(&mut self, path: &str, left: &Resources, right: &Resources) {
+        let Resources {
+            cpu,
+            cpu_limit,
+            ram,
+            ram_limit,
+            disk,
+            gpus,
+            preemptible,
+            zones,
+        } = left;
+
+        self.value(format!("{path}.cpu"), cpu, &right.cpu);
+        self.value(format!("{path}.cpu-limit"), cpu_limit, &right.cpu_limit);
+        self.value(format!("{path}.ram"), ram, &right.ram);
+        self.value(format!("{path}.ram-limit"), ram_limit, &right.ram_limit);
+        self.value(format!("{path}.disk"), disk, &right.disk);
+        self.value(format!("{path}.gpus"), gpus, &right.gpus);
+        self.value(
+            format!("{path}.preemptible"),
+            preemptible,
+            &right.preemptible,
+        );
+        self.value(format!("{path}.zones"), zones, &right.zones);
+    }
+}
+
+impl Task {
+    /// Computes the differences between this task and another task.
+    ///
+    /// This is intended to answer what changed between two runs of a task
+    /// (e.g., a run that succeeded and a run that failed). Every field of the
+    /// tasks is compared.
+    pub fn diff(&self, other: &Task) -> Vec<Difference> {
+        // Destructure the task so that a new field cannot be missed
+        let Task {
+            name,
+            description,
+            inputs,
+            outputs,
+            resources,
+            executions,
+            volumes,
+            timeout,
+            priority,
+            trace_context,
+            hostname,
+            attempt,
+            variables,
+            retry,
+            group,
+        } = self;
+
+        let mut differ = Differ::default();
+        differ.value("name", name, &other.name);
+        differ.value("description", description, &other.description);
+
+        let left = executions.iter().collect::<Vec<_>>();
+        let right = other.executions.iter().collect::<Vec<_>>();
+        differ.list("executions", &left, &right, |d, p, l, r| {
+            d.execution(p, l, r)
+        });
+
+        differ.list("inputs", inputs, &other.inputs, Differ::input);
+        differ.list("outputs", outputs, &other.outputs, Differ::output);
+
+        match (resources, &other.resources) {
+            (Some(l), Some(r)) => differ.resources("resources", l, r),
+            (l, r) => differ.option("resources", l.as_ref(), r.as_ref()),
+        }
+
+        differ.value("volumes", volumes, &other.volumes);
+        differ.value("timeout", timeout, &other.timeout);
+        differ.value("priority", priority, &other.priority);
+        differ.value("trace-context", trace_context, &other.trace_context);
+        differ.value("hostname", hostname, &other.hostname);
+        differ.value("attempt", attempt, &other.attempt);
+        differ.value("variables", variables, &other.variables);
+        differ.value("retry", retry, &other.retry);
+        differ.value("group", group, &other.group);
+        differ.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nonempty::NonEmpty;
+
+    use super::*;
+    use crate::task::input::Contents;
+
+    fn task(image: &str, env: &[(&str, &str)]) -> Task {
+        Task::builder()
+            .executions(NonEmpty::new(
+                Execution::builder()
+                    .image(image)
+                    .program("echo")
+                    .env(
+                        env.iter()
+                            .map(|(k, v)| (k.to_string(), v.to_string()))
+                            .collect::<IndexMap<_, _>>(),
+                    )
+                    .build(),
+            ))
+            .build()
+    }
+
+    #[test]
+    fn identical_tasks_have_no_differences() {
+        let task = task("ubuntu:22.04", &[("FOO", "bar")]);
+        assert!(task.diff(&task.clone()).is_empty());
+    }
+
+    #[test]
+    fn differences_are_reported() {
+        let left = task("ubuntu:20.04", &[("FOO", "bar")]);
+        let right = task("ubuntu:22.04", &[("BAZ", "qux")]);
+
+        let differences = left.diff(&right);
+        let rendered = differences
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rendered,
+            [
+                r#"executions[0].image: "ubuntu:20.04" -> "ubuntu:22.04""#,
+                r#"executions[0].env.FOO: "bar" -> <none>"#,
+                r#"executions[0].env.BAZ: <none> -> "qux""#,
+            ]
+        );
+    }
+
+    /// A change to a task.
+    type Change = fn(&mut Task);
+
+    #[test]
+    fn every_field_is_compared() {
+        let mut base = task("ubuntu:22.04", &[]);
+        base.add_input(
+            Input::builder()
+                .contents(Contents::Literal(b"hello".to_vec()))
+                .path("/in")
+                .ty(crate::task::input::Type::File)
+                .build(),
+        );
+        base.add_output(
+            Output::builder()
+                .url("file:///out".parse::<url::Url>().unwrap())
+                .path("/out")
+                .ty(crate::task::output::Type::File)
+                .build(),
+        );
+        base.resources = Some(Resources::default());
+
+        // Each change must be reported at exactly the given path
+        let changes: &[(&str, Change)] = &[
+            ("name", |t| t.name = Some("other".into())),
+            ("description", |t| t.description = Some("other".into())),
+            ("executions[0].image", |t| {
+                t.executions.head.image = "other".into()
+            }),
+            ("executions[0].program", |t| {
+                t.executions.head.program = "other".into()
+            }),
+            ("executions[0].args", |t| {
+                t.executions.head.args = vec!["other".into()]
+            }),
+            ("executions[0].work-dir", |t| {
+                t.executions.head.work_dir = Some("/other".into())
+            }),
+            ("executions[0].stdin", |t| {
+                t.executions.head.stdin = Some("/other".into())
+            }),
+            ("executions[0].stdout", |t| {
+                t.executions.head.stdout = Some("/other".into())
+            }),
+            ("executions[0].stderr", |t| {
+                t.executions.head.stderr = Some("/other".into())
+            }),
+            ("executions[0].env.FOO", |t| {
+                t.executions.head.env.insert("FOO".into(), "bar".into());
+            }),
+            ("executions[0].secret-env.FOO", |t| {
+                t.executions
+                    .head
+                    .secret_env
+                    .insert("FOO".into(), Secret::from("bar"));
+            }),
+            ("executions[0].error-on-nonzero", |t| {
+                t.executions.head.error_on_nonzero = !t.executions.head.error_on_nonzero
+            }),
+            ("executions[1]", |t| {
+                t.add_execution(Execution::builder().image("other").program("echo").build())
+            }),
+            ("inputs[0].name", |t| {
+                t.inputs[0].name = Some("other".into())
+            }),
+            ("inputs[0].description", |t| {
+                t.inputs[0].description = Some("other".into())
+            }),
+            ("inputs[0].path", |t| t.inputs[0].path = "/other".into()),
+            ("inputs[0].contents", |t| {
+                t.inputs[0].contents = Contents::Literal(b"other".to_vec())
+            }),
+            ("inputs[0].ty", |t| {
+                t.inputs[0].ty = crate::task::input::Type::Directory
+            }),
+            ("inputs[0].read-only", |t| t.inputs[0].read_only = false),
+            ("inputs[0].checksum", |t| {
+                t.inputs[0].checksum = Some("other".into())
+            }),
+            ("outputs[0].name", |t| {
+                t.outputs[0].name = Some("other".into())
+            }),
+            ("outputs[0].description", |t| {
+                t.outputs[0].description = Some("other".into())
+            }),
+            ("outputs[0].url", |t| {
+                t.outputs[0].url = "file:///other".parse().unwrap()
+            }),
+            ("outputs[0].path", |t| t.outputs[0].path = "/other".into()),
+            ("outputs[0].ty", |t| {
+                t.outputs[0].ty = crate::task::output::Type::Directory
+            }),
+            ("resources.cpu", |t| {
+                t.resources.as_mut().unwrap().cpu = Some(2.0)
+            }),
+            ("resources.cpu-limit", |t| {
+                t.resources.as_mut().unwrap().cpu_limit = Some(2.0)
+            }),
+            ("resources.ram", |t| {
+                t.resources.as_mut().unwrap().ram = Some(4.0)
+            }),
+            ("resources.ram-limit", |t| {
+                t.resources.as_mut().unwrap().ram_limit = Some(2.0)
+            }),
+            ("resources.disk", |t| {
+                t.resources.as_mut().unwrap().disk = Some(16.0)
+            }),
+            ("resources.gpus", |t| {
+                t.resources.as_mut().unwrap().gpus = Some(crate::task::Gpus::Count(1))
+            }),
+            ("resources.preemptible", |t| {
+                t.resources.as_mut().unwrap().preemptible = Some(true)
+            }),
+            ("resources.zones", |t| {
+                t.resources.as_mut().unwrap().zones = vec!["other".into()]
+            }),
+            ("volumes", |t| t.volumes = vec!["/other".into()]),
+            ("timeout", |t| {
+                t.timeout = Some(std::time::Duration::from_secs(1))
+            }),
+            ("priority", |t| t.priority = 1),
+            ("trace-context", |t| {
+                t.trace_context = Some(
+                    crate::task::TraceContext::new(
+                        "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+                    )
+                    .unwrap(),
+                )
+            }),
+            ("hostname", |t| t.hostname = Some("other".into())),
+            ("attempt", |t| t.attempt = Some(1)),
+            ("variables", |t| t.variables = Some(IndexMap::new())),
+            ("retry", |t| {
+                t.retry = Some(crate::task::RetrySpec::builder().max_attempts(2).build())
+            }),
+            ("group", |t| t.group = Some("other".into())),
+        ];
+
+        for (path, change) in changes {
+            let mut other = base.clone();
+            change(&mut other);
+
+            let paths = base
+                .diff(&other)
+                .into_iter()
+                .map(|d| d.path)
+                .collect::<Vec<_>>();
+            assert_eq!(paths, [*path]);
+        }
+    }
+
+    #[test]
+    fn secrets_are_redacted() {
+        let task = |value: &str| {
+            Task::builder()
+                .executions(NonEmpty::new(
+                    Execution::builder()
+                        .image("ubuntu:22.04")
+                        .program("echo")
+                        .secret_env(IndexMap::from([("TOKEN".to_string(), Secret::from(value))]))
+                        .build(),
+                ))
+                .build()
+        };
+
+        let left = task("hunter2");
+        let right = task("hunter3");
+        assert!(!format!("{left:?}").contains("hunter2"));
+
+        let rendered = left
+            .diff(&right)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rendered,
+            ["executions[0].secret-env.TOKEN: <redacted> -> <redacted>"]
+        );
+    }
+}
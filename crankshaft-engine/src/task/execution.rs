@@ -1,12 +1,49 @@
 //! A unit of executable work.
 
 use std::collections::BTreeMap;
+use std::fmt;
 
 use bon::Builder;
 use indexmap::IndexMap;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A sensitive value.
+///
+/// The value is redacted when formatted with [`Debug`](fmt::Debug) so that it
+/// never appears in tracing output.
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Gets the underlying value.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<redacted>")
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
 
 /// An execution.
-#[derive(Builder, Clone, Debug)]
+#[derive(Builder, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 #[builder(builder_type = Builder)]
 pub struct Execution {
     /// The container image.
@@ -18,6 +55,7 @@ pub struct Execution {
     pub(crate) program: String,
 
     /// The arguments to the program.
+    #[serde(default)]
     #[builder(into, default)]
     pub(crate) args: Vec<String>,
 
@@ -41,8 +79,28 @@ pub struct Execution {
     pub(crate) stderr: Option<String>,
 
     /// A map of environment variables, if configured.
+    #[serde(default)]
     #[builder(into, default)]
     pub(crate) env: IndexMap<String, String>,
+
+    /// A map of environment variables whose values are sensitive, if
+    /// configured.
+    ///
+    /// These take precedence over [`Self::env`] and are redacted from debug
+    /// and tracing output.
+    #[serde(default)]
+    #[builder(into, default)]
+    pub(crate) secret_env: IndexMap<String, Secret>,
+
+    /// Whether a non-zero exit of the execution is treated as an error.
+    ///
+    /// When enabled, a non-zero exit stops any subsequent executions of the
+    /// task from running and the task fails with
+    /// [`TaskRunError::NonZeroExit`](crate::service::runner::backend::TaskRunError::NonZeroExit).
+    /// Otherwise, the exit status is simply reported to the caller.
+    #[serde(default)]
+    #[builder(default)]
+    pub(crate) error_on_nonzero: bool,
 }
 
 impl Execution {
@@ -85,6 +143,41 @@ impl Execution {
     pub fn env(&self) -> &IndexMap<String, String> {
         &self.env
     }
+
+    /// The environment variables for the execution whose values are
+    /// sensitive.
+    pub fn secret_env(&self) -> &IndexMap<String, Secret> {
+        &self.secret_env
+    }
+
+    /// Whether a non-zero exit of the execution is treated as an error.
+    pub fn error_on_nonzero(&self) -> bool {
+        self.error_on_nonzero
+    }
+
+    /// Creates an execution that runs a different program within the same
+    /// image, working directory, and environment as this one.
+    ///
+    /// The standard streams are not carried over, as subsequent executions
+    /// typically redirect them elsewhere.
+    pub fn then(
+        &self,
+        program: impl Into<String>,
+        args: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            image: self.image.clone(),
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            work_dir: self.work_dir.clone(),
+            stdin: None,
+            stdout: None,
+            stderr: None,
+            env: self.env.clone(),
+            secret_env: self.secret_env.clone(),
+            error_on_nonzero: self.error_on_nonzero,
+        }
+    }
 }
 
 impl From<Execution> for tes::v1::types::task::Executor {
@@ -92,6 +185,7 @@ impl From<Execution> for tes::v1::types::task::Executor {
         let env = execution
             .env
             .into_iter()
+            .chain(execution.secret_env.into_iter().map(|(k, v)| (k, v.0)))
             .collect::<BTreeMap<String, String>>();
 
         let env = if env.is_empty() { None } else { Some(env) };
@@ -108,7 +202,33 @@ impl From<Execution> for tes::v1::types::task::Executor {
             stdout: execution.stdout,
             stderr: execution.stderr,
             env,
-            ignore_error: Some(true),
+            ignore_error: Some(!execution.error_on_nonzero),
         }
     }
 }
+
+impl TryFrom<tes::v1::types::task::Executor> for Execution {
+    type Error = anyhow::Error;
+
+    fn try_from(executor: tes::v1::types::task::Executor) -> Result<Self, Self::Error> {
+        let mut command = executor.command.into_iter();
+        let program = command
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("executor for `{}` has no command", executor.image))?;
+
+        Ok(Self {
+            image: executor.image,
+            program,
+            args: command.collect(),
+            work_dir: executor.workdir,
+            stdin: executor.stdin,
+            stdout: executor.stdout,
+            stderr: executor.stderr,
+            env: executor.env.unwrap_or_default().into_iter().collect(),
+            secret_env: Default::default(),
+            // TES stops a task at the first failing executor unless told to
+            // ignore its errors
+            error_on_nonzero: !executor.ignore_error.unwrap_or(false),
+        })
+    }
+}
@@ -0,0 +1,138 @@
+//! Hashes of the contents of tasks.
+
+use sha2::Digest as _;
+use sha2::Sha256;
+
+use super::RUN_ID_ENV;
+use super::TASK_ATTEMPT_ENV;
+use super::TASK_NAME_ENV;
+use super::Task;
+use super::input::download;
+
+/// The fields of a task that do not affect what it runs.
+const IGNORED_FIELDS: &[&str] = &[
+    "name",
+    "description",
+    "priority",
+    "trace-context",
+    "attempt",
+    "variables",
+    "retry",
+    "group",
+];
+
+impl Task {
+    /// Computes a hash of what the task runs.
+    ///
+    /// Tasks with the same hash run the same programs in the same images with
+    /// the same environment, inputs, outputs, and resources; they only differ
+    /// by their names, descriptions, priorities, attempt numbers, or tracing
+    /// contexts. The identifiers the engine sets in the environment of the
+    /// task's executions (e.g., [`RUN_ID_ENV`]) are also ignored.
+    ///
+    /// The contents of inputs are identified by their URLs or paths rather
+    /// than read, so the hash only identifies the same work for tasks that
+    /// run at around the same time.
+    pub fn content_hash(&self) -> String {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(fields) = value.as_object_mut() {
+            for field in IGNORED_FIELDS {
+                fields.remove(*field);
+            }
+        }
+
+        if let Some(executions) = value
+            .get_mut("executions")
+            .and_then(serde_json::Value::as_array_mut)
+        {
+            for env in executions
+                .iter_mut()
+                .filter_map(|e| e.get_mut("env"))
+                .filter_map(serde_json::Value::as_object_mut)
+            {
+                for var in [TASK_NAME_ENV, TASK_ATTEMPT_ENV, RUN_ID_ENV] {
+                    env.remove(var);
+                }
+            }
+        }
+
+        format!(
+            "sha256:{}",
+            download::hex(Sha256::digest(value.to_string()))
+        )
+    }
+
+    /// Computes the key that identical tasks are coalesced by.
+    ///
+    /// Tasks are only coalesced if they have the same content hash (see
+    /// [`Self::content_hash()`]), retry policy, and group, as a task coalesced
+    /// onto another receives the result of the other task's retries and has
+    /// its time charged to the other task's group.
+    pub(crate) fn coalescing_key(&self) -> String {
+        let value = serde_json::json!({
+            "hash": self.content_hash(),
+            "retry": self.retry,
+            "group": self.group,
+        });
+
+        format!(
+            "sha256:{}",
+            download::hex(Sha256::digest(value.to_string()))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nonempty::NonEmpty;
+
+    use super::*;
+    use crate::task::Execution;
+
+    #[test]
+    fn hashes_contents() {
+        let task = |name: &str, program: &str| {
+            Task::builder()
+                .name(name)
+                .priority(1)
+                .executions(NonEmpty::new(
+                    Execution::builder()
+                        .image("ubuntu:22.04")
+                        .program(program)
+                        .build(),
+                ))
+                .build()
+        };
+
+        let mut identified = task("first", "true");
+        identified.identify(1);
+        assert_eq!(
+            identified.content_hash(),
+            task("second", "true").content_hash()
+        );
+        assert_ne!(
+            task("first", "true").content_hash(),
+            task("first", "false").content_hash()
+        );
+
+        // Tasks with different retry policies or groups are not coalesced
+        let mut grouped = task("first", "true");
+        grouped.group = Some(String::from("group"));
+        assert_eq!(grouped.content_hash(), task("first", "true").content_hash());
+        assert_ne!(
+            grouped.coalescing_key(),
+            task("first", "true").coalescing_key()
+        );
+
+        let mut retried = task("first", "true");
+        retried.retry = Some(crate::task::RetrySpec::builder().max_attempts(3).build());
+        assert_ne!(
+            retried.coalescing_key(),
+            task("first", "true").coalescing_key()
+        );
+        assert_eq!(
+            identified.coalescing_key(),
+            task("second", "true").coalescing_key()
+        );
+    }
+}
@@ -1,15 +1,22 @@
 //! Task inputs.
 
+use std::path::Path;
+use std::path::PathBuf;
+
 use anyhow::Context;
 use anyhow::Result;
 use bon::Builder;
+use serde::Deserialize;
+use serde::Serialize;
 
 mod contents;
+pub(crate) mod download;
 
 pub use contents::Contents;
 
 /// A type of input.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum Type {
     /// A file.
     File,
@@ -18,8 +25,15 @@ pub enum Type {
     Directory,
 }
 
+/// A utility function used to set the default value for `read_only` via
+/// serde.
+fn default_read_only() -> bool {
+    true
+}
+
 /// An input to a task.
-#[derive(Builder, Clone, Debug)]
+#[derive(Builder, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 #[builder(builder_type = Builder)]
 pub struct Input {
     /// An optional name to give the input.
@@ -45,8 +59,18 @@ pub struct Input {
     /// Whether or not the input should be treated as read-only.
     ///
     /// Defaults to `true`.
+    #[serde(default = "default_read_only")]
     #[builder(default = true)]
     pub(crate) read_only: bool,
+
+    /// The expected checksum of the input's contents (e.g.,
+    /// `sha256:<hex digest>`).
+    ///
+    /// When set, the contents of a file input are verified against the
+    /// checksum after they are fetched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub(crate) checksum: Option<String>,
 }
 
 impl Input {
@@ -81,6 +105,34 @@ impl Input {
     pub fn read_only(&self) -> bool {
         self.read_only
     }
+
+    /// The expected checksum of the input's contents (if it exists).
+    pub fn checksum(&self) -> Option<&str> {
+        self.checksum.as_deref()
+    }
+
+    /// Fetches the input's contents locally and verifies its checksum (if it
+    /// has one).
+    ///
+    /// Remote contents are downloaded into `cache_dir`, where they are reused
+    /// by later fetches of the same URL; if no cache directory is provided,
+    /// they are downloaded into `temp_dir`. See [`Contents::fetch()`].
+    ///
+    /// Returns the path to the contents.
+    pub async fn fetch(&self, temp_dir: &Path, cache_dir: Option<&Path>) -> Result<PathBuf> {
+        let path = self.contents.fetch(temp_dir, cache_dir).await?.into_owned();
+
+        if let (Some(checksum), Type::File) = (&self.checksum, &self.ty) {
+            download::verify(&path, checksum).await.with_context(|| {
+                format!(
+                    "failed to verify input `{name}`",
+                    name = self.name.as_deref().unwrap_or(&self.path)
+                )
+            })?;
+        }
+
+        Ok(path)
+    }
 }
 
 impl TryFrom<Input> for tes::v1::types::task::Input {
@@ -94,6 +146,7 @@ impl TryFrom<Input> for tes::v1::types::task::Input {
             path,
             ty,
             read_only: _,
+            checksum: _,
         } = input;
 
         let (url, content) = contents.one_hot()?;
@@ -116,3 +169,40 @@ impl TryFrom<Input> for tes::v1::types::task::Input {
         })
     }
 }
+
+impl TryFrom<tes::v1::types::task::Input> for Input {
+    type Error = anyhow::Error;
+
+    fn try_from(input: tes::v1::types::task::Input) -> Result<Self, Self::Error> {
+        let tes::v1::types::task::Input {
+            name,
+            description,
+            url,
+            path,
+            ty,
+            content,
+            streamable: _,
+        } = input;
+
+        let contents = match (url, content) {
+            (Some(url), None) => Contents::url_from_str(url)?,
+            (None, Some(content)) => Contents::Literal(content.into_bytes()),
+            _ => anyhow::bail!("input `{path}` must have exactly one of a URL or content"),
+        };
+
+        let ty = match ty {
+            tes::v1::types::task::IoType::File => Type::File,
+            tes::v1::types::task::IoType::Directory => Type::Directory,
+        };
+
+        Ok(Self {
+            name,
+            description,
+            contents,
+            path,
+            ty,
+            read_only: true,
+            checksum: None,
+        })
+    }
+}
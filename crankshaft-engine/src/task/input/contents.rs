@@ -9,6 +9,8 @@ use std::path::PathBuf;
 use anyhow::Context;
 use anyhow::anyhow;
 use anyhow::bail;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
 use url::Url;
 
@@ -24,7 +26,8 @@ pub enum Error {
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// The source of an input.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum Contents {
     /// Contents sourced from a URL.
     Url(Url),
@@ -72,10 +75,16 @@ impl Contents {
     ///
     /// If the contents is a literal, they are written to a temporary file.
     ///
-    /// If the contents is a URL, the file is downloaded to a temporary file.
+    /// If the contents is an `http`, `https`, or `s3` URL, the file is
+    /// downloaded into `cache_dir` (or `temp_dir` if there is no cache
+    /// directory); files previously downloaded from the same URL are reused.
     ///
     /// Returns the path to the contents.
-    pub async fn fetch(&self, temp_dir: &Path) -> anyhow::Result<Cow<'_, Path>> {
+    pub async fn fetch(
+        &self,
+        temp_dir: &Path,
+        cache_dir: Option<&Path>,
+    ) -> anyhow::Result<Cow<'_, Path>> {
         let contents: Cow<'_, [u8]> = match self {
             Self::Url(url) => {
                 match url.scheme() {
@@ -90,9 +99,13 @@ impl Contents {
                         })?;
                         return Ok(path.into());
                     }
-                    // TODO: remotely fetched contents should be cached somewhere
-                    "http" | "https" => bail!("support for HTTP URLs is not yet implemented"),
-                    "s3" => bail!("support for S3 URLs is not yet implemented"),
+                    "http" | "https" | "s3" => {
+                        return Ok(
+                            super::download::download(url, cache_dir.unwrap_or(temp_dir))
+                                .await?
+                                .into(),
+                        );
+                    }
                     "az" => bail!("support for Azure Storage URLs is not yet implemented"),
                     "gs" => bail!("support for Google Cloud Storage URLs is not yet implemented"),
                     scheme => bail!("URL has unsupported scheme `{scheme}`"),
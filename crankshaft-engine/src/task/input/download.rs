@@ -0,0 +1,328 @@
+//! Downloading of inputs from remote URLs and checksumming of files.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use sha2::Digest as _;
+use sha2::Sha256;
+use tokio::io::AsyncReadExt as _;
+use tokio::io::AsyncWriteExt as _;
+use tracing::debug;
+use url::Url;
+
+/// The size of the buffer used when hashing files.
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The number of pins held on each downloaded file keyed by path.
+static PINNED: LazyLock<Mutex<HashMap<PathBuf, usize>>> = LazyLock::new(Default::default);
+
+/// A pin on a downloaded file that prevents it from being evicted (see
+/// [`evict()`]).
+///
+/// The file is unpinned when the last pin on it is dropped.
+#[derive(Debug)]
+pub(crate) struct Pin(PathBuf);
+
+impl Pin {
+    /// Pins the file at the given path.
+    pub(crate) fn new(path: PathBuf) -> Self {
+        *PINNED.lock().unwrap().entry(path.clone()).or_default() += 1;
+        Self(path)
+    }
+}
+
+impl Drop for Pin {
+    fn drop(&mut self) {
+        let mut pinned = PINNED.lock().unwrap();
+        if let Some(count) = pinned.get_mut(&self.0) {
+            *count -= 1;
+            if *count == 0 {
+                pinned.remove(&self.0);
+            }
+        }
+    }
+}
+
+/// Formats a SHA-256 digest as a lowercase hexadecimal string.
+pub(crate) fn hex(digest: impl AsRef<[u8]>) -> String {
+    digest.as_ref().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Resolves the HTTP(S) URL to download the contents of a remote URL from.
+///
+/// `s3://bucket/key` URLs are resolved to the bucket's public virtual-hosted
+/// endpoint; as such, only publicly readable objects are supported.
+fn resolve(url: &Url) -> Result<Url> {
+    match url.scheme() {
+        "http" | "https" => Ok(url.clone()),
+        "s3" => {
+            let bucket = url
+                .host_str()
+                .with_context(|| format!("S3 URL `{url}` does not specify a bucket"))?;
+            let key = url.path().trim_start_matches('/');
+            if key.is_empty() {
+                bail!("S3 URL `{url}` does not specify an object key");
+            }
+
+            format!("https://{bucket}.s3.amazonaws.com/{key}")
+                .parse()
+                .with_context(|| format!("invalid S3 URL `{url}`"))
+        }
+        scheme => bail!("URL has unsupported scheme `{scheme}`"),
+    }
+}
+
+/// Gets the path that the contents of a remote URL are downloaded to within
+/// the given directory.
+pub(crate) fn path(url: &Url, dir: &Path) -> PathBuf {
+    dir.join(hex(Sha256::digest(url.as_str())))
+}
+
+/// Downloads the contents of a remote URL into the given directory, returning
+/// the path to the downloaded file.
+///
+/// Files are named after the SHA-256 digest of their URL; if a file for the
+/// URL already exists in the directory, it is reused rather than downloaded
+/// again. Downloads are written to a temporary file and then moved into place
+/// so that a partially downloaded file is never reused.
+pub(crate) async fn download(url: &Url, dir: &Path) -> Result<PathBuf> {
+    let path = path(url, dir);
+    if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+        debug!(
+            "using cached download of `{url}` at `{path}`",
+            path = path.display()
+        );
+
+        // Mark the file as recently used so that it is evicted last
+        let _ = std::fs::File::options()
+            .append(true)
+            .open(&path)
+            .and_then(|f| f.set_modified(SystemTime::now()));
+        return Ok(path);
+    }
+
+    tokio::fs::create_dir_all(dir).await.with_context(|| {
+        format!(
+            "failed to create download directory `{dir}`",
+            dir = dir.display()
+        )
+    })?;
+
+    let source = resolve(url)?;
+    debug!("downloading `{url}` from `{source}`");
+
+    let mut response = reqwest::get(source.clone())
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("failed to download `{url}`"))?;
+
+    let temp = tempfile::NamedTempFile::new_in(dir).with_context(|| {
+        format!(
+            "failed to create temporary download file in `{dir}`",
+            dir = dir.display()
+        )
+    })?;
+    let mut file = tokio::fs::File::from_std(temp.reopen()?);
+
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .with_context(|| format!("failed to download `{url}`"))?
+    {
+        file.write_all(&chunk).await.with_context(|| {
+            format!(
+                "failed to write download of `{url}` to `{path}`",
+                path = temp.path().display()
+            )
+        })?;
+    }
+
+    file.flush().await?;
+    drop(file);
+
+    temp.persist(&path).with_context(|| {
+        format!(
+            "failed to move download of `{url}` to `{path}`",
+            path = path.display()
+        )
+    })?;
+
+    Ok(path)
+}
+
+/// Evicts downloaded files from a directory.
+///
+/// Files that were last used (i.e., downloaded or reused) longer than
+/// `max_age` ago are removed first. The least recently used files are then
+/// removed until the total size of the directory is at most `max_bytes`.
+/// Files that are pinned (see [`Pin`]) and in-progress downloads are never
+/// removed.
+///
+/// Returns the number of bytes that were removed.
+pub(crate) fn evict(dir: &Path, max_bytes: Option<u64>, max_age: Option<Duration>) -> Result<u64> {
+    let now = SystemTime::now();
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("failed to read directory `{dir}`", dir = dir.display()));
+        }
+    };
+
+    let mut files = Vec::new();
+    let mut total = 0;
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        // Temporary files of in-progress downloads start with a `.`
+        if !metadata.is_file() || entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        total += metadata.len();
+        files.push((metadata.modified()?, entry.path(), metadata.len()));
+    }
+
+    // Least recently used first
+    files.sort();
+
+    let pinned = PINNED.lock().unwrap();
+    let mut removed = 0;
+    for (modified, path, size) in files {
+        let expired = max_age
+            .map(|max_age| now.duration_since(modified).unwrap_or_default() > max_age)
+            .unwrap_or(false);
+        let oversized = max_bytes.map(|max| total > max).unwrap_or(false);
+
+        if (!expired && !oversized) || pinned.contains_key(&path) {
+            continue;
+        }
+
+        debug!("evicting cached download `{path}`", path = path.display());
+        std::fs::remove_file(&path)
+            .with_context(|| format!("failed to remove `{path}`", path = path.display()))?;
+        total -= size;
+        removed += size;
+    }
+
+    Ok(removed)
+}
+
+/// Computes the SHA-256 checksum of the file at the given path.
+///
+/// The checksum is returned in the form `sha256:<hex digest>`.
+pub(crate) async fn sha256(path: &Path) -> Result<String> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .with_context(|| format!("failed to open `{path}`", path = path.display()))?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; HASH_BUFFER_SIZE];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .await
+            .with_context(|| format!("failed to read `{path}`", path = path.display()))?;
+        if read == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("sha256:{digest}", digest = hex(hasher.finalize())))
+}
+
+/// Verifies that the file at the given path has the expected checksum.
+///
+/// Checksums are of the form `sha256:<hex digest>`.
+pub(crate) async fn verify(path: &Path, expected: &str) -> Result<()> {
+    if !matches!(expected.split_once(':'), Some(("sha256", _))) {
+        bail!("unsupported checksum `{expected}`: expected `sha256:<hex digest>`");
+    }
+
+    let actual = sha256(path).await?;
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!(
+            "checksum mismatch for `{path}`: expected `{expected}` but found `{actual}`",
+            path = path.display()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_s3_urls() {
+        let url = "s3://bucket/path/to/file.txt".parse().unwrap();
+        assert_eq!(
+            resolve(&url).unwrap().as_str(),
+            "https://bucket.s3.amazonaws.com/path/to/file.txt"
+        );
+
+        assert!(resolve(&"s3://bucket".parse().unwrap()).is_err());
+        assert!(resolve(&"ftp://host/file".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn evicts_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        let create = |name: &str, age: u64| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, [0; 10]).unwrap();
+            std::fs::File::options()
+                .append(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age))
+                .unwrap();
+            path
+        };
+
+        let oldest = create("oldest", 300);
+        let older = create("older", 200);
+        let old = create("old", 100);
+        let new = create("new", 0);
+        let temp = create(".download", 1000);
+
+        // The oldest file is pinned, so the next oldest is evicted by age
+        let pin = Pin::new(oldest.clone());
+        assert_eq!(
+            evict(dir.path(), None, Some(Duration::from_secs(150))).unwrap(),
+            10
+        );
+        assert!(oldest.exists() && !older.exists());
+
+        // Once unpinned, the least recently used files are evicted by size
+        drop(pin);
+        assert_eq!(evict(dir.path(), Some(10), None).unwrap(), 20);
+        assert!(!oldest.exists() && !old.exists() && new.exists() && temp.exists());
+    }
+
+    #[tokio::test]
+    async fn verifies_checksums() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        std::fs::write(&path, "hello").unwrap();
+
+        let digest = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(verify(&path, &format!("sha256:{digest}")).await.is_ok());
+        assert!(verify(&path, "sha256:00").await.is_err());
+        assert!(verify(&path, &format!("md5:{digest}")).await.is_err());
+    }
+}
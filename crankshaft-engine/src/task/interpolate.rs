@@ -0,0 +1,111 @@
+//! Interpolation of variables within the paths and arguments of tasks.
+//!
+//! Placeholders are written as `${name}`, where `name` is either a variable of
+//! the task (see [`Task::builder()`](crate::Task::builder)), one of the
+//! built-in variables below, or `env:VAR` to read `VAR` from the engine's
+//! environment. A literal `${` is written as `$${`.
+//!
+//! | Variable    | Value                                                  |
+//! |-------------|--------------------------------------------------------|
+//! | `task.name` | The name of the task.                                  |
+//! | `attempt`   | The attempt number of the task (if it has one).        |
+//! | `run.id`    | The identifier the engine assigned to the run.         |
+
+use thiserror::Error;
+
+/// The prefix of placeholders read from the engine's environment.
+const ENV_PREFIX: &str = "env:";
+
+/// An error interpolating variables.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A placeholder was not closed.
+    #[error("unterminated placeholder in `{0}`")]
+    Unterminated(String),
+
+    /// A placeholder referred to a variable that is not defined.
+    #[error("undefined variable `{0}`")]
+    Undefined(String),
+}
+
+/// A [`Result`](std::result::Result) with an [`Error`](enum@Error).
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Interpolates the placeholders within a string.
+///
+/// Each placeholder other than `env:VAR` is resolved with `lookup`.
+pub fn interpolate(s: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(start) = rest.find("${") {
+        // An escaped placeholder is written without its leading `$`
+        if rest[..start].ends_with('$') {
+            result.push_str(&rest[..start - 1]);
+            result.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        result.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| Error::Unterminated(s.to_string()))?;
+        let name = &rest[start + 2..start + end];
+
+        let value = match name.strip_prefix(ENV_PREFIX) {
+            Some(var) => std::env::var(var).ok(),
+            None => lookup(name),
+        };
+        result.push_str(&value.ok_or_else(|| Error::Undefined(name.to_string()))?);
+        rest = &rest[start + end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolates() {
+        let lookup = |name: &str| match name {
+            "task.name" => Some(String::from("align")),
+            "attempt" => Some(String::from("2")),
+            _ => None,
+        };
+
+        assert_eq!(
+            interpolate("/work/${task.name}/${attempt}", lookup).unwrap(),
+            "/work/align/2"
+        );
+        assert_eq!(
+            interpolate("no placeholders", lookup).unwrap(),
+            "no placeholders"
+        );
+        assert_eq!(
+            interpolate("echo $${HOME} ${attempt}", lookup).unwrap(),
+            "echo ${HOME} 2"
+        );
+
+        assert_eq!(
+            interpolate("${env:PATH}", lookup).unwrap(),
+            std::env::var("PATH").unwrap()
+        );
+
+        assert!(matches!(
+            interpolate("${missing}", lookup),
+            Err(Error::Undefined(name)) if name == "missing"
+        ));
+        assert!(matches!(
+            interpolate("${env:CRANKSHAFT_INTERPOLATE_MISSING}", lookup),
+            Err(Error::Undefined(_))
+        ));
+        assert!(matches!(
+            interpolate("${attempt", lookup),
+            Err(Error::Unterminated(_))
+        ));
+    }
+}
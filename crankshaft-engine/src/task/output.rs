@@ -1,10 +1,13 @@
 //! Task outputs.
 
 use bon::Builder;
+use serde::Deserialize;
+use serde::Serialize;
 use url::Url;
 
 /// A type of task output.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum Type {
     /// A file.
     File,
@@ -14,7 +17,8 @@ pub enum Type {
 }
 
 /// A task output.
-#[derive(Builder, Clone, Debug)]
+#[derive(Builder, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 #[builder(builder_type = Builder)]
 pub struct Output {
     /// An optional name.
@@ -90,3 +94,37 @@ impl From<Output> for tes::v1::types::task::Output {
         }
     }
 }
+
+impl TryFrom<tes::v1::types::task::Output> for Output {
+    type Error = anyhow::Error;
+
+    fn try_from(output: tes::v1::types::task::Output) -> Result<Self, Self::Error> {
+        let tes::v1::types::task::Output {
+            name,
+            description,
+            url,
+            path,
+            path_prefix,
+            ty,
+        } = output;
+
+        if path_prefix.is_some() {
+            anyhow::bail!("output path prefixes are not supported within Crankshaft");
+        }
+
+        let ty = match ty {
+            tes::v1::types::task::IoType::File => Type::File,
+            tes::v1::types::task::IoType::Directory => Type::Directory,
+        };
+
+        Ok(Self {
+            name,
+            description,
+            url: url
+                .parse()
+                .map_err(|e| anyhow::anyhow!("invalid output URL `{url}`: {e}"))?,
+            path,
+            ty,
+        })
+    }
+}
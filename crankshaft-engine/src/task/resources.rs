@@ -3,14 +3,116 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 
+use bollard::secret::DeviceRequest;
 use bollard::secret::HostConfig;
 use bollard::secret::TaskSpecResources;
 use bon::Builder;
 use crankshaft_config::backend::Defaults;
+use crankshaft_config::size;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
 use tracing::debug;
+use tracing::warn;
+
+/// The prefix of image labels that specify resource defaults.
+///
+/// For example, an image labeled with `crankshaft.cpu=4` recommends four CPU
+/// cores.
+pub const LABEL_PREFIX: &str = "crankshaft.";
+
+/// The smallest number of CPU cores that may be requested (one millicpu).
+pub const MIN_CPU: f64 = 0.001;
+
+/// An error validating [`Resources`].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A CPU request or limit was not a finite number of at least
+    /// [`MIN_CPU`] cores.
+    #[error("invalid {name} `{value}`: expected a number of cores of at least {MIN_CPU}")]
+    InvalidCpu {
+        /// The name of the invalid field.
+        name: &'static str,
+
+        /// The invalid value.
+        value: f64,
+    },
+
+    /// A RAM or disk size was not a finite, non-negative number.
+    #[error("invalid {name} `{value}`: expected a non-negative number of GiB")]
+    InvalidSize {
+        /// The name of the invalid field.
+        name: &'static str,
+
+        /// The invalid value.
+        value: f64,
+    },
+
+    /// A limit was less than the corresponding request.
+    #[error("{limit_name} `{limit}` is less than the requested {name} `{value}`")]
+    LimitBelowRequest {
+        /// The name of the requested field.
+        name: &'static str,
+
+        /// The requested value.
+        value: f64,
+
+        /// The name of the limit field.
+        limit_name: &'static str,
+
+        /// The limit.
+        limit: f64,
+    },
+}
+
+/// A request for GPUs.
+///
+/// Deserializes from either a number of GPUs (e.g., `2`) or a list of device
+/// IDs (e.g., `["0", "3"]`).
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Gpus {
+    /// A number of GPUs on any devices.
+    Count(u32),
+
+    /// The IDs (or UUIDs) of specific GPU devices.
+    Devices(Vec<String>),
+}
+
+impl Gpus {
+    /// Gets the number of requested GPUs.
+    pub fn count(&self) -> usize {
+        match self {
+            Self::Count(count) => *count as usize,
+            Self::Devices(devices) => devices.len(),
+        }
+    }
+
+    /// Gets the IDs of the requested devices, if specific devices were
+    /// requested.
+    pub fn devices(&self) -> Option<&[String]> {
+        match self {
+            Self::Count(_) => None,
+            Self::Devices(devices) => Some(devices),
+        }
+    }
+}
+
+impl From<u32> for Gpus {
+    fn from(count: u32) -> Self {
+        Self::Count(count)
+    }
+}
+
+impl From<Vec<String>> for Gpus {
+    fn from(devices: Vec<String>) -> Self {
+        Self::Devices(devices)
+    }
+}
 
 /// A set of requested resources.
-#[derive(Builder, Clone, Debug)]
+#[derive(Builder, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 #[builder(builder_type = Builder)]
 pub struct Resources {
     /// The requested number of CPU cores.
@@ -25,21 +127,34 @@ pub struct Resources {
     pub(crate) cpu_limit: Option<f64>,
 
     /// The requested random access memory size (in GiB).
+    #[serde(default, with = "crankshaft_config::size::gib")]
     pub(crate) ram: Option<f64>,
 
     /// The requested RAM limit (in GiB).
     ///
     /// Not all backends support limits on memory usage.
+    #[serde(default, with = "crankshaft_config::size::gib")]
     pub(crate) ram_limit: Option<f64>,
 
     /// The requested disk size (in GiB).
+    #[serde(default, with = "crankshaft_config::size::gib")]
     pub(crate) disk: Option<f64>,
 
+    /// The requested GPUs.
+    ///
+    /// When a runner's capacity lists the GPU devices available to tasks, a
+    /// request for a number of GPUs is assigned specific devices that are not
+    /// in use by other running tasks.
+    #[serde(default)]
+    #[builder(into)]
+    pub(crate) gpus: Option<Gpus>,
+
     /// Whether or not the task may use preemptible resources.
     #[builder(into)]
     pub(crate) preemptible: Option<bool>,
 
     /// The associated compute zones.
+    #[serde(default)]
     #[builder(into, default)]
     pub(crate) zones: Vec<String>,
 }
@@ -70,6 +185,11 @@ impl Resources {
         self.disk
     }
 
+    /// The requested GPUs.
+    pub fn gpus(&self) -> Option<&Gpus> {
+        self.gpus.as_ref()
+    }
+
     /// Whether the instance should be preemptible.
     pub fn preemptible(&self) -> Option<bool> {
         self.preemptible
@@ -80,6 +200,51 @@ impl Resources {
         &self.zones
     }
 
+    /// Validates the requested resources.
+    ///
+    /// CPU requests and limits may be fractional (e.g., `0.5` or `2.5` cores)
+    /// but must be at least [`MIN_CPU`]; RAM and disk sizes must be
+    /// non-negative. Limits must not be less than their requests.
+    pub fn validate(&self) -> Result<(), Error> {
+        for (name, value) in [("cpu", self.cpu), ("cpu-limit", self.cpu_limit)] {
+            if let Some(value) = value {
+                if !value.is_finite() || value < MIN_CPU {
+                    return Err(Error::InvalidCpu { name, value });
+                }
+            }
+        }
+
+        for (name, value) in [
+            ("ram", self.ram),
+            ("ram-limit", self.ram_limit),
+            ("disk", self.disk),
+        ] {
+            if let Some(value) = value {
+                if !value.is_finite() || value < 0.0 {
+                    return Err(Error::InvalidSize { name, value });
+                }
+            }
+        }
+
+        for (name, value, limit_name, limit) in [
+            ("cpu", self.cpu, "cpu-limit", self.cpu_limit),
+            ("ram", self.ram, "ram-limit", self.ram_limit),
+        ] {
+            if let (Some(value), Some(limit)) = (value, limit) {
+                if limit < value {
+                    return Err(Error::LimitBelowRequest {
+                        name,
+                        value,
+                        limit_name,
+                        limit,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Applies any provided options in `other` to the [`Resources`].
     pub fn apply(mut self, other: &Self) -> Self {
         if let Some(cores) = other.cpu {
@@ -102,6 +267,10 @@ impl Resources {
             self.disk = Some(disk);
         }
 
+        if let Some(gpus) = &other.gpus {
+            self.gpus = Some(gpus.clone());
+        }
+
         if let Some(preemptible) = other.preemptible {
             self.preemptible = Some(preemptible);
         }
@@ -110,6 +279,58 @@ impl Resources {
         self
     }
 
+    /// Creates [`Resources`] from the labels of an image.
+    ///
+    /// The following labels are recognized (each prefixed with
+    /// [`LABEL_PREFIX`]): `cpu`, `cpu-limit`, `ram`, `ram-limit`, and `disk`.
+    /// RAM and disk values are in gibibytes (GiB) unless written with a unit
+    /// (e.g., `crankshaft.ram=512MiB`; see [`crankshaft_config::size`]).
+    ///
+    /// Labels with values that cannot be parsed are ignored with a warning.
+    ///
+    /// Returns `None` if none of the labels are present.
+    pub fn from_labels(labels: &HashMap<String, String>) -> Option<Self> {
+        let get = |name: &str| {
+            let key = format!("{LABEL_PREFIX}{name}");
+            let value = labels.get(&key)?;
+            let parsed = if name.starts_with("cpu") {
+                value.parse::<f64>().ok()
+            } else {
+                size::parse(value).ok()
+            };
+
+            match parsed {
+                Some(v) if v.is_finite() && v >= 0.0 => Some(v),
+                _ => {
+                    warn!("ignoring image label `{key}` with invalid value `{value}`");
+                    None
+                }
+            }
+        };
+
+        let resources = Self {
+            cpu: get("cpu"),
+            cpu_limit: get("cpu-limit"),
+            ram: get("ram"),
+            ram_limit: get("ram-limit"),
+            disk: get("disk"),
+            gpus: None,
+            preemptible: None,
+            zones: Default::default(),
+        };
+
+        if resources.cpu.is_none()
+            && resources.cpu_limit.is_none()
+            && resources.ram.is_none()
+            && resources.ram_limit.is_none()
+            && resources.disk.is_none()
+        {
+            return None;
+        }
+
+        Some(resources)
+    }
+
     /// Creates a [`HashMap`] representation of the resources.
     ///
     /// This is used when doing command substitution for generic backends.
@@ -147,6 +368,15 @@ impl Resources {
             map.insert("disk_mb".into(), (disk * 1024.0).to_string().into());
         }
 
+        if let Some(gpus) = &self.gpus {
+            map.insert("gpus".into(), gpus.count().to_string().into());
+
+            // Suitable for setting `CUDA_VISIBLE_DEVICES`
+            if let Some(devices) = gpus.devices() {
+                map.insert("gpu_devices".into(), devices.join(",").into());
+            }
+        }
+
         if let Some(preemptible) = self.preemptible {
             map.insert("preemptible".into(), preemptible.to_string().into());
         }
@@ -164,6 +394,7 @@ impl Default for Resources {
             ram: Some(2.0),
             ram_limit: None,
             disk: Some(8.0),
+            gpus: None,
             preemptible: Some(false),
             zones: Default::default(),
         }
@@ -178,6 +409,7 @@ impl From<&Defaults> for Resources {
             ram: defaults.ram(),
             ram_limit: defaults.ram_limit(),
             disk: defaults.disk(),
+            gpus: None,
             preemptible: Default::default(),
             zones: Default::default(),
         }
@@ -220,6 +452,22 @@ impl From<&Resources> for HostConfig {
             host_config.storage_opt = Some(storage_opt);
         }
 
+        // The container runtime makes only the requested devices visible
+        // within the container (e.g., by setting `NVIDIA_VISIBLE_DEVICES`)
+        if let Some(gpus) = resources.gpus() {
+            let (count, device_ids) = match gpus {
+                Gpus::Count(count) => (Some(*count as i64), None),
+                Gpus::Devices(devices) => (None, Some(devices.clone())),
+            };
+
+            host_config.device_requests = Some(vec![DeviceRequest {
+                count,
+                device_ids,
+                capabilities: Some(vec![vec!["gpu".to_string()]]),
+                ..Default::default()
+            }]);
+        }
+
         host_config
     }
 }
@@ -273,6 +521,25 @@ impl From<Resources> for tes::v1::types::task::Resources {
     }
 }
 
+impl From<tes::v1::types::task::Resources> for Resources {
+    fn from(resources: tes::v1::types::task::Resources) -> Self {
+        fn gb_to_gib(v: f64) -> f64 {
+            (v * (1000.0 * 1000.0 * 1000.0)) / (1024.0 * 1024.0 * 1024.0)
+        }
+
+        Self {
+            cpu: resources.cpu_cores.map(f64::from),
+            cpu_limit: None,
+            ram: resources.ram_gb.map(gb_to_gib),
+            ram_limit: None,
+            disk: resources.disk_gb.map(gb_to_gib),
+            gpus: None,
+            preemptible: resources.preemptible,
+            zones: resources.zones.unwrap_or_default(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use approx::assert_relative_eq;
@@ -287,6 +554,7 @@ mod test {
             ram: Some(16.),
             ram_limit: None,
             disk: Some(80.),
+            gpus: None,
             preemptible: Some(true),
             zones: vec!["foo".into(), "bar".into(), "baz".into()],
         };
@@ -303,4 +571,69 @@ mod test {
         assert_eq!(tes.backend_parameters, None);
         assert_eq!(tes.backend_parameters_strict, None);
     }
+
+    #[test]
+    fn validate() {
+        let resources = |cpu, cpu_limit| Resources::builder().cpu(cpu).cpu_limit(cpu_limit).build();
+
+        assert!(resources(0.5, 2.5).validate().is_ok());
+        assert!(matches!(
+            resources(0.0, 1.0).validate(),
+            Err(Error::InvalidCpu { name: "cpu", .. })
+        ));
+        assert!(matches!(
+            resources(1.0, f64::NAN).validate(),
+            Err(Error::InvalidCpu {
+                name: "cpu-limit",
+                ..
+            })
+        ));
+        assert!(matches!(
+            resources(2.0, 1.5).validate(),
+            Err(Error::LimitBelowRequest { .. })
+        ));
+        assert!(matches!(
+            Resources::builder().ram(-1.0).build().validate(),
+            Err(Error::InvalidSize { name: "ram", .. })
+        ));
+    }
+
+    #[test]
+    fn gpus() {
+        let resources: Resources = serde_json::from_str(r#"{ "gpus": 2 }"#).unwrap();
+        assert_eq!(resources.gpus(), Some(&Gpus::Count(2)));
+
+        let resources: Resources = serde_json::from_str(r#"{ "gpus": ["1", "3"] }"#).unwrap();
+        let host_config = HostConfig::from(&resources);
+        let request = &host_config.device_requests.unwrap()[0];
+        assert_eq!(request.count, None);
+        assert_eq!(request.device_ids, Some(vec!["1".into(), "3".into()]));
+        assert_eq!(
+            resources
+                .to_hashmap()
+                .get("gpu_devices")
+                .map(|v| v.as_ref()),
+            Some("1,3")
+        );
+    }
+
+    #[test]
+    fn resources_from_labels() {
+        let labels = HashMap::from_iter([
+            (String::from("crankshaft.cpu"), String::from("4")),
+            (String::from("crankshaft.ram"), String::from("8.5")),
+            (String::from("crankshaft.ram-limit"), String::from("16GiB")),
+            (String::from("crankshaft.disk"), String::from("lots")),
+            (String::from("maintainer"), String::from("someone")),
+        ]);
+
+        let resources = Resources::from_labels(&labels).unwrap();
+        assert_eq!(resources.cpu(), Some(4.0));
+        assert_eq!(resources.ram(), Some(8.5));
+        assert_eq!(resources.ram_limit(), Some(16.0));
+        assert_eq!(resources.disk(), None);
+        assert_eq!(resources.cpu_limit(), None);
+
+        assert!(Resources::from_labels(&HashMap::new()).is_none());
+    }
 }
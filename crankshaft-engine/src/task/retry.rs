@@ -0,0 +1,268 @@
+//! Policies for automatically retrying tasks that fail.
+
+use std::time::Duration;
+
+use bon::Builder;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::Task;
+use crate::service::runner::backend::TaskRunError;
+
+/// The maximum delay between attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// A class of failure of a task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Failure {
+    /// An execution that treats a non-zero exit as an error exited with a
+    /// non-zero status (other than by running out of memory).
+    NonZeroExit,
+
+    /// An execution was killed for running out of memory (see
+    /// [`TaskRunError::out_of_memory()`]).
+    OutOfMemory,
+
+    /// The task exceeded its wall-clock limit.
+    TimedOut,
+
+    /// The task was preempted.
+    Preempted,
+
+    /// Another error occurred (e.g., an image could not be pulled).
+    Error,
+}
+
+impl Failure {
+    /// Every class of failure.
+    pub const ALL: &[Self] = &[
+        Self::NonZeroExit,
+        Self::OutOfMemory,
+        Self::TimedOut,
+        Self::Preempted,
+        Self::Error,
+    ];
+
+    /// Classifies the error a task failed with.
+    ///
+    /// Returns `None` if the task was canceled, as cancellation is never
+    /// retried.
+    pub fn classify(error: &TaskRunError) -> Option<Self> {
+        match error {
+            TaskRunError::Canceled => None,
+            TaskRunError::Preempted => Some(Self::Preempted),
+            TaskRunError::TimedOut(_) => Some(Self::TimedOut),
+            TaskRunError::OutOfMemory { .. } => Some(Self::OutOfMemory),
+            TaskRunError::NonZeroExit { .. } => Some(Self::NonZeroExit),
+            TaskRunError::Other(_) => Some(Self::Error),
+        }
+    }
+}
+
+/// Returns every class of failure (the default failures that are retried).
+fn all() -> Vec<Failure> {
+    Failure::ALL.to_vec()
+}
+
+/// A policy for automatically retrying a task that fails.
+///
+/// Failed attempts are retried by the runner until the task succeeds, fails
+/// in a way that is not retried, or the maximum number of attempts is
+/// reached; only the result of the final attempt is reported. Each retry
+/// increments the task's attempt number.
+#[derive(Builder, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[builder(builder_type = Builder)]
+pub struct RetrySpec {
+    /// The maximum number of attempts (including the first).
+    max_attempts: u32,
+
+    /// The classes of failure that are retried.
+    ///
+    /// Defaults to every class of failure.
+    #[serde(default = "all")]
+    #[builder(into, default = all())]
+    on: Vec<Failure>,
+
+    /// The exit codes of non-zero exits that are retried.
+    ///
+    /// When empty, every non-zero exit is retried (if non-zero exits are
+    /// retried at all).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[builder(into, default)]
+    exit_codes: Vec<i32>,
+
+    /// The delay before the first retry, which is doubled for each
+    /// subsequent retry (up to an hour).
+    ///
+    /// When serialized, the delay is expressed in seconds.
+    #[serde(default, with = "super::seconds")]
+    backoff: Option<Duration>,
+
+    /// The factor the task's requested memory (and memory limit) is
+    /// multiplied by when retrying an attempt that ran out of memory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    memory_factor: Option<f64>,
+}
+
+impl RetrySpec {
+    /// Gets the maximum number of attempts (including the first).
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Gets the classes of failure that are retried.
+    pub fn on(&self) -> &[Failure] {
+        &self.on
+    }
+
+    /// Gets the exit codes of non-zero exits that are retried.
+    pub fn exit_codes(&self) -> &[i32] {
+        &self.exit_codes
+    }
+
+    /// Gets the delay before the first retry (if there is one).
+    pub fn backoff(&self) -> Option<Duration> {
+        self.backoff
+    }
+
+    /// Gets the factor the task's requested memory is multiplied by when
+    /// retrying an attempt that ran out of memory (if there is one).
+    pub fn memory_factor(&self) -> Option<f64> {
+        self.memory_factor
+    }
+
+    /// Determines if a task that failed after the given number of attempts
+    /// should be retried.
+    pub fn retries(&self, attempts: u32, error: &TaskRunError) -> bool {
+        if attempts >= self.max_attempts {
+            return false;
+        }
+
+        let Some(failure) = Failure::classify(error) else {
+            return false;
+        };
+
+        if !self.on.contains(&failure) {
+            return false;
+        }
+
+        match error {
+            TaskRunError::NonZeroExit {
+                index, statuses, ..
+            } if failure == Failure::NonZeroExit && !self.exit_codes.is_empty() => statuses[*index]
+                .code()
+                .is_some_and(|code| self.exit_codes.contains(&code)),
+            _ => true,
+        }
+    }
+
+    /// Gets the delay before the given retry (starting at `1`).
+    pub fn delay(&self, retry: u32) -> Duration {
+        let Some(backoff) = self.backoff else {
+            return Duration::ZERO;
+        };
+
+        backoff
+            .checked_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .unwrap_or(MAX_BACKOFF)
+            .min(MAX_BACKOFF)
+    }
+
+    /// Prepares a task for its next attempt after the given error.
+    ///
+    /// The task's attempt number is incremented and, if the attempt ran out of
+    /// memory, its requested memory is escalated.
+    pub(crate) fn prepare(&self, task: &mut Task, error: &TaskRunError) {
+        task.next_attempt();
+
+        let Some(factor) = self.memory_factor else {
+            return;
+        };
+
+        if Failure::classify(error) != Some(Failure::OutOfMemory) {
+            return;
+        }
+
+        if let Some(resources) = &mut task.resources {
+            for ram in [&mut resources.ram, &mut resources.ram_limit]
+                .into_iter()
+                .flatten()
+            {
+                *ram *= factor;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::ExitStatus;
+
+    use nonempty::NonEmpty;
+
+    use super::*;
+
+    /// Creates an error for an execution that exited with the given code.
+    fn exited(code: i32) -> TaskRunError {
+        #[cfg(unix)]
+        let status = {
+            use std::os::unix::process::ExitStatusExt as _;
+            ExitStatus::from_raw(code << 8)
+        };
+        #[cfg(windows)]
+        let status = {
+            use std::os::windows::process::ExitStatusExt as _;
+            ExitStatus::from_raw(code as u32)
+        };
+
+        TaskRunError::NonZeroExit {
+            index: 0,
+            statuses: NonEmpty::new(status),
+            tail: None,
+        }
+    }
+
+    #[test]
+    fn retries_failures() {
+        let spec: RetrySpec = serde_json::from_str(
+            r#"{ "max-attempts": 3, "on": ["non-zero-exit", "out-of-memory"], "exit-codes": [75] }"#,
+        )
+        .unwrap();
+
+        assert!(spec.retries(1, &exited(75)));
+        assert!(spec.retries(
+            2,
+            &TaskRunError::OutOfMemory {
+                index: 0,
+                statuses: NonEmpty::new(ExitStatus::default()),
+            }
+        ));
+        // An execution killed by `SIGKILL` is not assumed to have run out of memory
+        assert!(!spec.retries(1, &exited(137)));
+        assert!(!spec.retries(3, &exited(75)));
+        assert!(!spec.retries(1, &exited(1)));
+        assert!(!spec.retries(1, &TaskRunError::Canceled));
+        assert!(!spec.retries(1, &TaskRunError::Preempted));
+
+        let spec = RetrySpec::builder().max_attempts(2).build();
+        assert!(spec.retries(1, &TaskRunError::Preempted));
+        assert!(spec.retries(1, &exited(1)));
+    }
+
+    #[test]
+    fn backs_off() {
+        let spec = RetrySpec::builder()
+            .max_attempts(10)
+            .backoff(Duration::from_secs(5))
+            .build();
+        assert_eq!(spec.delay(1), Duration::from_secs(5));
+        assert_eq!(spec.delay(3), Duration::from_secs(20));
+        assert_eq!(spec.delay(40), MAX_BACKOFF);
+        assert_eq!(
+            RetrySpec::builder().max_attempts(2).build().delay(1),
+            Duration::ZERO
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Workflow runtime attributes.
+//!
+//! Workflow languages describe the container and resources of a task with a
+//! map of runtime attributes (e.g., the `runtime` section of a WDL task).
+//! [`RuntimeAttributes`] maps the commonly used keys onto an image and a set
+//! of [`Resources`] so that workflow engines embedding Crankshaft don't each
+//! need to.
+
+use std::collections::HashMap;
+
+use crankshaft_config::size;
+use thiserror::Error;
+
+use crate::task::Gpus;
+use crate::task::Resources;
+use crate::task::resources;
+
+/// An error mapping runtime attributes.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A runtime attribute had a value that could not be parsed.
+    #[error("invalid value `{value}` for runtime attribute `{key}`")]
+    InvalidValue {
+        /// The key of the runtime attribute.
+        key: String,
+
+        /// The invalid value.
+        value: String,
+    },
+
+    /// The mapped resources were invalid.
+    #[error(transparent)]
+    Resources(#[from] resources::Error),
+}
+
+/// The container and resources described by a set of runtime attributes.
+#[derive(Clone, Debug)]
+pub struct RuntimeAttributes {
+    /// The container image.
+    image: Option<String>,
+
+    /// The requested resources.
+    resources: Resources,
+}
+
+impl RuntimeAttributes {
+    /// Maps the runtime attributes of a workflow task.
+    ///
+    /// The following keys are recognized:
+    ///
+    /// * `docker` or `container`: the container image.
+    /// * `cpu`: the number of CPU cores.
+    /// * `memory`: the amount of RAM, as a size with an optional unit (e.g.,
+    ///   `"4 GiB"` or `"512M"`; see [`crankshaft_config::size`]).
+    /// * `disks`: the disk space, either as a size or as a comma-separated list
+    ///   of disks such as `"local-disk 100 SSD"` (the sizes of multiple disks
+    ///   are added together). Sizes without a unit are in GiB.
+    /// * `gpu`: either `true`/`false` or a number of GPUs.
+    /// * `gpuCount` or `gpu_count`: a number of GPUs.
+    ///
+    /// Other keys are ignored.
+    pub fn from_runtime_attrs(attrs: &HashMap<String, String>) -> Result<Self, Error> {
+        let invalid = |key: &str, value: &str| Error::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+
+        let image = attrs
+            .get("docker")
+            .or_else(|| attrs.get("container"))
+            .map(|image| image.trim().to_string());
+
+        let cpu = attrs
+            .get("cpu")
+            .map(|value| {
+                value
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| invalid("cpu", value))
+            })
+            .transpose()?;
+
+        let ram = attrs
+            .get("memory")
+            .map(|value| size::parse(value).map_err(|_| invalid("memory", value)))
+            .transpose()?;
+
+        let disk = attrs
+            .get("disks")
+            .map(|value| parse_disks(value).ok_or_else(|| invalid("disks", value)))
+            .transpose()?;
+
+        let gpus = ["gpuCount", "gpu_count", "gpu"]
+            .into_iter()
+            .find_map(|key| attrs.get(key).map(|value| (key, value)));
+        let gpus = match gpus {
+            Some((key, value)) => match value.trim() {
+                "true" if key == "gpu" => Some(Gpus::Count(1)),
+                "false" if key == "gpu" => None,
+                count => match count.parse::<u32>() {
+                    Ok(0) => None,
+                    Ok(count) => Some(Gpus::Count(count)),
+                    Err(_) => return Err(invalid(key, value)),
+                },
+            },
+            None => None,
+        };
+
+        let resources = Resources::builder()
+            .maybe_cpu(cpu)
+            .maybe_ram(ram)
+            .maybe_disk(disk)
+            .maybe_gpus(gpus)
+            .build();
+        resources.validate()?;
+
+        Ok(Self { image, resources })
+    }
+
+    /// Gets the container image (if one was specified).
+    pub fn image(&self) -> Option<&str> {
+        self.image.as_deref()
+    }
+
+    /// Gets the requested resources.
+    pub fn resources(&self) -> &Resources {
+        &self.resources
+    }
+
+    /// Consumes `self` and returns the image and resources.
+    pub fn into_parts(self) -> (Option<String>, Resources) {
+        (self.image, self.resources)
+    }
+}
+
+/// Parses the total size (in GiB) of a `disks` runtime attribute.
+///
+/// Each comma-separated disk is made up of an optional mount point, a size,
+/// an optional unit, and an optional disk type (e.g., `local-disk 100 SSD` or
+/// `/mnt/data 10 GiB`).
+fn parse_disks(value: &str) -> Option<f64> {
+    value
+        .split(',')
+        .map(|disk| {
+            let tokens = disk.split_whitespace().collect::<Vec<_>>();
+            let index = tokens
+                .iter()
+                .position(|token| token.starts_with(|c: char| c.is_ascii_digit()))?;
+
+            // Try the size together with a following unit first
+            if let Some(unit) = tokens.get(index + 1) {
+                if let Ok(size) = size::parse(&format!("{}{unit}", tokens[index])) {
+                    return Some(size);
+                }
+            }
+
+            size::parse(tokens[index]).ok()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a map of runtime attributes.
+    fn attrs(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn maps_runtime_attributes() {
+        let runtime = RuntimeAttributes::from_runtime_attrs(&attrs(&[
+            ("docker", "ubuntu:22.04"),
+            ("cpu", "2"),
+            ("memory", "4 GiB"),
+            ("disks", "local-disk 10 SSD, /mnt/data 2 GiB"),
+            ("gpu", "true"),
+            ("maxRetries", "3"),
+        ]))
+        .unwrap();
+
+        assert_eq!(runtime.image(), Some("ubuntu:22.04"));
+        let resources = runtime.resources();
+        assert_eq!(resources.cpu(), Some(2.0));
+        assert_eq!(resources.ram(), Some(4.0));
+        assert_eq!(resources.disk(), Some(12.0));
+        assert_eq!(resources.gpus(), Some(&Gpus::Count(1)));
+
+        let runtime =
+            RuntimeAttributes::from_runtime_attrs(&attrs(&[("container", "alpine")])).unwrap();
+        assert_eq!(runtime.image(), Some("alpine"));
+        assert_eq!(runtime.resources().cpu(), None);
+    }
+
+    #[test]
+    fn rejects_invalid_attributes() {
+        assert!(matches!(
+            RuntimeAttributes::from_runtime_attrs(&attrs(&[("memory", "lots")])),
+            Err(Error::InvalidValue { key, .. }) if key == "memory"
+        ));
+        assert!(matches!(
+            RuntimeAttributes::from_runtime_attrs(&attrs(&[("disks", "local-disk SSD")])),
+            Err(Error::InvalidValue { key, .. }) if key == "disks"
+        ));
+        assert!(matches!(
+            RuntimeAttributes::from_runtime_attrs(&attrs(&[("cpu", "0")])),
+            Err(Error::Resources(_))
+        ));
+    }
+}
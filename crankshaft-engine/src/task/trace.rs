@@ -0,0 +1,146 @@
+//! Distributed tracing context for tasks.
+//!
+//! A [`TraceContext`] carries a [W3C Trace Context] into a task's containers
+//! as the `TRACEPARENT` and `TRACESTATE` environment variables so that
+//! tooling within the containers can correlate its traces with those of the
+//! caller.
+//!
+//! [W3C Trace Context]: https://www.w3.org/TR/trace-context/
+
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+/// The environment variable for the `traceparent` header.
+pub const TRACEPARENT_ENV: &str = "TRACEPARENT";
+
+/// The environment variable for the `tracestate` header.
+pub const TRACESTATE_ENV: &str = "TRACESTATE";
+
+/// An error for a malformed `traceparent` value.
+#[derive(Debug, Error)]
+#[error(
+    "invalid traceparent `{0}`: expected `<version>-<trace id>-<parent id>-<flags>` in lowercase \
+     hex"
+)]
+pub struct InvalidTraceparent(String);
+
+/// The trace context of a task.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", try_from = "RawTraceContext")]
+pub struct TraceContext {
+    /// The `traceparent` value.
+    traceparent: String,
+
+    /// The `tracestate` value, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tracestate: Option<String>,
+}
+
+/// A trace context as deserialized before validation.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawTraceContext {
+    /// The `traceparent` value.
+    traceparent: String,
+
+    /// The `tracestate` value, if any.
+    #[serde(default)]
+    tracestate: Option<String>,
+}
+
+impl TryFrom<RawTraceContext> for TraceContext {
+    type Error = InvalidTraceparent;
+
+    fn try_from(raw: RawTraceContext) -> Result<Self, Self::Error> {
+        Ok(Self::new(raw.traceparent)?.with_tracestate(raw.tracestate))
+    }
+}
+
+impl TraceContext {
+    /// Creates a trace context from a `traceparent` value (e.g.,
+    /// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`).
+    pub fn new(traceparent: impl Into<String>) -> Result<Self, InvalidTraceparent> {
+        let traceparent = traceparent.into();
+
+        let lengths = traceparent.split('-').map(str::len).collect::<Vec<_>>();
+        let hex = traceparent
+            .chars()
+            .all(|c| c == '-' || c.is_ascii_digit() || ('a'..='f').contains(&c));
+        let zero = |part: Option<&str>| part.is_some_and(|p| p.chars().all(|c| c == '0'));
+        let mut parts = traceparent.split('-').skip(1);
+
+        if lengths != [2, 32, 16, 2] || !hex || zero(parts.next()) || zero(parts.next()) {
+            return Err(InvalidTraceparent(traceparent));
+        }
+
+        Ok(Self {
+            traceparent,
+            tracestate: None,
+        })
+    }
+
+    /// Sets the `tracestate` value.
+    pub fn with_tracestate(mut self, tracestate: impl Into<Option<String>>) -> Self {
+        self.tracestate = tracestate.into().filter(|s| !s.is_empty());
+        self
+    }
+
+    /// Gets the `traceparent` value.
+    pub fn traceparent(&self) -> &str {
+        &self.traceparent
+    }
+
+    /// Gets the trace ID.
+    pub fn trace_id(&self) -> &str {
+        &self.traceparent[3..35]
+    }
+
+    /// Gets the `tracestate` value (if there is one).
+    pub fn tracestate(&self) -> Option<&str> {
+        self.tracestate.as_deref()
+    }
+
+    /// Gets the environment variables that carry the trace context.
+    pub fn env(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        std::iter::once((TRACEPARENT_ENV, self.traceparent.as_str()))
+            .chain(self.tracestate().map(|s| (TRACESTATE_ENV, s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_traceparent() {
+        let context = TraceContext::new("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+            .unwrap()
+            .with_tracestate(String::from("vendor=value"));
+        assert_eq!(context.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(
+            context.env().collect::<Vec<_>>(),
+            [
+                (TRACEPARENT_ENV, context.traceparent()),
+                (TRACESTATE_ENV, "vendor=value")
+            ]
+        );
+
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+        ] {
+            assert!(TraceContext::new(invalid).is_err(), "{invalid}");
+        }
+
+        let context: TraceContext = serde_json::from_str(
+            r#"{ "traceparent": "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01" }"#,
+        )
+        .unwrap();
+        assert_eq!(context.tracestate(), None);
+        assert!(serde_json::from_str::<TraceContext>(r#"{ "traceparent": "nope" }"#).is_err());
+    }
+}
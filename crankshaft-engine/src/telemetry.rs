@@ -0,0 +1,160 @@
+//! Anonymous usage statistics.
+//!
+//! See [`Telemetry`] for details on what is reported.
+
+use std::collections::BTreeMap;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crankshaft_config::telemetry::Telemetry;
+use nonempty::NonEmpty;
+use serde::Serialize;
+use tracing::debug;
+use tracing::warn;
+
+use crate::service::runner::backend::TaskRunError;
+
+/// A report of anonymous usage statistics.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Report {
+    /// The number of tasks run keyed by backend type.
+    pub tasks: BTreeMap<String, u64>,
+
+    /// The number of failed tasks keyed by failure category.
+    pub failures: BTreeMap<String, u64>,
+}
+
+impl Report {
+    /// Determines if the report has nothing to report.
+    fn is_empty(&self) -> bool {
+        self.tasks.is_empty() && self.failures.is_empty()
+    }
+
+    /// Merges another report into this one.
+    fn merge(&mut self, other: Report) {
+        for (key, count) in other.tasks {
+            *self.tasks.entry(key).or_default() += count;
+        }
+
+        for (key, count) in other.failures {
+            *self.failures.entry(key).or_default() += count;
+        }
+    }
+}
+
+/// The counters aggregated between reports.
+#[derive(Debug, Default)]
+pub(crate) struct Counters(Mutex<Report>);
+
+impl Counters {
+    /// Records the result of a task run on a backend of the given type.
+    pub(crate) fn record(
+        &self,
+        backend: &str,
+        result: &Result<NonEmpty<ExitStatus>, TaskRunError>,
+    ) {
+        let category = match result {
+            Ok(_) => None,
+            Err(TaskRunError::Canceled) => Some("canceled"),
+            Err(TaskRunError::Preempted) => Some("preempted"),
+            Err(TaskRunError::TimedOut(_)) => Some("timed-out"),
+            Err(TaskRunError::NonZeroExit { .. }) => Some("non-zero-exit"),
+            Err(TaskRunError::OutOfMemory { .. }) => Some("out-of-memory"),
+            Err(TaskRunError::Other(_)) => Some("other"),
+        };
+
+        let mut report = self.0.lock().unwrap();
+        *report.tasks.entry(backend.to_string()).or_default() += 1;
+
+        if let Some(category) = category {
+            *report.failures.entry(category.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Takes the counters aggregated since the last report.
+    fn take(&self) -> Report {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+
+    /// Restores counters that failed to be reported.
+    fn restore(&self, report: Report) {
+        self.0.lock().unwrap().merge(report);
+    }
+}
+
+/// Spawns a task that periodically reports the aggregated counters.
+///
+/// Counters that fail to be reported are kept for the next report.
+pub(crate) fn spawn(config: Telemetry) -> Arc<Counters> {
+    let counters = Arc::new(Counters::default());
+    let interval = Duration::from_secs(config.interval().max(1));
+    let endpoint = config.endpoint().clone();
+
+    tokio::spawn({
+        let counters = counters.clone();
+        async move {
+            let client = reqwest::Client::new();
+            let mut ticker = tokio::time::interval(interval);
+
+            // The first tick completes immediately
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+
+                let report = counters.take();
+                if report.is_empty() {
+                    continue;
+                }
+
+                let result = match serde_json::to_string(&report) {
+                    Ok(body) => client
+                        .post(endpoint.clone())
+                        .header("content-type", "application/json")
+                        .body(body)
+                        .send()
+                        .await
+                        .and_then(|response| response.error_for_status())
+                        .map(|_| ())
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+
+                match result {
+                    Ok(()) => debug!("reported usage statistics to `{endpoint}`"),
+                    Err(e) => {
+                        warn!("failed to report usage statistics to `{endpoint}`: {e}");
+                        counters.restore(report);
+                    }
+                }
+            }
+        }
+    });
+
+    counters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts() {
+        let counters = Counters::default();
+        counters.record("docker", &Ok(NonEmpty::new(ExitStatus::default())));
+        counters.record("docker", &Err(TaskRunError::Canceled));
+        counters.record("tes", &Err(TaskRunError::TimedOut(Duration::from_secs(1))));
+
+        let report = counters.take();
+        assert_eq!(report.tasks["docker"], 2);
+        assert_eq!(report.tasks["tes"], 1);
+        assert_eq!(report.failures["canceled"], 1);
+        assert_eq!(report.failures["timed-out"], 1);
+        assert!(counters.take().is_empty());
+
+        counters.restore(report.clone());
+        assert_eq!(counters.take(), report);
+    }
+}
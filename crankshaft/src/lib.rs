@@ -1,5 +1,7 @@
 //! Crankshaft.
 
+pub mod prelude;
+
 #[cfg(feature = "config")]
 #[doc(inline)]
 pub use crankshaft_config as config;
@@ -0,0 +1,66 @@
+//! The commonly used types of Crankshaft.
+//!
+//! The prelude is intended to be glob imported by downstream crates:
+//!
+//! ```
+//! use crankshaft::prelude::*;
+//! ```
+//!
+//! Only types that are part of the stable public API are exported here;
+//! types that are expected to grow are marked `#[non_exhaustive]` so that
+//! adding to them is not a breaking change.
+
+#[cfg(feature = "config")]
+#[doc(no_inline)]
+pub use crankshaft_config::Config;
+#[cfg(feature = "config")]
+#[doc(no_inline)]
+pub use crankshaft_config::backend::Config as BackendConfig;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::Engine;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::Task;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::budget::Budget;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::budget::BudgetExhausted;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::service::runner::Backend;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::service::runner::ExceedsCapacity;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::service::runner::GroupCanceled;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::service::runner::QueueFull;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::service::runner::TaskHandle;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::service::runner::TaskStatus;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::service::runner::backend::TaskRunError;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::task::Execution;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::task::Input;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::task::Output;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::task::Resources;
+#[cfg(feature = "engine")]
+#[doc(no_inline)]
+pub use crankshaft_engine::task::RetrySpec;
@@ -5,6 +5,7 @@
 //! `cargo run --release --bin docker`
 
 use std::env::current_dir;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use anyhow::Context;
@@ -15,6 +16,7 @@ use crankshaft::Engine;
 use crankshaft::config::backend::Kind;
 use crankshaft::config::backend::docker::Config;
 use crankshaft::engine::Task;
+use crankshaft::engine::store::Store;
 use crankshaft::engine::task::Execution;
 use crankshaft::engine::task::Output;
 use crankshaft::engine::task::output::Type;
@@ -44,20 +46,51 @@ pub struct Args {
     /// The number of jobs to submit in total.
     #[arg(short, long, default_value_t = 1000)]
     n_jobs: usize,
+
+    /// The path to a database in which to record every task attempt.
+    #[arg(long)]
+    store: Option<PathBuf>,
+
+    /// Prints the attempts recorded in the store as JSON lines instead of
+    /// running any tasks.
+    #[arg(long, requires = "store")]
+    attempts: bool,
 }
 
 /// Starting point for task execution.
 async fn run(args: Args, token: CancellationToken) -> Result<()> {
+    let store = args
+        .store
+        .as_ref()
+        .map(|path| {
+            Store::open(path)
+                .with_context(|| format!("failed to open store `{path}`", path = path.display()))
+        })
+        .transpose()?;
+
+    if args.attempts {
+        if let Some(store) = &store {
+            for attempt in store.attempts(None).await? {
+                println!("{}", serde_json::to_string(&attempt)?);
+            }
+        }
+
+        return Ok(());
+    }
+
     let config = crankshaft::config::backend::Config::builder()
         .name("docker")
         .kind(Kind::Docker(Config::builder().build()))
         .max_tasks(args.max_tasks)
         .build();
 
-    let engine = Engine::default()
+    let mut engine = Engine::default()
         .with(config)
         .await
         .context("initializing Docker backend")?;
+    if let Some(store) = store {
+        engine = engine.with_store(store);
+    }
 
     let task = Task::builder()
         .description("a longer description")